@@ -0,0 +1,95 @@
+use std::fs;
+use std::hint::black_box;
+use std::io::Cursor;
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ewc::counter::{count_directory, count_from_reader, Count, FilterConfig, IncrementalCount};
+
+const SIZES: [usize; 3] = [1_000, 100_000, 500_000];
+
+fn ascii_text(lines: usize) -> String {
+    "hello world test line for benchmark\n".repeat(lines)
+}
+
+fn multibyte_text(lines: usize) -> String {
+    "こんにちは 世界 test ligne déjà vue\n".repeat(lines)
+}
+
+fn from_content(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_content");
+    for lines in SIZES {
+        for (name, text) in [
+            ("ascii", ascii_text(lines)),
+            ("multibyte", multibyte_text(lines)),
+        ] {
+            group.throughput(Throughput::Bytes(text.len() as u64));
+            group.bench_with_input(BenchmarkId::new(name, lines), &text, |b, text| {
+                b.iter(|| Count::from_content(black_box(text)))
+            });
+        }
+    }
+    group.finish();
+}
+
+/// The byte-level paths used for stdin and streamed input.
+fn byte_scanning(c: &mut Criterion) {
+    let text = ascii_text(100_000);
+    let mut group = c.benchmark_group("byte_scanning");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("from_bytes", |b| {
+        b.iter(|| Count::from_bytes(black_box(text.as_bytes())).unwrap())
+    });
+    group.bench_function("count_from_reader", |b| {
+        b.iter(|| count_from_reader(Cursor::new(black_box(text.as_bytes()))).unwrap())
+    });
+    for chunk_size in [4 * 1024, 64 * 1024] {
+        group.bench_with_input(
+            BenchmarkId::new("incremental_feed", chunk_size),
+            &chunk_size,
+            |b, &chunk_size| {
+                b.iter(|| {
+                    let mut counter = IncrementalCount::new();
+                    for chunk in text.as_bytes().chunks(chunk_size) {
+                        counter.feed(black_box(chunk)).unwrap();
+                    }
+                    counter.finish().unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Writes `dirs` directories of `files_per_dir` small files each.
+fn create_tree(root: &Path, dirs: usize, files_per_dir: usize) {
+    for d in 0..dirs {
+        let dir = root.join(format!("dir{d}"));
+        fs::create_dir_all(&dir).unwrap();
+        for f in 0..files_per_dir {
+            fs::write(dir.join(format!("file{f}.rs")), ascii_text(50)).unwrap();
+        }
+    }
+}
+
+fn directory_traversal(c: &mut Criterion) {
+    let tree = tempfile::tempdir().unwrap();
+    create_tree(tree.path(), 20, 50);
+    let plain = FilterConfig::default().compile().unwrap();
+    let filtered = FilterConfig::new(false, vec!["*1.rs".to_string()], Vec::new())
+        .compile()
+        .unwrap();
+
+    let mut group = c.benchmark_group("count_directory");
+    group.sample_size(20);
+    group.bench_function("1000_files", |b| {
+        b.iter(|| count_directory(black_box(tree.path()), &plain).unwrap())
+    });
+    group.bench_function("1000_files_excluded", |b| {
+        b.iter(|| count_directory(black_box(tree.path()), &filtered).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, from_content, byte_scanning, directory_traversal);
+criterion_main!(benches);
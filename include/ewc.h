@@ -0,0 +1,39 @@
+/*
+ * C interface to ewc, built with `cargo build --release --features ffi`
+ * (link against target/release/libewc.so, libewc.dylib or ewc.dll).
+ */
+#ifndef EWC_H
+#define EWC_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Return codes */
+#define EWC_OK 0
+#define EWC_ERR_NULL (-1) /* a pointer argument was NULL */
+#define EWC_ERR_PATH (-2) /* the path was not valid UTF-8 */
+#define EWC_ERR_IO (-3)   /* the path could not be read */
+
+typedef struct EwcCountResult {
+    uint64_t lines;
+    uint64_t words;
+    uint64_t bytes;
+    uint64_t max_line_length;
+    uint64_t file_count;
+} EwcCountResult;
+
+/* Counts one file. */
+int ewc_count_file(const char *path, EwcCountResult *out);
+
+/* Counts every file under a directory, skipping generated files, and hidden
+ * files unless include_hidden is non-zero. */
+int ewc_count_directory(const char *path, int include_hidden, EwcCountResult *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* EWC_H */
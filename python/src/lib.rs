@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use pyo3::prelude::*;
+
+use ewc::counter::{self, FilterConfig};
+
+/// Line, word and byte counts, as ewc reports them.
+#[pyclass(name = "Count", module = "ewc", frozen, eq, get_all, from_py_object)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PyCount {
+    lines: usize,
+    words: usize,
+    bytes: usize,
+    max_line_length: usize,
+}
+
+impl From<counter::Count> for PyCount {
+    fn from(count: counter::Count) -> Self {
+        Self {
+            lines: count.lines,
+            words: count.words,
+            bytes: count.bytes,
+            max_line_length: count.max_line_length,
+        }
+    }
+}
+
+impl From<PyCount> for counter::Count {
+    fn from(count: PyCount) -> Self {
+        Self {
+            lines: count.lines,
+            words: count.words,
+            bytes: count.bytes,
+            max_line_length: count.max_line_length,
+        }
+    }
+}
+
+#[pymethods]
+impl PyCount {
+    #[new]
+    #[pyo3(signature = (lines=0, words=0, bytes=0, max_line_length=0))]
+    fn new(lines: usize, words: usize, bytes: usize, max_line_length: usize) -> Self {
+        Self {
+            lines,
+            words,
+            bytes,
+            max_line_length,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Count(lines={}, words={}, bytes={}, max_line_length={})",
+            self.lines, self.words, self.bytes, self.max_line_length
+        )
+    }
+
+    /// Totals two counts, keeping the longer of the longest lines.
+    fn __add__(&self, other: &Self) -> Self {
+        (counter::Count::from(*self) + counter::Count::from(*other)).into()
+    }
+}
+
+/// Counts one file; raises OSError if it is missing or not UTF-8 text.
+#[pyfunction]
+fn count_file(py: Python<'_>, path: PathBuf) -> PyResult<PyCount> {
+    let count = py.detach(|| counter::count_file(&path))?;
+    Ok(count.into())
+}
+
+/// Counts every file under a directory with ewc's filters, returning the total and
+/// a dict of per-file counts keyed by path.
+#[pyfunction]
+#[pyo3(signature = (path, *, hidden=false, exclude=Vec::new(), include=Vec::new(), include_generated=false))]
+fn count_dir(
+    py: Python<'_>,
+    path: PathBuf,
+    hidden: bool,
+    exclude: Vec<String>,
+    include: Vec<String>,
+    include_generated: bool,
+) -> PyResult<(PyCount, BTreeMap<String, PyCount>)> {
+    let mut config = FilterConfig::new(hidden, exclude, include);
+    config.include_generated = include_generated;
+    let (entries, total) = py.detach(|| {
+        let config = config.compile()?;
+        counter::count_directory_detailed(&path, &config)
+    })?;
+    let files = entries
+        .into_iter()
+        .map(|entry| (entry.path.display().to_string(), entry.count.into()))
+        .collect();
+    Ok((total.into(), files))
+}
+
+#[pymodule]
+#[pyo3(name = "ewc")]
+fn ewc_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCount>()?;
+    m.add_function(wrap_pyfunction!(count_file, m)?)?;
+    m.add_function(wrap_pyfunction!(count_dir, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,30 @@
+import pathlib
+
+import pytest
+
+import ewc
+
+
+def test_count_file(tmp_path: pathlib.Path) -> None:
+    path = tmp_path / "a.txt"
+    path.write_text("hello world\nbye\n")
+    assert ewc.count_file(path) == ewc.Count(lines=2, words=3, bytes=16, max_line_length=11)
+
+
+def test_count_file_missing_raises_oserror(tmp_path: pathlib.Path) -> None:
+    with pytest.raises(OSError):
+        ewc.count_file(tmp_path / "missing.txt")
+
+
+def test_count_dir_applies_filters(tmp_path: pathlib.Path) -> None:
+    (tmp_path / "a.rs").write_text("fn a() {}\n")
+    (tmp_path / "b.md").write_text("# b\n\ntext\n")
+    (tmp_path / ".hidden").write_text("secret\n")
+
+    total, files = ewc.count_dir(tmp_path, exclude=["*.md"])
+    assert total.lines == 1
+    assert list(files) == [str(tmp_path / "a.rs")]
+
+    total, files = ewc.count_dir(tmp_path, hidden=True)
+    assert len(files) == 3
+    assert total == sum(files.values(), ewc.Count())
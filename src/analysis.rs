@@ -0,0 +1,495 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::path::Path;
+
+use crate::checksum::{Checksum, HashAlgorithm};
+use crate::complexity;
+use crate::counter::LineRatios;
+use crate::dedupe::DedupeStats;
+use crate::dialogue::DialogueStats;
+use crate::documents::{count_pages, is_book, Chapters};
+use crate::entropy::ByteHistogram;
+use crate::i18n::{is_locale_file, I18nStats};
+use crate::languages::{language_of, LineKinds};
+#[cfg(feature = "logs")]
+use crate::logs::LevelMatcher;
+use crate::logs::{is_log, LogLevels};
+use crate::markdown::{is_markdown, MarkdownStats};
+use crate::meta::FileMeta;
+use crate::natural::NaturalLanguages;
+use crate::po::{is_po, PoStats};
+use crate::records::{RecordCount, RecordFormat};
+use crate::structure::{is_config, StructureStats};
+use crate::subtitles::{is_subtitle, SubtitleStats};
+use crate::symbols::SymbolCounts;
+use crate::vocabulary::Vocabulary;
+
+/// Which format-aware analyzers to run on each counted file.
+#[derive(Debug, Default, Clone)]
+pub struct AnalysisOptions {
+    /// Document statistics for Markdown files (`--markdown`)
+    pub markdown: bool,
+    /// Function, type and test counts for supported languages (`--symbols`, needs the
+    /// `symbols` feature)
+    pub symbols: bool,
+    /// Decision-point estimates for source files (`--complexity`)
+    pub complexity: bool,
+    /// Record counts, parsing every file in this format (`--records`, needs the `json`
+    /// feature)
+    pub records: Option<RecordFormat>,
+    /// Keys, depth and arrays of YAML, TOML and INI files (`--structure`, needs the
+    /// `structure` feature)
+    pub structure: bool,
+    /// Lines per severity in log files (`--log-levels`)
+    #[cfg(feature = "logs")]
+    pub log_levels: Option<LevelMatcher>,
+    /// Distinct and repeated lines in every file (`--dedupe-lines`)
+    pub dedupe_lines: bool,
+    /// Byte entropy of every file (`--entropy`)
+    pub entropy: bool,
+    /// A content hash of every file (`--hash`, needs the `checksum` feature)
+    pub hash: Option<HashAlgorithm>,
+    /// Size on disk, modification time and permissions of every file (`--show-meta`),
+    /// from its metadata rather than its content
+    pub meta: bool,
+    /// The natural language of every file (`--detect-language`, needs the
+    /// `language-detection` feature)
+    pub natural_language: bool,
+    /// Distinct words and words used once in every file (`--vocabulary`)
+    pub vocabulary: bool,
+    /// Quotations and the words inside them in every file (`--dialogue`)
+    pub dialogue: bool,
+    /// Pages of documents, or form-feed separated pages of text (`--pages`)
+    pub pages: bool,
+    /// Words per chapter of EPUB books (`--chapters`)
+    pub chapters: bool,
+    /// Cues, words and reading speed of subtitle files (`--subtitles`)
+    pub subtitles: bool,
+    /// Messages by translation state in gettext catalogs (`--po`)
+    pub po: bool,
+    /// Strings and their words in JSON and YAML locale files, and the keys each locale
+    /// misses (`--i18n`, needs the `structure` feature)
+    pub i18n: bool,
+    /// Blank, comment and code lines of files in a known language (`--comment-ratio`,
+    /// `--cocomo`, `--format cloc` and `tokei-json`)
+    pub line_kinds: bool,
+    /// Words and bytes per line (`--ratios`), from the counts without reading files
+    pub ratios: bool,
+}
+
+impl AnalysisOptions {
+    /// Whether no analyzer is on.
+    pub fn is_empty(&self) -> bool {
+        !self.markdown
+            && !self.symbols
+            && !self.complexity
+            && self.records.is_none()
+            && !self.structure
+            && !self.log_levels()
+            && !self.dedupe_lines
+            && !self.entropy
+            && self.hash.is_none()
+            && !self.meta
+            && !self.natural_language
+            && !self.vocabulary
+            && !self.dialogue
+            && !self.pages
+            && !self.chapters
+            && !self.subtitles
+            && !self.po
+            && !self.i18n
+            && !self.line_kinds
+            && !self.ratios
+    }
+
+    #[cfg(feature = "logs")]
+    fn log_levels(&self) -> bool {
+        self.log_levels.is_some()
+    }
+
+    #[cfg(not(feature = "logs"))]
+    fn log_levels(&self) -> bool {
+        false
+    }
+
+    /// Whether any analyzer applies to `path`, so its content is worth reading.
+    pub fn applies_to(&self, path: &Path) -> bool {
+        (self.markdown && is_markdown(path))
+            || (self.symbols && symbols_supported(path))
+            || (self.complexity && complexity::is_supported(path))
+            || self.records.is_some()
+            || (self.structure && is_config(path))
+            || (self.log_levels() && is_log(path))
+            || self.dedupe_lines
+            || self.entropy
+            || self.hash.is_some()
+            || self.natural_language
+            || self.vocabulary
+            || self.dialogue
+            || self.pages
+            || (self.chapters && is_book(path))
+            || (self.subtitles && is_subtitle(path))
+            || (self.po && is_po(path))
+            || (self.i18n && is_locale_file(path))
+            || (self.line_kinds && language_of(path).is_some())
+    }
+}
+
+/// What the analyzers found in a file, or summed over several; each is `None` when it
+/// was not asked for or does not apply.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileAnalysis {
+    pub markdown: Option<MarkdownStats>,
+    pub symbols: Option<SymbolCounts>,
+    /// Branching keywords and short-circuit operators, see [`complexity::decision_points`]
+    pub complexity: Option<usize>,
+    pub records: Option<RecordCount>,
+    pub structure: Option<StructureStats>,
+    pub log_levels: Option<LogLevels>,
+    pub dedupe_lines: Option<DedupeStats>,
+    /// See [`ByteHistogram::bits_per_byte`]
+    pub entropy: Option<ByteHistogram>,
+    /// Only for a single file: a sum of several has no hash
+    pub hash: Option<Checksum>,
+    /// Filled in by the caller, who has the file's metadata
+    pub meta: Option<FileMeta>,
+    pub natural_language: Option<NaturalLanguages>,
+    pub vocabulary: Option<Vocabulary>,
+    pub dialogue: Option<DialogueStats>,
+    pub pages: Option<usize>,
+    pub chapters: Option<Chapters>,
+    pub subtitles: Option<SubtitleStats>,
+    pub po: Option<PoStats>,
+    pub i18n: Option<I18nStats>,
+    pub line_kinds: Option<LineKinds>,
+    /// Filled in by the caller, who has the file's counts
+    pub ratios: Option<LineRatios>,
+}
+
+/// Content an analyzer could not parse, such as a malformed `--records` line or
+/// `--structure` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line and column
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl FileAnalysis {
+    /// Runs the analyzers in `options` that apply to `path` over its content.
+    pub fn of(path: &Path, content: &str, options: &AnalysisOptions) -> Self {
+        Self::with_errors(path, content, options).0
+    }
+
+    /// Like [`FileAnalysis::of`], also returning what could not be parsed.
+    pub fn with_errors(
+        path: &Path,
+        content: &str,
+        options: &AnalysisOptions,
+    ) -> (Self, Vec<ParseError>) {
+        let (records, mut errors) = match options.records {
+            Some(format) => count_records(content, format),
+            None => (None, Vec::new()),
+        };
+        let structure = if options.structure {
+            config_structure(path, content).map(|(stats, structure_errors)| {
+                errors.extend(structure_errors);
+                stats
+            })
+        } else {
+            None
+        };
+        let i18n = if options.i18n && is_locale_file(path) {
+            match locale_strings(path, content) {
+                Some(Ok(stats)) => Some(stats),
+                Some(Err(error)) => {
+                    errors.push(error);
+                    None
+                }
+                None => None,
+            }
+        } else {
+            None
+        };
+        let analysis = Self {
+            markdown: (options.markdown && is_markdown(path))
+                .then(|| MarkdownStats::from_content(content)),
+            symbols: if options.symbols {
+                count_symbols(path, content)
+            } else {
+                None
+            },
+            complexity: if options.complexity {
+                complexity::decision_points(path, content)
+            } else {
+                None
+            },
+            records,
+            structure,
+            log_levels: count_log_levels(path, content, options),
+            dedupe_lines: options
+                .dedupe_lines
+                .then(|| DedupeStats::from_content(content)),
+            entropy: options
+                .entropy
+                .then(|| ByteHistogram::from_bytes(content.as_bytes())),
+            hash: options
+                .hash
+                .and_then(|algorithm| checksum(content.as_bytes(), algorithm)),
+            meta: None,
+            natural_language: if options.natural_language {
+                detect_language(content)
+            } else {
+                None
+            },
+            vocabulary: options
+                .vocabulary
+                .then(|| Vocabulary::from_content(content)),
+            dialogue: options
+                .dialogue
+                .then(|| DialogueStats::from_content(content)),
+            pages: options.pages.then(|| count_pages(content)),
+            chapters: if options.chapters && is_book(path) {
+                book_chapters(path)
+            } else {
+                None
+            },
+            subtitles: (options.subtitles && is_subtitle(path))
+                .then(|| SubtitleStats::from_content(content)),
+            po: (options.po && is_po(path)).then(|| PoStats::from_content(content)),
+            i18n,
+            line_kinds: if options.line_kinds {
+                language_of(path).map(|language| language.count_lines(content))
+            } else {
+                None
+            },
+            ratios: None,
+        };
+        (analysis, errors)
+    }
+
+    /// Like [`FileAnalysis::with_errors`] for a file only `part` of which is counted,
+    /// such as the visible text of a page; the hash and byte entropy are still of the
+    /// whole `content`, so they describe the file.
+    pub fn of_part_with_errors(
+        path: &Path,
+        content: &str,
+        part: &str,
+        options: &AnalysisOptions,
+    ) -> (Self, Vec<ParseError>) {
+        let (mut analysis, errors) = Self::with_errors(path, part, options);
+        analysis.entropy = options
+            .entropy
+            .then(|| ByteHistogram::from_bytes(content.as_bytes()));
+        analysis.hash = options
+            .hash
+            .and_then(|algorithm| checksum(content.as_bytes(), algorithm));
+        (analysis, errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn add_options<T: Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    }
+}
+
+impl Add for FileAnalysis {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            markdown: add_options(self.markdown, other.markdown),
+            symbols: add_options(self.symbols, other.symbols),
+            complexity: add_options(self.complexity, other.complexity),
+            records: add_options(self.records, other.records),
+            structure: add_options(self.structure, other.structure),
+            log_levels: add_options(self.log_levels, other.log_levels),
+            dedupe_lines: add_options(self.dedupe_lines, other.dedupe_lines),
+            entropy: add_options(self.entropy, other.entropy),
+            hash: match (self.hash, other.hash) {
+                (Some(_), Some(_)) => None,
+                (a, b) => a.or(b),
+            },
+            meta: add_options(self.meta, other.meta),
+            natural_language: add_options(self.natural_language, other.natural_language),
+            vocabulary: add_options(self.vocabulary, other.vocabulary),
+            dialogue: add_options(self.dialogue, other.dialogue),
+            pages: add_options(self.pages, other.pages),
+            chapters: add_options(self.chapters, other.chapters),
+            subtitles: add_options(self.subtitles, other.subtitles),
+            po: add_options(self.po, other.po),
+            i18n: add_options(self.i18n, other.i18n),
+            line_kinds: add_options(self.line_kinds, other.line_kinds),
+            ratios: add_options(self.ratios, other.ratios),
+        }
+    }
+}
+
+impl AddAssign for FileAnalysis {
+    fn add_assign(&mut self, other: Self) {
+        *self = std::mem::take(self) + other;
+    }
+}
+
+impl Sum for FileAnalysis {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a FileAnalysis> for FileAnalysis {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.cloned().sum()
+    }
+}
+
+#[cfg(feature = "symbols")]
+fn symbols_supported(path: &Path) -> bool {
+    crate::symbols::is_supported(path)
+}
+
+#[cfg(not(feature = "symbols"))]
+fn symbols_supported(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(feature = "symbols")]
+fn count_symbols(path: &Path, content: &str) -> Option<SymbolCounts> {
+    crate::symbols::count_symbols(path, content)
+}
+
+#[cfg(not(feature = "symbols"))]
+fn count_symbols(_path: &Path, _content: &str) -> Option<SymbolCounts> {
+    None
+}
+
+#[cfg(feature = "json")]
+fn count_records(content: &str, format: RecordFormat) -> (Option<RecordCount>, Vec<ParseError>) {
+    let (count, errors) = crate::records::count_records(content, format);
+    (Some(count), errors)
+}
+
+#[cfg(not(feature = "json"))]
+fn count_records(_content: &str, _format: RecordFormat) -> (Option<RecordCount>, Vec<ParseError>) {
+    (None, Vec::new())
+}
+
+#[cfg(feature = "structure")]
+fn config_structure(path: &Path, content: &str) -> Option<(StructureStats, Vec<ParseError>)> {
+    crate::structure::structure(path, content)
+}
+
+#[cfg(not(feature = "structure"))]
+fn config_structure(_path: &Path, _content: &str) -> Option<(StructureStats, Vec<ParseError>)> {
+    None
+}
+
+#[cfg(feature = "structure")]
+fn locale_strings(path: &Path, content: &str) -> Option<Result<I18nStats, ParseError>> {
+    Some(crate::i18n::locale_strings(path, content))
+}
+
+#[cfg(not(feature = "structure"))]
+fn locale_strings(_path: &Path, _content: &str) -> Option<Result<I18nStats, ParseError>> {
+    None
+}
+
+#[cfg(feature = "logs")]
+fn count_log_levels(path: &Path, content: &str, options: &AnalysisOptions) -> Option<LogLevels> {
+    let matcher = options.log_levels.as_ref()?;
+    is_log(path).then(|| matcher.count(content))
+}
+
+#[cfg(not(feature = "logs"))]
+fn count_log_levels(_path: &Path, _content: &str, _options: &AnalysisOptions) -> Option<LogLevels> {
+    None
+}
+
+#[cfg(feature = "checksum")]
+fn checksum(bytes: &[u8], algorithm: HashAlgorithm) -> Option<Checksum> {
+    Some(crate::checksum::checksum(bytes, algorithm))
+}
+
+#[cfg(not(feature = "checksum"))]
+fn checksum(_bytes: &[u8], _algorithm: HashAlgorithm) -> Option<Checksum> {
+    None
+}
+
+#[cfg(feature = "language-detection")]
+fn detect_language(content: &str) -> Option<NaturalLanguages> {
+    Some(NaturalLanguages::single(crate::natural::detect(content)))
+}
+
+#[cfg(not(feature = "language-detection"))]
+fn detect_language(_content: &str) -> Option<NaturalLanguages> {
+    None
+}
+
+#[cfg(feature = "office")]
+fn book_chapters(path: &Path) -> Option<Chapters> {
+    crate::documents::chapters(path).ok()
+}
+
+#[cfg(not(feature = "office"))]
+fn book_chapters(_path: &Path) -> Option<Chapters> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_only_the_analyzers_asked_for() {
+        let options = AnalysisOptions {
+            markdown: true,
+            ..AnalysisOptions::default()
+        };
+        assert!(options.applies_to(Path::new("README.md")));
+        assert!(!options.applies_to(Path::new("main.rs")));
+
+        let analysis = FileAnalysis::of(Path::new("README.md"), "# Title\n", &options);
+        assert_eq!(analysis.markdown.unwrap().headings, 1);
+        assert_eq!(analysis.symbols, None);
+        assert!(FileAnalysis::of(Path::new("a.txt"), "# Title\n", &options).is_empty());
+    }
+
+    #[test]
+    fn sums_keep_what_any_file_had() {
+        let markdown = FileAnalysis {
+            markdown: Some(MarkdownStats {
+                words: 2,
+                ..MarkdownStats::default()
+            }),
+            ..FileAnalysis::default()
+        };
+        let symbols = FileAnalysis {
+            symbols: Some(SymbolCounts {
+                functions: 1,
+                ..SymbolCounts::default()
+            }),
+            ..FileAnalysis::default()
+        };
+        let total: FileAnalysis = [markdown.clone(), symbols, markdown].iter().sum();
+        assert_eq!(total.markdown.unwrap().words, 4);
+        assert_eq!(total.symbols.unwrap().functions, 1);
+    }
+}
@@ -0,0 +1,225 @@
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::counter::{
+    count_directory_iter, count_directory_observed, count_file_chunked, count_from_reader_limited,
+    Count, CountObserver, EntryOrder, FileEntry, FilterConfig, SpecialFileLimits, SymlinkPolicy,
+    DEFAULT_CHUNK_SIZE,
+};
+use crate::git::GitSelection;
+
+/// The counts for one path: a single file, or every counted file under a directory.
+pub struct PathCount {
+    pub path: PathBuf,
+    pub total: Count,
+    pub is_directory: bool,
+    /// Per-file counts, by path unless [`Counter::order`] asks otherwise; a single entry
+    /// for a file
+    pub files: Vec<FileEntry>,
+}
+
+/// Counts files and directories with the same filters as the command line.
+///
+/// ```no_run
+/// use ewc::Counter;
+///
+/// let result = Counter::new()
+///     .hidden(true)
+///     .exclude("*.md")
+///     .max_depth(3)
+///     .count_path("src")?;
+/// println!("{} lines in {} files", result.total.lines, result.files.len());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Counter {
+    config: FilterConfig,
+}
+
+impl Counter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Includes hidden files and directories (`--all`).
+    pub fn hidden(mut self, include: bool) -> Self {
+        self.config.include_hidden = include;
+        self
+    }
+
+    /// Counts only hidden files and those in hidden directories (`--only-hidden`).
+    pub fn only_hidden(mut self, only: bool) -> Self {
+        self.config.only_hidden = only;
+        self
+    }
+
+    /// Skips files matching a glob pattern (`--exclude`); may be called repeatedly.
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.config.exclude_patterns.push(pattern.into());
+        self
+    }
+
+    /// Only counts files matching a glob pattern (`--include`); may be called repeatedly.
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.config.include_patterns.push(pattern.into());
+        self
+    }
+
+    /// Counts generated and vendored files too (`--include-generated`).
+    pub fn include_generated(mut self, include: bool) -> Self {
+        self.config.include_generated = include;
+        self
+    }
+
+    /// Only descends `depth` directories below the counted path (1 = its direct children).
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.config.max_depth = Some(depth);
+        self
+    }
+
+    /// Reads named pipes, sockets and devices in directories within `limits`
+    /// (`--special-files`) instead of reporting them as skipped.
+    pub fn special_files(mut self, limits: SpecialFileLimits) -> Self {
+        self.config.special_files = Some(limits);
+        self
+    }
+
+    /// What to do with symbolic links in directories (`--symlinks`); skipped by default.
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.config.symlinks = policy;
+        self
+    }
+
+    /// The order of a directory's files (`--order`); by path by default.
+    pub fn order(mut self, order: EntryOrder) -> Self {
+        self.config.order = order;
+        self
+    }
+
+    /// Stops walking and counting directories at `deadline` (`--timeout`), leaving the
+    /// rest out as timed out.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.config.deadline = Some(deadline);
+        self
+    }
+
+    /// Limits directory counts to files git selects (`--git`, `--changed`, `--staged`).
+    pub fn git(mut self, selection: GitSelection) -> Self {
+        self.config.git_selection = Some(selection);
+        self
+    }
+
+    /// Reads pipes, devices and readers `size` bytes at a time (`--chunk-size`);
+    /// [`DEFAULT_CHUNK_SIZE`] by default.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.config.chunk_size = Some(size);
+        self
+    }
+
+    pub fn config(&self) -> &FilterConfig {
+        &self.config
+    }
+
+    pub fn count_path(&self, path: impl AsRef<Path>) -> io::Result<PathCount> {
+        self.count_path_observed(path, &())
+    }
+
+    /// Counts like [`Counter::count_path`], reporting directory progress to `observer`.
+    pub fn count_path_observed(
+        &self,
+        path: impl AsRef<Path>,
+        observer: &dyn CountObserver,
+    ) -> io::Result<PathCount> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            let (files, total) = count_directory_observed(path, &self.config, observer)?;
+            Ok(PathCount {
+                path: path.to_path_buf(),
+                total,
+                is_directory: true,
+                files,
+            })
+        } else {
+            let total = count_file_chunked(path, self.read_chunk_size())?;
+            Ok(PathCount {
+                path: path.to_path_buf(),
+                total,
+                is_directory: false,
+                files: vec![FileEntry {
+                    path: path.to_path_buf(),
+                    count: total,
+                }],
+            })
+        }
+    }
+
+    /// Counts the files under a directory one at a time; see [`count_directory_iter`].
+    pub fn count_iter(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> io::Result<impl Iterator<Item = io::Result<FileEntry>>> {
+        count_directory_iter(path.as_ref(), &self.config)
+    }
+
+    pub fn count_reader(&self, reader: impl Read) -> io::Result<Count> {
+        let chunk_size = self.read_chunk_size();
+        count_from_reader_limited(reader, chunk_size, None, Duration::MAX, |_| {})
+            .map(|(count, _)| count)
+    }
+
+    fn read_chunk_size(&self) -> usize {
+        self.config.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn builder_sets_filters() {
+        let counter = Counter::new()
+            .hidden(true)
+            .exclude("*.md")
+            .include("*.rs")
+            .max_depth(2)
+            .chunk_size(4096);
+        let config = counter.config();
+        assert!(config.include_hidden);
+        assert_eq!(config.exclude_patterns, vec!["*.md"]);
+        assert_eq!(config.include_patterns, vec!["*.rs"]);
+        assert_eq!(config.max_depth, Some(2));
+        assert_eq!(config.chunk_size, Some(4096));
+        let count = counter.count_reader("one two\nthree".as_bytes()).unwrap();
+        assert_eq!(count, Count::from_content("one two\nthree"));
+    }
+
+    #[test]
+    fn counts_directories_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(dir.path().join("notes.md"), "# Notes\n").unwrap();
+        fs::write(dir.path().join(".hidden.rs"), "fn h() {}\n").unwrap();
+
+        let result = Counter::new()
+            .exclude("*.md")
+            .count_path(dir.path())
+            .unwrap();
+        assert!(result.is_directory);
+        assert_eq!(result.files.len(), 1);
+        assert_eq!(result.total.lines, 1);
+
+        let result = Counter::new().hidden(true).count_path(dir.path()).unwrap();
+        assert_eq!(result.files.len(), 3);
+
+        let first = Counter::new().count_iter(dir.path()).unwrap().next();
+        assert_eq!(first.unwrap().unwrap().path, dir.path().join("a.rs"));
+
+        let result = Counter::new().count_path(dir.path().join("a.rs")).unwrap();
+        assert!(!result.is_directory);
+        assert_eq!(result.total.words, 3);
+        assert_eq!(result.files[0].path, dir.path().join("a.rs"));
+    }
+}
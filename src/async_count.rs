@@ -0,0 +1,185 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::{self, JoinSet};
+
+use crate::counter::{
+    count_read, read_text, skip_generated, split_special_files, walk_directory, Count,
+    CountObserver, FileEntry, FilterConfig,
+};
+use crate::documents;
+
+/// How many files [`count_directory_async`] reads at once unless told otherwise
+pub const DEFAULT_CONCURRENCY: usize = 64;
+
+fn content_count(content: Vec<u8>) -> io::Result<Count> {
+    let content =
+        String::from_utf8(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Count::from_content(&content))
+}
+
+/// Counts a file like [`crate::counter::count_file`] without blocking the runtime.
+pub async fn count_file_async(path: impl AsRef<Path>) -> io::Result<Count> {
+    let path = path.as_ref().to_path_buf();
+    if documents::is_document(&path) {
+        // Unpacking an archive is blocking work
+        let content = task::spawn_blocking(move || documents::read_to_string(&path))
+            .await
+            .map_err(io::Error::other)??;
+        return Ok(Count::from_content(&content));
+    }
+    content_count(tokio::fs::read(path).await?)
+}
+
+/// Counts a directory like [`crate::counter::count_directory_detailed`], reading at most
+/// `concurrency` files at a time. Must be called from within a tokio runtime. Named pipes,
+/// sockets and devices are left out.
+pub async fn count_directory_async(
+    path: impl AsRef<Path>,
+    config: &FilterConfig,
+    concurrency: usize,
+) -> io::Result<(Vec<FileEntry>, Count)> {
+    count_directory_async_observed(path, config, concurrency, &()).await
+}
+
+/// Like [`count_directory_async`], passing the files it reads, skips or cannot read to
+/// `observer`, as [`crate::counter::count_directory_observed`] does.
+pub async fn count_directory_async_observed(
+    path: impl AsRef<Path>,
+    config: &FilterConfig,
+    concurrency: usize,
+    observer: &dyn CountObserver,
+) -> io::Result<(Vec<FileEntry>, Count)> {
+    // Walking and .gitattributes lookups are blocking filesystem work
+    let root = path.as_ref().to_path_buf();
+    let walk_config = config.clone();
+    let walked = task::spawn_blocking(move || walk_directory(&root, &walk_config))
+        .await
+        .map_err(io::Error::other)??;
+    let (file_paths, _special) = split_special_files(walked, config, observer);
+    let root = path.as_ref().to_path_buf();
+    let walk_config = config.clone();
+    let candidates = task::spawn_blocking(move || skip_generated(&root, file_paths, &walk_config))
+        .await
+        .map_err(io::Error::other)?;
+    for (file_path, _) in &candidates {
+        observer.on_file_discovered(file_path);
+    }
+
+    let limit = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut reads = JoinSet::new();
+    for (file_path, check_content) in candidates {
+        let permit = Arc::clone(&limit)
+            .acquire_owned()
+            .await
+            .map_err(io::Error::other)?;
+        // Documents are unpacked, so every read goes through the blocking pool
+        reads.spawn_blocking(move || {
+            let _permit = permit;
+            let content = read_text(&file_path);
+            (file_path, check_content, content)
+        });
+    }
+
+    let mut entries = Vec::new();
+    while let Some(read) = reads.join_next().await {
+        let (file_path, check_content, content) = read.map_err(io::Error::other)?;
+        entries.extend(count_read(file_path, check_content, content, observer));
+    }
+
+    // Sort for deterministic output
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    let total = entries.iter().map(|e| e.count).sum();
+    Ok((entries, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::count_directory_detailed;
+    use std::fs;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn counts_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "one two\nthree\n").unwrap();
+        let count = block_on(count_file_async(&path)).unwrap();
+        assert_eq!(count, Count::from_content("one two\nthree\n"));
+
+        fs::write(&path, [0xff, 0xfe]).unwrap();
+        let error = block_on(count_file_async(&path)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn counts_directory_like_sync_version() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        for i in 0..10 {
+            fs::write(dir.path().join(format!("sub/{i}.txt")), "x y\n".repeat(i)).unwrap();
+        }
+        fs::write(dir.path().join("gen.rs"), "// @generated\n").unwrap();
+        let config = FilterConfig::default();
+
+        let (entries, total) = block_on(count_directory_async(dir.path(), &config, 2)).unwrap();
+        let (expected, expected_total) = count_directory_detailed(dir.path(), &config).unwrap();
+        assert_eq!(total, expected_total);
+        let paths: Vec<_> = entries.iter().map(|e| &e.path).collect();
+        let expected_paths: Vec<_> = expected.iter().map(|e| &e.path).collect();
+        assert_eq!(paths, expected_paths);
+    }
+
+    #[test]
+    fn reports_files_it_leaves_out() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        fs::write(dir.path().join("b.bin"), [0xff, 0xfe]).unwrap();
+        fs::write(dir.path().join(".hidden"), "two\n").unwrap();
+        let skipped = crate::counter::SkippedFiles::default();
+        let config = FilterConfig::default();
+
+        let (entries, total) = block_on(count_directory_async_observed(
+            dir.path(),
+            &config,
+            2,
+            &skipped,
+        ))
+        .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(total.lines, 1);
+        let summary = skipped.summary();
+        assert_eq!((summary.binary, summary.hidden), (1, 1));
+    }
+
+    #[cfg(feature = "office")]
+    #[test]
+    fn counts_documents() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        let doc = dir.path().join("doc.docx");
+        let mut archive = zip::ZipWriter::new(fs::File::create(&doc).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        archive.start_file("word/document.xml", options).unwrap();
+        let body = "<w:document><w:body><w:p><w:r><w:t>Dear reader</w:t></w:r></w:p></w:body></w:document>";
+        archive.write_all(body.as_bytes()).unwrap();
+        archive.finish().unwrap();
+        let config = FilterConfig::default();
+
+        let (entries, total) = block_on(count_directory_async(dir.path(), &config, 2)).unwrap();
+        let (_, expected_total) = count_directory_detailed(dir.path(), &config).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(total, expected_total);
+        assert_eq!(block_on(count_file_async(&doc)).unwrap().words, 2);
+    }
+}
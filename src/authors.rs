@@ -0,0 +1,109 @@
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+use crate::git;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorLines {
+    pub author: String,
+    pub lines: usize,
+}
+
+/// Sorts authors by line count, most lines first, breaking ties by name.
+fn leaderboard(totals: HashMap<String, usize>) -> Vec<AuthorLines> {
+    let mut authors: Vec<AuthorLines> = totals
+        .into_iter()
+        .map(|(author, lines)| AuthorLines { author, lines })
+        .collect();
+    authors.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.author.cmp(&b.author)));
+    authors
+}
+
+/// Attributes the current lines of `files` to authors with `git blame`.
+///
+/// Files git cannot blame, such as untracked ones, are skipped. If none of the
+/// files can be blamed, the first error is returned instead.
+pub fn count_by_author(files: &[PathBuf]) -> io::Result<Vec<AuthorLines>> {
+    // One blame per file, in parallel like the counting pipeline
+    let results: Vec<io::Result<HashMap<String, usize>>> = files
+        .par_iter()
+        .map(|file| git::blame_authors(file))
+        .collect();
+
+    let mut totals: HashMap<String, usize> = HashMap::new();
+    let mut first_error = None;
+    let mut blamed = 0;
+    for result in results {
+        match result {
+            Ok(authors) => {
+                blamed += 1;
+                for (author, lines) in authors {
+                    *totals.entry(author).or_default() += lines;
+                }
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match first_error {
+        Some(e) if blamed == 0 => Err(e),
+        _ => Ok(leaderboard(totals)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{git, init_repo};
+
+    #[test]
+    fn leaderboard_sorted_by_lines_then_name() {
+        let totals = HashMap::from([
+            ("Carol".to_string(), 5),
+            ("Alice".to_string(), 10),
+            ("Bob".to_string(), 5),
+        ]);
+        let authors: Vec<(String, usize)> = leaderboard(totals)
+            .into_iter()
+            .map(|a| (a.author, a.lines))
+            .collect();
+        assert_eq!(
+            authors,
+            vec![
+                ("Alice".to_string(), 10),
+                ("Bob".to_string(), 5),
+                ("Carol".to_string(), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn count_by_author_skips_untracked_files() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("a.txt"), "one\ntwo\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+        std::fs::write(dir.path().join("untracked.txt"), "three\n").unwrap();
+
+        let authors =
+            count_by_author(&[dir.path().join("a.txt"), dir.path().join("untracked.txt")]).unwrap();
+        assert_eq!(
+            authors,
+            vec![AuthorLines {
+                author: "ewc".to_string(),
+                lines: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn count_by_author_outside_repository_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        assert!(count_by_author(&[dir.path().join("a.txt")]).is_err());
+    }
+}
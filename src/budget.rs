@@ -0,0 +1,183 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::counter::{Count, FileEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Lines,
+    Words,
+    Bytes,
+    MaxLineLength,
+}
+
+impl Metric {
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Lines => "lines",
+            Metric::Words => "words",
+            Metric::Bytes => "bytes",
+            Metric::MaxLineLength => "max-line-length",
+        }
+    }
+
+    pub fn value(self, count: &Count) -> usize {
+        match self {
+            Metric::Lines => count.lines,
+            Metric::Words => count.words,
+            Metric::Bytes => count.bytes,
+            Metric::MaxLineLength => count.max_line_length,
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lines" => Ok(Metric::Lines),
+            "words" => Ok(Metric::Words),
+            "bytes" => Ok(Metric::Bytes),
+            "max-line-length" => Ok(Metric::MaxLineLength),
+            _ => Err(format!(
+                "unknown metric '{s}' (expected lines, words, bytes or max-line-length)"
+            )),
+        }
+    }
+}
+
+/// A per-file limit such as `lines=500`, also written `lines>500`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Threshold {
+    pub metric: Metric,
+    pub limit: usize,
+}
+
+impl FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (metric, limit) = s
+            .split_once(['=', '>'])
+            .ok_or_else(|| format!("invalid limit '{s}' (expected METRIC=N or METRIC>N)"))?;
+        let metric = metric.trim().parse()?;
+        let limit = limit
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid number '{}' in '{s}'", limit.trim()))?;
+        Ok(Threshold { metric, limit })
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.metric.name(), self.limit)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub path: PathBuf,
+    pub threshold: Threshold,
+    pub actual: usize,
+}
+
+pub fn check_entries(entries: &[FileEntry], thresholds: &[Threshold]) -> Vec<Violation> {
+    entries
+        .iter()
+        .flat_map(|entry| {
+            thresholds.iter().filter_map(|&threshold| {
+                let actual = threshold.metric.value(&entry.count);
+                (actual > threshold.limit).then(|| Violation {
+                    path: entry.path.clone(),
+                    threshold,
+                    actual,
+                })
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, lines: usize, max_line_length: usize) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            count: Count {
+                lines,
+                words: 0,
+                bytes: 0,
+                max_line_length,
+            },
+        }
+    }
+
+    #[test]
+    fn parse_threshold() {
+        let threshold: Threshold = "lines=500".parse().unwrap();
+        assert_eq!(threshold.metric, Metric::Lines);
+        assert_eq!(threshold.limit, 500);
+    }
+
+    #[test]
+    fn parse_threshold_with_greater_than() {
+        let threshold: Threshold = "words>2000".parse().unwrap();
+        assert_eq!(threshold.metric, Metric::Words);
+        assert_eq!(threshold.limit, 2000);
+    }
+
+    #[test]
+    fn parse_threshold_max_line_length() {
+        let threshold: Threshold = "max-line-length=120".parse().unwrap();
+        assert_eq!(threshold.metric, Metric::MaxLineLength);
+        assert_eq!(threshold.limit, 120);
+    }
+
+    #[test]
+    fn parse_threshold_rejects_unknown_metric() {
+        assert!("pages=5".parse::<Threshold>().is_err());
+    }
+
+    #[test]
+    fn parse_threshold_rejects_missing_limit() {
+        assert!("lines".parse::<Threshold>().is_err());
+        assert!("lines=many".parse::<Threshold>().is_err());
+    }
+
+    #[test]
+    fn threshold_display_round_trips() {
+        let threshold: Threshold = "bytes=1024".parse().unwrap();
+        assert_eq!(threshold.to_string(), "bytes=1024");
+    }
+
+    #[test]
+    fn check_entries_reports_only_exceeding_files() {
+        let entries = vec![entry("small.rs", 10, 40), entry("big.rs", 600, 80)];
+        let thresholds = vec!["lines=500".parse().unwrap()];
+        let violations = check_entries(&entries, &thresholds);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, PathBuf::from("big.rs"));
+        assert_eq!(violations[0].actual, 600);
+    }
+
+    #[test]
+    fn check_entries_limit_is_inclusive() {
+        let entries = vec![entry("exact.rs", 500, 0)];
+        let thresholds = vec!["lines=500".parse().unwrap()];
+        assert!(check_entries(&entries, &thresholds).is_empty());
+    }
+
+    #[test]
+    fn check_entries_multiple_thresholds() {
+        let entries = vec![entry("wide.rs", 600, 200)];
+        let thresholds = vec![
+            "lines=500".parse().unwrap(),
+            "max-line-length=120".parse().unwrap(),
+        ];
+        assert_eq!(check_entries(&entries, &thresholds).len(), 2);
+    }
+}
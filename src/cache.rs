@@ -0,0 +1,490 @@
+use rayon::prelude::*;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, trace};
+
+use crate::counter::{
+    count_special_files, order_entries, read_text, skip_generated_observed,
+    skipped_generated_content, split_special_files, timed_out, walk_directory, Count,
+    CountObserver, FileEntry, FilterConfig,
+};
+use crate::generated::is_generated_content;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS files (
+    path TEXT PRIMARY KEY,
+    options TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    mtime INTEGER NOT NULL,
+    readable INTEGER NOT NULL,
+    generated INTEGER NOT NULL,
+    lines INTEGER NOT NULL,
+    words INTEGER NOT NULL,
+    bytes INTEGER NOT NULL,
+    max_line_length INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS runs (
+    path TEXT PRIMARY KEY,
+    files INTEGER NOT NULL,
+    lines INTEGER NOT NULL,
+    words INTEGER NOT NULL,
+    bytes INTEGER NOT NULL,
+    max_line_length INTEGER NOT NULL
+);
+";
+
+/// Files modified this recently are not cached, since a further write within the
+/// same timestamp tick would go unnoticed
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+fn cache_error(e: rusqlite::Error) -> io::Error {
+    io::Error::other(format!("cache: {e}"))
+}
+
+/// Where the cache lives: `$XDG_CACHE_HOME/ewc`, `~/.cache/ewc` or `%LOCALAPPDATA%\ewc`.
+pub fn default_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(dir.join("ewc").join("counts.sqlite"))
+}
+
+/// Deletes the cache file, returning whether there was one.
+pub fn clear(path: &Path) -> io::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// What counting a file produced, valid while its size and mtime stay the same.
+#[derive(Debug, Clone, PartialEq)]
+struct CachedFile {
+    size: u64,
+    mtime: i64,
+    /// `None` for files that could not be read as UTF-8 text
+    count: Option<Count>,
+    /// Whether the content carries a generated-code marker
+    generated: bool,
+}
+
+fn stamp(path: &Path) -> io::Result<(u64, i64)> {
+    let metadata = fs::metadata(path)?;
+    let mtime = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_err(io::Error::other)?;
+    let mtime = i64::try_from(mtime.as_nanos()).map_err(io::Error::other)?;
+    Ok((metadata.len(), mtime))
+}
+
+fn is_racy(mtime: i64) -> bool {
+    let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) else {
+        return true;
+    };
+    let threshold = now.saturating_sub(RACY_WINDOW).as_nanos();
+    i64::try_from(threshold).is_ok_and(|threshold| mtime >= threshold)
+}
+
+/// Reads a file for the cache; only read errors, not files that are not UTF-8 text,
+/// are errors, since those may go away without the file changing.
+fn read_file(path: &Path, size: u64, mtime: i64) -> io::Result<CachedFile> {
+    let content = read_text(path)?;
+    Ok(CachedFile {
+        size,
+        mtime,
+        count: content.as_deref().map(Count::from_content),
+        generated: content.as_deref().is_some_and(is_generated_content),
+    })
+}
+
+/// A file's counted entry, if it is counted, numbered by when it finished, and its new
+/// cache record, if it needs storing.
+type Counted = (Option<(usize, FileEntry)>, Option<(PathBuf, CachedFile)>);
+
+/// Per-file counts kept on disk between runs, keyed by absolute path, size and mtime.
+pub struct Cache {
+    conn: Connection,
+    options: String,
+}
+
+impl Cache {
+    /// Opens (or creates) the cache. Entries recorded under different `options`, the
+    /// settings that change how a file is counted, are treated as missing.
+    pub fn open(path: &Path, options: &str) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path).map_err(cache_error)?;
+        conn.execute_batch(SCHEMA).map_err(cache_error)?;
+        Ok(Self {
+            conn,
+            options: options.to_string(),
+        })
+    }
+
+    fn entries_under(&self, root: &Path) -> io::Result<HashMap<PathBuf, CachedFile>> {
+        // Every path under the root sorts between "root/" and "root0"
+        let prefix = root.join("").display().to_string();
+        let mut end = prefix.clone();
+        end.pop();
+        end.push(char::from(std::path::MAIN_SEPARATOR as u8 + 1));
+
+        let mut query = self
+            .conn
+            .prepare(
+                "SELECT path, size, mtime, readable, generated, lines, words, bytes, max_line_length
+                 FROM files WHERE path >= ?1 AND path < ?2 AND options = ?3",
+            )
+            .map_err(cache_error)?;
+        let rows = query
+            .query_map(params![prefix, end, self.options], |row| {
+                let readable: bool = row.get(3)?;
+                let count = Count {
+                    lines: row.get::<_, i64>(5)? as usize,
+                    words: row.get::<_, i64>(6)? as usize,
+                    bytes: row.get::<_, i64>(7)? as usize,
+                    max_line_length: row.get::<_, i64>(8)? as usize,
+                };
+                Ok((
+                    PathBuf::from(row.get::<_, String>(0)?),
+                    CachedFile {
+                        size: row.get::<_, i64>(1)? as u64,
+                        mtime: row.get(2)?,
+                        count: readable.then_some(count),
+                        generated: row.get(4)?,
+                    },
+                ))
+            })
+            .map_err(cache_error)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(cache_error)
+    }
+
+    fn store(&mut self, files: &[(PathBuf, CachedFile)]) -> io::Result<()> {
+        let tx = self.conn.transaction().map_err(cache_error)?;
+        {
+            let mut insert = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO files
+                     (path, options, size, mtime, readable, generated, lines, words, bytes, max_line_length)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                )
+                .map_err(cache_error)?;
+            for (path, file) in files {
+                let count = file.count.unwrap_or_default();
+                insert
+                    .execute(params![
+                        path.display().to_string(),
+                        self.options,
+                        file.size as i64,
+                        file.mtime,
+                        file.count.is_some(),
+                        file.generated,
+                        count.lines as i64,
+                        count.words as i64,
+                        count.bytes as i64,
+                        count.max_line_length as i64
+                    ])
+                    .map_err(cache_error)?;
+            }
+        }
+        tx.commit().map_err(cache_error)
+    }
+
+    /// The total and file count of `path`, an absolute path, when `--delta` last
+    /// counted it, or `None` if it never did.
+    pub fn last_run(&self, path: &Path) -> io::Result<Option<(Count, usize)>> {
+        let mut query = self
+            .conn
+            .prepare("SELECT files, lines, words, bytes, max_line_length FROM runs WHERE path = ?1")
+            .map_err(cache_error)?;
+        let mut rows = query
+            .query_map(params![path.display().to_string()], |row| {
+                let count = Count {
+                    lines: row.get::<_, i64>(1)? as usize,
+                    words: row.get::<_, i64>(2)? as usize,
+                    bytes: row.get::<_, i64>(3)? as usize,
+                    max_line_length: row.get::<_, i64>(4)? as usize,
+                };
+                Ok((count, row.get::<_, i64>(0)? as usize))
+            })
+            .map_err(cache_error)?;
+        rows.next().transpose().map_err(cache_error)
+    }
+
+    /// Keeps the total and file count of `path` for the next [`Cache::last_run`].
+    pub fn record_last_run(&self, path: &Path, count: &Count, files: usize) -> io::Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO runs (path, files, lines, words, bytes, max_line_length)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    path.display().to_string(),
+                    files as i64,
+                    count.lines as i64,
+                    count.words as i64,
+                    count.bytes as i64,
+                    count.max_line_length as i64
+                ],
+            )
+            .map_err(cache_error)?;
+        Ok(())
+    }
+
+    /// Counts a directory like [`crate::counter::count_directory_detailed`], only reading
+    /// files whose size or mtime changed since they were last cached.
+    pub fn count_directory(
+        &mut self,
+        path: &Path,
+        config: &FilterConfig,
+    ) -> io::Result<(Vec<FileEntry>, Count)> {
+        self.count_directory_observed(path, config, &())
+    }
+
+    /// Like [`Cache::count_directory`], reporting files as they are counted, and those
+    /// that could not be read or are not text, to `observer`. Only `on_file_counted`,
+    /// `on_error` and `on_file_not_text` are called for regular files. Special files
+    /// are never cached.
+    pub fn count_directory_observed(
+        &mut self,
+        path: &Path,
+        config: &FilterConfig,
+        observer: &dyn CountObserver,
+    ) -> io::Result<(Vec<FileEntry>, Count)> {
+        let started = Instant::now();
+        let root = fs::canonicalize(path)?;
+        let (files, special) = split_special_files(walk_directory(path, config)?, config, observer);
+        let candidates = skip_generated_observed(path, files, config, observer);
+        let known = self.entries_under(&root)?;
+        // Numbers files as they finish, for `EntryOrder::Completion`
+        let finished = AtomicUsize::new(0);
+
+        let results: Vec<Counted> = candidates
+            .into_par_iter()
+            .filter(|(file_path, _)| !timed_out(file_path, config, observer))
+            .filter_map(|(file_path, check_content)| {
+                let read = stamp(&file_path).and_then(|(size, mtime)| {
+                    let relative = file_path.strip_prefix(path).unwrap_or(&file_path);
+                    let key = root.join(relative);
+                    match known.get(&key) {
+                        Some(cached) if cached.size == size && cached.mtime == mtime => {
+                            trace!(path = %file_path.display(), "cache hit");
+                            Ok((key, cached.clone(), false))
+                        }
+                        _ => Ok((key, read_file(&file_path, size, mtime)?, true)),
+                    }
+                });
+                let (key, file, changed) = match read {
+                    Ok(read) => read,
+                    Err(e) => {
+                        observer.on_error(&file_path, &e);
+                        return None;
+                    }
+                };
+                let mtime = file.mtime;
+                let entry = match &file.count {
+                    Some(_) if check_content && file.generated => {
+                        skipped_generated_content(&file_path, observer);
+                        None
+                    }
+                    Some(count) => {
+                        let entry = FileEntry {
+                            path: file_path,
+                            count: *count,
+                        };
+                        observer.on_file_counted(&entry);
+                        Some((finished.fetch_add(1, Ordering::Relaxed), entry))
+                    }
+                    None => {
+                        observer.on_file_not_text(&file_path);
+                        None
+                    }
+                };
+                let update = (changed && !is_racy(mtime)).then_some((key, file));
+                Some((entry, update))
+            })
+            .collect();
+
+        let (entries, updates): (Vec<_>, Vec<_>) = results.into_iter().unzip();
+        let mut entries: Vec<(usize, FileEntry)> = entries.into_iter().flatten().collect();
+        entries.extend(
+            count_special_files(special, config, observer)
+                .into_iter()
+                .map(|entry| (finished.fetch_add(1, Ordering::Relaxed), entry)),
+        );
+        let updates: Vec<(PathBuf, CachedFile)> = updates.into_iter().flatten().collect();
+        debug!(
+            counted = entries.len(),
+            stored = updates.len(),
+            elapsed = ?started.elapsed(),
+            "counted directory with cache"
+        );
+        if !updates.is_empty() {
+            self.store(&updates)?;
+        }
+
+        let entries = order_entries(entries, config.order);
+        let total = entries.iter().map(|e| e.count).sum();
+        Ok((entries, total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::count_directory_detailed;
+    use std::fs::File;
+    use std::sync::Mutex;
+
+    /// Writes a file with a fixed mtime old enough to be cached.
+    fn write_old(path: &Path, content: &str) {
+        fs::write(path, content).unwrap();
+        let old = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+    }
+
+    #[test]
+    fn reuses_counts_while_size_and_mtime_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = dir.path().join("tree");
+        fs::create_dir(&tree).unwrap();
+        write_old(&tree.join("a.txt"), "one two\n");
+        write_old(&tree.join("b.txt"), "three\n");
+        let config = FilterConfig::default();
+        let mut cache = Cache::open(&dir.path().join("cache.sqlite"), "v1").unwrap();
+
+        let (first, _) = cache.count_directory(&tree, &config).unwrap();
+        let (expected, _) = count_directory_detailed(&tree, &config).unwrap();
+        let lines = |entries: &[FileEntry]| -> Vec<usize> {
+            entries.iter().map(|e| e.count.lines).collect()
+        };
+        assert_eq!(lines(&first), lines(&expected));
+
+        // Same size and mtime: the stale cached count is used without reading the file
+        write_old(&tree.join("a.txt"), "one\ntwo\n");
+        let (second, total) = cache.count_directory(&tree, &config).unwrap();
+        assert_eq!(second[0].count.lines, 1);
+        assert_eq!(total.lines, 2);
+
+        // A different size is noticed
+        write_old(&tree.join("a.txt"), "one\ntwo\nthree\n");
+        let (third, _) = cache.count_directory(&tree, &config).unwrap();
+        assert_eq!(third[0].count.lines, 3);
+    }
+
+    #[test]
+    fn reports_cached_and_read_files_as_counted() {
+        struct Counted(Mutex<Vec<PathBuf>>);
+        impl CountObserver for Counted {
+            fn on_file_counted(&self, entry: &FileEntry) {
+                self.0.lock().unwrap().push(entry.path.clone());
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let tree = dir.path().join("tree");
+        fs::create_dir(&tree).unwrap();
+        write_old(&tree.join("a.txt"), "one\n");
+        write_old(&tree.join("b.txt"), "two\n");
+        let mut cache = Cache::open(&dir.path().join("cache.sqlite"), "v1").unwrap();
+        // Read the first time, cached the second
+        for _ in 0..2 {
+            let counted = Counted(Mutex::new(Vec::new()));
+            cache
+                .count_directory_observed(&tree, &FilterConfig::default(), &counted)
+                .unwrap();
+            let mut counted = counted.0.into_inner().unwrap();
+            counted.sort();
+            assert_eq!(counted, [tree.join("a.txt"), tree.join("b.txt")]);
+        }
+    }
+
+    #[test]
+    fn other_options_miss_and_recent_files_are_not_stored() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = dir.path().join("tree");
+        fs::create_dir(&tree).unwrap();
+        write_old(&tree.join("old.txt"), "old\n");
+        fs::write(tree.join("new.txt"), "new\n").unwrap();
+        let cache_path = dir.path().join("cache.sqlite");
+        let config = FilterConfig::default();
+
+        let mut cache = Cache::open(&cache_path, "v1").unwrap();
+        cache.count_directory(&tree, &config).unwrap();
+        let root = fs::canonicalize(&tree).unwrap();
+        let known = cache.entries_under(&root).unwrap();
+        assert!(known.contains_key(&root.join("old.txt")));
+        assert!(!known.contains_key(&root.join("new.txt")));
+
+        let other = Cache::open(&cache_path, "v2").unwrap();
+        assert!(other.entries_under(&root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn generated_markers_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = dir.path().join("tree");
+        fs::create_dir(&tree).unwrap();
+        write_old(&tree.join("gen.rs"), "// @generated\nfn x() {}\n");
+        write_old(&tree.join("lib.rs"), "fn y() {}\n");
+        let mut cache = Cache::open(&dir.path().join("cache.sqlite"), "v1").unwrap();
+
+        for _ in 0..2 {
+            let (entries, _) = cache
+                .count_directory(&tree, &FilterConfig::default())
+                .unwrap();
+            assert_eq!(entries.len(), 1);
+        }
+        let config = FilterConfig {
+            include_generated: true,
+            ..FilterConfig::default()
+        };
+        assert_eq!(cache.count_directory(&tree, &config).unwrap().0.len(), 2);
+    }
+
+    #[test]
+    fn keeps_the_last_run_of_each_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(&dir.path().join("cache.sqlite"), "v1").unwrap();
+        let src = Path::new("/work/src");
+        assert_eq!(cache.last_run(src).unwrap(), None);
+
+        let count = Count {
+            lines: 12,
+            words: 30,
+            bytes: 200,
+            max_line_length: 40,
+        };
+        cache.record_last_run(src, &count, 3).unwrap();
+        cache
+            .record_last_run(src, &Count { lines: 14, ..count }, 4)
+            .unwrap();
+        assert_eq!(
+            cache.last_run(src).unwrap(),
+            Some((Count { lines: 14, ..count }, 4))
+        );
+        assert_eq!(cache.last_run(Path::new("/work/docs")).unwrap(), None);
+    }
+
+    #[test]
+    fn clear_removes_cache_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ewc/counts.sqlite");
+        Cache::open(&path, "v1").unwrap();
+        assert!(clear(&path).unwrap());
+        assert!(!clear(&path).unwrap());
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+
+/// The hash functions `--hash` offers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// The name used for JSON keys and CSV columns.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+/// A file's content hash, as shown with `--hash`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: HashAlgorithm,
+    /// Lowercase hex digest
+    pub hex: String,
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm.name(), self.hex)
+    }
+}
+
+/// Hashes `bytes` with `algorithm`.
+#[cfg(feature = "checksum")]
+pub fn checksum(bytes: &[u8], algorithm: HashAlgorithm) -> Checksum {
+    use sha2::{Digest, Sha256};
+
+    let hex = match algorithm {
+        HashAlgorithm::Sha256 => Sha256::digest(bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+        HashAlgorithm::Blake3 => blake3::hash(bytes).to_hex().to_string(),
+    };
+    Checksum { algorithm, hex }
+}
+
+#[cfg(all(test, feature = "checksum"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_match_known_digests() {
+        assert_eq!(
+            checksum(b"", HashAlgorithm::Sha256).to_string(),
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            checksum(b"", HashAlgorithm::Blake3).to_string(),
+            "blake3:af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            checksum(b"hello world\n", HashAlgorithm::Sha256).hex,
+            "a948904f2f0f479b8f8197694b30184b0d2ed1c1cd2a1ec0fb85d299a192a447"
+        );
+    }
+}
@@ -1,62 +1,1006 @@
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::env;
+use std::ffi::OsString;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::budget::{Metric, Threshold};
+use crate::config::{self, Config, DefaultFormat};
+use crate::logs::LevelPattern;
 
 #[derive(Parser, Debug)]
 #[command(
     name = "ewc",
     about = "Enhanced Word Count - A modern alternative to wc",
-    version
+    version,
+    disable_help_subcommand = true,
+    // Lets the command line repeat a flag already set in EWC_OPTS
+    args_override_self = true
 )]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Files to process
     #[arg(value_name = "FILE")]
     pub files: Vec<String>,
 
     /// Show line count only
-    #[arg(short = 'l', long)]
+    #[arg(short = 'l', long, global = true)]
     pub lines: bool,
 
     /// Show word count only
-    #[arg(short = 'w', long)]
+    #[arg(short = 'w', long, global = true)]
     pub words: bool,
 
     /// Show byte count only
-    #[arg(short = 'c', long)]
+    #[arg(short = 'c', long, global = true)]
     pub bytes: bool,
 
     /// Show longest line length
-    #[arg(short = 'L', long)]
+    #[arg(short = 'L', long, global = true)]
     pub max_line_length: bool,
 
+    /// Also show average words and bytes per line, per file and in total
+    #[arg(long, global = true)]
+    pub ratios: bool,
+
     /// Disable colors and icons
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub no_color: bool,
 
     /// Include hidden files and directories
-    #[arg(short = 'a', long)]
+    #[arg(short = 'a', long, global = true)]
     pub all: bool,
 
+    /// Count only hidden files and files in hidden directories, e.g. to see how much
+    /// configuration and tooling lives in a repository
+    #[arg(long, global = true, conflicts_with = "all")]
+    pub only_hidden: bool,
+
     /// Compact one-line output format
-    #[arg(short = 'C', long)]
+    #[arg(short = 'C', long, global = true)]
     pub compact: bool,
 
     /// Show file list for directories
-    #[arg(short = 'v', long)]
+    #[arg(short = 'v', long, global = true)]
     pub verbose: bool,
 
+    /// Print only the total as bare numbers, e.g. `ewc -l -q src` prints `12345`
+    #[arg(
+        short = 'q',
+        long,
+        global = true,
+        conflicts_with_all = [
+            "json",
+            "format",
+            "verbose",
+            "compact",
+            "list",
+            "files_over",
+            "baseline"
+        ]
+    )]
+    pub total_only: bool,
+
     /// Output in JSON format
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub json: bool,
 
     /// Exclude files matching glob pattern (repeatable)
-    #[arg(long, value_name = "PATTERN")]
+    #[arg(long, value_name = "PATTERN", global = true)]
     pub exclude: Vec<String>,
 
     /// Include only files matching glob pattern (repeatable)
-    #[arg(long, value_name = "PATTERN")]
+    #[arg(long, value_name = "PATTERN", global = true)]
     pub include: Vec<String>,
+
+    /// Count generated and vendored files in directories
+    #[arg(long, global = true)]
+    pub include_generated: bool,
+
+    /// Read named pipes, sockets and devices in directories, giving up after 5 seconds
+    /// or 64 MiB; by default they are skipped with a warning
+    #[arg(long, global = true)]
+    pub special_files: bool,
+
+    /// What to do with symbolic links in directories; links given as paths are always
+    /// followed
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value = "skip",
+        global = true
+    )]
+    pub symlinks: Symlinks,
+
+    /// The order of a directory's files in listings and reports: reproducible, or as
+    /// early as they are known
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ORDER",
+        default_value = "sorted",
+        global = true
+    )]
+    pub order: Order,
+
+    /// Output format
+    #[arg(long, value_enum, value_name = "FORMAT", global = true)]
+    pub format: Option<OutputFormat>,
+
+    /// Flag files exceeding a limit, e.g. lines=500 (repeatable)
+    #[arg(long, value_name = "METRIC=N", global = true)]
+    pub over: Vec<Threshold>,
+
+    /// Only count files tracked by git in directories
+    #[arg(long, global = true)]
+    pub git: bool,
+
+    /// Only count files changed relative to BASE (default: HEAD), including untracked files
+    #[arg(
+        long,
+        value_name = "BASE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "HEAD",
+        conflicts_with_all = ["git", "staged"]
+    )]
+    pub changed: Option<String>,
+
+    /// Only count files with changes staged in the git index
+    #[arg(long, conflicts_with = "git")]
+    pub staged: bool,
+
+    /// Compare against a JSON report saved with --json
+    #[arg(long, value_name = "REPORT", global = true)]
+    pub baseline: Option<PathBuf>,
+
+    /// Exit with an error if counts grew compared to --baseline
+    #[arg(long, requires = "baseline", global = true)]
+    pub fail_on_growth: bool,
+
+    /// Show each count's change since the last run with --delta, which is kept per path
+    /// in the cache directory
+    #[arg(
+        long,
+        global = true,
+        conflicts_with_all = ["json", "format", "total_only", "list", "files_over", "baseline"]
+    )]
+    pub delta: bool,
+
+    /// Append per-file and total counts to a SQLite database
+    #[arg(long, value_name = "PATH", global = true)]
+    pub db: Option<PathBuf>,
+
+    /// Attribute current lines to authors with git blame
+    #[arg(long, conflicts_with_all = ["list", "files_over", "total_only", "delta"])]
+    pub by_author: bool,
+
+    /// Count each git submodule listed in .gitmodules, and the superproject without
+    /// them, as a row of its own
+    #[arg(
+        long,
+        conflicts_with_all = ["by_author", "list", "files_over", "total_only", "delta"]
+    )]
+    pub by_submodule: bool,
+
+    /// Count each package of a Cargo workspace, package.json workspaces or Go modules
+    /// as a row of its own, with files outside them in a row for the directory
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "by_author",
+            "by_submodule",
+            "list",
+            "files_over",
+            "total_only",
+            "delta"
+        ]
+    )]
+    pub by_package: bool,
+
+    /// Show the directory hierarchy with a bar colored by each directory's share of
+    /// the lines
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "json",
+            "format",
+            "baseline",
+            "by_author",
+            "by_submodule",
+            "by_package",
+            "list",
+            "files_over",
+            "total_only",
+            "delta"
+        ]
+    )]
+    pub heatmap: bool,
+
+    /// Print each file of a directory as soon as it is counted, in no particular order,
+    /// before the directory's total
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "json",
+            "format",
+            "verbose",
+            "by_author",
+            "by_submodule",
+            "by_package",
+            "heatmap",
+            "list",
+            "files_over",
+            "total_only",
+            "strip_markup",
+            "code_blocks_only"
+        ]
+    )]
+    pub stream: bool,
+
+    /// Keep running and redraw the counts whenever files change
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "json",
+            "format",
+            "baseline",
+            "by_author",
+            "by_submodule",
+            "by_package",
+            "heatmap",
+            "stream",
+            "list",
+            "files_over",
+            "total_only",
+            "delta"
+        ]
+    )]
+    pub watch: bool,
+
+    /// Keep reading a growing file like `tail -f`, printing counts as it grows
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["format", "baseline", "by_author", "watch", "list", "total_only"]
+    )]
+    pub follow: Option<PathBuf>,
+
+    /// Stop walking and counting after this long, e.g. `30s` or `2m`: what was counted by
+    /// then is printed marked incomplete and the exit status is 4
+    #[arg(
+        long,
+        global = true,
+        value_name = "DURATION",
+        value_parser = parse_duration
+    )]
+    pub timeout: Option<Duration>,
+
+    /// Print running counts to stderr this often while reading stdin, e.g. `2s` or `500ms`
+    /// (also the --follow poll interval)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub interval: Option<Duration>,
+
+    /// Stop reading stdin after this many bytes, e.g. `512M` or `2G`, and warn that the
+    /// counts are of the start of the input only
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_stdin_bytes: Option<u64>,
+
+    /// Bytes read at a time from stdin, pipes, devices and followed files, e.g. `1M`
+    /// (default: 64K); larger chunks can help on network filesystems
+    #[arg(long, value_name = "SIZE", value_parser = parse_chunk_size, global = true)]
+    pub chunk_size: Option<usize>,
+
+    /// Print the files that would be counted, after all filters, without reading them
+    #[arg(
+        long,
+        visible_alias = "dry-run",
+        global = true,
+        group = "file_selection",
+        conflicts_with_all = ["format", "baseline"]
+    )]
+    pub list: bool,
+
+    /// Print only the files beyond a limit, e.g. "lines>500", with their counts, largest
+    /// first (repeatable)
+    #[arg(
+        long,
+        value_name = "METRIC>N",
+        global = true,
+        group = "file_selection",
+        conflicts_with_all = ["format", "baseline"]
+    )]
+    pub files_over: Vec<Threshold>,
+
+    /// End each file name --list or --files-over prints with a NUL byte instead of a
+    /// newline, for `xargs -0`
+    #[arg(
+        long,
+        global = true,
+        requires = "file_selection",
+        conflicts_with = "json"
+    )]
+    pub print0: bool,
+
+    /// Answer newline-delimited JSON requests on stdin, one JSON response per line
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "files",
+            "format",
+            "watch",
+            "follow",
+            "by_author",
+            "by_submodule",
+            "by_package",
+            "baseline",
+            "list",
+            "total_only"
+        ]
+    )]
+    pub rpc: bool,
+
+    /// Exit with an error if any file inside a directory could not be read or is not
+    /// UTF-8 text, after reporting all of them
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// Exit 0 even when inputs fail or limits are exceeded; usage errors still exit 2
+    #[arg(long, global = true)]
+    pub no_fail: bool,
+
+    /// How errors are written to stderr
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "text",
+        global = true
+    )]
+    pub errors: ErrorFormat,
+
+    /// Print a summary of the run to stderr: files counted and skipped, symlinks not
+    /// followed, and time taken
+    #[arg(long, global = true)]
+    pub stats: bool,
+
+    /// Count a path given more than once every time; by default repeats of the same
+    /// file or directory are dropped with a warning
+    #[arg(long, global = true)]
+    pub allow_duplicates: bool,
+
+    /// Stop at the first error, such as a path or a file in a directory that cannot be
+    /// read, instead of going on and failing at the end; with --strict also at the first
+    /// file skipped in a directory, such as one that is not UTF-8 text
+    #[arg(long, global = true)]
+    pub fail_fast: bool,
+
+    /// Count HTML, XHTML, XML and SVG files by the text a reader sees: tags, comments,
+    /// scripts and styles left out, entities decoded and blank lines dropped
+    #[arg(long, global = true)]
+    pub strip_markup: bool,
+
+    /// Count only the fenced code blocks of Markdown files, or with `=LANG` only blocks
+    /// tagged with that language, e.g. `--code-blocks-only=rust`
+    #[arg(long, value_name = "LANG", num_args = 0..=1, require_equals = true, global = true)]
+    pub code_blocks_only: Option<Option<String>>,
+
+    /// For Markdown files, also report words outside code blocks and front matter, and
+    /// counts of headings, links, images and code blocks
+    #[arg(long, global = true)]
+    pub markdown: bool,
+
+    /// Also report functions, types and tests in Rust, Python, JavaScript, TypeScript
+    /// and Go files (needs the `symbols` feature)
+    #[arg(long, global = true)]
+    pub symbols: bool,
+
+    /// Also estimate complexity by counting branching keywords and && / || in source
+    /// files; with -v, files are listed most complex first
+    #[arg(long, global = true)]
+    pub complexity: bool,
+
+    /// Also count records, parsing every file as JSON (top-level array elements) or
+    /// JSON Lines (one per line); parse errors are reported with line numbers
+    #[arg(long, value_enum, value_name = "FORMAT", global = true)]
+    pub records: Option<Records>,
+
+    /// Also report keys, maximum nesting depth and arrays in YAML, TOML and INI files
+    #[arg(long, global = true)]
+    pub structure: bool,
+
+    /// Also count lines per level (error, warn, info, debug) in *.log files
+    #[arg(long, global = true)]
+    pub log_levels: bool,
+
+    /// Regex deciding a --log-levels level instead of the default, e.g. 'error=^E '
+    /// (repeatable)
+    #[arg(long, value_name = "LEVEL=REGEX", global = true)]
+    pub log_pattern: Vec<LevelPattern>,
+
+    /// Also report distinct and repeated lines per file, and the most repeated line
+    #[arg(long, global = true)]
+    pub dedupe_lines: bool,
+
+    /// Also report byte entropy in bits per byte (0-8); compressed or encoded data
+    /// approaches 8
+    #[arg(long, global = true)]
+    pub entropy: bool,
+
+    /// Also hash each file's content, shown with -v and in --json and --format csv
+    #[arg(long, value_enum, value_name = "ALGORITHM", global = true)]
+    pub hash: Option<Checksum>,
+
+    /// Also show each file's size on disk, modification time and permissions, with -v
+    /// and in --json and --format csv
+    #[arg(long, global = true)]
+    pub show_meta: bool,
+
+    /// Also detect the predominant natural language (en, ja, de, ...) of each file,
+    /// shown with -v and in --json
+    #[arg(long, global = true)]
+    pub detect_language: bool,
+
+    /// Also report distinct words, words used once (hapax) and the type-token ratio
+    /// (distinct words per word) as a measure of lexical diversity
+    #[arg(long, global = true)]
+    pub vocabulary: bool,
+
+    /// Also count quotations and the share of words inside them, to balance dialogue
+    /// against narration
+    #[arg(long, global = true)]
+    pub dialogue: bool,
+
+    /// Also count pages: of PDF documents, or of text split into pages by form feeds
+    #[arg(long, global = true)]
+    pub pages: bool,
+
+    /// For EPUB books, also report the words of each chapter, in reading order
+    #[arg(long, global = true)]
+    pub chapters: bool,
+
+    /// For SubRip (.srt) and WebVTT (.vtt) files, also report cues, subtitle words and
+    /// words per minute of on-screen time, to check pacing
+    #[arg(long, global = true)]
+    pub subtitles: bool,
+
+    /// For gettext catalogs (.po, .pot), also report translated, fuzzy and untranslated
+    /// messages with their source and translated words
+    #[arg(long, global = true)]
+    pub po: bool,
+
+    /// For JSON and YAML locale files, also count translatable strings and their words,
+    /// and list the keys each locale misses that another locale of the same resources has
+    #[arg(long, global = true)]
+    pub i18n: bool,
+
+    /// Also count blank, comment and code lines of source files, with comment lines per
+    /// 100 code lines
+    #[arg(long, global = true)]
+    pub comment_ratio: bool,
+
+    /// Also estimate the effort, schedule and cost of writing the code lines again with
+    /// basic COCOMO, like scc, after the counts
+    #[arg(long, global = true)]
+    pub cocomo: bool,
+
+    /// Project type for --cocomo [default: organic]
+    #[arg(
+        long,
+        value_enum,
+        value_name = "TYPE",
+        requires = "cocomo",
+        global = true
+    )]
+    pub cocomo_type: Option<CocomoType>,
+
+    /// Yearly salary of one developer for --cocomo [default: 56286]
+    #[arg(long, value_name = "AMOUNT", requires = "cocomo", global = true)]
+    pub cocomo_wage: Option<f64>,
+
+    /// Multiplier from salary to the full cost of a developer for --cocomo [default: 2.4]
+    #[arg(long, value_name = "FACTOR", requires = "cocomo", global = true)]
+    pub cocomo_overhead: Option<f64>,
+
+    /// Effort adjustment factor for --cocomo [default: 1.0]
+    #[arg(long, value_name = "FACTOR", requires = "cocomo", global = true)]
+    pub cocomo_eaf: Option<f64>,
+
+    /// Read every file instead of reusing cached counts of unchanged files
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
+    /// Number of threads counting files (default: one per CPU)
+    #[arg(long, value_name = "N", global = true)]
+    pub threads: Option<NonZeroUsize>,
+
+    /// Ignore ~/.config/ewc/config.toml and .ewc.toml
+    #[arg(long, global = true)]
+    pub no_config: bool,
+
+    /// Apply the config file's [profile.NAME] settings
+    #[arg(long, value_name = "NAME", global = true, conflicts_with = "no_config")]
+    pub profile: Option<String>,
+
+    /// Log to stderr which files are skipped and why, and how long each phase takes
+    /// (RUST_LOG also works, e.g. `RUST_LOG=ewc=debug`)
+    #[arg(long, global = true)]
+    pub debug: bool,
+
+    /// Like --debug, also logging every file as it is counted
+    #[arg(long, global = true)]
+    pub trace: bool,
+}
+
+/// Splits `EWC_OPTS` into arguments at whitespace, keeping quoted runs together
+/// (`'...'` literally, `"..."` with backslash escapes) and honouring backslash escapes.
+fn split_opts(opts: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = opts.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(current.take()),
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err("unterminated ' quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => arg.push(c),
+                            None => return Err("unterminated \" quote".to_string()),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err("unterminated \" quote".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => current.get_or_insert_with(String::new).push(c),
+                None => return Err("trailing backslash".to_string()),
+            },
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+/// Parses a duration such as `500ms`, `2s` or `1m`; a bare number is seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("invalid duration '{s}' (expected e.g. 2s or 500ms)"))?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(value),
+        "" | "s" => Duration::from_secs(value),
+        "m" => Duration::from_secs(value * 60),
+        _ => {
+            return Err(format!(
+                "unknown unit '{unit}' in '{s}' (expected ms, s or m)"
+            ))
+        }
+    };
+    if duration.is_zero() {
+        return Err("interval must be greater than zero".to_string());
+    }
+    Ok(duration)
+}
+
+/// Parses a size such as `4096`, `64K`, `512M` or `2G`, in bytes with binary multiples;
+/// `KiB`, `MB` and the like are read the same way.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("invalid size '{s}' (expected e.g. 4096, 64K or 2G)"))?;
+    let unit = unit
+        .trim_end_matches(['B', 'b'])
+        .trim_end_matches(['i', 'I']);
+    let multiplier: u64 = match unit.to_ascii_uppercase().as_str() {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("unknown unit in '{s}' (expected K, M, G or T)")),
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size '{s}' is too large"))
+}
+
+/// Parses a read buffer size like [`parse_size`], which must be at least one byte.
+fn parse_chunk_size(s: &str) -> Result<usize, String> {
+    match parse_size(s)? {
+        0 => Err("chunk size must be greater than zero".to_string()),
+        size => usize::try_from(size).map_err(|_| format!("chunk size '{s}' is too large")),
+    }
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Count files and directories (what `ewc FILE...` does)
+    Count {
+        /// Files to process
+        #[arg(value_name = "FILE")]
+        files: Vec<String>,
+    },
+
+    /// Check --over limits against files, printing only the violations
+    Check {
+        /// Files and directories to check (default: current directory)
+        #[arg(value_name = "PATH")]
+        paths: Vec<String>,
+    },
+
+    /// Report lines and words added and removed between two git revisions
+    #[command(visible_alias = "diff-git")]
+    Diff {
+        /// Revision range, e.g. main..HEAD
+        #[arg(value_name = "REF1..REF2")]
+        range: String,
+
+        /// Limit the diff to these paths
+        #[arg(value_name = "PATH")]
+        paths: Vec<String>,
+    },
+
+    /// Keep running and redraw the counts whenever files change (what --watch does)
+    Watch {
+        /// Files and directories to watch (default: current directory)
+        #[arg(value_name = "FILE")]
+        files: Vec<String>,
+    },
+
+    /// Report how total counts evolved over git history
+    History {
+        /// How far back to go, e.g. 6m, 1y, 30d or a date like 2024-01-01
+        #[arg(long, value_name = "WHEN", default_value = "1y")]
+        since: String,
+
+        /// Sample one commit per interval
+        #[arg(long, value_enum, value_name = "INTERVAL", default_value = "month")]
+        interval: Interval,
+
+        /// Limit the history to these paths
+        #[arg(value_name = "PATH")]
+        paths: Vec<String>,
+    },
+
+    /// Check --over limits against the files a commit would contain, for pre-commit
+    Hook {
+        /// Only check files with staged changes
+        #[arg(long)]
+        staged: bool,
+    },
+
+    /// Compare two directory trees file by file
+    Compare {
+        /// The tree to compare from
+        #[arg(value_name = "DIR_A")]
+        dir_a: String,
+
+        /// The tree to compare to
+        #[arg(value_name = "DIR_B")]
+        dir_b: String,
+    },
+
+    /// Render a JSON report saved with --json in another --format without counting again
+    Convert {
+        /// Report to render
+        #[arg(value_name = "REPORT")]
+        report: PathBuf,
+    },
+
+    /// Combine JSON reports saved with --json, such as those of sharded CI jobs, into one
+    Merge {
+        /// Reports to combine
+        #[arg(value_name = "REPORT", required = true)]
+        reports: Vec<PathBuf>,
+    },
+
+    /// Save per-file counts under a name, or diff the current state against one
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+
+    /// Show how counts recorded with --db evolved across runs
+    Report {
+        /// Show the trend of a single file instead of the totals
+        #[arg(value_name = "FILE")]
+        file: Option<String>,
+    },
+
+    /// Serve counts over HTTP, e.g. GET /counts?path=src&format=json
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to bind; use 0.0.0.0 to accept connections from other hosts
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1")]
+        host: String,
+
+        /// Directory whose contents are served
+        #[arg(value_name = "DIR", default_value = ".")]
+        dir: String,
+    },
+
+    /// Keep an index of counts under DIR, updated on file changes, and answer --rpc style
+    /// requests on a Unix socket
+    Daemon {
+        /// Socket to listen on [default: DIR/.ewc/daemon.sock]
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+
+        /// Directory to index
+        #[arg(value_name = "DIR", default_value = ".")]
+        dir: String,
+    },
+
+    /// Manage the cache of per-file counts
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Install a git pre-commit hook running `ewc hook --staged` with the given --over limits
+    InstallHook {
+        /// Replace an existing pre-commit hook
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub enum SnapshotAction {
+    /// Save per-file counts to .ewc/snapshots/NAME.json
+    Save {
+        /// Snapshot name, e.g. a release tag
+        name: String,
+
+        /// Files and directories to count (default: current directory)
+        #[arg(value_name = "PATH")]
+        paths: Vec<String>,
+    },
+
+    /// Show what changed since a saved snapshot
+    Diff {
+        /// Snapshot name
+        name: String,
+
+        /// Files and directories to count (default: current directory)
+        #[arg(value_name = "PATH")]
+        paths: Vec<String>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interval {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Warning lines for people
+    Text,
+    /// One {"path","kind","message"} object per line
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Leave links out
+    Skip,
+    /// Count linked files and descend into linked directories
+    Follow,
+    /// Leave links out with a warning for each
+    Report,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// By path, the same from run to run
+    Sorted,
+    /// As the directory walk found them
+    Discovery,
+    /// As they finished counting, differing from run to run
+    Completion,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Records {
+    /// JSON documents; each element of a top-level array is a record
+    Json,
+    /// One JSON value per line
+    Jsonl,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    Sha256,
+    Blake3,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CocomoType {
+    Organic,
+    SemiDetached,
+    Embedded,
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub enum CacheAction {
+    /// Delete all cached counts
+    Clear,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// SARIF 2.1.0 report of --over violations
+    Sarif,
+    /// Markdown summary sized for a pull request comment
+    PrComment,
+    /// Prometheus text exposition format
+    Prometheus,
+    /// Markdown table
+    Markdown,
+    /// Comma-separated values, one row per file
+    Csv,
+    /// cloc's summary table of blank, comment and code lines per language
+    Cloc,
+    /// cloc's --csv layout of the same summary
+    ClocCsv,
+    /// tokei's JSON output: lines per language, with a report per file
+    TokeiJson,
+    /// Standalone HTML page with a table
+    Html,
+    /// Excel workbook with a summary sheet and a sheet of files, written to stdout
+    Xlsx,
+    /// Mermaid flowchart of the directories with the lines under each
+    Mermaid,
+    /// Graphviz graph of the directories with the lines under each
+    Dot,
 }
 
 impl Args {
+    /// Parses the command line, exiting with a usage error like [`Parser::parse`], and
+    /// fills in defaults from the config files unless `--no-config` is given.
+    pub fn parse_command_line() -> Self {
+        let mut argv: Vec<OsString> = env::args_os().collect();
+        if let Some(opts) = env::var_os("EWC_OPTS") {
+            match split_opts(&opts.to_string_lossy()) {
+                Ok(extra) => {
+                    let at = argv.len().min(1);
+                    argv.splice(at..at, extra.into_iter().map(OsString::from));
+                }
+                Err(e) => Self::command()
+                    .error(ErrorKind::InvalidValue, format!("EWC_OPTS: {e}"))
+                    .exit(),
+            }
+        }
+        let mut args = Self::parse_from(argv);
+        if let Err(e) = args.fold_command() {
+            e.exit();
+        }
+        if !args.no_config {
+            let config = config::load_defaults().and_then(|config| match &args.profile {
+                Some(name) => config.with_profile(name),
+                None => Ok(config),
+            });
+            match config {
+                Ok(config) => args.apply_config(config),
+                Err(e) => Self::command()
+                    .error(ErrorKind::InvalidValue, format!("config: {e}"))
+                    .exit(),
+            }
+        }
+        args
+    }
+
+    /// Applies config-file defaults to whatever the command line left unset. Config
+    /// patterns come before those given with `--exclude` / `--include`, and a default
+    /// format only applies to plain counts.
+    pub fn apply_config(&mut self, config: Config) {
+        if let Some(format) = config.format {
+            let plain_count = self.command.is_none()
+                && !self.total_only
+                && !self.json
+                && self.format.is_none()
+                && !self.watch
+                && !self.list
+                && !self.rpc
+                && !self.by_author
+                && !self.by_submodule
+                && !self.by_package
+                && !self.heatmap
+                && !self.stream
+                && self.files_over.is_empty()
+                && self.follow.is_none();
+            if plain_count {
+                match format {
+                    DefaultFormat::Text => {}
+                    DefaultFormat::Json => self.json = true,
+                    DefaultFormat::Format(format) => self.format = Some(format),
+                }
+            }
+        }
+        self.exclude.splice(0..0, config.exclude);
+        self.include.splice(0..0, config.include);
+        if config.color == Some(false) {
+            self.no_color = true;
+        }
+        self.threads = self.threads.or(config.threads);
+        if !(self.lines || self.words || self.bytes || self.max_line_length) {
+            for metric in config.metrics {
+                match metric {
+                    Metric::Lines => self.lines = true,
+                    Metric::Words => self.words = true,
+                    Metric::Bytes => self.bytes = true,
+                    Metric::MaxLineLength => self.max_line_length = true,
+                }
+            }
+        }
+    }
+
+    /// Turns `count` and `watch` into the flat form the counting modes read, so
+    /// `ewc count src` behaves exactly like `ewc src`.
+    fn fold_command(&mut self) -> Result<(), clap::Error> {
+        match self.command.take() {
+            Some(Command::Count { files }) => self.files = files,
+            Some(Command::Watch { files }) => {
+                if self.json
+                    || self.format.is_some()
+                    || self.baseline.is_some()
+                    || self.list
+                    || self.total_only
+                {
+                    return Err(Self::command().error(
+                        ErrorKind::ArgumentConflict,
+                        "watch cannot be used with --json, --format, --baseline, --list or --total-only",
+                    ));
+                }
+                self.files = files;
+                self.watch = true;
+            }
+            command => self.command = command,
+        }
+        Ok(())
+    }
+
     pub fn show_lines(&self) -> bool {
         self.lines || self.show_all()
     }
@@ -84,18 +1028,89 @@ mod tests {
 
     fn default_args() -> Args {
         Args {
+            command: None,
             files: vec![],
             lines: false,
             words: false,
             bytes: false,
             max_line_length: false,
+            ratios: false,
             no_color: false,
             all: false,
+            only_hidden: false,
             compact: false,
             verbose: false,
+            total_only: false,
             json: false,
             exclude: vec![],
             include: vec![],
+            include_generated: false,
+            special_files: false,
+            symlinks: Symlinks::Skip,
+            order: Order::Sorted,
+            format: None,
+            over: vec![],
+            git: false,
+            changed: None,
+            staged: false,
+            baseline: None,
+            fail_on_growth: false,
+            delta: false,
+            db: None,
+            by_author: false,
+            by_submodule: false,
+            by_package: false,
+            heatmap: false,
+            stream: false,
+            watch: false,
+            follow: None,
+            interval: None,
+            max_stdin_bytes: None,
+            chunk_size: None,
+            rpc: false,
+            strict: false,
+            no_fail: false,
+            errors: ErrorFormat::Text,
+            stats: false,
+            allow_duplicates: false,
+            fail_fast: false,
+            timeout: None,
+            strip_markup: false,
+            code_blocks_only: None,
+            markdown: false,
+            symbols: false,
+            complexity: false,
+            records: None,
+            structure: false,
+            log_levels: false,
+            log_pattern: vec![],
+            dedupe_lines: false,
+            entropy: false,
+            hash: None,
+            show_meta: false,
+            detect_language: false,
+            vocabulary: false,
+            dialogue: false,
+            pages: false,
+            chapters: false,
+            subtitles: false,
+            po: false,
+            i18n: false,
+            comment_ratio: false,
+            cocomo: false,
+            cocomo_type: None,
+            cocomo_wage: None,
+            cocomo_overhead: None,
+            cocomo_eaf: None,
+            no_cache: false,
+            debug: false,
+            trace: false,
+            list: false,
+            print0: false,
+            files_over: Vec::new(),
+            threads: None,
+            no_config: false,
+            profile: None,
         }
     }
 
@@ -239,4 +1254,637 @@ mod tests {
         assert_eq!(args.include.len(), 1);
         assert_eq!(args.include[0], "*.rs");
     }
+
+    #[test]
+    fn format_flag_parsed() {
+        let args = Args::parse_from(["ewc", "--format", "sarif", "src"]);
+        assert_eq!(args.format, Some(OutputFormat::Sarif));
+    }
+
+    #[test]
+    fn format_pr_comment_parsed() {
+        let args = Args::parse_from(["ewc", "--format", "pr-comment", "src"]);
+        assert_eq!(args.format, Some(OutputFormat::PrComment));
+    }
+
+    #[test]
+    fn format_prometheus_parsed() {
+        let args = Args::parse_from(["ewc", "--format", "prometheus", "src"]);
+        assert_eq!(args.format, Some(OutputFormat::Prometheus));
+    }
+
+    #[test]
+    fn over_thresholds_parsed() {
+        let args = Args::parse_from(["ewc", "--over", "lines=500", "--over", "bytes=10", "src"]);
+        assert_eq!(args.over.len(), 2);
+        assert_eq!(args.over[0].limit, 500);
+        assert_eq!(args.over[1].to_string(), "bytes=10");
+    }
+
+    #[test]
+    fn over_invalid_threshold_rejected() {
+        assert!(Args::try_parse_from(["ewc", "--over", "lines", "src"]).is_err());
+    }
+
+    #[test]
+    fn baseline_flag_parsed() {
+        let args = Args::parse_from([
+            "ewc",
+            "--baseline",
+            "report.json",
+            "--fail-on-growth",
+            "src",
+        ]);
+        assert_eq!(args.baseline, Some(PathBuf::from("report.json")));
+        assert!(args.fail_on_growth);
+    }
+
+    #[test]
+    fn fail_on_growth_requires_baseline() {
+        assert!(Args::try_parse_from(["ewc", "--fail-on-growth", "src"]).is_err());
+    }
+
+    #[test]
+    fn cocomo_parameters_require_cocomo() {
+        assert!(Args::try_parse_from(["ewc", "--cocomo-wage", "90000", "src"]).is_err());
+        let args = Args::try_parse_from([
+            "ewc",
+            "--cocomo",
+            "--cocomo-type",
+            "semi-detached",
+            "--cocomo-eaf",
+            "1.2",
+            "src",
+        ])
+        .unwrap();
+        assert_eq!(args.cocomo_type, Some(CocomoType::SemiDetached));
+        assert_eq!(args.cocomo_eaf, Some(1.2));
+    }
+
+    #[test]
+    fn git_flag_parsed() {
+        let args = Args {
+            git: true,
+            ..default_args()
+        };
+        assert!(args.git);
+    }
+
+    #[test]
+    fn changed_flag_defaults_to_head() {
+        let args = Args::parse_from(["ewc", "--changed", "src"]);
+        assert_eq!(args.changed.as_deref(), Some("HEAD"));
+        assert_eq!(args.files, vec!["src"]);
+    }
+
+    #[test]
+    fn changed_flag_with_base() {
+        let args = Args::parse_from(["ewc", "--changed=origin/main", "src"]);
+        assert_eq!(args.changed.as_deref(), Some("origin/main"));
+    }
+
+    #[test]
+    fn staged_conflicts_with_changed() {
+        assert!(Args::try_parse_from(["ewc", "--staged", "--changed", "src"]).is_err());
+    }
+
+    #[test]
+    fn diff_git_subcommand_parsed() {
+        let args = Args::parse_from(["ewc", "diff-git", "main..HEAD", "src", "--json"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Diff {
+                range: "main..HEAD".to_string(),
+                paths: vec!["src".to_string()],
+            })
+        );
+        assert!(args.json);
+        assert!(args.files.is_empty());
+    }
+
+    #[test]
+    fn count_subcommand_matches_bare_paths() {
+        let mut args = Args::parse_from(["ewc", "count", "-l", "--exclude", "*.md", "src"]);
+        args.fold_command().unwrap();
+        let bare = Args::parse_from(["ewc", "-l", "--exclude", "*.md", "src"]);
+        assert_eq!(args.command, None);
+        assert_eq!(args.files, bare.files);
+        assert!(args.lines);
+        assert_eq!(args.exclude, bare.exclude);
+    }
+
+    #[test]
+    fn watch_subcommand_sets_watch() {
+        let mut args = Args::parse_from(["ewc", "watch", "src", "docs"]);
+        args.fold_command().unwrap();
+        assert!(args.watch);
+        assert_eq!(args.files, vec!["src", "docs"]);
+
+        let mut args = Args::parse_from(["ewc", "watch", "--json", "src"]);
+        assert!(args.fold_command().is_err());
+    }
+
+    #[test]
+    fn check_and_diff_subcommands_parsed() {
+        let args = Args::parse_from(["ewc", "check", "--over", "lines=500", "src"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Check {
+                paths: vec!["src".to_string()]
+            })
+        );
+        assert_eq!(args.over.len(), 1);
+
+        let args = Args::parse_from(["ewc", "diff", "main..HEAD"]);
+        assert!(matches!(args.command, Some(Command::Diff { .. })));
+    }
+
+    #[test]
+    fn completions_subcommand_parsed() {
+        let args = Args::parse_from(["ewc", "completions", "zsh"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Completions { shell: Shell::Zsh })
+        );
+        assert!(Args::try_parse_from(["ewc", "completions", "tcsh"]).is_err());
+    }
+
+    #[test]
+    fn history_subcommand_defaults() {
+        let args = Args::parse_from(["ewc", "history"]);
+        assert_eq!(
+            args.command,
+            Some(Command::History {
+                since: "1y".to_string(),
+                interval: Interval::Month,
+                paths: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn history_subcommand_with_csv_format() {
+        let args = Args::parse_from([
+            "ewc",
+            "history",
+            "--since",
+            "6m",
+            "--interval",
+            "week",
+            "src",
+            "--format",
+            "csv",
+        ]);
+        assert_eq!(
+            args.command,
+            Some(Command::History {
+                since: "6m".to_string(),
+                interval: Interval::Week,
+                paths: vec!["src".to_string()],
+            })
+        );
+        assert_eq!(args.format, Some(OutputFormat::Csv));
+    }
+
+    #[test]
+    fn include_generated_flag() {
+        assert!(!Args::parse_from(["ewc", "src"]).include_generated);
+        assert!(Args::parse_from(["ewc", "--include-generated", "src"]).include_generated);
+    }
+
+    #[test]
+    fn watch_flag() {
+        let args = Args::parse_from(["ewc", "--watch", "-C", "src"]);
+        assert!(args.watch);
+        assert!(Args::try_parse_from(["ewc", "--watch", "--json", "src"]).is_err());
+    }
+
+    #[test]
+    fn follow_takes_a_file() {
+        let args = Args::parse_from(["ewc", "--follow", "app.log", "-l"]);
+        assert_eq!(args.follow, Some(PathBuf::from("app.log")));
+        assert!(args.files.is_empty());
+        assert!(Args::try_parse_from(["ewc", "--follow", "a.log", "--watch"]).is_err());
+    }
+
+    #[test]
+    fn interval_durations() {
+        assert_eq!(parse_duration("2s"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1m"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_duration("3"), Ok(Duration::from_secs(3)));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("2h").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn sizes_use_binary_multiples() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("64K"), Ok(64 * 1024));
+        assert_eq!(parse_size("512MiB"), Ok(512 << 20));
+        assert_eq!(parse_size("2gb"), Ok(2 << 30));
+        assert!(parse_size("G").is_err());
+        assert!(parse_size("3X").is_err());
+        assert!(parse_size("99999999999T").is_err());
+        let args = Args::try_parse_from(["ewc", "--max-stdin-bytes", "1M"]).unwrap();
+        assert_eq!(args.max_stdin_bytes, Some(1 << 20));
+        let args = Args::try_parse_from(["ewc", "--chunk-size", "256K"]).unwrap();
+        assert_eq!(args.chunk_size, Some(256 << 10));
+        assert!(Args::try_parse_from(["ewc", "--chunk-size", "0"]).is_err());
+    }
+
+    #[test]
+    fn interval_flag_distinct_from_history_interval() {
+        let args = Args::parse_from(["ewc", "--interval", "2s"]);
+        assert_eq!(args.interval, Some(Duration::from_secs(2)));
+
+        let args = Args::parse_from(["ewc", "history", "--interval", "week"]);
+        assert_eq!(args.interval, None);
+        assert!(matches!(
+            args.command,
+            Some(Command::History {
+                interval: Interval::Week,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rpc_flag() {
+        assert!(Args::parse_from(["ewc", "--rpc"]).rpc);
+        assert!(Args::try_parse_from(["ewc", "--rpc", "src"]).is_err());
+    }
+
+    #[test]
+    fn by_submodule_conflicts_with_by_author() {
+        assert!(
+            Args::try_parse_from(["ewc", "--by-submodule", "."])
+                .unwrap()
+                .by_submodule
+        );
+        assert!(Args::try_parse_from(["ewc", "--by-submodule", "--by-author", "."]).is_err());
+    }
+
+    #[test]
+    fn by_package_flag() {
+        assert!(
+            Args::try_parse_from(["ewc", "--by-package"])
+                .unwrap()
+                .by_package
+        );
+        assert!(Args::try_parse_from(["ewc", "--by-package", "--by-submodule"]).is_err());
+    }
+
+    #[test]
+    fn by_author_flag() {
+        let args = Args::parse_from(["ewc", "--by-author", "src"]);
+        assert!(args.by_author);
+    }
+
+    #[test]
+    fn hook_subcommand_with_limits() {
+        let args = Args::parse_from(["ewc", "hook", "--staged", "--over", "lines=500"]);
+        assert_eq!(args.command, Some(Command::Hook { staged: true }));
+        assert_eq!(args.over.len(), 1);
+    }
+
+    #[test]
+    fn install_hook_subcommand() {
+        let args = Args::parse_from(["ewc", "install-hook", "--force", "--over", "bytes=10000"]);
+        assert_eq!(args.command, Some(Command::InstallHook { force: true }));
+        assert_eq!(args.over[0].to_string(), "bytes=10000");
+    }
+
+    #[test]
+    fn compare_subcommand_parsed() {
+        let args = Args::parse_from(["ewc", "compare", "v1", "v2", "--json"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Compare {
+                dir_a: "v1".to_string(),
+                dir_b: "v2".to_string(),
+            })
+        );
+        assert!(args.json);
+    }
+
+    #[test]
+    fn convert_subcommand_parsed() {
+        let args = Args::parse_from(["ewc", "convert", "report.json", "--format", "html"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Convert {
+                report: PathBuf::from("report.json"),
+            })
+        );
+        assert_eq!(args.format, Some(OutputFormat::Html));
+    }
+
+    #[test]
+    fn merge_subcommand_needs_reports() {
+        let args = Args::parse_from(["ewc", "merge", "a.json", "b.json", "--json"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Merge {
+                reports: vec![PathBuf::from("a.json"), PathBuf::from("b.json")],
+            })
+        );
+        assert!(Args::try_parse_from(["ewc", "merge"]).is_err());
+    }
+
+    #[test]
+    fn snapshot_subcommands_parsed() {
+        let args = Args::parse_from(["ewc", "snapshot", "save", "v1.0", "src"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Snapshot {
+                action: SnapshotAction::Save {
+                    name: "v1.0".to_string(),
+                    paths: vec!["src".to_string()],
+                },
+            })
+        );
+
+        let args = Args::parse_from(["ewc", "snapshot", "diff", "v1.0", "--json"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Snapshot {
+                action: SnapshotAction::Diff {
+                    name: "v1.0".to_string(),
+                    paths: vec![],
+                },
+            })
+        );
+        assert!(args.json);
+    }
+
+    #[test]
+    fn report_subcommand_with_db() {
+        let args = Args::parse_from(["ewc", "report", "--db", "metrics.sqlite"]);
+        assert_eq!(args.command, Some(Command::Report { file: None }));
+        assert_eq!(args.db, Some(PathBuf::from("metrics.sqlite")));
+    }
+
+    #[test]
+    fn serve_subcommand_defaults() {
+        let args = Args::parse_from(["ewc", "serve"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Serve {
+                port: 8080,
+                host: "127.0.0.1".to_string(),
+                dir: ".".to_string(),
+            })
+        );
+
+        let args = Args::parse_from(["ewc", "serve", "--port", "9000", "repo"]);
+        assert!(matches!(
+            args.command,
+            Some(Command::Serve { port: 9000, ref dir, .. }) if dir == "repo"
+        ));
+    }
+
+    #[test]
+    fn daemon_subcommand() {
+        let args = Args::parse_from(["ewc", "daemon"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Daemon {
+                socket: None,
+                dir: ".".to_string(),
+            })
+        );
+
+        let args = Args::parse_from(["ewc", "daemon", "--socket", "/tmp/ewc.sock", "repo"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Daemon {
+                socket: Some(PathBuf::from("/tmp/ewc.sock")),
+                dir: "repo".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn command_definition_is_valid() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn config_fills_in_what_the_command_line_left_unset() {
+        let config = Config::parse(
+            "format = \"csv\"\nexclude = [\"target/**\"]\ncolor = false\nthreads = 2",
+        )
+        .unwrap();
+
+        let mut args = Args::parse_from(["ewc", "--exclude", "*.md", "src"]);
+        args.apply_config(config.clone());
+        assert_eq!(args.format, Some(OutputFormat::Csv));
+        assert_eq!(args.exclude, ["target/**", "*.md"]);
+        assert!(args.no_color);
+        assert_eq!(args.threads, NonZeroUsize::new(2));
+
+        let mut args = Args::parse_from(["ewc", "--json", "--threads", "8", "src"]);
+        args.apply_config(config.clone());
+        assert_eq!(args.format, None);
+        assert_eq!(args.threads, NonZeroUsize::new(8));
+
+        // Other commands keep their own output
+        let mut args = Args::parse_from(["ewc", "diff", "HEAD~1..HEAD"]);
+        args.apply_config(config);
+        assert_eq!(args.format, None);
+
+        // As do the modes that cannot be written as JSON
+        let config = Config::parse("format = \"json\"").unwrap();
+        for flags in [
+            &["--stream"][..],
+            &["--heatmap"],
+            &["--files-over", "lines=10"],
+        ] {
+            let mut args = Args::parse_from(["ewc"].iter().chain(flags).chain(&["src"]));
+            args.apply_config(config.clone());
+            assert!(!args.json, "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn config_metrics_apply_unless_counts_are_chosen() {
+        let config = Config::parse(r#"metrics = ["words", "max-line-length"]"#).unwrap();
+
+        let mut args = Args::parse_from(["ewc", "src"]);
+        args.apply_config(config.clone());
+        assert!(args.words && args.max_line_length);
+        assert!(!args.lines && !args.bytes);
+
+        let mut args = Args::parse_from(["ewc", "-l", "src"]);
+        args.apply_config(config);
+        assert!(args.lines);
+        assert!(!args.words && !args.max_line_length);
+    }
+
+    #[test]
+    fn profile_conflicts_with_no_config() {
+        assert_eq!(
+            Args::parse_from(["ewc", "count", "--profile", "docs"])
+                .profile
+                .as_deref(),
+            Some("docs")
+        );
+        assert!(Args::try_parse_from(["ewc", "--profile", "docs", "--no-config"]).is_err());
+    }
+
+    #[test]
+    fn split_opts_handles_quotes_and_escapes() {
+        assert_eq!(
+            split_opts(r#"  --no-color --exclude 'target/**' --exclude "my \"dir\"/*" a\ b ''"#)
+                .unwrap(),
+            [
+                "--no-color",
+                "--exclude",
+                "target/**",
+                "--exclude",
+                r#"my "dir"/*"#,
+                "a b",
+                ""
+            ]
+        );
+        assert!(split_opts("").unwrap().is_empty());
+        assert!(split_opts("--exclude 'target").is_err());
+        assert!(split_opts("trailing\\").is_err());
+    }
+
+    #[test]
+    fn repeated_flags_and_options_let_the_last_win() {
+        let args = Args::parse_from(["ewc", "--no-color", "--format", "csv", "--no-color"]);
+        assert!(args.no_color);
+        let args = Args::parse_from(["ewc", "--format", "csv", "--format", "markdown"]);
+        assert_eq!(args.format, Some(OutputFormat::Markdown));
+        let args = Args::parse_from(["ewc", "--exclude", "a", "--exclude", "b"]);
+        assert_eq!(args.exclude, ["a", "b"]);
+    }
+
+    #[test]
+    fn total_only_flag() {
+        let args = Args::parse_from(["ewc", "-l", "-q", "src"]);
+        assert!(args.total_only && args.lines);
+        assert!(Args::parse_from(["ewc", "count", "--total-only"]).total_only);
+        assert!(Args::try_parse_from(["ewc", "-q", "--json", "src"]).is_err());
+        assert!(Args::try_parse_from(["ewc", "-q", "--watch", "src"]).is_err());
+    }
+
+    #[test]
+    fn strict_flag_works_after_subcommands() {
+        assert!(Args::parse_from(["ewc", "--strict", "src"]).strict);
+        assert!(Args::parse_from(["ewc", "check", "--strict", "--over", "lines=5"]).strict);
+    }
+
+    #[test]
+    fn no_fail_flag_works_after_subcommands() {
+        assert!(Args::parse_from(["ewc", "--no-fail", "src"]).no_fail);
+        assert!(Args::parse_from(["ewc", "check", "--no-fail", "--over", "lines=5"]).no_fail);
+    }
+
+    #[test]
+    fn errors_format_defaults_to_text() {
+        assert_eq!(Args::parse_from(["ewc", "src"]).errors, ErrorFormat::Text);
+        let args = Args::parse_from(["ewc", "check", "--errors", "json", "--over", "lines=5"]);
+        assert_eq!(args.errors, ErrorFormat::Json);
+        assert!(Args::try_parse_from(["ewc", "--errors", "yaml", "src"]).is_err());
+    }
+
+    #[test]
+    fn special_files_flag_works_after_subcommands() {
+        assert!(!Args::parse_from(["ewc", "src"]).special_files);
+        assert!(Args::parse_from(["ewc", "count", "--special-files", "src"]).special_files);
+    }
+
+    #[test]
+    fn symlinks_policy_defaults_to_skip() {
+        assert_eq!(Args::parse_from(["ewc", "src"]).symlinks, Symlinks::Skip);
+        let args = Args::parse_from(["ewc", "count", "--symlinks", "follow", "src"]);
+        assert_eq!(args.symlinks, Symlinks::Follow);
+        assert!(Args::try_parse_from(["ewc", "--symlinks", "maybe", "src"]).is_err());
+    }
+
+    #[test]
+    fn order_defaults_to_sorted() {
+        assert_eq!(Args::parse_from(["ewc", "src"]).order, Order::Sorted);
+        let args = Args::parse_from(["ewc", "count", "--order", "completion", "src"]);
+        assert_eq!(args.order, Order::Completion);
+        assert!(Args::try_parse_from(["ewc", "--order", "random", "src"]).is_err());
+    }
+
+    #[test]
+    fn log_patterns_are_validated_while_parsing() {
+        let args = Args::parse_from([
+            "ewc",
+            "--log-levels",
+            "--log-pattern",
+            "error=^E ",
+            "app.log",
+        ]);
+        assert_eq!(args.log_pattern.len(), 1);
+        assert!(Args::try_parse_from(["ewc", "--log-pattern", "error=(", "app.log"]).is_err());
+        assert!(Args::try_parse_from(["ewc", "--log-pattern", "notice=x", "app.log"]).is_err());
+    }
+
+    #[test]
+    fn allow_duplicates_flag_works_after_subcommands() {
+        assert!(!Args::parse_from(["ewc", "a", "a"]).allow_duplicates);
+        assert!(
+            Args::parse_from(["ewc", "count", "--allow-duplicates", "a", "a"]).allow_duplicates
+        );
+    }
+
+    #[test]
+    fn print0_needs_list() {
+        assert!(Args::parse_from(["ewc", "--list", "--print0", "src"]).print0);
+        assert!(Args::parse_from(["ewc", "--files-over", "lines>5", "--print0"]).print0);
+        assert!(Args::try_parse_from(["ewc", "--print0", "src"]).is_err());
+        assert!(Args::try_parse_from(["ewc", "--list", "--print0", "--json", "src"]).is_err());
+    }
+
+    #[test]
+    fn files_over_thresholds() {
+        let args = Args::parse_from(["ewc", "--files-over", "lines>500", "--files-over=bytes=9"]);
+        assert_eq!(args.files_over.len(), 2);
+        assert_eq!(args.files_over[0].metric, Metric::Lines);
+        assert!(Args::try_parse_from(["ewc", "--files-over", "lines>5", "--list"]).is_err());
+    }
+
+    #[test]
+    fn list_flag_and_dry_run_alias() {
+        assert!(Args::parse_from(["ewc", "--list", "src"]).list);
+        assert!(Args::parse_from(["ewc", "count", "--dry-run", "src"]).list);
+        assert!(Args::try_parse_from(["ewc", "--list", "--format", "csv", "src"]).is_err());
+        assert!(Args::try_parse_from(["ewc", "--list", "--watch", "src"]).is_err());
+    }
+
+    #[test]
+    fn debug_and_trace_flags_work_after_subcommands() {
+        let args = Args::parse_from(["ewc", "count", "--debug", "src"]);
+        assert!(args.debug);
+        assert!(!args.trace);
+        assert!(Args::parse_from(["ewc", "--trace", "src"]).trace);
+    }
+
+    #[test]
+    fn cache_clear_subcommand_and_no_cache_flag() {
+        let args = Args::parse_from(["ewc", "cache", "clear"]);
+        assert_eq!(
+            args.command,
+            Some(Command::Cache {
+                action: CacheAction::Clear
+            })
+        );
+        assert!(Args::parse_from(["ewc", "--no-cache", "src"]).no_cache);
+    }
+
+    #[test]
+    fn files_still_parsed_without_subcommand() {
+        let args = Args::parse_from(["ewc", "a.txt", "b.txt"]);
+        assert_eq!(args.command, None);
+        assert_eq!(args.files, vec!["a.txt", "b.txt"]);
+    }
 }
@@ -0,0 +1,121 @@
+/// The kinds of project basic COCOMO has coefficients for.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectType {
+    /// Small teams on familiar problems
+    #[default]
+    Organic,
+    SemiDetached,
+    /// Tight hardware, software or operational constraints
+    Embedded,
+}
+
+impl ProjectType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ProjectType::Organic => "organic",
+            ProjectType::SemiDetached => "semi-detached",
+            ProjectType::Embedded => "embedded",
+        }
+    }
+
+    /// Effort `a`, `b` and schedule `c`, `d` in `effort = a * KLOC^b` and
+    /// `schedule = c * effort^d`.
+    fn coefficients(self) -> (f64, f64, f64, f64) {
+        match self {
+            ProjectType::Organic => (2.4, 1.05, 2.5, 0.38),
+            ProjectType::SemiDetached => (3.0, 1.12, 2.5, 0.35),
+            ProjectType::Embedded => (3.6, 1.20, 2.5, 0.32),
+        }
+    }
+}
+
+/// What `--cocomo` assumes; the defaults are scc's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CocomoParams {
+    pub project_type: ProjectType,
+    /// Yearly salary of one developer
+    pub annual_wage: f64,
+    /// Multiplier from salary to the full cost of a developer
+    pub overhead: f64,
+    /// Effort adjustment factor scaling the effort estimate
+    pub eaf: f64,
+}
+
+impl Default for CocomoParams {
+    fn default() -> Self {
+        Self {
+            project_type: ProjectType::Organic,
+            annual_wage: 56_286.0,
+            overhead: 2.4,
+            eaf: 1.0,
+        }
+    }
+}
+
+/// A basic COCOMO estimate of what writing the code again would take.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// Person-months
+    pub effort: f64,
+    /// Months from start to finish
+    pub schedule: f64,
+    pub people: f64,
+    pub cost: f64,
+}
+
+/// Estimates effort, schedule, people and cost from `code_lines` (comments and blank
+/// lines left out). A fun figure rather than a plan: basic COCOMO knows nothing but
+/// size.
+pub fn estimate(code_lines: usize, params: &CocomoParams) -> Estimate {
+    let (a, b, c, d) = params.project_type.coefficients();
+    let kloc = code_lines as f64 / 1000.0;
+    let effort = a * kloc.powf(b) * params.eaf;
+    let schedule = c * effort.powf(d);
+    let people = if schedule > 0.0 {
+        effort / schedule
+    } else {
+        0.0
+    };
+    Estimate {
+        effort,
+        schedule,
+        people,
+        cost: effort * params.annual_wage / 12.0 * params.overhead,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn estimates_like_scc() {
+        let estimate = estimate(10_000, &CocomoParams::default());
+        assert!(close(estimate.effort, 26.93), "{estimate:?}");
+        assert!(close(estimate.schedule, 8.74), "{estimate:?}");
+        assert!(close(estimate.people, 3.08), "{estimate:?}");
+        assert!((estimate.cost - 303_139.0).abs() < 1.0, "{estimate:?}");
+    }
+
+    #[test]
+    fn harder_projects_cost_more() {
+        let params = |project_type| CocomoParams {
+            project_type,
+            ..CocomoParams::default()
+        };
+        let organic = estimate(50_000, &params(ProjectType::Organic));
+        let embedded = estimate(50_000, &params(ProjectType::Embedded));
+        assert!(embedded.effort > organic.effort);
+    }
+
+    #[test]
+    fn no_code_costs_nothing() {
+        let estimate = estimate(0, &CocomoParams::default());
+        assert_eq!(estimate.cost, 0.0);
+        assert_eq!(estimate.people, 0.0);
+    }
+}
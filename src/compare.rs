@@ -0,0 +1,126 @@
+use std::io;
+use std::path::Path;
+
+use crate::counter::{count_directory_detailed, FilterConfig};
+use crate::delta::{compare_reports, ReportDelta};
+use crate::report::{Report, ReportEntry};
+
+/// Per-file differences between two directory trees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeComparison {
+    /// Totals of the first tree
+    pub a: ReportEntry,
+    /// Totals of the second tree
+    pub b: ReportEntry,
+    /// Changes from `a` to `b`, keyed by path relative to each root
+    pub delta: ReportDelta,
+}
+
+fn tree_report(root: &Path, config: &FilterConfig) -> io::Result<Report> {
+    if !root.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a directory",
+        ));
+    }
+
+    let (entries, _) = count_directory_detailed(root, config)?;
+    Ok(Report::from_entries(
+        entries
+            .into_iter()
+            .map(|entry| {
+                let relative = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+                ReportEntry {
+                    // Normalize separators so the same file matches on every platform
+                    name: relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/"),
+                    count: entry.count,
+                    file_count: 1,
+                    is_directory: false,
+                }
+            })
+            .collect(),
+    ))
+}
+
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {e}", path.display()))
+}
+
+fn summary(name: &Path, report: &Report) -> ReportEntry {
+    ReportEntry {
+        name: name.display().to_string(),
+        count: report.total,
+        file_count: report.file_count,
+        is_directory: true,
+    }
+}
+
+/// Counts both trees and matches their files by relative path.
+///
+/// Errors name the directory that could not be counted.
+pub fn compare_trees(a: &Path, b: &Path, config: &FilterConfig) -> io::Result<TreeComparison> {
+    let report_a = tree_report(a, config).map_err(|e| with_path(a, e))?;
+    let report_b = tree_report(b, config).map_err(|e| with_path(b, e))?;
+
+    Ok(TreeComparison {
+        a: summary(a, &report_a),
+        b: summary(b, &report_b),
+        delta: compare_reports(&report_b, &report_a),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delta::ChangeKind;
+    use std::fs;
+
+    #[test]
+    fn compare_trees_only_in_and_changed() {
+        let a = tempfile::tempdir().unwrap();
+        let b = tempfile::tempdir().unwrap();
+        fs::create_dir(a.path().join("src")).unwrap();
+        fs::create_dir(b.path().join("src")).unwrap();
+        fs::write(a.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(b.path().join("src/main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        fs::write(a.path().join("same.txt"), "same\n").unwrap();
+        fs::write(b.path().join("same.txt"), "same\n").unwrap();
+        fs::write(a.path().join("old.txt"), "old\n").unwrap();
+        fs::write(b.path().join("new.txt"), "new file\n").unwrap();
+
+        let comparison = compare_trees(a.path(), b.path(), &FilterConfig::default()).unwrap();
+        assert_eq!(comparison.a.file_count, 3);
+        assert_eq!(comparison.b.file_count, 3);
+
+        let changes: Vec<(&str, ChangeKind)> = comparison
+            .delta
+            .changes
+            .iter()
+            .map(|c| (c.name.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("new.txt", ChangeKind::Added),
+                ("src/main.rs", ChangeKind::Changed),
+                ("old.txt", ChangeKind::Removed),
+            ]
+        );
+        assert_eq!(comparison.delta.changes[1].delta.lines, 2);
+        assert_eq!(comparison.delta.total.files, 0);
+    }
+
+    #[test]
+    fn compare_trees_rejects_files() {
+        let a = tempfile::tempdir().unwrap();
+        let file = a.path().join("a.txt");
+        fs::write(&file, "a\n").unwrap();
+
+        let err = compare_trees(a.path(), &file, &FilterConfig::default()).unwrap_err();
+        assert!(err.to_string().contains("a.txt: not a directory"));
+    }
+}
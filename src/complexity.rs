@@ -0,0 +1,189 @@
+use std::path::Path;
+
+/// What the decision-point scan needs to know about a language.
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Characters that open a string literal closed by the same character
+    quotes: &'static [u8],
+    /// Keywords that each add a decision point
+    keywords: &'static [&'static str],
+    /// Operators that each add a decision point
+    operators: &'static [&'static str],
+}
+
+const RUST: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    // Not `'`, which also starts lifetimes
+    quotes: b"\"",
+    keywords: &["if", "for", "while", "match"],
+    operators: &["&&", "||"],
+};
+
+const C_FAMILY: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: b"\"'`",
+    keywords: &["if", "for", "while", "case", "catch"],
+    operators: &["&&", "||"],
+};
+
+const GO: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: b"\"'`",
+    keywords: &["if", "for", "case"],
+    operators: &["&&", "||"],
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: b"\"'",
+    keywords: &["if", "elif", "for", "while", "except", "case", "and", "or"],
+    operators: &[],
+};
+
+const RUBY: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: b"\"'",
+    keywords: &[
+        "if", "elsif", "unless", "while", "until", "for", "when", "rescue", "and", "or",
+    ],
+    operators: &["&&", "||"],
+};
+
+const SHELL: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: b"\"'",
+    keywords: &["if", "elif", "for", "while", "until", "case"],
+    operators: &["&&", "||"],
+};
+
+fn syntax_for(path: &Path) -> Option<&'static Syntax> {
+    let syntax = match path.extension()?.to_str()? {
+        "rs" => &RUST,
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "java" | "js" | "jsx" | "mjs" | "cjs"
+        | "ts" | "tsx" | "mts" | "cts" | "cs" | "kt" | "kts" | "swift" | "scala" | "dart"
+        | "php" => &C_FAMILY,
+        "go" => &GO,
+        "py" | "pyi" => &PYTHON,
+        "rb" => &RUBY,
+        "sh" | "bash" | "zsh" => &SHELL,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Whether `--complexity` knows the language of this file, judged by its extension.
+pub fn is_supported(path: &Path) -> bool {
+    syntax_for(path).is_some()
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// A cheap complexity estimate for `content`: the number of branching keywords (`if`,
+/// `for`, `while`, `match`, `case`, ...) and short-circuit operators (`&&`, `||`) outside
+/// comments and string literals, using the rules for `path`'s extension. `None` for
+/// languages it does not know.
+pub fn decision_points(path: &Path, content: &str) -> Option<usize> {
+    let syntax = syntax_for(path)?;
+    let bytes = content.as_bytes();
+    let mut points = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &content[i..];
+        if syntax.line_comments.iter().any(|c| rest.starts_with(c)) {
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else if let Some((open, close)) = syntax
+            .block_comment
+            .filter(|(open, _)| rest.starts_with(open))
+        {
+            i += open.len()
+                + rest[open.len()..]
+                    .find(close)
+                    .map_or(rest.len() - open.len(), |end| end + close.len());
+        } else if syntax.quotes.contains(&bytes[i]) {
+            let quote = bytes[i];
+            i += 1;
+            while i < bytes.len() && bytes[i] != quote {
+                // Skip the escaped character too
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if is_identifier_byte(bytes[i]) {
+            let start = i;
+            while i < bytes.len() && is_identifier_byte(bytes[i]) {
+                i += 1;
+            }
+            if syntax.keywords.contains(&&content[start..i]) {
+                points += 1;
+            }
+        } else if let Some(op) = syntax.operators.iter().find(|op| rest.starts_with(*op)) {
+            points += 1;
+            i += op.len();
+        } else {
+            // Step over a whole character, so slicing stays on boundaries
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    Some(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(name: &str, content: &str) -> usize {
+        decision_points(Path::new(name), content).unwrap()
+    }
+
+    #[test]
+    fn unknown_languages_have_no_estimate() {
+        assert!(!is_supported(Path::new("notes.txt")));
+        assert_eq!(decision_points(Path::new("notes.txt"), "if a && b"), None);
+    }
+
+    #[test]
+    fn counts_keywords_and_operators_outside_comments_and_strings() {
+        let content = r#"
+fn classify(n: i32) -> &'static str {
+    // if this were a loop
+    /* while && || */
+    if n > 0 && n < 10 {
+        "if small"
+    } else if n == 0 || n == -1 {
+        "zero-ish"
+    } else {
+        match n { _ => "other" }
+    }
+}
+"#;
+        // if, &&, if, ||, match
+        assert_eq!(points("lib.rs", content), 5);
+    }
+
+    #[test]
+    fn identifiers_containing_keywords_do_not_count() {
+        assert_eq!(points("a.js", "const iffy = forEach(whileLoop);"), 0);
+        assert_eq!(points("a.js", "switch (x) { case 1: case 2: break; }"), 2);
+    }
+
+    #[test]
+    fn python_counts_boolean_keywords() {
+        let content = "def f(a, b):\n    \"\"\"if docstring\"\"\"\n    if a and b:  # or not\n        return 1\n    elif a or b:\n        return 2\n";
+        assert_eq!(points("f.py", content), 4);
+    }
+
+    #[test]
+    fn unterminated_strings_and_comments_end_at_the_end() {
+        assert_eq!(points("a.c", "if (x) /* if"), 1);
+        assert_eq!(points("a.c", "if (x) \"if"), 1);
+        assert_eq!(points("a.c", "if (x) \"\\"), 1);
+    }
+}
@@ -0,0 +1,313 @@
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+use crate::budget::Metric;
+use crate::cli::OutputFormat;
+
+/// Name of the project configuration file, looked up from the working directory upwards
+pub const PROJECT_FILE: &str = ".ewc.toml";
+
+/// The output a config file asks for when no `--json` or `--format` is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultFormat {
+    Text,
+    Json,
+    Format(OutputFormat),
+}
+
+/// Defaults read from configuration files; command-line flags override them.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Config {
+    pub format: Option<DefaultFormat>,
+    pub exclude: Vec<String>,
+    pub include: Vec<String>,
+    /// `false` behaves like `--no-color`
+    pub color: Option<bool>,
+    pub threads: Option<NonZeroUsize>,
+    /// Counts to show when none of `-l`, `-w`, `-c` or `-L` is given
+    pub metrics: Vec<Metric>,
+    /// `[profile.NAME]` sections, selected with `--profile NAME`
+    pub profiles: BTreeMap<String, Config>,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_patterns(value: &Value, key: &str) -> io::Result<Vec<String>> {
+    let not_list = || invalid(format!("'{key}' must be a list of glob patterns"));
+    value
+        .as_array()
+        .ok_or_else(not_list)?
+        .iter()
+        .map(|pattern| pattern.as_str().map(str::to_string).ok_or_else(not_list))
+        .collect()
+}
+
+fn read_format(value: &Value) -> io::Result<DefaultFormat> {
+    let name = value
+        .as_str()
+        .ok_or_else(|| invalid("'format' must be a string".to_string()))?;
+    match name {
+        "text" => Ok(DefaultFormat::Text),
+        "json" => Ok(DefaultFormat::Json),
+        _ => OutputFormat::from_str(name, false)
+            .map(DefaultFormat::Format)
+            .map_err(|_| invalid(format!("unknown format '{name}'"))),
+    }
+}
+
+fn read_metrics(value: &Value) -> io::Result<Vec<Metric>> {
+    let not_list = || invalid("'metrics' must be a list of metric names".to_string());
+    value
+        .as_array()
+        .ok_or_else(not_list)?
+        .iter()
+        .map(|name| name.as_str().ok_or_else(not_list)?.parse().map_err(invalid))
+        .collect()
+}
+
+impl Config {
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let table: Table = content.parse().map_err(|e| invalid(format!("{e}")))?;
+        Self::read_table(&table, true)
+    }
+
+    fn read_table(table: &Table, top_level: bool) -> io::Result<Self> {
+        let mut config = Self::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "format" => config.format = Some(read_format(value)?),
+                "exclude" => config.exclude = read_patterns(value, key)?,
+                "include" => config.include = read_patterns(value, key)?,
+                "color" => {
+                    config.color = Some(
+                        value
+                            .as_bool()
+                            .ok_or_else(|| invalid("'color' must be true or false".to_string()))?,
+                    )
+                }
+                "threads" => {
+                    let threads = value
+                        .as_integer()
+                        .and_then(|n| usize::try_from(n).ok())
+                        .and_then(NonZeroUsize::new)
+                        .ok_or_else(|| {
+                            invalid("'threads' must be a positive integer".to_string())
+                        })?;
+                    config.threads = Some(threads);
+                }
+                "metrics" => config.metrics = read_metrics(value)?,
+                "profile" if top_level => {
+                    let not_table =
+                        || invalid("'profile' must hold [profile.NAME] tables".to_string());
+                    for (name, profile) in value.as_table().ok_or_else(not_table)? {
+                        let profile =
+                            Self::read_table(profile.as_table().ok_or_else(not_table)?, false)
+                                .map_err(|e| invalid(format!("profile '{name}': {e}")))?;
+                        config.profiles.insert(name.clone(), profile);
+                    }
+                }
+                _ => return Err(invalid(format!("unknown setting '{key}'"))),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Reads a config file, or `None` if there is none at `path`.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content)
+                .map(Some)
+                .map_err(|e| invalid(format!("{}: {e}", path.display()))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Layers `other` over `self`: its settings win, and its patterns are added to ours.
+    pub fn merge(mut self, other: Self) -> Self {
+        self.format = other.format.or(self.format);
+        self.exclude.extend(other.exclude);
+        self.include.extend(other.include);
+        self.color = other.color.or(self.color);
+        self.threads = other.threads.or(self.threads);
+        if !other.metrics.is_empty() {
+            self.metrics = other.metrics;
+        }
+        for (name, profile) in other.profiles {
+            let merged = match self.profiles.remove(&name) {
+                Some(existing) => existing.merge(profile),
+                None => profile,
+            };
+            self.profiles.insert(name, merged);
+        }
+        self
+    }
+
+    /// Layers the named profile over the top-level settings.
+    pub fn with_profile(mut self, name: &str) -> io::Result<Self> {
+        let Some(profile) = self.profiles.remove(name) else {
+            let available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            let message = if available.is_empty() {
+                format!("unknown profile '{name}' (no profiles are defined)")
+            } else {
+                format!(
+                    "unknown profile '{name}' (available: {})",
+                    available.join(", ")
+                )
+            };
+            return Err(invalid(message));
+        };
+        Ok(self.merge(profile))
+    }
+}
+
+/// Where the user config lives: `$XDG_CONFIG_HOME/ewc`, `~/.config/ewc` or `%APPDATA%\ewc`.
+pub fn user_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(dir.join("ewc").join("config.toml"))
+}
+
+/// The nearest `.ewc.toml` in `start` or one of its ancestors.
+pub fn project_path(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// The user config with the project config layered over it.
+pub fn load_defaults() -> io::Result<Config> {
+    let mut config = Config::default();
+    if let Some(user) = user_path() {
+        config = config.merge(Config::load(&user)?.unwrap_or_default());
+    }
+    if let Some(project) = env::current_dir().ok().and_then(|dir| project_path(&dir)) {
+        config = config.merge(Config::load(&project)?.unwrap_or_default());
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_all_settings() {
+        let config = Config::parse(
+            r#"
+            format = "markdown"
+            exclude = ["target/**", "*.lock"]
+            include = ["*.rs"]
+            color = false
+            threads = 4
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                format: Some(DefaultFormat::Format(OutputFormat::Markdown)),
+                exclude: vec!["target/**".to_string(), "*.lock".to_string()],
+                include: vec!["*.rs".to_string()],
+                color: Some(false),
+                threads: NonZeroUsize::new(4),
+                ..Config::default()
+            }
+        );
+        assert_eq!(
+            Config::parse(r#"format = "json""#).unwrap().format,
+            Some(DefaultFormat::Json)
+        );
+    }
+
+    #[test]
+    fn parse_rejects_bad_settings() {
+        for content in [
+            r#"format = "yaml""#,
+            r#"exclude = "target""#,
+            "threads = 0",
+            "colour = false",
+            r#"metrics = ["lines", "pages"]"#,
+            "[profile.docs]\nprofile = {}",
+            "not toml",
+        ] {
+            assert!(Config::parse(content).is_err(), "{content}");
+        }
+    }
+
+    #[test]
+    fn merge_overrides_settings_and_adds_patterns() {
+        let user = Config::parse("exclude = [\"*.md\"]\nthreads = 2\ncolor = false").unwrap();
+        let project = Config::parse("exclude = [\"target/**\"]\nthreads = 8").unwrap();
+        let config = user.merge(project);
+        assert_eq!(config.exclude, ["*.md", "target/**"]);
+        assert_eq!(config.threads, NonZeroUsize::new(8));
+        assert_eq!(config.color, Some(false));
+    }
+
+    #[test]
+    fn profiles_layer_over_top_level_settings() {
+        let config = Config::parse(
+            r#"
+            exclude = ["target/**"]
+            threads = 2
+
+            [profile.docs]
+            include = ["*.md"]
+            metrics = ["words", "max-line-length"]
+
+            [profile.code]
+            exclude = ["*.md"]
+            metrics = ["lines"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.profiles.keys().collect::<Vec<_>>(), ["code", "docs"]);
+
+        let docs = config.clone().with_profile("docs").unwrap();
+        assert_eq!(docs.exclude, ["target/**"]);
+        assert_eq!(docs.include, ["*.md"]);
+        assert_eq!(docs.metrics, [Metric::Words, Metric::MaxLineLength]);
+        assert_eq!(docs.threads, NonZeroUsize::new(2));
+
+        let code = config.clone().with_profile("code").unwrap();
+        assert_eq!(code.exclude, ["target/**", "*.md"]);
+
+        let error = config.with_profile("tests").unwrap_err();
+        assert!(error.to_string().contains("available: code, docs"));
+    }
+
+    #[test]
+    fn project_profiles_extend_user_profiles() {
+        let user = Config::parse("[profile.docs]\ninclude = [\"*.md\"]\nthreads = 1").unwrap();
+        let project = Config::parse("[profile.docs]\ninclude = [\"*.rst\"]").unwrap();
+        let docs = user.merge(project).with_profile("docs").unwrap();
+        assert_eq!(docs.include, ["*.md", "*.rst"]);
+        assert_eq!(docs.threads, NonZeroUsize::new(1));
+    }
+
+    #[test]
+    fn project_path_searches_ancestors() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(project_path(&nested), None);
+
+        fs::write(dir.path().join(PROJECT_FILE), "threads = 1\n").unwrap();
+        assert_eq!(project_path(&nested), Some(dir.path().join(PROJECT_FILE)));
+        assert!(Config::load(&dir.path().join("missing.toml"))
+            .unwrap()
+            .is_none());
+    }
+}
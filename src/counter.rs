@@ -1,18 +1,119 @@
+#[cfg(feature = "walk")]
 use globset::{Glob, GlobSet, GlobSetBuilder};
+#[cfg(feature = "walk")]
 use rayon::prelude::*;
 use std::fs;
 use std::io::{self, Read};
 use std::iter::Sum;
 use std::ops::{Add, AddAssign};
 use std::path::{Path, PathBuf};
+#[cfg(feature = "walk")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "walk")]
+use std::sync::{mpsc, Arc, Mutex};
+#[cfg(feature = "walk")]
+use std::thread;
+use std::time::{Duration, Instant};
+#[cfg(feature = "walk")]
+use tracing::{debug, trace};
+#[cfg(feature = "walk")]
 use walkdir::WalkDir;
 
+use crate::documents;
+#[cfg(feature = "walk")]
+use crate::generated::{is_generated_content, Generated, GeneratedFilter};
+#[cfg(feature = "walk")]
+use crate::git::{self, GitSelection};
+#[cfg(all(feature = "uring", target_os = "linux"))]
+use crate::uring;
+
 pub struct FileEntry {
     pub path: PathBuf,
     pub count: Count,
 }
 
-#[derive(Debug, Default, PartialEq, Clone)]
+/// A file, or a directory that could not be listed, left out of a count and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub kind: io::ErrorKind,
+    pub reason: String,
+}
+
+impl SkippedFile {
+    pub fn new(path: &Path, error: &io::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind: error.kind(),
+            reason: error.to_string(),
+        }
+    }
+}
+
+/// How many entries directory counts left out, by why.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SkipSummary {
+    /// Hidden files, and hidden directories as one entry each
+    pub hidden: usize,
+    /// Files left out for not being hidden, when only hidden ones are counted
+    pub visible: usize,
+    /// Files matching an exclude pattern or no include pattern
+    pub excluded: usize,
+    /// Generated and vendored files
+    pub generated: usize,
+    /// Files that are not UTF-8 text
+    pub binary: usize,
+    /// Files and directories that could not be read
+    pub unreadable: usize,
+    /// Named pipes, sockets and devices not read
+    pub special: usize,
+    /// Symbolic links not followed
+    pub symlinks: usize,
+    /// Files not counted before [`FilterConfig::deadline`], and the rest of a walk it
+    /// cut short as one entry
+    pub timed_out: usize,
+}
+
+impl SkipSummary {
+    /// Whether nothing was left out
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Each reason with its count, for those that left anything out.
+    pub fn reasons(&self) -> Vec<(&'static str, usize)> {
+        [
+            ("hidden", self.hidden),
+            ("visible", self.visible),
+            ("excluded", self.excluded),
+            ("generated", self.generated),
+            ("binary", self.binary),
+            ("unreadable", self.unreadable),
+            ("special", self.special),
+            ("symlinks", self.symlinks),
+            ("timed out", self.timed_out),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .collect()
+    }
+}
+
+impl AddAssign for SkipSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.hidden += other.hidden;
+        self.visible += other.visible;
+        self.excluded += other.excluded;
+        self.generated += other.generated;
+        self.binary += other.binary;
+        self.unreadable += other.unreadable;
+        self.special += other.special;
+        self.symlinks += other.symlinks;
+        self.timed_out += other.timed_out;
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
 pub struct Count {
     pub lines: usize,
     pub words: usize,
@@ -21,13 +122,23 @@ pub struct Count {
 }
 
 impl Count {
+    /// Counts `content` in one pass over its bytes. Lines and their lengths follow
+    /// [`str::lines`], so a `\r\n` ending is not part of a line, and words are split
+    /// on Unicode whitespace like [`str::split_whitespace`].
     pub fn from_content(content: &str) -> Self {
-        Self {
-            lines: content.lines().count(),
-            words: content.split_whitespace().count(),
-            bytes: content.len(),
-            max_line_length: content.lines().map(|l| l.len()).max().unwrap_or(0),
-        }
+        let mut counter = IncrementalCount::new();
+        counter
+            .feed(content.as_bytes())
+            .expect("a str is valid UTF-8");
+        counter.count()
+    }
+
+    /// Counts raw bytes like [`Count::from_content`] without copying them into a
+    /// `String`; only bytes outside ASCII are checked to be valid UTF-8.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut counter = IncrementalCount::new();
+        counter.feed(bytes)?;
+        counter.finish()
     }
 }
 
@@ -59,13 +170,159 @@ impl Sum for Count {
     }
 }
 
+impl<'a> Sum<&'a Count> for Count {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+/// Lines, words and bytes of a file or several, for the averages per line of `--ratios`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct LineRatios {
+    pub lines: usize,
+    pub words: usize,
+    pub bytes: usize,
+}
+
+impl LineRatios {
+    /// Average words per line, or `None` without lines.
+    pub fn words_per_line(&self) -> Option<f64> {
+        (self.lines > 0).then(|| self.words as f64 / self.lines as f64)
+    }
+
+    /// Average bytes per line, or `None` without lines.
+    pub fn bytes_per_line(&self) -> Option<f64> {
+        (self.lines > 0).then(|| self.bytes as f64 / self.lines as f64)
+    }
+}
+
+impl From<&Count> for LineRatios {
+    fn from(count: &Count) -> Self {
+        Self {
+            lines: count.lines,
+            words: count.words,
+            bytes: count.bytes,
+        }
+    }
+}
+
+impl Add for LineRatios {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            lines: self.lines + other.lines,
+            words: self.words + other.words,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+}
+
+#[cfg(feature = "walk")]
 #[derive(Debug, Default, Clone)]
 pub struct FilterConfig {
     pub include_hidden: bool,
+    /// Count only hidden files and the files in hidden directories, below the root
+    pub only_hidden: bool,
     pub exclude_patterns: Vec<String>,
     pub include_patterns: Vec<String>,
+    pub git_selection: Option<GitSelection>,
+    pub include_generated: bool,
+    /// Only descend this many directories below the root (1 = files directly in it)
+    pub max_depth: Option<usize>,
+    /// Read named pipes, sockets and devices met in a walk within these limits; by
+    /// default they are reported as skipped
+    pub special_files: Option<SpecialFileLimits>,
+    /// What to do with symbolic links met in a walk, to files and directories alike
+    pub symlinks: SymlinkPolicy,
+    /// The order a directory count returns its files in
+    pub order: EntryOrder,
+    /// Stop walking and counting once this passes, leaving the rest out as
+    /// [`FilterReason::TimedOut`]
+    pub deadline: Option<Instant>,
+    /// Bytes read at a time from streamed input such as pipes; [`DEFAULT_CHUNK_SIZE`]
+    /// when unset
+    pub chunk_size: Option<usize>,
+    /// Globs built by [`FilterConfig::compile`]
+    pub(crate) compiled: Option<Arc<CompiledGlobs>>,
+}
+
+/// How long to wait for, and how much to read from, a named pipe, socket or device.
+#[cfg(feature = "walk")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpecialFileLimits {
+    /// Give up on a file whose input has not ended by then
+    pub timeout: Duration,
+    /// Give up on a file that yields more bytes than this
+    pub max_bytes: usize,
+}
+
+#[cfg(feature = "walk")]
+impl Default for SpecialFileLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// What a walk does with a symbolic link below the counted path. A path given to count
+/// is always followed, whatever it points to.
+#[cfg(feature = "walk")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Leave links out
+    #[default]
+    Skip,
+    /// Count the files links point to and descend into linked directories
+    Follow,
+    /// Leave links out and report each one as a skipped file
+    Report,
 }
 
+/// The order a directory count returns its files in.
+#[cfg(feature = "walk")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EntryOrder {
+    /// By path, the same from run to run
+    #[default]
+    Sorted,
+    /// As the walk found them, special files last
+    Discovery,
+    /// As they finished counting, which differs from run to run
+    Completion,
+}
+
+/// Files of a directory count, each with its place in the order they finished
+/// counting, put in `order`.
+#[cfg(feature = "walk")]
+pub(crate) fn order_entries(
+    mut entries: Vec<(usize, FileEntry)>,
+    order: EntryOrder,
+) -> Vec<FileEntry> {
+    match order {
+        EntryOrder::Sorted => entries.sort_by(|(_, a), (_, b)| a.path.cmp(&b.path)),
+        EntryOrder::Discovery => {}
+        EntryOrder::Completion => entries.sort_by_key(|(finished, _)| *finished),
+    }
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Exclude and include glob sets, with the patterns they were built from.
+#[cfg(feature = "walk")]
+#[derive(Debug)]
+pub(crate) struct CompiledGlobs {
+    exclude_patterns: Vec<String>,
+    include_patterns: Vec<String>,
+    exclude: GlobSet,
+    include: GlobSet,
+    /// The hidden names include patterns spell out, such as `.github` in `.github/**`,
+    /// whose files and directories are walked even without `include_hidden`
+    included_hidden: GlobSet,
+}
+
+#[cfg(feature = "walk")]
 impl FilterConfig {
     pub fn new(
         include_hidden: bool,
@@ -74,8 +331,57 @@ impl FilterConfig {
     ) -> Self {
         Self {
             include_hidden,
+            only_hidden: false,
             exclude_patterns,
             include_patterns,
+            git_selection: None,
+            include_generated: false,
+            max_depth: None,
+            special_files: None,
+            symlinks: SymlinkPolicy::Skip,
+            order: EntryOrder::Sorted,
+            deadline: None,
+            chunk_size: None,
+            compiled: None,
+        }
+    }
+
+    /// Parses the glob patterns once, so every directory counted with this config
+    /// reuses them, and reports invalid patterns up front.
+    pub fn compile(mut self) -> io::Result<Self> {
+        self.compiled = Some(Arc::new(self.build_globs()?));
+        Ok(self)
+    }
+
+    fn build_globs(&self) -> io::Result<CompiledGlobs> {
+        let started = Instant::now();
+        let globs = CompiledGlobs {
+            exclude: Self::build_globset(&self.exclude_patterns)?,
+            include: Self::build_globset(&self.include_patterns)?,
+            included_hidden: Self::build_globset(&hidden_components(&self.include_patterns))?,
+            exclude_patterns: self.exclude_patterns.clone(),
+            include_patterns: self.include_patterns.clone(),
+        };
+        debug!(
+            exclude = ?self.exclude_patterns,
+            include = ?self.include_patterns,
+            elapsed = ?started.elapsed(),
+            "compiled glob patterns"
+        );
+        Ok(globs)
+    }
+
+    /// The compiled globs, built afresh if the config was not compiled or its patterns
+    /// changed since.
+    fn globs(&self) -> io::Result<Arc<CompiledGlobs>> {
+        match &self.compiled {
+            Some(compiled)
+                if compiled.exclude_patterns == self.exclude_patterns
+                    && compiled.include_patterns == self.include_patterns =>
+            {
+                Ok(Arc::clone(compiled))
+            }
+            _ => self.build_globs().map(Arc::new),
         }
     }
 
@@ -99,17 +405,320 @@ impl FilterConfig {
     }
 }
 
+/// Joins a relative path onto `root` a component at a time. Under a Windows verbatim
+/// root (`\\?\C:\...`) only `\` separates components, so the `/`-separated paths git
+/// lists would otherwise name a file that does not exist.
+#[cfg(feature = "walk")]
+pub(crate) fn join_relative(root: &Path, relative: &Path) -> PathBuf {
+    let mut path = root.to_path_buf();
+    path.extend(relative.components());
+    path
+}
+
+/// Bytes read at a time from streamed input unless configured otherwise
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Counts a file. Pipes, sockets and devices, such as `/dev/stdin` or the `/dev/fd/N`
+/// paths of shell process substitution, are streamed rather than read whole, and
+/// documents such as PDF are counted by their text (see [`documents::is_document`]).
 pub fn count_file(path: &Path) -> io::Result<Count> {
-    let content = fs::read_to_string(path)?;
-    Ok(Count::from_content(&content))
+    count_file_chunked(path, DEFAULT_CHUNK_SIZE)
+}
+
+/// Like [`count_file`], streaming pipes, sockets and devices `chunk_size` bytes at a time.
+pub fn count_file_chunked(path: &Path, chunk_size: usize) -> io::Result<Count> {
+    if is_pipe_like(path) {
+        let file = fs::File::open(path)?;
+        return count_chunks(file, chunk_size, None, |_| {}).map(|(count, _)| count);
+    }
+    if documents::is_document(path) {
+        let content = documents::read_to_string(path)?;
+        return Ok(Count::from_content(&content));
+    }
+    Count::from_bytes(&fs::read(path)?)
 }
 
-pub fn count_from_reader<R: Read>(mut reader: R) -> io::Result<Count> {
-    let mut content = String::new();
-    reader.read_to_string(&mut content)?;
-    Ok(Count::from_content(&content))
+/// Whether `path`, after following symlinks, is something other than a file or directory.
+fn is_pipe_like(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| {
+        let file_type = metadata.file_type();
+        !file_type.is_file() && !file_type.is_dir()
+    })
+}
+
+pub fn count_from_reader<R: Read>(reader: R) -> io::Result<Count> {
+    count_chunks(reader, DEFAULT_CHUNK_SIZE, None, |_| {}).map(|(count, _)| count)
+}
+
+/// Like [`count_from_reader`], but streams the input and calls `progress` with the
+/// running counts at most once per `interval`.
+pub fn count_from_reader_with_progress<R: Read>(
+    reader: R,
+    interval: Duration,
+    progress: impl FnMut(&Count),
+) -> io::Result<Count> {
+    count_from_reader_limited(reader, DEFAULT_CHUNK_SIZE, None, interval, progress)
+        .map(|(count, _)| count)
+}
+
+/// Like [`count_from_reader_with_progress`], but reads `chunk_size` bytes at a time and
+/// no more than `limit` bytes when there is one. Returns the counts and whether input
+/// was left unread; a character cut off by the limit is not counted.
+pub fn count_from_reader_limited<R: Read>(
+    reader: R,
+    chunk_size: usize,
+    limit: Option<u64>,
+    interval: Duration,
+    mut progress: impl FnMut(&Count),
+) -> io::Result<(Count, bool)> {
+    let mut last_report = Instant::now();
+    count_chunks(reader, chunk_size, limit, |count| {
+        if last_report.elapsed() >= interval {
+            progress(count);
+            last_report = Instant::now();
+        }
+    })
+}
+
+/// Counts `reader` `chunk_size` bytes at a time, so the input never has to fit in
+/// memory, calling `on_chunk` with the running counts after each chunk. Stops after
+/// `limit` bytes, if given, and tells whether input was left unread.
+fn count_chunks<R: Read>(
+    mut reader: R,
+    chunk_size: usize,
+    limit: Option<u64>,
+    mut on_chunk: impl FnMut(&Count),
+) -> io::Result<(Count, bool)> {
+    let mut counter = IncrementalCount::new();
+    let mut buffer = vec![0; chunk_size.max(1)];
+    let mut remaining = limit.unwrap_or(u64::MAX);
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if read as u64 > remaining {
+            // The limit falls inside this chunk; a character it cuts off is dropped
+            counter.feed(&buffer[..remaining as usize])?;
+            on_chunk(&counter.count());
+            return Ok((counter.count(), true));
+        }
+        remaining -= read as u64;
+        counter.feed(&buffer[..read])?;
+        on_chunk(&counter.count());
+    }
+    counter.finish().map(|count| (count, false))
+}
+
+/// Counts content fed in chunks, giving the same result as [`Count::from_content`]
+/// on the whole of it. This is the counting core for strings, files and streams: it
+/// scans bytes without allocating and decodes only characters outside ASCII.
+#[derive(Debug, Default, Clone)]
+pub struct IncrementalCount {
+    scan: Scan,
+    /// The start of a UTF-8 sequence split across chunks, and how many bytes of it
+    partial: [u8; 4],
+    partial_len: usize,
+}
+
+/// The running counts of [`IncrementalCount`], copied into locals while a chunk is
+/// scanned so they stay in registers.
+#[derive(Debug, Default, Clone, Copy)]
+struct Scan {
+    complete_lines: usize,
+    words: usize,
+    bytes: usize,
+    max_line_length: usize,
+    /// Bytes in the line not yet ended by a newline
+    line_length: usize,
+    /// Whether the unfinished line ends with `\r`, which a following `\n` strips
+    pending_cr: bool,
+    in_word: bool,
+}
+
+impl Scan {
+    /// Adds a character of `len` bytes.
+    #[inline(always)]
+    fn add_char(&mut self, is_whitespace: bool, len: usize, is_newline: bool, is_cr: bool) {
+        self.bytes += len;
+        if is_newline {
+            let length = self.line_length - usize::from(self.pending_cr);
+            self.max_line_length = self.max_line_length.max(length);
+            self.complete_lines += 1;
+            self.line_length = 0;
+        } else {
+            self.line_length += len;
+        }
+        self.pending_cr = is_cr;
+
+        if !is_whitespace && !self.in_word {
+            self.words += 1;
+        }
+        self.in_word = !is_whitespace;
+    }
+
+    /// Scans `chunk` up to a sequence cut off at its end, returning where that starts.
+    fn scan(&mut self, chunk: &[u8]) -> io::Result<usize> {
+        let mut i = 0;
+        while i < chunk.len() {
+            let byte = chunk[i];
+            if byte.is_ascii() {
+                // The ASCII White_Space characters, which include the vertical tab
+                let is_whitespace = matches!(byte, b'\t' | b'\n' | 0x0B | 0x0C | b'\r' | b' ');
+                self.add_char(is_whitespace, 1, byte == b'\n', byte == b'\r');
+                i += 1;
+                continue;
+            }
+            let len = sequence_len(byte).ok_or_else(invalid_utf8)?;
+            let Some(sequence) = chunk.get(i..i + len) else {
+                return if valid_prefix(&chunk[i..]) {
+                    Ok(i)
+                } else {
+                    Err(invalid_utf8())
+                };
+            };
+            let c = decode(sequence).ok_or_else(invalid_utf8)?;
+            self.add_char(c.is_whitespace(), len, false, false);
+            i += len;
+        }
+        Ok(i)
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+/// The length of the UTF-8 sequence starting with `lead`, a byte outside ASCII, or
+/// `None` for a byte no sequence starts with.
+fn sequence_len(lead: u8) -> Option<usize> {
+    match lead {
+        0xC2..=0xDF => Some(2),
+        0xE0..=0xEF => Some(3),
+        0xF0..=0xF4 => Some(4),
+        _ => None,
+    }
+}
+
+/// Whether `byte` may follow `lead` as the second byte of a sequence, which rules out
+/// overlong encodings, surrogates and code points past U+10FFFF.
+fn valid_second(lead: u8, byte: u8) -> bool {
+    match lead {
+        0xE0 => (0xA0..=0xBF).contains(&byte),
+        0xED => (0x80..=0x9F).contains(&byte),
+        0xF0 => (0x90..=0xBF).contains(&byte),
+        0xF4 => (0x80..=0x8F).contains(&byte),
+        _ => (0x80..=0xBF).contains(&byte),
+    }
+}
+
+/// Whether `bytes`, the start of a sequence led by a byte outside ASCII, can begin a
+/// character.
+fn valid_prefix(bytes: &[u8]) -> bool {
+    match bytes {
+        [] => true,
+        [lead, rest @ ..] => {
+            sequence_len(*lead).is_some_and(|len| rest.len() < len)
+                && rest
+                    .first()
+                    .is_none_or(|&second| valid_second(*lead, second))
+                && rest
+                    .iter()
+                    .skip(1)
+                    .all(|&byte| (0x80..=0xBF).contains(&byte))
+        }
+    }
 }
 
+/// The character of a whole sequence led by a byte outside ASCII, or `None` if it is
+/// not valid UTF-8.
+fn decode(sequence: &[u8]) -> Option<char> {
+    let continuation = |byte: u8| u32::from(byte & 0x3F);
+    let is_continuation = |byte: u8| byte & 0xC0 == 0x80;
+    let code = match *sequence {
+        [lead @ 0xC2..=0xDF, b] if is_continuation(b) => {
+            (u32::from(lead & 0x1F) << 6) | continuation(b)
+        }
+        [lead @ 0xE0..=0xEF, b, c] if valid_second(lead, b) && is_continuation(c) => {
+            (u32::from(lead & 0x0F) << 12) | (continuation(b) << 6) | continuation(c)
+        }
+        [lead @ 0xF0..=0xF4, b, c, d]
+            if valid_second(lead, b) && is_continuation(c) && is_continuation(d) =>
+        {
+            (u32::from(lead & 0x07) << 18)
+                | (continuation(b) << 12)
+                | (continuation(c) << 6)
+                | continuation(d)
+        }
+        _ => return None,
+    };
+    char::from_u32(code)
+}
+
+impl IncrementalCount {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the next chunk; multi-byte characters may be split between chunks.
+    pub fn feed(&mut self, mut chunk: &[u8]) -> io::Result<()> {
+        if self.partial_len > 0 {
+            let len = sequence_len(self.partial[0]).unwrap_or_default();
+            let needed = (len - self.partial_len).min(chunk.len());
+            self.partial[self.partial_len..self.partial_len + needed]
+                .copy_from_slice(&chunk[..needed]);
+            self.partial_len += needed;
+            chunk = &chunk[needed..];
+            let sequence = &self.partial[..self.partial_len];
+            if self.partial_len < len {
+                // Still cut off; make sure what there is can start a character
+                return if valid_prefix(sequence) {
+                    Ok(())
+                } else {
+                    Err(invalid_utf8())
+                };
+            }
+            let c = decode(sequence).ok_or_else(invalid_utf8)?;
+            self.partial_len = 0;
+            self.scan.add_char(c.is_whitespace(), len, false, false);
+        }
+
+        let mut scan = self.scan;
+        let scanned = scan.scan(chunk)?;
+        self.scan = scan;
+        let rest = &chunk[scanned..];
+        self.partial[..rest.len()].copy_from_slice(rest);
+        self.partial_len = rest.len();
+        Ok(())
+    }
+
+    /// Counts of everything fed so far.
+    pub fn count(&self) -> Count {
+        let scan = &self.scan;
+        let unfinished = scan.line_length > 0;
+        Count {
+            lines: scan.complete_lines + usize::from(unfinished),
+            words: scan.words,
+            bytes: scan.bytes,
+            max_line_length: scan.max_line_length.max(scan.line_length),
+        }
+    }
+
+    /// Final counts, failing if the input ended in the middle of a character.
+    pub fn finish(&self) -> io::Result<Count> {
+        if self.partial_len > 0 {
+            return Err(invalid_utf8());
+        }
+        Ok(self.count())
+    }
+}
+
+#[cfg(feature = "walk")]
 fn is_hidden(entry: &walkdir::DirEntry) -> bool {
     entry
         .file_name()
@@ -117,69 +726,879 @@ fn is_hidden(entry: &walkdir::DirEntry) -> bool {
         .is_some_and(|s| s.starts_with('.'))
 }
 
+/// A path found by a directory walk.
+#[cfg(feature = "walk")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Walked {
+    File(PathBuf),
+    /// A named pipe, socket or device, which may block or never end when read
+    Special(PathBuf, &'static str),
+    /// A symbolic link that is not followed
+    Symlink(PathBuf),
+    /// A file, or a whole hidden directory, the filters left out
+    Filtered(PathBuf, FilterReason),
+}
+
+/// Why a directory walk left an entry out, as passed to [`CountObserver::on_filtered`].
+#[cfg(feature = "walk")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterReason {
+    /// A hidden file, or a hidden directory not descended into
+    Hidden,
+    /// A file neither hidden nor in a hidden directory, with [`FilterConfig::only_hidden`]
+    Visible,
+    /// A file matching an exclude pattern, or no include pattern
+    Excluded,
+    /// A generated or vendored file, by its path or a marker in its content
+    Generated,
+    /// A named pipe, socket or device not read without [`FilterConfig::special_files`]
+    Special,
+    /// A file not counted, or the rest of a walk not done, before [`FilterConfig::deadline`]
+    TimedOut,
+}
+
+/// Whether `config.deadline` has passed, leaving `path` out of the count if it has.
+#[cfg(feature = "walk")]
+pub(crate) fn timed_out(path: &Path, config: &FilterConfig, observer: &dyn CountObserver) -> bool {
+    let passed = config
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline);
+    if passed {
+        debug!(path = %path.display(), reason = "timed out", "skipped");
+        observer.on_filtered(path, FilterReason::TimedOut);
+    }
+    passed
+}
+
+/// What kind of special file this is, or `None` for files, directories and symlinks.
+#[cfg(feature = "walk")]
+fn special_kind(file_type: fs::FileType) -> Option<&'static str> {
+    if file_type.is_file() || file_type.is_dir() || file_type.is_symlink() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return Some("named pipe");
+        } else if file_type.is_socket() {
+            return Some("socket");
+        } else if file_type.is_block_device() {
+            return Some("block device");
+        } else if file_type.is_char_device() {
+            return Some("character device");
+        }
+    }
+    Some("special file")
+}
+
+#[cfg(feature = "walk")]
+fn has_hidden_component(relative_path: &Path) -> bool {
+    relative_path
+        .components()
+        .any(|c| c.as_os_str().to_str().is_some_and(|s| s.starts_with('.')))
+}
+
+/// The components of `patterns` naming hidden files or directories, such as `.github`
+/// in `.github/workflows/*.yml` or `.*rc` in `**/.*rc`.
+#[cfg(feature = "walk")]
+fn hidden_components(patterns: &[String]) -> Vec<String> {
+    patterns
+        .iter()
+        .flat_map(|pattern| pattern.split('/'))
+        .filter(|component| component.starts_with('.') && !matches!(*component, "." | ".."))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether `relative_path` is hidden, or in a hidden directory, that no include
+/// pattern names.
+#[cfg(feature = "walk")]
+fn has_hidden_component_not_included(relative_path: &Path, included: &GlobSet) -> bool {
+    relative_path.components().any(|c| {
+        let name = c.as_os_str();
+        name.to_str().is_some_and(|s| s.starts_with('.')) && !included.is_match(name)
+    })
+}
+
+#[cfg(feature = "walk")]
 fn matches_glob(glob_set: &GlobSet, relative_path: &Path) -> bool {
     let path_str = relative_path.to_string_lossy();
     glob_set.is_match(&*path_str) || glob_set.is_match(relative_path)
 }
 
-fn walk_directory(path: &Path, config: &FilterConfig) -> io::Result<Vec<PathBuf>> {
-    let exclude_set = FilterConfig::build_globset(&config.exclude_patterns)?;
-    let include_set = FilterConfig::build_globset(&config.include_patterns)?;
-    let has_include_patterns = !config.include_patterns.is_empty();
+/// Applies the hidden-file and glob filters to paths relative to a scan root.
+#[cfg(feature = "walk")]
+pub(crate) fn filter_relative_paths(
+    paths: Vec<PathBuf>,
+    config: &FilterConfig,
+) -> io::Result<Vec<PathBuf>> {
+    let globs = config.globs()?;
+    Ok(select_relative_paths(paths, config, &globs, &mut |_, _| {}))
+}
 
-    let entries = WalkDir::new(path)
+/// Why the glob filters drop a path, if they do.
+#[cfg(feature = "walk")]
+fn glob_rejection(
+    relative_path: &Path,
+    exclude_set: &GlobSet,
+    include_set: Option<&GlobSet>,
+) -> Option<&'static str> {
+    if matches_glob(exclude_set, relative_path) {
+        Some("matches an exclude pattern")
+    } else if include_set.is_some_and(|set| !matches_glob(set, relative_path)) {
+        Some("matches no include pattern")
+    } else {
+        None
+    }
+}
+
+/// The paths the hidden, depth and glob filters keep, passing the hidden and excluded
+/// ones to `on_filtered`.
+#[cfg(feature = "walk")]
+fn select_relative_paths(
+    paths: Vec<PathBuf>,
+    config: &FilterConfig,
+    globs: &CompiledGlobs,
+    on_filtered: &mut dyn FnMut(&Path, FilterReason),
+) -> Vec<PathBuf> {
+    paths
         .into_iter()
-        .filter_entry(|e| e.depth() == 0 || config.include_hidden || !is_hidden(e))
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter_map(|entry| {
-            let file_path = entry.path();
-            let relative_path = file_path.strip_prefix(path).unwrap_or(file_path);
+        .filter(|relative_path| {
+            let reason = if config.only_hidden && !has_hidden_component(relative_path) {
+                on_filtered(relative_path, FilterReason::Visible);
+                Some("not hidden")
+            } else if !config.include_hidden
+                && !config.only_hidden
+                && has_hidden_component_not_included(relative_path, &globs.included_hidden)
+            {
+                on_filtered(relative_path, FilterReason::Hidden);
+                Some("hidden")
+            } else if config
+                .max_depth
+                .is_some_and(|depth| relative_path.components().count() > depth)
+            {
+                Some("deeper than the maximum depth")
+            } else {
+                let include_set = (!config.include_patterns.is_empty()).then_some(&globs.include);
+                let reason = glob_rejection(relative_path, &globs.exclude, include_set);
+                if reason.is_some() {
+                    on_filtered(relative_path, FilterReason::Excluded);
+                }
+                reason
+            };
+            if let Some(reason) = reason {
+                debug!(path = %relative_path.display(), reason, "skipped");
+            }
+            reason.is_none()
+        })
+        .collect()
+}
 
-            if matches_glob(&exclude_set, relative_path) {
-                return None;
+#[cfg(feature = "walk")]
+fn walk_git(
+    path: &Path,
+    selection: &GitSelection,
+    config: &FilterConfig,
+    globs: &CompiledGlobs,
+) -> io::Result<Vec<Walked>> {
+    let files = git::list_files(path, selection)?;
+    let mut filtered = Vec::new();
+    let mut on_filtered = |relative_path: &Path, reason| {
+        filtered.push(Walked::Filtered(join_relative(path, relative_path), reason));
+    };
+    let selected = select_relative_paths(files, config, globs, &mut on_filtered);
+    let entries = selected
+        .into_iter()
+        .map(|relative_path| join_relative(path, &relative_path))
+        .filter_map(|file_path| {
+            // Tracked files deleted from the working tree are skipped
+            let metadata = fs::symlink_metadata(&file_path).ok()?;
+            if !metadata.file_type().is_symlink() {
+                return metadata.is_file().then_some(Walked::File(file_path));
+            }
+            if config.symlinks != SymlinkPolicy::Follow {
+                return Some(Walked::Symlink(file_path));
             }
+            file_path.is_file().then_some(Walked::File(file_path))
+        })
+        .chain(filtered)
+        .collect();
 
-            if has_include_patterns && !matches_glob(&include_set, relative_path) {
+    Ok(entries)
+}
+
+/// Lazily lists the files under `path` that pass the filters, in walk order, with any
+/// special files among them, and then what the filters left out.
+#[cfg(feature = "walk")]
+fn walk_files(
+    path: &Path,
+    config: &FilterConfig,
+) -> io::Result<Box<dyn Iterator<Item = io::Result<Walked>> + Send>> {
+    let globs = config.globs()?;
+
+    if let Some(selection) = &config.git_selection {
+        let files = walk_git(path, selection, config, &globs)?;
+        return Ok(Box::new(files.into_iter().map(Ok)));
+    }
+
+    let mut walker = WalkDir::new(path)
+        .follow_links(config.symlinks == SymlinkPolicy::Follow)
+        .sort_by_file_name();
+    if let Some(depth) = config.max_depth {
+        walker = walker.max_depth(depth);
+    }
+    let root = path.to_path_buf();
+    let include_hidden = config.include_hidden || config.only_hidden;
+    let only_hidden = config.only_hidden;
+    let has_include_patterns = !config.include_patterns.is_empty();
+    // Hidden entries are never yielded by the walk, so they follow it
+    let hidden = Arc::new(Mutex::new(Vec::new()));
+    let walk_hidden = Arc::clone(&hidden);
+    let walk_globs = Arc::clone(&globs);
+    // Past the deadline the walk ends, where it stopped standing for all it left undone
+    let deadline = config.deadline;
+    let stopped_at = Arc::new(Mutex::new(None));
+    let walk_stopped_at = Arc::clone(&stopped_at);
+    let entries = walker
+        .into_iter()
+        .filter_entry(move |e| {
+            let keep = e.depth() == 0
+                || include_hidden
+                || !is_hidden(e)
+                || walk_globs.included_hidden.is_match(e.file_name());
+            if !keep {
+                debug!(path = %e.path().display(), reason = "hidden", "skipped");
+                let mut hidden = walk_hidden.lock().unwrap_or_else(|e| e.into_inner());
+                hidden.push(e.path().to_path_buf());
+            }
+            keep
+        })
+        .map_while(move |entry| {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                let path = match &entry {
+                    Ok(entry) => entry.path(),
+                    Err(e) => e.path().unwrap_or(Path::new("")),
+                };
+                debug!(path = %path.display(), reason = "timed out", "walk stopped");
+                *walk_stopped_at.lock().unwrap_or_else(|e| e.into_inner()) =
+                    Some(path.to_path_buf());
+                return None;
+            }
+            Some(entry)
+        })
+        .filter_map(move |entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(io::Error::from(e))),
+            };
+            // Links are only listed as links when not followed
+            let symlink = entry.file_type().is_symlink();
+            let special = special_kind(entry.file_type());
+            if !entry.file_type().is_file() && special.is_none() && !symlink {
                 return None;
             }
+            let file_path = entry.path();
+            let relative_path = file_path.strip_prefix(&root).unwrap_or(file_path);
+
+            if only_hidden && !has_hidden_component(relative_path) {
+                debug!(path = %file_path.display(), reason = "not hidden", "skipped");
+                return Some(Ok(Walked::Filtered(
+                    entry.into_path(),
+                    FilterReason::Visible,
+                )));
+            }
+            let include_set = has_include_patterns.then_some(&globs.include);
+            if let Some(reason) = glob_rejection(relative_path, &globs.exclude, include_set) {
+                debug!(path = %file_path.display(), reason, "skipped");
+                return Some(Ok(Walked::Filtered(
+                    entry.into_path(),
+                    FilterReason::Excluded,
+                )));
+            }
+
+            Some(Ok(match special {
+                _ if symlink => Walked::Symlink(entry.into_path()),
+                Some(kind) => Walked::Special(entry.into_path(), kind),
+                None => Walked::File(entry.into_path()),
+            }))
+        });
+    let hidden = std::iter::once_with(move || {
+        let hidden = std::mem::take(&mut *hidden.lock().unwrap_or_else(|e| e.into_inner()));
+        hidden
+            .into_iter()
+            .map(|path| Ok(Walked::Filtered(path, FilterReason::Hidden)))
+    })
+    .flatten();
+    let timed_out = std::iter::once_with(move || {
+        let stopped_at = stopped_at.lock().unwrap_or_else(|e| e.into_inner()).take();
+        stopped_at.map(|path| Ok(Walked::Filtered(path, FilterReason::TimedOut)))
+    })
+    .flatten();
+
+    Ok(Box::new(entries.chain(hidden).chain(timed_out)))
+}
+
+// Used by the count cache and the async API
+#[cfg(any(feature = "cli", feature = "async"))]
+pub(crate) fn walk_directory(path: &Path, config: &FilterConfig) -> io::Result<Vec<Walked>> {
+    // Unreadable directories are skipped, as in any full count
+    Ok(walk_files(path, config)?.filter_map(Result::ok).collect())
+}
+
+/// Splits a walk into regular files and the special files to read, reporting the
+/// special files to `observer` as skipped unless `config.special_files` is set, the
+/// symbolic links left out as `config.symlinks` asks, and what the filters left out.
+#[cfg(feature = "walk")]
+pub(crate) fn split_special_files(
+    walked: Vec<Walked>,
+    config: &FilterConfig,
+    observer: &dyn CountObserver,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut files = Vec::new();
+    let mut special = Vec::new();
+    for walked in walked {
+        match walked {
+            Walked::File(file_path) => files.push(file_path),
+            Walked::Special(file_path, _) if config.special_files.is_some() => {
+                special.push(file_path)
+            }
+            Walked::Special(file_path, kind) => {
+                debug!(path = %file_path.display(), reason = kind, "skipped");
+                observer.on_filtered(&file_path, FilterReason::Special);
+                observer.on_error(&file_path, &special_file_error(kind));
+            }
+            Walked::Filtered(file_path, reason) => observer.on_filtered(&file_path, reason),
+            Walked::Symlink(file_path) => {
+                debug!(path = %file_path.display(), reason = "symbolic link", "skipped");
+                observer.on_symlink_skipped(&file_path);
+                if config.symlinks == SymlinkPolicy::Report {
+                    observer.on_error(&file_path, &symlink_error());
+                }
+            }
+        }
+    }
+    (files, special)
+}
+
+#[cfg(feature = "walk")]
+fn symlink_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "symbolic link not followed")
+}
+
+#[cfg(feature = "walk")]
+fn deadline_error() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "not counted before the deadline")
+}
+
+#[cfg(feature = "walk")]
+fn special_file_error(kind: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("not a regular file ({kind})"),
+    )
+}
+
+/// Reads a named pipe, socket or device within `limits`; `None` if it is not UTF-8 text.
+/// A read that times out is left blocked on its own thread.
+#[cfg(feature = "walk")]
+pub(crate) fn read_special_file(
+    path: &Path,
+    limits: SpecialFileLimits,
+) -> io::Result<Option<String>> {
+    let (sender, receiver) = mpsc::channel();
+    let file_path = path.to_path_buf();
+    // Opening a pipe blocks until it has a writer, so the open is timed too
+    thread::spawn(move || {
+        let read = fs::File::open(&file_path).and_then(|file| {
+            let mut content = Vec::new();
+            file.take(limits.max_bytes as u64 + 1)
+                .read_to_end(&mut content)?;
+            Ok(content)
+        });
+        // The receiver is gone if the read timed out
+        let _ = sender.send(read);
+    });
+    let content = receiver.recv_timeout(limits.timeout).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("input did not end within {:?}", limits.timeout),
+        )
+    })??;
+    if content.len() > limits.max_bytes {
+        return Err(io::Error::new(
+            io::ErrorKind::FileTooLarge,
+            format!("more than {} bytes", limits.max_bytes),
+        ));
+    }
+    Ok(String::from_utf8(content).ok())
+}
 
-            Some(file_path.to_path_buf())
+/// Counts the special files [`split_special_files`] kept, like regular files but within
+/// the configured limits and without the generated-content check.
+#[cfg(feature = "walk")]
+pub(crate) fn count_special_files(
+    special: Vec<PathBuf>,
+    config: &FilterConfig,
+    observer: &dyn CountObserver,
+) -> Vec<FileEntry> {
+    let Some(limits) = config.special_files else {
+        return Vec::new();
+    };
+    special
+        .into_par_iter()
+        .filter(|file_path| !timed_out(file_path, config, observer))
+        .filter_map(|file_path| match read_special_file(&file_path, limits) {
+            Ok(Some(content)) => {
+                let entry = FileEntry {
+                    count: Count::from_content(&content),
+                    path: file_path,
+                };
+                observer.on_file_counted(&entry);
+                Some(entry)
+            }
+            Ok(None) => {
+                observer.on_file_not_text(&file_path);
+                None
+            }
+            Err(e) => {
+                observer.on_error(&file_path, &e);
+                None
+            }
         })
+        .collect()
+}
+
+/// The files a directory count would read, after the hidden, glob, git and generated-path
+/// filters, without opening any of them. Files that turn out not to be UTF-8 text, or to
+/// carry a generated marker in their content, are still listed; special files only with
+/// [`FilterConfig::special_files`].
+#[cfg(feature = "walk")]
+pub fn list_directory(path: &Path, config: &FilterConfig) -> io::Result<Vec<PathBuf>> {
+    // Unreadable directories are skipped, as in a count
+    let walked = walk_files(path, config)?.filter_map(Result::ok).collect();
+    let (files, special) = split_special_files(walked, config, &());
+    let mut files: Vec<PathBuf> = skip_generated(path, files, config)
+        .into_iter()
+        .map(|(file_path, _)| file_path)
+        .chain(special)
         .collect();
+    files.sort();
+    Ok(files)
+}
 
-    Ok(entries)
+/// Drops files known to be generated, flagging the rest whose content still needs a look.
+#[cfg(feature = "walk")]
+pub(crate) fn skip_generated(
+    root: &Path,
+    file_paths: Vec<PathBuf>,
+    config: &FilterConfig,
+) -> Vec<(PathBuf, bool)> {
+    skip_generated_observed(root, file_paths, config, &())
 }
 
+/// Like [`skip_generated`], passing the dropped files to `observer`.
+#[cfg(feature = "walk")]
+pub(crate) fn skip_generated_observed(
+    root: &Path,
+    file_paths: Vec<PathBuf>,
+    config: &FilterConfig,
+    observer: &dyn CountObserver,
+) -> Vec<(PathBuf, bool)> {
+    if config.include_generated {
+        return file_paths.into_iter().map(|p| (p, false)).collect();
+    }
+
+    let mut filter = GeneratedFilter::new(root);
+    file_paths
+        .into_iter()
+        .filter_map(|file_path| {
+            let relative_path = file_path.strip_prefix(root).unwrap_or(&file_path);
+            match filter.classify(relative_path) {
+                Generated::Yes => {
+                    debug!(path = %file_path.display(), reason = "generated or vendored", "skipped");
+                    observer.on_filtered(&file_path, FilterReason::Generated);
+                    None
+                }
+                Generated::No => Some((file_path, false)),
+                Generated::Unknown => Some((file_path, true)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "walk")]
 pub fn count_directory(path: &Path, config: &FilterConfig) -> io::Result<(Count, usize)> {
     let (entries, total) = count_directory_detailed(path, config)?;
     Ok((total, entries.len()))
 }
 
+#[cfg(feature = "walk")]
 pub fn count_directory_detailed(
     path: &Path,
     config: &FilterConfig,
 ) -> io::Result<(Vec<FileEntry>, Count)> {
-    let file_paths = walk_directory(path, config)?;
+    count_directory_observed(path, config, &())
+}
 
-    // Parallel file counting with rayon
-    let mut entries: Vec<FileEntry> = file_paths
-        .par_iter()
-        .filter_map(|file_path| {
-            count_file(file_path).ok().map(|count| FileEntry {
-                path: file_path.clone(),
-                count,
-            })
-        })
+/// Reads a file, or the text of a document, for a directory count; `None` for files
+/// that are not UTF-8 text or documents that cannot be parsed, which are skipped
+/// rather than reported.
+#[cfg(feature = "walk")]
+pub(crate) fn read_text(path: &Path) -> io::Result<Option<String>> {
+    match documents::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            debug!(path = %path.display(), reason = "not UTF-8 text", "skipped");
+            Ok(None)
+        }
+        Err(e) => {
+            debug!(path = %path.display(), error = %e, "could not read file");
+            Err(e)
+        }
+    }
+}
+
+/// Like [`read_text`] for each of `paths`, reading the files that are not documents with
+/// batched io_uring reads when the kernel allows it.
+#[cfg(all(feature = "uring", target_os = "linux"))]
+fn read_texts(paths: &[&Path]) -> Vec<io::Result<Option<String>>> {
+    let plain: Vec<&Path> = paths
+        .iter()
+        .copied()
+        .filter(|path| !documents::is_document(path))
         .collect();
+    let Ok(read) = uring::read_files(&plain) else {
+        return paths.iter().map(|path| read_text(path)).collect();
+    };
+    let mut read = read.into_iter();
+    paths
+        .iter()
+        .map(|path| {
+            if documents::is_document(path) {
+                return read_text(path);
+            }
+            match read
+                .next()
+                .unwrap_or_else(|| Err(io::ErrorKind::Other.into()))
+            {
+                Ok(bytes) => Ok(String::from_utf8(bytes).ok().or_else(|| {
+                    debug!(path = %path.display(), reason = "not UTF-8 text", "skipped");
+                    None
+                })),
+                Err(e) => {
+                    debug!(path = %path.display(), error = %e, "could not read file");
+                    Err(e)
+                }
+            }
+        })
+        .collect()
+}
+
+/// The entry for a file of a directory count given what reading it gave, or `None`
+/// for a file that is skipped, with `observer` told why.
+#[cfg(feature = "walk")]
+pub(crate) fn count_read(
+    file_path: PathBuf,
+    check_content: bool,
+    content: io::Result<Option<String>>,
+    observer: &dyn CountObserver,
+) -> Option<FileEntry> {
+    let content = match content {
+        Ok(Some(content)) => content,
+        Ok(None) => {
+            observer.on_file_not_text(&file_path);
+            return None;
+        }
+        Err(e) => {
+            observer.on_error(&file_path, &e);
+            return None;
+        }
+    };
+    if check_content && is_generated_content(&content) {
+        skipped_generated_content(&file_path, observer);
+        return None;
+    }
+    let entry = FileEntry {
+        path: file_path,
+        count: Count::from_content(&content),
+    };
+    trace!(path = %entry.path.display(), lines = entry.count.lines, "counted");
+    observer.on_file_counted(&entry);
+    Some(entry)
+}
+
+#[cfg(feature = "walk")]
+pub(crate) fn skipped_generated_content(path: &Path, observer: &dyn CountObserver) {
+    debug!(path = %path.display(), reason = "generated marker in content", "skipped");
+    observer.on_filtered(path, FilterReason::Generated);
+}
+
+/// Events from a directory count, e.g. for progress reporting. Files are counted in
+/// parallel, so `on_file_counted` and `on_error` may be called from several threads.
+#[cfg(feature = "walk")]
+pub trait CountObserver: Sync {
+    /// A file passed the filters and will be read
+    fn on_file_discovered(&self, _path: &Path) {}
+
+    /// A file was read and counted
+    fn on_file_counted(&self, _entry: &FileEntry) {}
+
+    /// A file could not be read, or a directory (`path`) could not be listed; the count
+    /// carries on without it.
+    fn on_error(&self, _path: &Path, _error: &io::Error) {}
+
+    /// A file was left out because it is not UTF-8 text
+    fn on_file_not_text(&self, _path: &Path) {}
+
+    /// A symbolic link was left out, as [`FilterConfig::symlinks`] asks; with
+    /// [`SymlinkPolicy::Report`] it is also passed to `on_error`
+    fn on_symlink_skipped(&self, _path: &Path) {}
 
-    // Sort for deterministic output
-    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    /// An entry was left out by the filters; special files are also passed to `on_error`
+    fn on_filtered(&self, _path: &Path, _reason: FilterReason) {}
+}
+
+#[cfg(feature = "walk")]
+impl CountObserver for () {}
+
+/// Collects the errors of a directory count, so the files it left out can be reported,
+/// and sums up everything it left out.
+#[cfg(feature = "walk")]
+#[derive(Debug, Default)]
+pub struct SkippedFiles {
+    files: Mutex<Vec<SkippedFile>>,
+    include_not_text: bool,
+    summary: Mutex<SkipSummary>,
+}
+
+#[cfg(feature = "walk")]
+impl SkippedFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also collects files left out for not being UTF-8 text, as `InvalidData`.
+    pub fn including_not_text() -> Self {
+        Self {
+            include_not_text: true,
+            ..Self::default()
+        }
+    }
+
+    fn push(&self, file: SkippedFile) {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.push(file);
+    }
+
+    fn tally(&self, add: impl FnOnce(&mut SkipSummary)) {
+        add(&mut self.summary.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    /// How many symbolic links were left out, reported or not.
+    pub fn symlinks_skipped(&self) -> usize {
+        self.summary().symlinks
+    }
 
-    let total = entries.iter().map(|e| e.count.clone()).sum();
+    /// How many entries were left out, by why.
+    pub fn summary(&self) -> SkipSummary {
+        *self.summary.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The collected files, sorted by path.
+    pub fn into_vec(self) -> Vec<SkippedFile> {
+        let mut skipped = self.files.into_inner().unwrap_or_else(|e| e.into_inner());
+        skipped.sort_by(|a, b| a.path.cmp(&b.path));
+        skipped
+    }
+}
+
+#[cfg(feature = "walk")]
+impl CountObserver for SkippedFiles {
+    fn on_error(&self, path: &Path, error: &io::Error) {
+        // Special files and reported links have events of their own
+        if error.kind() != io::ErrorKind::Unsupported {
+            self.tally(|summary| summary.unreadable += 1);
+        }
+        self.push(SkippedFile::new(path, error));
+    }
+
+    fn on_file_not_text(&self, path: &Path) {
+        self.tally(|summary| summary.binary += 1);
+        if self.include_not_text {
+            self.push(SkippedFile {
+                path: path.to_path_buf(),
+                kind: io::ErrorKind::InvalidData,
+                reason: "not UTF-8 text".to_string(),
+            });
+        }
+    }
+
+    fn on_symlink_skipped(&self, _path: &Path) {
+        self.tally(|summary| summary.symlinks += 1);
+    }
+
+    fn on_filtered(&self, _path: &Path, reason: FilterReason) {
+        self.tally(|summary| match reason {
+            FilterReason::Hidden => summary.hidden += 1,
+            FilterReason::Visible => summary.visible += 1,
+            FilterReason::Excluded => summary.excluded += 1,
+            FilterReason::Generated => summary.generated += 1,
+            FilterReason::Special => summary.special += 1,
+            FilterReason::TimedOut => summary.timed_out += 1,
+        });
+    }
+}
+
+/// Counts a directory like [`count_directory_detailed`], reporting progress to `observer`.
+#[cfg(feature = "walk")]
+pub fn count_directory_observed(
+    path: &Path,
+    config: &FilterConfig,
+    observer: &dyn CountObserver,
+) -> io::Result<(Vec<FileEntry>, Count)> {
+    let started = Instant::now();
+    let mut walked = Vec::new();
+    for file_path in walk_files(path, config)? {
+        match file_path {
+            Ok(file_path) => walked.push(file_path),
+            Err(e) => {
+                debug!(path = %path.display(), error = %e, "could not list directory");
+                observer.on_error(path, &e);
+            }
+        }
+    }
+    debug!(files = walked.len(), elapsed = ?started.elapsed(), "walked directory");
+    let (file_paths, special) = split_special_files(walked, config, observer);
+
+    let started = Instant::now();
+    let candidates = skip_generated_observed(path, file_paths, config, observer);
+    debug!(files = candidates.len(), elapsed = ?started.elapsed(), "filtered generated files");
+    for (file_path, _) in &candidates {
+        observer.on_file_discovered(file_path);
+    }
+
+    // Parallel file counting with rayon, numbering files as they finish
+    let started = Instant::now();
+    let finished = AtomicUsize::new(0);
+    let finish = |entry: FileEntry| (finished.fetch_add(1, Ordering::Relaxed), entry);
+    #[cfg(not(all(feature = "uring", target_os = "linux")))]
+    let mut entries: Vec<(usize, FileEntry)> = candidates
+        .into_par_iter()
+        .filter(|(file_path, _)| !timed_out(file_path, config, observer))
+        .filter_map(|(file_path, check_content)| {
+            let content = read_text(&file_path);
+            count_read(file_path, check_content, content, observer).map(finish)
+        })
+        .collect();
+    // Batches big enough to save syscalls, yet enough of them to keep every thread busy
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    let mut entries: Vec<(usize, FileEntry)> = {
+        let batch = (candidates.len() / rayon::current_num_threads()).clamp(1, uring::BATCH_SIZE);
+        candidates
+            .par_chunks(batch)
+            .flat_map_iter(|batch| {
+                let batch: Vec<_> = batch
+                    .iter()
+                    .filter(|(file_path, _)| !timed_out(file_path, config, observer))
+                    .collect();
+                let paths: Vec<&Path> = batch.iter().map(|(path, _)| path.as_path()).collect();
+                let contents = read_texts(&paths);
+                batch
+                    .iter()
+                    .zip(contents)
+                    .filter_map(|((file_path, check_content), content)| {
+                        count_read(file_path.clone(), *check_content, content, observer).map(finish)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    };
+    entries.extend(
+        count_special_files(special, config, observer)
+            .into_iter()
+            .map(finish),
+    );
+    debug!(files = entries.len(), elapsed = ?started.elapsed(), "counted files");
+
+    let entries = order_entries(entries, config.order);
+    let total = entries.iter().map(|e| e.count).sum();
     Ok((entries, total))
 }
 
+/// Counts the files under `path` one at a time, in walk order, so callers can handle
+/// results as they arrive or stop early.
+///
+/// Files that are not UTF-8 text are skipped, as in [`count_directory_detailed`]; other
+/// read errors, special files not allowed by [`FilterConfig::special_files`], links
+/// reported under [`SymlinkPolicy::Report`], directories that cannot be listed and a walk
+/// cut short by [`FilterConfig::deadline`] are yielded as errors.
+#[cfg(feature = "walk")]
+pub fn count_directory_iter(
+    path: &Path,
+    config: &FilterConfig,
+) -> io::Result<impl Iterator<Item = io::Result<FileEntry>>> {
+    let root = path.to_path_buf();
+    let mut generated = (!config.include_generated).then(|| GeneratedFilter::new(path));
+    let special_files = config.special_files;
+    let report_symlinks = config.symlinks == SymlinkPolicy::Report;
+    let with_path = |file_path: &Path, e: io::Error| {
+        io::Error::new(e.kind(), format!("{}: {e}", file_path.display()))
+    };
+
+    Ok(walk_files(path, config)?.filter_map(move |walked| {
+        let file_path = match walked {
+            Ok(Walked::File(file_path)) => file_path,
+            Ok(Walked::Special(file_path, kind)) => {
+                let read = match special_files {
+                    Some(limits) => read_special_file(&file_path, limits),
+                    None => Err(special_file_error(kind)),
+                };
+                return match read {
+                    Ok(Some(content)) => Some(Ok(FileEntry {
+                        count: Count::from_content(&content),
+                        path: file_path,
+                    })),
+                    Ok(None) => None,
+                    Err(e) => Some(Err(with_path(&file_path, e))),
+                };
+            }
+            Ok(Walked::Symlink(file_path)) => {
+                return report_symlinks.then(|| Err(with_path(&file_path, symlink_error())));
+            }
+            Ok(Walked::Filtered(file_path, FilterReason::TimedOut)) => {
+                return Some(Err(with_path(&file_path, deadline_error())));
+            }
+            Ok(Walked::Filtered(..)) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut check_content = false;
+        if let Some(filter) = &mut generated {
+            let relative_path = file_path.strip_prefix(&root).unwrap_or(&file_path);
+            match filter.classify(relative_path) {
+                Generated::Yes => {
+                    debug!(path = %file_path.display(), reason = "generated or vendored", "skipped");
+                    return None;
+                }
+                Generated::No => {}
+                Generated::Unknown => check_content = true,
+            }
+        }
+        match read_text(&file_path) {
+            Ok(Some(content)) if check_content && is_generated_content(&content) => {
+                skipped_generated_content(&file_path, &());
+                None
+            }
+            Ok(Some(content)) => Some(Ok(FileEntry {
+                count: Count::from_content(&content),
+                path: file_path,
+            })),
+            Ok(None) => None,
+            Err(e) => Some(Err(with_path(&file_path, e))),
+        }
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -202,6 +1621,18 @@ mod tests {
         assert_eq!(count.max_line_length, 5);
     }
 
+    #[test]
+    fn sum_counts_by_reference() {
+        let counts = [
+            Count::from_content("a b\n"),
+            Count::from_content("long line\nx\n"),
+        ];
+        let total: Count = counts.iter().sum();
+        assert_eq!(total, counts[0] + counts[1]);
+        assert_eq!(total.lines, 3);
+        assert_eq!(total.max_line_length, 9);
+    }
+
     #[test]
     fn count_multiple_lines() {
         let count = Count::from_content("hello\nworld");
@@ -234,6 +1665,140 @@ mod tests {
         assert_eq!(count.bytes, 19);
     }
 
+    #[test]
+    fn count_from_content_matches_str_methods() {
+        let samples = [
+            "",
+            "\n",
+            "\n\n",
+            "a\r",
+            "\r\n",
+            "crlf line\r\nnext\r\n",
+            "bare\rcarriage\r\r\n",
+            "tab\tvertical\x0btab\x0cfeed",
+            "no\u{a0}break\u{2003}em\u{3000}ideographic\u{85}next",
+            "  spaced   words \n\n\tあいう えお\n",
+            "zero\u{200b}width",
+        ];
+        for content in samples {
+            assert_eq!(
+                Count::from_content(content),
+                Count {
+                    lines: content.lines().count(),
+                    words: content.split_whitespace().count(),
+                    bytes: content.len(),
+                    max_line_length: content.lines().map(str::len).max().unwrap_or(0),
+                },
+                "{content:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn count_from_bytes_validates_only_what_it_decodes() {
+        let content = "  spaced   words \n\n\tあいう えお\r\n\u{3000}x";
+        assert_eq!(
+            Count::from_bytes(content.as_bytes()).unwrap(),
+            Count::from_content(content)
+        );
+        for invalid in [
+            &b"\xff"[..],
+            b"ok \x80",
+            b"\xc0\xaf",
+            b"\xed\xa0\x80",
+            b"\xe3\x81",
+            b"\xe3\x41\x41",
+            b"\xf5\x80\x80\x80",
+        ] {
+            let err = Count::from_bytes(invalid).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{invalid:?}");
+        }
+
+        // A character fed a byte at a time
+        let mut incremental = IncrementalCount::new();
+        for byte in "aあ\u{1F600}".bytes() {
+            incremental.feed(&[byte]).unwrap();
+        }
+        assert_eq!(
+            incremental.finish().unwrap(),
+            Count::from_content("aあ\u{1F600}")
+        );
+    }
+
+    #[test]
+    fn incremental_count_matches_whole_content() {
+        let samples = [
+            "",
+            "\n",
+            "hello world\nfoo bar",
+            "crlf line\r\nnext\r\n",
+            "trailing cr\r",
+            "  spaced   words \n\n\tあいう えお\n",
+        ];
+        for content in samples {
+            // Split at every byte, including inside multi-byte characters
+            for split in 0..=content.len() {
+                let (head, tail) = content.as_bytes().split_at(split);
+                let mut incremental = IncrementalCount::new();
+                incremental.feed(head).unwrap();
+                incremental.feed(tail).unwrap();
+                assert_eq!(
+                    incremental.count(),
+                    Count::from_content(content),
+                    "{content:?} split at {split}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn count_from_reader_with_progress_reports_running_counts() {
+        let reader = io::Cursor::new("one two\nthree\n");
+        let mut reports = Vec::new();
+        let count = count_from_reader_with_progress(
+            reader.chain(io::Cursor::new("four\n")),
+            Duration::ZERO,
+            |c| reports.push(c.lines),
+        )
+        .unwrap();
+        assert_eq!(count, Count::from_content("one two\nthree\nfour\n"));
+        assert_eq!(reports, vec![2, 3]);
+    }
+
+    #[test]
+    fn limited_count_stops_at_the_limit() {
+        let count = |content: &str, limit| {
+            count_from_reader_limited(
+                content.as_bytes(),
+                DEFAULT_CHUNK_SIZE,
+                Some(limit),
+                Duration::MAX,
+                |_| {},
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            count("one two\nthree\n", 7),
+            (Count::from_content("one two"), true)
+        );
+        assert_eq!(count("one\n", 4), (Count::from_content("one\n"), false));
+        // A character cut off by the limit is left out rather than failing
+        assert_eq!(count("aあ", 2), (Count::from_content("a"), true));
+    }
+
+    #[test]
+    fn incremental_count_rejects_invalid_utf8() {
+        let mut incremental = IncrementalCount::new();
+        assert!(incremental.feed(b"ok\n").is_ok());
+        let err = incremental.feed(&[0xff, b'a']).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A character cut off by the end of input
+        let mut incremental = IncrementalCount::new();
+        incremental.feed(&"あ".as_bytes()[..2]).unwrap();
+        assert!(incremental.finish().is_err());
+    }
+
     #[test]
     fn count_file_success() {
         use std::io::Write;
@@ -452,6 +2017,408 @@ mod tests {
         assert_eq!(count.words, 4); // "visible" + "nested in hidden"
     }
 
+    #[test]
+    fn include_patterns_naming_hidden_paths_reach_into_them() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join(".github/workflows")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".cache")).unwrap();
+        std::fs::write(dir.path().join(".github/workflows/ci.yml"), "on: push\n").unwrap();
+        std::fs::write(dir.path().join(".cache/ci.yml"), "stale\n").unwrap();
+        std::fs::write(dir.path().join(".eslintrc"), "{}\n").unwrap();
+        std::fs::write(dir.path().join("app.yml"), "app\n").unwrap();
+        let listed = |patterns: &[&str]| {
+            let config = FilterConfig::new(
+                false,
+                Vec::new(),
+                patterns.iter().map(|p| p.to_string()).collect(),
+            );
+            let files = list_directory(dir.path(), &config).unwrap();
+            let relative: Vec<PathBuf> = files
+                .iter()
+                .map(|file| file.strip_prefix(dir.path()).unwrap().to_path_buf())
+                .collect();
+            relative
+        };
+
+        assert_eq!(
+            listed(&[".github/**"]),
+            [PathBuf::from(".github/workflows/ci.yml")]
+        );
+        assert_eq!(
+            listed(&["**/.*rc", "*.yml"]),
+            [PathBuf::from(".eslintrc"), PathBuf::from("app.yml")]
+        );
+        // Patterns that do not name a hidden path leave hidden directories out
+        assert_eq!(listed(&["**/*.yml"]), [PathBuf::from("app.yml")]);
+        assert_eq!(
+            hidden_components(&["**/.github/*.yml".to_string(), "./src/*".to_string()]),
+            [".github"]
+        );
+    }
+
+    #[test]
+    fn count_directory_only_hidden_counts_dotfiles_and_dot_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("visible.txt"), "visible\n").unwrap();
+        std::fs::write(dir.path().join(".env"), "KEY=1\n").unwrap();
+        std::fs::create_dir_all(dir.path().join(".github/workflows")).unwrap();
+        std::fs::write(dir.path().join(".github/workflows/ci.yml"), "on: push\n").unwrap();
+        let config = FilterConfig {
+            only_hidden: true,
+            ..default_config()
+        };
+
+        let skipped = SkippedFiles::new();
+        let (entries, _) = count_directory_observed(dir.path(), &config, &skipped).unwrap();
+        let paths: Vec<_> = entries.iter().map(|entry| entry.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                dir.path().join(".env"),
+                dir.path().join(".github/workflows/ci.yml")
+            ]
+        );
+        assert_eq!(skipped.summary().visible, 1);
+        assert_eq!(list_directory(dir.path(), &config).unwrap(), paths);
+    }
+
+    #[test]
+    fn compile_reports_invalid_patterns() {
+        let config = FilterConfig::new(false, vec!["[".to_string()], vec![]);
+        let error = config.compile().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("Invalid glob pattern '['"));
+    }
+
+    #[test]
+    fn compiled_globs_are_reused_until_patterns_change() {
+        let config = FilterConfig::new(false, vec!["*.md".to_string()], vec![])
+            .compile()
+            .unwrap();
+        let compiled = config.compiled.clone().unwrap();
+        assert!(Arc::ptr_eq(&config.globs().unwrap(), &compiled));
+
+        let mut changed = config.clone();
+        changed.exclude_patterns = vec!["*.txt".to_string()];
+        let globs = changed.globs().unwrap();
+        assert!(!Arc::ptr_eq(&globs, &compiled));
+        assert!(globs.exclude.is_match("notes.txt"));
+    }
+
+    #[test]
+    fn count_directory_respects_max_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("top.txt"), "top\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        std::fs::write(dir.path().join("a/mid.txt"), "mid\n").unwrap();
+        std::fs::write(dir.path().join("a/b/deep.txt"), "deep\n").unwrap();
+
+        for (depth, expected) in [(1, 1), (2, 2), (3, 3)] {
+            let config = FilterConfig {
+                max_depth: Some(depth),
+                ..FilterConfig::default()
+            };
+            let (_, file_count) = count_directory(dir.path(), &config).unwrap();
+            assert_eq!(file_count, expected, "depth {depth}");
+        }
+    }
+
+    #[test]
+    fn list_directory_applies_path_filters_without_reading() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a.md"), "doc\n").unwrap();
+        std::fs::write(dir.path().join("sub/b.rs"), "fn b() {}\n").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "hidden\n").unwrap();
+        std::fs::write(dir.path().join("Cargo.lock"), "lock\n").unwrap();
+        std::fs::write(dir.path().join("bin.dat"), [0xff, 0xfe]).unwrap();
+        let config = FilterConfig::new(false, vec!["*.md".to_string()], vec![]);
+
+        let files = list_directory(dir.path(), &config).unwrap();
+        // Content is not read, so the binary file is listed
+        assert_eq!(
+            files,
+            vec![dir.path().join("bin.dat"), dir.path().join("sub/b.rs")]
+        );
+    }
+
+    #[test]
+    fn count_directory_iter_matches_detailed_and_stops_early() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("b.txt"), "one two\n").unwrap();
+        std::fs::write(dir.path().join("sub/a.txt"), "three\n").unwrap();
+        std::fs::write(dir.path().join("bin.dat"), [0xff, 0xfe]).unwrap();
+        std::fs::write(dir.path().join("gen.rs"), "// @generated\n").unwrap();
+        let config = FilterConfig::default();
+
+        let (expected, _) = count_directory_detailed(dir.path(), &config).unwrap();
+        let entries: Vec<FileEntry> = count_directory_iter(dir.path(), &config)
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        let paths = |entries: &[FileEntry]| -> Vec<PathBuf> {
+            entries.iter().map(|e| e.path.clone()).collect()
+        };
+        assert_eq!(paths(&entries), paths(&expected));
+
+        let first = count_directory_iter(dir.path(), &config)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.path, dir.path().join("b.txt"));
+    }
+
+    #[test]
+    fn count_directory_observed_reports_events() {
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Recorder {
+            discovered: Mutex<Vec<PathBuf>>,
+            counted: Mutex<usize>,
+        }
+        impl CountObserver for Recorder {
+            fn on_file_discovered(&self, path: &Path) {
+                self.discovered.lock().unwrap().push(path.to_path_buf());
+            }
+            fn on_file_counted(&self, _entry: &FileEntry) {
+                *self.counted.lock().unwrap() += 1;
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "b\n").unwrap();
+        std::fs::write(dir.path().join("bin.dat"), [0xff, 0xfe]).unwrap();
+        let recorder = Recorder::default();
+
+        let (entries, _) =
+            count_directory_observed(dir.path(), &FilterConfig::default(), &recorder).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(recorder.discovered.lock().unwrap().len(), 3);
+        assert_eq!(*recorder.counted.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn skipped_files_collects_errors_sorted_by_path() {
+        let skipped = SkippedFiles::new();
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        skipped.on_error(Path::new("b.txt"), &denied);
+        skipped.on_error(Path::new("a.txt"), &io::Error::other("disk on fire"));
+
+        let skipped = skipped.into_vec();
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].path, Path::new("a.txt"));
+        assert_eq!(skipped[0].reason, "disk on fire");
+        assert_eq!(skipped[1].kind, io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn skipped_files_sum_up_what_was_left_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".git/objects")).unwrap();
+        std::fs::write(root.join(".git/HEAD"), "ref\n").unwrap();
+        std::fs::write(root.join(".env"), "KEY=1\n").unwrap();
+        std::fs::write(root.join("a.txt"), "a\n").unwrap();
+        std::fs::write(root.join("notes.md"), "# notes\n").unwrap();
+        std::fs::write(root.join("bin.dat"), [0xff, 0xfe]).unwrap();
+        std::fs::write(root.join("package-lock.json"), "{}\n").unwrap();
+        std::fs::write(root.join("gen.rs"), "// @generated\nfn x() {}\n").unwrap();
+        let config = FilterConfig::new(false, vec!["*.md".to_string()], Vec::new());
+
+        let skipped = SkippedFiles::new();
+        let (entries, _) = count_directory_observed(root, &config, &skipped).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            skipped.summary(),
+            SkipSummary {
+                // `.git` is one entry, as it is never descended into
+                hidden: 2,
+                excluded: 1,
+                generated: 2,
+                binary: 1,
+                ..SkipSummary::default()
+            }
+        );
+        assert_eq!(
+            skipped.summary().reasons(),
+            [
+                ("hidden", 2),
+                ("excluded", 1),
+                ("generated", 2),
+                ("binary", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn skipped_files_can_include_files_that_are_not_text() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a\n").unwrap();
+        std::fs::write(dir.path().join("bin.dat"), [0xff, 0xfe]).unwrap();
+        let config = FilterConfig::default();
+
+        let skipped = SkippedFiles::new();
+        count_directory_observed(dir.path(), &config, &skipped).unwrap();
+        assert!(skipped.into_vec().is_empty());
+
+        let skipped = SkippedFiles::including_not_text();
+        count_directory_observed(dir.path(), &config, &skipped).unwrap();
+        let skipped = skipped.into_vec();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path, dir.path().join("bin.dat"));
+        assert_eq!(skipped[0].kind, io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(unix)]
+    fn make_fifo(path: &Path) {
+        let status = std::process::Command::new("mkfifo")
+            .arg(path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn count_file_streams_pipes() {
+        let dir = tempfile::tempdir().unwrap();
+        let pipe = dir.path().join("pipe");
+        make_fifo(&pipe);
+        assert!(is_pipe_like(&pipe));
+        assert!(!is_pipe_like(dir.path()));
+
+        let writer = {
+            let pipe = pipe.clone();
+            std::thread::spawn(move || std::fs::write(pipe, "a b\nc\n".repeat(50_000)).unwrap())
+        };
+        let count = count_file(&pipe).unwrap();
+        writer.join().unwrap();
+        assert_eq!(count.lines, 100_000);
+        assert_eq!(count.words, 150_000);
+        assert_eq!(count.bytes, 300_000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn special_files_are_skipped_unless_allowed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a\n").unwrap();
+        let pipe = dir.path().join("pipe");
+        make_fifo(&pipe);
+
+        let mut config = FilterConfig::default();
+        let skipped = SkippedFiles::new();
+        let (entries, _) = count_directory_observed(dir.path(), &config, &skipped).unwrap();
+        assert_eq!(entries.len(), 1);
+        let skipped = skipped.into_vec();
+        assert_eq!(skipped[0].path, pipe);
+        assert_eq!(skipped[0].kind, io::ErrorKind::Unsupported);
+        assert_eq!(skipped[0].reason, "not a regular file (named pipe)");
+        assert_eq!(list_directory(dir.path(), &config).unwrap().len(), 1);
+
+        config.special_files = Some(SpecialFileLimits::default());
+        let writer = {
+            let pipe = pipe.clone();
+            std::thread::spawn(move || std::fs::write(pipe, "one two\n").unwrap())
+        };
+        let (entries, total) = count_directory_detailed(dir.path(), &config).unwrap();
+        writer.join().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(total.words, 3);
+        assert_eq!(list_directory(dir.path(), &config).unwrap().len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_follow_the_configured_policy() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(root.join("a.txt"), "a\n").unwrap();
+        std::fs::write(outside.join("b.txt"), "b\n").unwrap();
+        symlink(outside.join("b.txt"), root.join("file-link")).unwrap();
+        symlink(&outside, root.join("dir-link")).unwrap();
+
+        let mut config = FilterConfig::default();
+        let skipped = SkippedFiles::new();
+        let (entries, _) = count_directory_observed(&root, &config, &skipped).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(skipped.symlinks_skipped(), 2);
+        assert!(skipped.into_vec().is_empty());
+        assert_eq!(list_directory(&root, &config).unwrap().len(), 1);
+
+        config.symlinks = SymlinkPolicy::Report;
+        let skipped = SkippedFiles::new();
+        count_directory_observed(&root, &config, &skipped).unwrap();
+        assert_eq!(skipped.symlinks_skipped(), 2);
+        let skipped = skipped.into_vec();
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].path, root.join("dir-link"));
+        assert_eq!(skipped[0].reason, "symbolic link not followed");
+        let errors = count_directory_iter(&root, &config)
+            .unwrap()
+            .filter(Result::is_err)
+            .count();
+        assert_eq!(errors, 2);
+
+        config.symlinks = SymlinkPolicy::Follow;
+        let skipped = SkippedFiles::new();
+        let (entries, total) = count_directory_observed(&root, &config, &skipped).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(total.lines, 3);
+        assert_eq!(skipped.symlinks_skipped(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn special_file_reads_stop_at_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let pipe = dir.path().join("pipe");
+        make_fifo(&pipe);
+
+        let limits = SpecialFileLimits {
+            timeout: Duration::from_millis(50),
+            max_bytes: 4,
+        };
+        // No writer ever opens the pipe
+        let error = read_special_file(&pipe, limits).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        // A fresh pipe, since the timed-out read is still waiting for a writer on the first
+        let pipe = dir.path().join("pipe2");
+        make_fifo(&pipe);
+        let writer = {
+            let pipe = pipe.clone();
+            std::thread::spawn(move || std::fs::write(pipe, "longer than four"))
+        };
+        let limits = SpecialFileLimits {
+            timeout: Duration::from_secs(5),
+            ..limits
+        };
+        let error = read_special_file(&pipe, limits).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::FileTooLarge);
+        // The reader stops early, so the writer may see a broken pipe
+        let _ = writer.join();
+    }
+
+    #[test]
+    fn join_relative_pushes_each_component() {
+        let root = Path::new("root");
+        assert_eq!(
+            join_relative(root, Path::new("src/lib.rs")),
+            root.join("src").join("lib.rs")
+        );
+        assert_eq!(join_relative(root, Path::new("")), root);
+    }
+
     #[test]
     fn count_directory_detailed_returns_file_entries() {
         use std::io::Write;
@@ -493,6 +2460,84 @@ mod tests {
         assert!(entries[2].path.to_string_lossy().contains("z_file"));
     }
 
+    #[test]
+    fn orders_entries_by_path_discovery_or_completion() {
+        let entry = |path: &str| FileEntry {
+            path: PathBuf::from(path),
+            count: Count::default(),
+        };
+        let paths = |order| -> Vec<PathBuf> {
+            // As walked, each numbered by when it finished counting
+            let walked = vec![(2, entry("b")), (0, entry("c")), (1, entry("a"))];
+            order_entries(walked, order)
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect()
+        };
+        assert_eq!(
+            paths(EntryOrder::Sorted),
+            ["a", "b", "c"].map(PathBuf::from)
+        );
+        assert_eq!(
+            paths(EntryOrder::Discovery),
+            ["b", "c", "a"].map(PathBuf::from)
+        );
+        assert_eq!(
+            paths(EntryOrder::Completion),
+            ["c", "a", "b"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn count_directory_stops_at_the_deadline() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        fs::write(dir.path().join("b.txt"), "two\n").unwrap();
+        let mut config = FilterConfig::new(false, Vec::new(), Vec::new());
+
+        config.deadline = Some(Instant::now() + Duration::from_secs(3600));
+        let skipped = SkippedFiles::new();
+        let (entries, _) = count_directory_observed(dir.path(), &config, &skipped).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(skipped.summary().is_empty());
+
+        // The walk ends at its first entry, standing for everything it left undone
+        config.deadline = Some(Instant::now());
+        let skipped = SkippedFiles::new();
+        let (entries, total) = count_directory_observed(dir.path(), &config, &skipped).unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(total, Count::default());
+        assert_eq!(skipped.summary().reasons(), [("timed out", 1)]);
+
+        let errors: Vec<_> = count_directory_iter(dir.path(), &config)
+            .unwrap()
+            .map(|entry| entry.err().map(|e| e.kind()))
+            .collect();
+        assert_eq!(errors, [Some(io::ErrorKind::TimedOut)]);
+    }
+
+    #[test]
+    fn count_directory_returns_every_file_in_any_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.txt", "a.txt", "c.txt"] {
+            std::fs::write(dir.path().join(name), "x\n").unwrap();
+        }
+        for order in [EntryOrder::Discovery, EntryOrder::Completion] {
+            let config = FilterConfig {
+                order,
+                ..default_config()
+            };
+            let (entries, total) = count_directory_detailed(dir.path(), &config).unwrap();
+            let mut paths: Vec<PathBuf> = entries.into_iter().map(|entry| entry.path).collect();
+            paths.sort();
+            assert_eq!(
+                paths,
+                ["a.txt", "b.txt", "c.txt"].map(|name| dir.path().join(name))
+            );
+            assert_eq!(total.lines, 3);
+        }
+    }
+
     // Phase 7: exclude/include pattern tests
     #[test]
     fn count_directory_exclude_pattern() {
@@ -659,4 +2704,108 @@ mod tests {
         assert_eq!(count.words, 0);
         assert_eq!(count.bytes, 0);
     }
+
+    // --git tests
+    fn git_config() -> FilterConfig {
+        FilterConfig {
+            git_selection: Some(GitSelection::Tracked),
+            ..FilterConfig::default()
+        }
+    }
+
+    #[test]
+    fn count_directory_git_tracked_only() {
+        use crate::git::test_support::{git, init_repo};
+        let dir = init_repo();
+        std::fs::write(dir.path().join("tracked.txt"), "tracked file\n").unwrap();
+        std::fs::write(dir.path().join("junk.log"), "untracked\n").unwrap();
+        git(dir.path(), &["add", "tracked.txt"]);
+
+        let (count, file_count) = count_directory(dir.path(), &git_config()).unwrap();
+        assert_eq!(file_count, 1);
+        assert_eq!(count.words, 2);
+    }
+
+    #[test]
+    fn count_directory_git_applies_hidden_and_glob_filters() {
+        use crate::git::test_support::{git, init_repo};
+        let dir = init_repo();
+        std::fs::create_dir(dir.path().join(".github")).unwrap();
+        std::fs::write(dir.path().join(".github/ci.yml"), "ci\n").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "main\n").unwrap();
+        std::fs::write(dir.path().join("README.md"), "readme\n").unwrap();
+        git(dir.path(), &["add", "."]);
+
+        let config = FilterConfig {
+            exclude_patterns: vec!["*.md".to_string()],
+            ..git_config()
+        };
+        let (entries, _) = count_directory_detailed(dir.path(), &config).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].path.ends_with("main.rs"));
+
+        // Naming the hidden directory in an include pattern reaches into it
+        let config = FilterConfig {
+            include_patterns: vec![".github/*".to_string()],
+            ..git_config()
+        };
+        let (entries, _) = count_directory_detailed(dir.path(), &config).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].path.ends_with(".github/ci.yml"));
+    }
+
+    #[test]
+    fn count_directory_git_skips_deleted_tracked_files() {
+        use crate::git::test_support::{git, init_repo};
+        let dir = init_repo();
+        std::fs::write(dir.path().join("gone.txt"), "gone\n").unwrap();
+        std::fs::write(dir.path().join("kept.txt"), "kept\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        std::fs::remove_file(dir.path().join("gone.txt")).unwrap();
+
+        let (_, file_count) = count_directory(dir.path(), &git_config()).unwrap();
+        assert_eq!(file_count, 1);
+    }
+
+    #[test]
+    fn count_directory_git_changed_only() {
+        use crate::git::test_support::{git, init_repo};
+        let dir = init_repo();
+        std::fs::write(dir.path().join("stable.txt"), "stable\n").unwrap();
+        std::fs::write(dir.path().join("edited.txt"), "one\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+        std::fs::write(dir.path().join("edited.txt"), "one\ntwo three\n").unwrap();
+
+        let config = FilterConfig {
+            git_selection: Some(GitSelection::Changed("HEAD".to_string())),
+            ..FilterConfig::default()
+        };
+        let (count, file_count) = count_directory(dir.path(), &config).unwrap();
+        assert_eq!(file_count, 1);
+        assert_eq!(count.lines, 2);
+        assert_eq!(count.words, 3);
+    }
+
+    #[test]
+    fn count_directory_skips_generated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.path().join("Cargo.lock"), "version = 3\n").unwrap();
+        std::fs::write(
+            dir.path().join("api.rs"),
+            "// @generated by build.rs\nfn api() {}\n",
+        )
+        .unwrap();
+
+        let (_, file_count) = count_directory(dir.path(), &default_config()).unwrap();
+        assert_eq!(file_count, 1);
+
+        let config = FilterConfig {
+            include_generated: true,
+            ..default_config()
+        };
+        let (_, file_count) = count_directory(dir.path(), &config).unwrap();
+        assert_eq!(file_count, 3);
+    }
 }
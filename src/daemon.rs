@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufReader};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::thread;
+
+use crate::analysis::FileAnalysis;
+use crate::counter::{filter_relative_paths, Count, FileEntry, FilterConfig, SkipSummary};
+use crate::output::JsonFileResult;
+use crate::rpc::{format_error, format_response, parse_request, serve_lines, Request};
+use crate::watch::{watch_paths, WatchTarget};
+
+/// Where the daemon listens unless told otherwise, relative to the indexed directory.
+pub const SOCKET_PATH: &str = ".ewc/daemon.sock";
+
+/// Per-file counts of everything under one directory, kept current by file-watch events.
+pub struct Index {
+    target: RwLock<WatchTarget>,
+    config: FilterConfig,
+}
+
+impl Index {
+    pub fn build(dir: &str, config: FilterConfig) -> io::Result<Self> {
+        let target = WatchTarget::new(dir, &config)?;
+        if !target.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a directory",
+            ));
+        }
+        Ok(Self {
+            target: RwLock::new(target),
+            config,
+        })
+    }
+
+    pub fn root(&self) -> PathBuf {
+        self.read().root().to_path_buf()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, WatchTarget> {
+        self.target.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Re-counts changed paths; a failed re-count keeps the previous counts until the next event.
+    pub fn update(&self, changed: &[PathBuf]) {
+        let mut target = self.target.write().unwrap_or_else(PoisonError::into_inner);
+        let _ = target.update(changed, &self.config);
+    }
+
+    fn relative_path(root: &Path, path: &str) -> Result<PathBuf, String> {
+        let path = Path::new(path);
+        let canonical;
+        let relative = if path.is_absolute() {
+            canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+            canonical
+                .strip_prefix(root)
+                .map_err(|_| "outside the indexed directory".to_string())?
+        } else {
+            path
+        };
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err("outside the indexed directory".to_string());
+        }
+        // Drop `./` so the path matches index keys
+        Ok(relative
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect())
+    }
+
+    fn answer(&self, request: &Request) -> Result<String, String> {
+        if request.config.include_hidden && !self.config.include_hidden {
+            return Err("option 'all' needs a daemon started with --all".to_string());
+        }
+        if request.config.include_generated != self.config.include_generated {
+            return Err("option 'include_generated' must match the daemon".to_string());
+        }
+
+        let target = self.read();
+        let relative = Self::relative_path(target.root(), &request.path)?;
+        let entries = target.files_under(&relative);
+        if entries.is_empty() && !target.root().join(&relative).exists() {
+            return Err("not found".to_string());
+        }
+        let is_file =
+            !relative.as_os_str().is_empty() && entries.len() == 1 && entries[0].path == relative;
+
+        // Requests may narrow what the index holds with their own patterns
+        let kept: HashSet<PathBuf> = filter_relative_paths(
+            entries.iter().map(|e| e.path.clone()).collect(),
+            &request.config,
+        )
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+        let entries: Vec<FileEntry> = entries
+            .into_iter()
+            .filter(|e| kept.contains(&e.path))
+            .collect();
+
+        let total: Count = entries.iter().map(|e| e.count).sum();
+        let result = JsonFileResult {
+            name: request.path.clone(),
+            count: total,
+            is_directory: !is_file,
+            file_count: (!is_file).then_some(entries.len()),
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
+        };
+        Ok(format_response(request, &result, &entries))
+    }
+
+    /// Answers one `--rpc` style request line from the index.
+    pub fn handle_line(&self, line: &str) -> String {
+        match parse_request(line, &self.config) {
+            Ok(request) => self.answer(&request).unwrap_or_else(|message| {
+                format_error(&request.id, &format!("{}: {message}", request.path))
+            }),
+            Err((id, message)) => format_error(&id, &message),
+        }
+    }
+}
+
+/// Binds the socket, replacing one left behind by a daemon that is no longer running.
+pub fn bind(socket: &Path) -> io::Result<UnixListener> {
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("a daemon is already listening on {}", socket.display()),
+            ));
+        }
+        fs::remove_file(socket)?;
+    }
+    if let Some(dir) = socket.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    UnixListener::bind(socket)
+}
+
+/// Answers each connection on its own thread, one request per line.
+pub fn accept_connections(listener: UnixListener, index: Arc<Index>) {
+    for stream in listener.incoming().flatten() {
+        let index = Arc::clone(&index);
+        thread::spawn(move || {
+            let Ok(reader) = stream.try_clone() else {
+                return;
+            };
+            // A client that goes away mid-request only ends its own connection
+            let _ = serve_lines(BufReader::new(reader), &stream, |line| {
+                index.handle_line(line)
+            });
+        });
+    }
+}
+
+/// Serves queries on `listener` while keeping the index current, until the process is stopped.
+pub fn run(index: Index, listener: UnixListener) -> io::Result<()> {
+    let index = Arc::new(index);
+    let root = index.root();
+    let connections = Arc::clone(&index);
+    thread::spawn(move || accept_connections(listener, connections));
+    watch_paths(&[root], |changed| index.update(changed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Write};
+
+    fn index_with_files() -> (tempfile::TempDir, Index) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("src/notes.md"), "# Notes\n").unwrap();
+        fs::write(dir.path().join("README.md"), "# Title\nText here\n").unwrap();
+        let index = Index::build(dir.path().to_str().unwrap(), FilterConfig::default()).unwrap();
+        (dir, index)
+    }
+
+    #[test]
+    fn answers_from_index() {
+        let (_dir, index) = index_with_files();
+        assert_eq!(
+            index.handle_line(r#"{"id":1,"path":"src/main.rs"}"#),
+            r#"{"id":1,"result":{"file":"src/main.rs","max_line_length":12,"lines":1,"words":3,"bytes":13}}"#
+        );
+
+        let response = index.handle_line(r#"{"id":2,"path":"."}"#);
+        assert!(response.contains(r#""directory":".","file_count":3,"#));
+
+        let response = index
+            .handle_line(r#"{"id":3,"path":"src","options":{"exclude":["*.md"],"files":true}}"#);
+        assert!(response.contains(r#""file_count":1,"#));
+        assert!(response.contains(r#""files":[{"file":"src/main.rs""#));
+    }
+
+    #[test]
+    fn update_invalidates_changed_files() {
+        let (dir, index) = index_with_files();
+        let root = index.root();
+        fs::write(dir.path().join("README.md"), "short\n").unwrap();
+        index.update(&[root.join("README.md")]);
+
+        let response = index.handle_line(r#"{"id":1,"path":"README.md"}"#);
+        assert!(response.contains(r#""lines":1,"words":1,"bytes":6"#));
+    }
+
+    #[test]
+    fn rejects_paths_outside_index_and_widening_options() {
+        let (_dir, index) = index_with_files();
+        assert_eq!(
+            index.handle_line(r#"{"id":1,"path":"../x"}"#),
+            r#"{"id":1,"error":"../x: outside the indexed directory"}"#
+        );
+        assert_eq!(
+            index.handle_line(r#"{"id":2,"path":"missing"}"#),
+            r#"{"id":2,"error":"missing: not found"}"#
+        );
+        assert!(index
+            .handle_line(r#"{"id":3,"path":".","options":{"all":true}}"#)
+            .contains("needs a daemon started with --all"));
+    }
+
+    #[test]
+    fn serves_requests_over_socket() {
+        let (dir, index) = index_with_files();
+        let socket = dir.path().join(SOCKET_PATH);
+        let listener = bind(&socket).unwrap();
+        // A second daemon on the same socket is refused
+        assert_eq!(bind(&socket).unwrap_err().kind(), io::ErrorKind::AddrInUse);
+        thread::spawn(move || accept_connections(listener, Arc::new(index)));
+
+        let mut stream = UnixStream::connect(&socket).unwrap();
+        writeln!(stream, r#"{{"id":1,"path":"README.md"}}"#).unwrap();
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line).unwrap();
+        assert!(line.starts_with(r#"{"id":1,"result":{"file":"README.md""#));
+    }
+}
@@ -0,0 +1,232 @@
+use rusqlite::{params, Connection};
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::counter::{Count, FileEntry};
+use crate::git::Commit;
+use crate::history::HistoryPoint;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    recorded_at INTEGER NOT NULL,
+    revision TEXT,
+    file_count INTEGER NOT NULL,
+    lines INTEGER NOT NULL,
+    words INTEGER NOT NULL,
+    bytes INTEGER NOT NULL,
+    max_line_length INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS files (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    path TEXT NOT NULL,
+    lines INTEGER NOT NULL,
+    words INTEGER NOT NULL,
+    bytes INTEGER NOT NULL,
+    max_line_length INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS files_path ON files(path);
+";
+
+fn db_error(e: rusqlite::Error) -> io::Error {
+    io::Error::other(format!("database: {e}"))
+}
+
+/// Opens (or creates) a metrics database.
+pub fn open(path: &Path) -> io::Result<Connection> {
+    let conn = Connection::open(path).map_err(db_error)?;
+    conn.execute_batch(SCHEMA).map_err(db_error)?;
+    Ok(conn)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Appends one run with its per-file counts, returning the run id.
+pub fn record_run(
+    conn: &mut Connection,
+    revision: Option<&str>,
+    entries: &[FileEntry],
+) -> io::Result<i64> {
+    record_run_at(conn, unix_now(), revision, entries)
+}
+
+fn record_run_at(
+    conn: &mut Connection,
+    recorded_at: i64,
+    revision: Option<&str>,
+    entries: &[FileEntry],
+) -> io::Result<i64> {
+    let total: Count = entries.iter().map(|e| e.count).sum();
+    let tx = conn.transaction().map_err(db_error)?;
+    tx.execute(
+        "INSERT INTO runs (recorded_at, revision, file_count, lines, words, bytes, max_line_length)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            recorded_at,
+            revision,
+            entries.len() as i64,
+            total.lines as i64,
+            total.words as i64,
+            total.bytes as i64,
+            total.max_line_length as i64
+        ],
+    )
+    .map_err(db_error)?;
+    let run_id = tx.last_insert_rowid();
+
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO files (run_id, path, lines, words, bytes, max_line_length)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .map_err(db_error)?;
+        for entry in entries {
+            insert
+                .execute(params![
+                    run_id,
+                    entry.path.display().to_string(),
+                    entry.count.lines as i64,
+                    entry.count.words as i64,
+                    entry.count.bytes as i64,
+                    entry.count.max_line_length as i64
+                ])
+                .map_err(db_error)?;
+        }
+    }
+    tx.commit().map_err(db_error)?;
+    Ok(run_id)
+}
+
+fn read_point(row: &rusqlite::Row) -> rusqlite::Result<HistoryPoint> {
+    let recorded_at: String = row.get(0)?;
+    let revision: Option<String> = row.get(1)?;
+    let date = recorded_at.get(..10).unwrap_or(&recorded_at).to_string();
+    Ok(HistoryPoint {
+        period: recorded_at,
+        commit: Commit {
+            id: revision.unwrap_or_default(),
+            date,
+        },
+        file_count: row.get::<_, i64>(2)? as usize,
+        count: Count {
+            lines: row.get::<_, i64>(3)? as usize,
+            words: row.get::<_, i64>(4)? as usize,
+            bytes: row.get::<_, i64>(5)? as usize,
+            max_line_length: row.get::<_, i64>(6)? as usize,
+        },
+    })
+}
+
+/// Recorded runs, oldest first, as totals or for a single file path.
+///
+/// Each point's period is the UTC time the run was recorded.
+pub fn load_trend(conn: &Connection, file: Option<&str>) -> io::Result<Vec<HistoryPoint>> {
+    let points = match file {
+        None => {
+            let mut query = conn
+                .prepare(
+                    "SELECT datetime(recorded_at, 'unixepoch'), revision, file_count,
+                            lines, words, bytes, max_line_length
+                     FROM runs ORDER BY recorded_at, id",
+                )
+                .map_err(db_error)?;
+            let rows = query.query_map([], read_point).map_err(db_error)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        }
+        Some(file) => {
+            let mut query = conn
+                .prepare(
+                    "SELECT datetime(runs.recorded_at, 'unixepoch'), runs.revision, 1,
+                            files.lines, files.words, files.bytes, files.max_line_length
+                     FROM files JOIN runs ON runs.id = files.run_id
+                     WHERE files.path = ?1 ORDER BY runs.recorded_at, runs.id",
+                )
+                .map_err(db_error)?;
+            let rows = query.query_map([file], read_point).map_err(db_error)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        }
+    };
+    points.map_err(db_error)
+}
+
+/// Number of runs recorded so far.
+pub fn run_count(conn: &Connection) -> io::Result<usize> {
+    conn.query_row("SELECT COUNT(*) FROM runs", [], |row| row.get::<_, i64>(0))
+        .map(|n| n as usize)
+        .map_err(db_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry(path: &str, lines: usize) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            count: Count {
+                lines,
+                words: lines * 2,
+                bytes: lines * 10,
+                max_line_length: 40,
+            },
+        }
+    }
+
+    #[test]
+    fn record_and_load_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = open(&dir.path().join("metrics.sqlite")).unwrap();
+
+        record_run_at(&mut conn, 0, Some("abc123"), &[entry("a.rs", 10)]).unwrap();
+        record_run_at(
+            &mut conn,
+            86_400,
+            None,
+            &[entry("a.rs", 12), entry("b.rs", 3)],
+        )
+        .unwrap();
+
+        let points = load_trend(&conn, None).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].period, "1970-01-01 00:00:00");
+        assert_eq!(points[0].commit.id, "abc123");
+        assert_eq!(points[0].commit.date, "1970-01-01");
+        assert_eq!(points[1].file_count, 2);
+        assert_eq!(points[1].count.lines, 15);
+        assert_eq!(points[1].commit.id, "");
+        assert_eq!(run_count(&conn).unwrap(), 2);
+    }
+
+    #[test]
+    fn load_trend_for_single_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = open(&dir.path().join("metrics.sqlite")).unwrap();
+        record_run_at(&mut conn, 0, None, &[entry("a.rs", 10), entry("b.rs", 1)]).unwrap();
+        record_run_at(&mut conn, 60, None, &[entry("b.rs", 2)]).unwrap();
+
+        let points = load_trend(&conn, Some("b.rs")).unwrap();
+        let lines: Vec<usize> = points.iter().map(|p| p.count.lines).collect();
+        assert_eq!(lines, vec![1, 2]);
+        assert!(load_trend(&conn, Some("missing.rs")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn open_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metrics.sqlite");
+        let mut conn = open(&path).unwrap();
+        record_run(&mut conn, None, &[entry("a.rs", 1)]).unwrap();
+        drop(conn);
+
+        let conn = open(&path).unwrap();
+        assert_eq!(run_count(&conn).unwrap(), 1);
+    }
+}
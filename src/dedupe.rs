@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// Distinct and repeated lines in a file, as shown with `--dedupe-lines`. Blank lines
+/// are left out.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DedupeStats {
+    /// Distinct lines
+    pub unique: usize,
+    /// Lines repeating an earlier line
+    pub duplicates: usize,
+    /// The line seen most often, if any line was seen twice, and how often
+    pub most_repeated: Option<(String, usize)>,
+}
+
+impl Add for DedupeStats {
+    type Output = Self;
+
+    /// Counts add up per file; the most repeated line is the one repeated most in any
+    /// single file.
+    fn add(self, other: Self) -> Self {
+        let most_repeated = match (self.most_repeated, other.most_repeated) {
+            (Some(a), Some(b)) => Some(if b.1 > a.1 { b } else { a }),
+            (a, b) => a.or(b),
+        };
+        Self {
+            unique: self.unique + other.unique,
+            duplicates: self.duplicates + other.duplicates,
+            most_repeated,
+        }
+    }
+}
+
+impl AddAssign for DedupeStats {
+    fn add_assign(&mut self, other: Self) {
+        *self = std::mem::take(self) + other;
+    }
+}
+
+impl Sum for DedupeStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl DedupeStats {
+    /// Counts the distinct and repeated lines of `content`, comparing lines exactly.
+    /// The most repeated line is the first to reach the highest count.
+    pub fn from_content(content: &str) -> Self {
+        // Each line's count and where it first appeared, to break ties
+        let mut seen: HashMap<&str, (usize, usize)> = HashMap::new();
+        let mut duplicates = 0;
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (count, _) = seen.entry(line).or_insert((0, index));
+            *count += 1;
+            if *count > 1 {
+                duplicates += 1;
+            }
+        }
+        let most_repeated = seen
+            .iter()
+            .filter(|(_, &(count, _))| count > 1)
+            .max_by(|(_, a), (_, b)| a.0.cmp(&b.0).then(b.1.cmp(&a.1)))
+            .map(|(line, &(count, _))| (line.to_string(), count));
+        Self {
+            unique: seen.len(),
+            duplicates,
+            most_repeated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_unique_and_repeated_lines() {
+        let content = "a\nb\na\n\n\nc\na\nb\n";
+        assert_eq!(
+            DedupeStats::from_content(content),
+            DedupeStats {
+                unique: 3,
+                duplicates: 3,
+                most_repeated: Some(("a".to_string(), 3)),
+            }
+        );
+    }
+
+    #[test]
+    fn no_most_repeated_line_without_repeats() {
+        let stats = DedupeStats::from_content("one\ntwo\n  \n");
+        assert_eq!(stats.unique, 2);
+        assert_eq!(stats.duplicates, 0);
+        assert_eq!(stats.most_repeated, None);
+    }
+
+    #[test]
+    fn ties_go_to_the_first_line() {
+        let stats = DedupeStats::from_content("x\ny\ny\nx\n");
+        assert_eq!(stats.most_repeated, Some(("x".to_string(), 2)));
+    }
+
+    #[test]
+    fn sums_keep_the_most_repeated_line_of_any_file() {
+        let a = DedupeStats::from_content("}\n}\n");
+        let b = DedupeStats::from_content("log\nlog\nlog\nend\n");
+        let total: DedupeStats = [a, b].into_iter().sum();
+        assert_eq!(total.unique, 3);
+        assert_eq!(total.duplicates, 3);
+        assert_eq!(total.most_repeated, Some(("log".to_string(), 3)));
+    }
+}
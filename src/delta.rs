@@ -0,0 +1,162 @@
+use crate::counter::Count;
+use crate::report::Report;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CountDelta {
+    pub files: i64,
+    pub lines: i64,
+    pub words: i64,
+    pub bytes: i64,
+}
+
+fn signed_diff(current: usize, previous: usize) -> i64 {
+    current as i64 - previous as i64
+}
+
+impl CountDelta {
+    pub fn between(
+        current: &Count,
+        current_files: usize,
+        previous: &Count,
+        previous_files: usize,
+    ) -> Self {
+        Self {
+            files: signed_diff(current_files, previous_files),
+            lines: signed_diff(current.lines, previous.lines),
+            words: signed_diff(current.words, previous.words),
+            bytes: signed_diff(current.bytes, previous.bytes),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub delta: CountDelta,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportDelta {
+    pub total: CountDelta,
+    pub changes: Vec<EntryChange>,
+}
+
+pub fn compare_reports(current: &Report, baseline: &Report) -> ReportDelta {
+    let empty = Count::default();
+    let mut changes = Vec::new();
+
+    for entry in &current.entries {
+        let previous = baseline.entries.iter().find(|b| b.name == entry.name);
+        let (kind, previous_count, previous_files) = match previous {
+            Some(p) => (ChangeKind::Changed, &p.count, p.file_count),
+            None => (ChangeKind::Added, &empty, 0),
+        };
+        let delta = CountDelta::between(
+            &entry.count,
+            entry.file_count,
+            previous_count,
+            previous_files,
+        );
+        if kind == ChangeKind::Added || !delta.is_zero() {
+            changes.push(EntryChange {
+                name: entry.name.clone(),
+                kind,
+                delta,
+            });
+        }
+    }
+
+    for previous in &baseline.entries {
+        if !current.entries.iter().any(|e| e.name == previous.name) {
+            changes.push(EntryChange {
+                name: previous.name.clone(),
+                kind: ChangeKind::Removed,
+                delta: CountDelta::between(&empty, 0, &previous.count, previous.file_count),
+            });
+        }
+    }
+
+    ReportDelta {
+        total: CountDelta::between(
+            &current.total,
+            current.file_count,
+            &baseline.total,
+            baseline.file_count,
+        ),
+        changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ReportEntry;
+
+    fn entry(name: &str, lines: usize, file_count: usize) -> ReportEntry {
+        ReportEntry {
+            name: name.to_string(),
+            count: Count {
+                lines,
+                words: lines * 2,
+                bytes: lines * 10,
+                max_line_length: 0,
+            },
+            file_count,
+            is_directory: file_count != 1,
+        }
+    }
+
+    #[test]
+    fn count_delta_between() {
+        let delta = CountDelta::between(&entry("a", 10, 1).count, 3, &entry("a", 25, 1).count, 5);
+        assert_eq!(delta.files, -2);
+        assert_eq!(delta.lines, -15);
+        assert_eq!(delta.words, -30);
+        assert_eq!(delta.bytes, -150);
+    }
+
+    #[test]
+    fn compare_reports_total_delta() {
+        let baseline = Report::from_entries(vec![entry("src", 100, 4)]);
+        let current = Report::from_entries(vec![entry("src", 220, 5)]);
+        let delta = compare_reports(&current, &baseline);
+        assert_eq!(delta.total.lines, 120);
+        assert_eq!(delta.total.files, 1);
+        assert_eq!(delta.changes.len(), 1);
+        assert_eq!(delta.changes[0].kind, ChangeKind::Changed);
+    }
+
+    #[test]
+    fn compare_reports_omits_unchanged_entries() {
+        let baseline = Report::from_entries(vec![entry("a", 10, 1), entry("b", 5, 1)]);
+        let current = Report::from_entries(vec![entry("a", 10, 1), entry("b", 7, 1)]);
+        let delta = compare_reports(&current, &baseline);
+        assert_eq!(delta.changes.len(), 1);
+        assert_eq!(delta.changes[0].name, "b");
+    }
+
+    #[test]
+    fn compare_reports_added_and_removed() {
+        let baseline = Report::from_entries(vec![entry("old.txt", 40, 1)]);
+        let current = Report::from_entries(vec![entry("new.txt", 20, 1)]);
+        let delta = compare_reports(&current, &baseline);
+        assert_eq!(delta.changes.len(), 2);
+        assert_eq!(delta.changes[0].kind, ChangeKind::Added);
+        assert_eq!(delta.changes[0].delta.lines, 20);
+        assert_eq!(delta.changes[1].kind, ChangeKind::Removed);
+        assert_eq!(delta.changes[1].delta.lines, -40);
+        assert_eq!(delta.total.lines, -20);
+    }
+}
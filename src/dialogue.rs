@@ -0,0 +1,112 @@
+use std::iter::Sum;
+use std::ops::Add;
+
+/// Quoted spans and the words inside them, for `--dialogue`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DialogueStats {
+    /// Opening quotation marks
+    pub quotes: usize,
+    /// Words that start inside a quotation
+    pub quoted_words: usize,
+    /// All whitespace-separated words, as `wc -w` counts them
+    pub words: usize,
+}
+
+impl Add for DialogueStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            quotes: self.quotes + other.quotes,
+            quoted_words: self.quoted_words + other.quoted_words,
+            words: self.words + other.words,
+        }
+    }
+}
+
+impl Sum for DialogueStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl DialogueStats {
+    /// Counts `"straight"`, `“curly”` and `「corner」` quotations. Single quotes are
+    /// left alone, as they cannot be told from apostrophes. A quotation still open at
+    /// a blank line ends there, so a paragraph with an unbalanced `"` does not turn
+    /// the rest of the text into dialogue.
+    pub fn from_content(content: &str) -> Self {
+        let mut stats = Self::default();
+        let mut in_quote = false;
+        let mut in_word = false;
+        let mut blank_line = true;
+        for c in content.chars() {
+            match c {
+                '"' if !in_quote => {
+                    in_quote = true;
+                    stats.quotes += 1;
+                }
+                '"' | '”' | '」' | '』' => in_quote = false,
+                '“' | '「' | '『' => {
+                    stats.quotes += usize::from(!in_quote);
+                    in_quote = true;
+                }
+                '\n' if blank_line => in_quote = false,
+                _ => {}
+            }
+            if c == '\n' {
+                blank_line = true;
+            }
+            if c.is_whitespace() {
+                in_word = false;
+            } else {
+                blank_line = false;
+                if !in_word {
+                    in_word = true;
+                    stats.words += 1;
+                    stats.quoted_words += usize::from(in_quote);
+                }
+            }
+        }
+        stats
+    }
+
+    /// Quoted words per word, `None` without words.
+    pub fn share(&self) -> Option<f64> {
+        (self.words > 0).then(|| self.quoted_words as f64 / self.words as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_quotes_and_the_words_inside() {
+        let stats = DialogueStats::from_content("\"Come in,\" she said. “Sit down.”\n");
+        assert_eq!(stats.quotes, 2);
+        assert_eq!(stats.quoted_words, 4);
+        assert_eq!(stats.words, 6);
+        assert_eq!(stats.share(), Some(4.0 / 6.0));
+    }
+
+    #[test]
+    fn corner_brackets_and_apostrophes() {
+        let stats = DialogueStats::from_content("彼は 「はい」 と 言った。 It's 'fine'.\n");
+        assert_eq!(stats.quotes, 1);
+        assert_eq!(stats.quoted_words, 1);
+    }
+
+    #[test]
+    fn unclosed_quote_ends_at_blank_line() {
+        let stats = DialogueStats::from_content("\"We left at dawn.\n\nThe road was long.\n");
+        assert_eq!(stats.quotes, 1);
+        assert_eq!(stats.quoted_words, 4);
+        assert_eq!(stats.words, 8);
+    }
+
+    #[test]
+    fn empty_text_has_no_share() {
+        assert_eq!(DialogueStats::from_content("").share(), None);
+    }
+}
@@ -0,0 +1,551 @@
+use std::fs;
+use std::io;
+#[cfg(feature = "office")]
+use std::io::Read;
+use std::path::Path;
+
+#[cfg(feature = "office")]
+use crate::markup::{decode_entities, visible_text};
+
+/// Ends each page of text extracted from a document, as in `pdftotext` output.
+pub const PAGE_BREAK: char = '\u{c}';
+
+/// Whether `path` is a document whose text is extracted before counting, rather than
+/// read as it is: PDF with the `pdf` feature; Word (`.docx`), OpenDocument (`.odt`)
+/// and EPUB with the `office` feature.
+pub fn is_document(path: &Path) -> bool {
+    document_kind(path).is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DocumentKind {
+    #[cfg(feature = "pdf")]
+    Pdf,
+    #[cfg(feature = "office")]
+    Docx,
+    #[cfg(feature = "office")]
+    Odt,
+    #[cfg(feature = "office")]
+    Epub,
+}
+
+fn document_kind(path: &Path) -> Option<DocumentKind> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        #[cfg(feature = "pdf")]
+        "pdf" => Some(DocumentKind::Pdf),
+        #[cfg(feature = "office")]
+        "docx" => Some(DocumentKind::Docx),
+        #[cfg(feature = "office")]
+        "odt" => Some(DocumentKind::Odt),
+        #[cfg(feature = "office")]
+        "epub" => Some(DocumentKind::Epub),
+        _ => None,
+    }
+}
+
+/// The text of `path`: extracted from documents (see [`is_document`]), read as UTF-8
+/// otherwise. A document that cannot be parsed fails with `InvalidData`, like a file
+/// that is not text.
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    match document_kind(path) {
+        #[cfg(feature = "pdf")]
+        Some(DocumentKind::Pdf) => pdf_text(path),
+        #[cfg(feature = "office")]
+        Some(DocumentKind::Docx) => office_text(path, &DOCX),
+        #[cfg(feature = "office")]
+        Some(DocumentKind::Odt) => office_text(path, &ODT),
+        #[cfg(feature = "office")]
+        Some(DocumentKind::Epub) => Ok(epub_chapters(path)?
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect()),
+        None => fs::read_to_string(path),
+    }
+}
+
+/// The text of every page, each ended by [`PAGE_BREAK`]. Pages whose text cannot be
+/// extracted, such as scanned images, count as empty.
+#[cfg(feature = "pdf")]
+fn pdf_text(path: &Path) -> io::Result<String> {
+    let document = lopdf::Document::load(path).map_err(|e| match e {
+        lopdf::Error::IO(e) => e,
+        e => io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("not a readable PDF: {e}"),
+        ),
+    })?;
+    let mut text = String::new();
+    for page in document.get_pages().into_keys() {
+        text.push_str(
+            document
+                .extract_text(&[page])
+                .unwrap_or_default()
+                .trim_end(),
+        );
+        text.push('\n');
+        text.push(PAGE_BREAK);
+    }
+    Ok(text)
+}
+
+/// Where an office format keeps its text, and the elements that shape it.
+#[cfg(feature = "office")]
+struct OfficeXml {
+    /// The archive member holding the document body
+    member: &'static str,
+    /// Text counts only inside these elements; whitespace between them is layout
+    text_within: &'static [&'static str],
+    /// Elements ended by a newline
+    paragraphs: &'static [&'static str],
+    tab: &'static str,
+    line_breaks: &'static [&'static str],
+    /// A run of spaces, as many as its `c` attribute says
+    spaces: Option<&'static str>,
+}
+
+#[cfg(feature = "office")]
+const DOCX: OfficeXml = OfficeXml {
+    member: "word/document.xml",
+    text_within: &["w:t"],
+    paragraphs: &["w:p"],
+    tab: "w:tab",
+    line_breaks: &["w:br", "w:cr"],
+    spaces: None,
+};
+
+#[cfg(feature = "office")]
+const ODT: OfficeXml = OfficeXml {
+    member: "content.xml",
+    text_within: &["text:p", "text:h"],
+    paragraphs: &["text:p", "text:h"],
+    tab: "text:tab",
+    line_breaks: &["text:line-break"],
+    spaces: Some("text:s"),
+};
+
+/// The body text of a Word or OpenDocument file, one line per paragraph, as the
+/// word processor's own word count sees it: headers, footers, comments and field
+/// codes left out.
+#[cfg(feature = "office")]
+fn office_text(path: &Path, format: &OfficeXml) -> io::Result<String> {
+    let mut archive = open_archive(path)?;
+    Ok(xml_text(&read_member(&mut archive, format.member)?, format))
+}
+
+#[cfg(feature = "office")]
+type Archive = zip::ZipArchive<fs::File>;
+
+#[cfg(feature = "office")]
+fn invalid_archive(e: zip::result::ZipError) -> io::Error {
+    match e {
+        zip::result::ZipError::Io(e) => e,
+        e => io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("not a readable document: {e}"),
+        ),
+    }
+}
+
+#[cfg(feature = "office")]
+fn open_archive(path: &Path) -> io::Result<Archive> {
+    zip::ZipArchive::new(fs::File::open(path)?).map_err(invalid_archive)
+}
+
+#[cfg(feature = "office")]
+fn read_member(archive: &mut Archive, name: &str) -> io::Result<String> {
+    let mut content = String::new();
+    archive
+        .by_name(name)
+        .map_err(invalid_archive)?
+        .read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Whether `path` is an EPUB book, whose chapters `--chapters` reports.
+pub fn is_book(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("epub"))
+}
+
+/// Words in each chapter of a book, in reading order, for `--chapters`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Chapters {
+    /// The chapter's file within the book, and its words
+    pub words: Vec<(String, usize)>,
+}
+
+impl std::ops::Add for Chapters {
+    type Output = Self;
+
+    /// The chapters of one book, then the other's.
+    fn add(mut self, other: Self) -> Self {
+        self.words.extend(other.words);
+        self
+    }
+}
+
+/// The words of each chapter of the EPUB book at `path`.
+#[cfg(feature = "office")]
+pub fn chapters(path: &Path) -> io::Result<Chapters> {
+    let words = epub_chapters(path)?
+        .into_iter()
+        .map(|(name, text)| (name, text.split_whitespace().count()))
+        .collect();
+    Ok(Chapters { words })
+}
+
+/// The visible text of each document in an EPUB's spine, in reading order, named by
+/// its path within the book.
+#[cfg(feature = "office")]
+fn epub_chapters(path: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut archive = open_archive(path)?;
+    let container = read_member(&mut archive, "META-INF/container.xml")?;
+    let package = elements(&container)
+        .find(|(name, _)| *name == "rootfile")
+        .and_then(|(_, attributes)| attribute(attributes, "full-path"))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "EPUB without a package document",
+            )
+        })?
+        .to_string();
+    let opf = read_member(&mut archive, &package)?;
+    let base = package.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+    let manifest: Vec<(&str, &str)> = elements(&opf)
+        .filter(|(name, _)| *name == "item")
+        .filter_map(|(_, attributes)| {
+            Some((attribute(attributes, "id")?, attribute(attributes, "href")?))
+        })
+        .collect();
+    let mut chapters = Vec::new();
+    for (_, attributes) in elements(&opf).filter(|(name, _)| *name == "itemref") {
+        let Some(idref) = attribute(attributes, "idref") else {
+            continue;
+        };
+        let Some((_, href)) = manifest.iter().find(|(id, _)| *id == idref) else {
+            continue;
+        };
+        let href = percent_decode(&decode_entities(href));
+        let member = if base.is_empty() {
+            href.clone()
+        } else {
+            format!("{base}/{href}")
+        };
+        let xhtml = read_member(&mut archive, &member)?;
+        chapters.push((href, visible_text(&xhtml)));
+    }
+    Ok(chapters)
+}
+
+/// The local name and attributes of each start or empty-element tag in `xml`.
+#[cfg(feature = "office")]
+fn elements(xml: &str) -> impl Iterator<Item = (&str, &str)> {
+    xml.split('<').skip(1).filter_map(|tag| {
+        let tag = tag.split_once('>')?.0.trim_end_matches('/');
+        if tag.starts_with(['/', '?', '!']) {
+            return None;
+        }
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let local = name.rsplit(':').next().unwrap_or(name);
+        Some((local, attributes))
+    })
+}
+
+/// Decodes `%XX` escapes in a URL path, as EPUB hrefs use for spaces and non-ASCII
+/// file names.
+#[cfg(feature = "office")]
+fn percent_decode(href: &str) -> String {
+    let bytes = href.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| href.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(feature = "office")]
+fn xml_text(xml: &str, format: &OfficeXml) -> String {
+    let mut text = String::new();
+    let mut depth = 0usize;
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        if depth > 0 {
+            text.push_str(&decode_entities(&rest[..start]));
+        }
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+        let closing = tag.starts_with('/');
+        let empty = tag.ends_with('/');
+        let tag = tag.trim_start_matches('/').trim_end_matches('/');
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        if format.text_within.contains(&name) {
+            if closing {
+                depth = depth.saturating_sub(1);
+            } else if !empty {
+                depth += 1;
+            }
+        }
+        if format.paragraphs.contains(&name) {
+            if closing || empty {
+                text.push('\n');
+            }
+        } else if closing {
+            continue;
+        } else if name == format.tab {
+            // Tab stops in paragraph properties share the name but have a position
+            if !attributes.contains("pos=") {
+                text.push('\t');
+            }
+        } else if format.line_breaks.contains(&name) {
+            text.push('\n');
+        } else if format.spaces == Some(name) {
+            let count = attribute(attributes, "text:c").and_then(|c| c.parse().ok());
+            text.extend(std::iter::repeat_n(' ', count.unwrap_or(1)));
+        }
+    }
+    text
+}
+
+/// The value of attribute `name` in the attributes of a tag.
+#[cfg(feature = "office")]
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let value = attributes
+        .split_whitespace()
+        .find_map(|attribute| attribute.strip_prefix(name)?.strip_prefix('='))?;
+    Some(value.trim_matches(|c| c == '"' || c == '\''))
+}
+
+/// Pages in `content`: one per [`PAGE_BREAK`], plus one for any text after the last,
+/// so text without page breaks is a single page.
+pub fn count_pages(content: &str) -> usize {
+    let breaks = content.matches(PAGE_BREAK).count();
+    let trailing = content.rsplit(PAGE_BREAK).next().unwrap_or_default();
+    breaks + usize::from(!trailing.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_pages_between_form_feeds() {
+        assert_eq!(count_pages(""), 0);
+        assert_eq!(count_pages("one page\n"), 1);
+        assert_eq!(count_pages("first\n\u{c}second\n\u{c}"), 2);
+        assert_eq!(count_pages("first\n\u{c}second\n"), 2);
+        assert_eq!(count_pages("\u{c}\u{c}"), 2);
+    }
+
+    #[test]
+    fn reads_other_files_as_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "plain text\n").unwrap();
+        assert!(!is_document(&path));
+        assert_eq!(read_to_string(&path).unwrap(), "plain text\n");
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn extracts_pdf_text_per_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.PDF");
+        test_pdf(&["Hello PDF world", "Second page"])
+            .save(&path)
+            .unwrap();
+        assert!(is_document(&path));
+        let text = read_to_string(&path).unwrap();
+        assert_eq!(text.split_whitespace().count(), 5);
+        assert_eq!(count_pages(&text), 2);
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn broken_pdf_is_invalid_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.pdf");
+        fs::write(&path, "not a pdf").unwrap();
+        let error = read_to_string(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "office")]
+    #[test]
+    fn docx_paragraphs_become_lines() {
+        let xml = r#"<?xml version="1.0"?><w:document><w:body>
+            <w:p><w:pPr><w:tabs><w:tab w:val="left" w:pos="720"/></w:tabs></w:pPr>
+            <w:r><w:t>Fish &amp;</w:t></w:r><w:r><w:t xml:space="preserve"> chips</w:t></w:r>
+            <w:r><w:tab/><w:t>£4</w:t></w:r></w:p>
+            <w:p/><w:p><w:r><w:instrText>PAGE</w:instrText><w:t>Done</w:t></w:r></w:p>
+            </w:body></w:document>"#;
+        assert_eq!(xml_text(xml, &DOCX), "Fish & chips\t£4\n\nDone\n");
+    }
+
+    #[cfg(feature = "office")]
+    #[test]
+    fn odt_body_text_with_spaces() {
+        let xml = r#"<office:document-content><office:automatic-styles>
+            <style:style style:name="P1"/></office:automatic-styles><office:body><office:text>
+            <text:h>Title</text:h><text:p>one<text:s text:c="3"/>two<text:line-break/>three</text:p>
+            </office:text></office:body></office:document-content>"#;
+        assert_eq!(xml_text(xml, &ODT), "Title\none   two\nthree\n");
+    }
+
+    #[cfg(feature = "office")]
+    #[test]
+    fn reads_docx_archives() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("letter.docx");
+        let mut archive = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        archive
+            .start_file(
+                "word/document.xml",
+                zip::write::SimpleFileOptions::default(),
+            )
+            .unwrap();
+        archive
+            .write_all(b"<w:document><w:body><w:p><w:r><w:t>Dear reader</w:t></w:r></w:p></w:body></w:document>")
+            .unwrap();
+        archive.finish().unwrap();
+        assert!(is_document(&path));
+        assert_eq!(read_to_string(&path).unwrap(), "Dear reader\n");
+
+        let broken = dir.path().join("broken.odt");
+        fs::write(&broken, "not a zip").unwrap();
+        assert_eq!(
+            read_to_string(&broken).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[cfg(feature = "office")]
+    #[test]
+    fn reads_epub_chapters_in_spine_order() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("novel.epub");
+        let mut archive = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let members = [
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                r#"<package><manifest>
+                <item id="c2" href="Text/chapter%202.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c1" href="Text/chapter1.xhtml" media-type="application/xhtml+xml"/>
+                </manifest><spine><itemref idref="c1"/><itemref idref="c2"/></spine></package>"#,
+            ),
+            (
+                "OEBPS/Text/chapter1.xhtml",
+                "<html><head><title>One</title></head><body><p>It was a dark night.</p></body></html>",
+            ),
+            (
+                "OEBPS/Text/chapter 2.xhtml",
+                "<html><body><p>Morning came.</p></body></html>",
+            ),
+        ];
+        for (name, content) in members {
+            archive
+                .start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            archive.write_all(content.as_bytes()).unwrap();
+        }
+        archive.finish().unwrap();
+
+        assert!(is_book(&path));
+        assert_eq!(
+            read_to_string(&path).unwrap(),
+            "One    It was a dark night.\nMorning came.\n"
+        );
+        assert_eq!(
+            chapters(&path).unwrap().words,
+            vec![
+                ("Text/chapter1.xhtml".to_string(), 6),
+                ("Text/chapter 2.xhtml".to_string(), 2)
+            ]
+        );
+    }
+
+    /// A document with one line of Helvetica text on each page.
+    #[cfg(feature = "pdf")]
+    fn test_pdf(pages: &[&str]) -> lopdf::Document {
+        use lopdf::content::{Content, Operation};
+        use lopdf::{dictionary, Object, Stream};
+
+        let mut document = lopdf::Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let resources_id = document.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let kids: Vec<Object> = pages
+            .iter()
+            .map(|text| {
+                let content = Content {
+                    operations: vec![
+                        Operation::new("BT", vec![]),
+                        Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                        Operation::new("Td", vec![72.into(), 720.into()]),
+                        Operation::new("Tj", vec![Object::string_literal(*text)]),
+                        Operation::new("ET", vec![]),
+                    ],
+                };
+                let content_id =
+                    document.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+                document
+                    .add_object(dictionary! {
+                        "Type" => "Page",
+                        "Parent" => pages_id,
+                        "Contents" => content_id,
+                    })
+                    .into()
+            })
+            .collect();
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+        document
+    }
+}
@@ -0,0 +1,103 @@
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// How often each byte value occurs in a file, for the `--entropy` estimate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteHistogram {
+    counts: [usize; 256],
+}
+
+impl Default for ByteHistogram {
+    fn default() -> Self {
+        Self { counts: [0; 256] }
+    }
+}
+
+impl Add for ByteHistogram {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+        self
+    }
+}
+
+impl AddAssign for ByteHistogram {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for ByteHistogram {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl ByteHistogram {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut histogram = Self::default();
+        for &byte in bytes {
+            histogram.counts[byte as usize] += 1;
+        }
+        histogram
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Shannon entropy in bits per byte, from 0 (one repeated byte) to 8 (every value
+    /// equally likely). Prose and code sit around 4 to 5; compressed, encrypted or
+    /// encoded data approaches 8 (base64 about 6).
+    pub fn bits_per_byte(&self) -> f64 {
+        let total = self.total() as f64;
+        if total == 0.0 {
+            return 0.0;
+        }
+        self.counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entropy(bytes: &[u8]) -> f64 {
+        ByteHistogram::from_bytes(bytes).bits_per_byte()
+    }
+
+    #[test]
+    fn entropy_of_simple_inputs() {
+        assert_eq!(entropy(b""), 0.0);
+        assert_eq!(entropy(b"aaaa"), 0.0);
+        assert_eq!(entropy(b"abab"), 1.0);
+        assert_eq!(entropy(b"abcd"), 2.0);
+        let every_byte: Vec<u8> = (0..=255).collect();
+        assert_eq!(entropy(&every_byte), 8.0);
+    }
+
+    #[test]
+    fn prose_is_lower_than_random_looking_data() {
+        let prose = entropy(b"the quick brown fox jumps over the lazy dog and the cat");
+        let encoded = entropy(b"q8Zx3LmP0vK7bN2cR5tY9wE1aS4dF6gH8jK0lZ2xC4vB6nM8qW0eR2tY4uI6oP8");
+        assert!(prose < 4.5, "{prose}");
+        assert!(encoded > 5.0, "{encoded}");
+    }
+
+    #[test]
+    fn sums_combine_the_bytes() {
+        let total = ByteHistogram::from_bytes(b"aa") + ByteHistogram::from_bytes(b"bb");
+        assert_eq!(total.total(), 4);
+        assert_eq!(total.bits_per_byte(), 1.0);
+    }
+}
@@ -0,0 +1,157 @@
+use std::ffi::{c_char, c_int, CStr};
+use std::path::Path;
+
+use crate::counter::{count_directory, count_file, Count, FilterConfig};
+
+/// The call succeeded and the result was written
+pub const EWC_OK: c_int = 0;
+/// A pointer argument was null
+pub const EWC_ERR_NULL: c_int = -1;
+/// The path was not valid UTF-8
+pub const EWC_ERR_PATH: c_int = -2;
+/// The path could not be read (missing, unreadable or not UTF-8 text)
+pub const EWC_ERR_IO: c_int = -3;
+
+/// Counts written by the `ewc_count_*` functions; mirrors `EwcCountResult` in `ewc.h`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CountResult {
+    pub lines: u64,
+    pub words: u64,
+    pub bytes: u64,
+    pub max_line_length: u64,
+    pub file_count: u64,
+}
+
+impl CountResult {
+    fn new(count: Count, file_count: usize) -> Self {
+        Self {
+            lines: count.lines as u64,
+            words: count.words as u64,
+            bytes: count.bytes as u64,
+            max_line_length: count.max_line_length as u64,
+            file_count: file_count as u64,
+        }
+    }
+}
+
+/// Reads a path argument and writes the result of `count`, returning a status code.
+///
+/// # Safety
+///
+/// `path` must be null or a NUL-terminated string, and `out` null or valid for writes.
+unsafe fn count_into(
+    path: *const c_char,
+    out: *mut CountResult,
+    count: impl FnOnce(&Path) -> std::io::Result<CountResult>,
+) -> c_int {
+    if path.is_null() || out.is_null() {
+        return EWC_ERR_NULL;
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return EWC_ERR_PATH;
+    };
+    match count(Path::new(path)) {
+        Ok(result) => {
+            out.write(result);
+            EWC_OK
+        }
+        Err(_) => EWC_ERR_IO,
+    }
+}
+
+/// Counts one file into `*out`.
+///
+/// # Safety
+///
+/// `path` must be null or a NUL-terminated string, and `out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ewc_count_file(path: *const c_char, out: *mut CountResult) -> c_int {
+    count_into(path, out, |path| Ok(CountResult::new(count_file(path)?, 1)))
+}
+
+/// Counts every file under a directory into `*out`, skipping hidden files unless
+/// `include_hidden` is non-zero, and generated files as the command line does.
+///
+/// # Safety
+///
+/// `path` must be null or a NUL-terminated string, and `out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ewc_count_directory(
+    path: *const c_char,
+    include_hidden: c_int,
+    out: *mut CountResult,
+) -> c_int {
+    count_into(path, out, |path| {
+        let config = FilterConfig::new(include_hidden != 0, vec![], vec![]);
+        let (count, file_count) = count_directory(path, &config)?;
+        Ok(CountResult::new(count, file_count))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::fs;
+    use std::ptr;
+
+    #[test]
+    fn counts_file_and_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "one two\nthree\n").unwrap();
+        fs::write(dir.path().join(".hidden"), "secret\n").unwrap();
+        let file = CString::new(dir.path().join("a.txt").to_str().unwrap()).unwrap();
+        let root = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mut result = CountResult::default();
+
+        assert_eq!(
+            unsafe { ewc_count_file(file.as_ptr(), &mut result) },
+            EWC_OK
+        );
+        assert_eq!(
+            result,
+            CountResult {
+                lines: 2,
+                words: 3,
+                bytes: 14,
+                max_line_length: 7,
+                file_count: 1,
+            }
+        );
+
+        assert_eq!(
+            unsafe { ewc_count_directory(root.as_ptr(), 0, &mut result) },
+            EWC_OK
+        );
+        assert_eq!(result.file_count, 1);
+        assert_eq!(
+            unsafe { ewc_count_directory(root.as_ptr(), 1, &mut result) },
+            EWC_OK
+        );
+        assert_eq!(result.file_count, 2);
+    }
+
+    #[test]
+    fn reports_errors_as_status_codes() {
+        let missing = CString::new("/nonexistent/ewc.txt").unwrap();
+        let mut result = CountResult::default();
+        assert_eq!(
+            unsafe { ewc_count_file(missing.as_ptr(), &mut result) },
+            EWC_ERR_IO
+        );
+        assert_eq!(
+            unsafe { ewc_count_file(ptr::null(), &mut result) },
+            EWC_ERR_NULL
+        );
+        assert_eq!(
+            unsafe { ewc_count_file(missing.as_ptr(), ptr::null_mut()) },
+            EWC_ERR_NULL
+        );
+        let invalid = CString::new(vec![0xff, 0xfe]).unwrap();
+        assert_eq!(
+            unsafe { ewc_count_file(invalid.as_ptr(), &mut result) },
+            EWC_ERR_PATH
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::counter::{Count, IncrementalCount, DEFAULT_CHUNK_SIZE};
+
+/// How often a followed file is checked for new content
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reads a growing file like `tail -f`, counting only what was appended since the last poll.
+pub struct Follower {
+    path: PathBuf,
+    file: File,
+    position: u64,
+    counter: IncrementalCount,
+    chunk_size: usize,
+}
+
+impl Follower {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: File::open(path)?,
+            position: 0,
+            counter: IncrementalCount::new(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        })
+    }
+
+    /// Reads appended content `size` bytes at a time.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+
+    /// Counts of everything read so far.
+    pub fn count(&self) -> Count {
+        self.counter.count()
+    }
+
+    /// Reads whatever was appended since the last call, returning the new counts if anything changed.
+    ///
+    /// A file that got shorter was truncated or rotated, so counting starts over from its beginning.
+    pub fn poll(&mut self) -> io::Result<Option<Count>> {
+        let restarted = fs::metadata(&self.path)?.len() < self.position;
+        if restarted {
+            self.file = File::open(&self.path)?;
+            self.position = 0;
+            self.counter = IncrementalCount::new();
+        }
+
+        let mut appended = false;
+        let mut buffer = vec![0; self.chunk_size];
+        loop {
+            let read = self.file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            self.counter.feed(&buffer[..read])?;
+            self.position += read as u64;
+            appended = true;
+        }
+
+        Ok((appended || restarted).then(|| self.count()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    fn append(path: &Path, content: &str) {
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn poll_counts_appended_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "started\n").unwrap();
+
+        let mut follower = Follower::open(&path).unwrap().chunk_size(3);
+        assert_eq!(follower.poll().unwrap().unwrap().lines, 1);
+        assert_eq!(follower.poll().unwrap(), None);
+
+        append(&path, "request one");
+        append(&path, " done\nrequest two\n");
+        let count = follower.poll().unwrap().unwrap();
+        assert_eq!(count.lines, 3);
+        assert_eq!(count.words, 6);
+        assert_eq!(
+            count,
+            Count::from_content("started\nrequest one done\nrequest two\n")
+        );
+    }
+
+    #[test]
+    fn poll_restarts_after_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        let mut follower = Follower::open(&path).unwrap();
+        follower.poll().unwrap();
+        fs::write(&path, "new\n").unwrap();
+
+        let count = follower.poll().unwrap().unwrap();
+        assert_eq!(count.lines, 1);
+        assert_eq!(count.bytes, 4);
+    }
+}
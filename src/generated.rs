@@ -0,0 +1,329 @@
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::counter::join_relative;
+
+/// Whether a file is generated (or vendored) code that should not be counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generated {
+    Yes,
+    No,
+    /// Nothing in the path says either way; the content may still tell
+    Unknown,
+}
+
+struct AttributeRule {
+    matcher: GlobMatcher,
+    generated: Option<bool>,
+    vendored: Option<bool>,
+}
+
+fn attribute_value(token: &str, name: &str) -> Option<bool> {
+    if let Some(rest) = token.strip_prefix('-') {
+        return (rest == name).then_some(false);
+    }
+    let rest = token.strip_prefix(name)?;
+    match rest {
+        "" | "=true" => Some(true),
+        "=false" => Some(false),
+        _ => None,
+    }
+}
+
+// Patterns without a slash match at any depth; others are relative to the file's directory
+fn attribute_glob(pattern: &str) -> String {
+    match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{pattern}"),
+    }
+}
+
+fn parse_gitattributes(content: &str) -> Vec<AttributeRule> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let pattern = tokens.next()?;
+            let mut generated = None;
+            let mut vendored = None;
+            for token in tokens {
+                generated = attribute_value(token, "linguist-generated").or(generated);
+                vendored = attribute_value(token, "linguist-vendored").or(vendored);
+            }
+            if generated.is_none() && vendored.is_none() {
+                return None;
+            }
+            let matcher = GlobBuilder::new(&attribute_glob(pattern))
+                .literal_separator(true)
+                .build()
+                .ok()?
+                .compile_matcher();
+            Some(AttributeRule {
+                matcher,
+                generated,
+                vendored,
+            })
+        })
+        .collect()
+}
+
+const GENERATED_NAMES: &[&str] = &[
+    "Cargo.lock",
+    "composer.lock",
+    "Gemfile.lock",
+    "go.sum",
+    "package-lock.json",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "yarn.lock",
+];
+
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".designer.cs",
+    ".freezed.dart",
+    ".g.dart",
+    ".js.map",
+    ".css.map",
+    ".min.css",
+    ".min.js",
+    ".pb.cc",
+    ".pb.go",
+    ".pb.h",
+    "_pb2.py",
+];
+
+/// Lock files, minified assets and common code generator outputs, judged by name.
+pub fn is_generated_name(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    GENERATED_NAMES.contains(&name) || GENERATED_SUFFIXES.iter().any(|s| name.ends_with(s))
+}
+
+const GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "do not edit",
+    "code generated by",
+    "auto-generated",
+    "autogenerated",
+    "automatically generated",
+];
+
+/// How many leading lines are searched for a generator marker
+const MARKER_LINES: usize = 5;
+
+/// Whether the first few lines carry a marker such as `@generated` or `DO NOT EDIT`.
+pub fn is_generated_content(content: &str) -> bool {
+    content.lines().take(MARKER_LINES).any(|line| {
+        let line = line.to_lowercase();
+        GENERATED_MARKERS.iter().any(|marker| line.contains(marker))
+    })
+}
+
+/// Classifies files under a scan root using `.gitattributes` and file names.
+///
+/// Attribute files are read from the root, from its ancestors up to the enclosing
+/// repository, and lazily from subdirectories as files in them are classified.
+pub struct GeneratedFilter {
+    root: PathBuf,
+    outer_dirs: Vec<PathBuf>,
+    attributes: HashMap<PathBuf, Vec<AttributeRule>>,
+}
+
+impl GeneratedFilter {
+    pub fn new(root: &Path) -> Self {
+        let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let mut outer_dirs: Vec<PathBuf> = Vec::new();
+        if !root.join(".git").exists() {
+            for dir in root.ancestors().skip(1) {
+                outer_dirs.push(dir.to_path_buf());
+                if dir.join(".git").exists() {
+                    break;
+                }
+            }
+            // Outside a repository only the scanned tree's own attributes apply
+            if !outer_dirs.last().is_some_and(|d| d.join(".git").exists()) {
+                outer_dirs.clear();
+            }
+        }
+        outer_dirs.reverse();
+
+        let mut filter = Self {
+            root,
+            outer_dirs,
+            attributes: HashMap::new(),
+        };
+        for dir in filter.outer_dirs.clone() {
+            filter.load_rules(&dir);
+        }
+        filter
+    }
+
+    fn load_rules(&mut self, dir: &Path) {
+        if !self.attributes.contains_key(dir) {
+            let rules = fs::read_to_string(dir.join(".gitattributes"))
+                .map(|content| parse_gitattributes(&content))
+                .unwrap_or_default();
+            self.attributes.insert(dir.to_path_buf(), rules);
+        }
+    }
+
+    /// Classifies a file given by its path relative to the scan root.
+    pub fn classify(&mut self, relative_path: &Path) -> Generated {
+        let file = join_relative(&self.root, relative_path);
+        // Directories from the root down to the file's own, borrowed from its path
+        let mut inner_dirs: Vec<&Path> = file
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .collect();
+        inner_dirs.reverse();
+        for dir in &inner_dirs {
+            self.load_rules(dir);
+        }
+
+        let mut generated = None;
+        let mut vendored = None;
+        // Inner attribute files, and later lines within a file, take precedence
+        let dirs = self
+            .outer_dirs
+            .iter()
+            .map(PathBuf::as_path)
+            .chain(inner_dirs);
+        for dir in dirs {
+            let Ok(relative) = file.strip_prefix(dir) else {
+                continue;
+            };
+            for rule in &self.attributes[dir] {
+                if rule.matcher.is_match(relative) {
+                    generated = rule.generated.or(generated);
+                    vendored = rule.vendored.or(vendored);
+                }
+            }
+        }
+
+        match (generated, vendored) {
+            (Some(true), _) | (_, Some(true)) => Generated::Yes,
+            (Some(false), _) => Generated::No,
+            _ if is_generated_name(relative_path) => Generated::Yes,
+            _ => Generated::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute_value_forms() {
+        assert_eq!(
+            attribute_value("linguist-generated", "linguist-generated"),
+            Some(true)
+        );
+        assert_eq!(
+            attribute_value("linguist-generated=true", "linguist-generated"),
+            Some(true)
+        );
+        assert_eq!(
+            attribute_value("linguist-generated=false", "linguist-generated"),
+            Some(false)
+        );
+        assert_eq!(
+            attribute_value("-linguist-generated", "linguist-generated"),
+            Some(false)
+        );
+        assert_eq!(attribute_value("binary", "linguist-generated"), None);
+        assert_eq!(
+            attribute_value("linguist-generated-extra", "linguist-generated"),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_gitattributes_skips_unrelated_lines() {
+        let rules = parse_gitattributes(
+            "# comment\n\n*.png binary\ndist/** linguist-generated\nvendor/** linguist-vendored=true\n",
+        );
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].generated, Some(true));
+        assert!(rules[0].matcher.is_match("dist/app.js"));
+        assert_eq!(rules[1].vendored, Some(true));
+    }
+
+    #[test]
+    fn attribute_glob_anchoring() {
+        assert_eq!(attribute_glob("*.pb.rs"), "**/*.pb.rs");
+        assert_eq!(attribute_glob("/schema.rs"), "schema.rs");
+        assert_eq!(attribute_glob("gen/*.rs"), "gen/*.rs");
+    }
+
+    #[test]
+    fn generated_names() {
+        assert!(is_generated_name(Path::new("web/package-lock.json")));
+        assert!(is_generated_name(Path::new("static/app.min.js")));
+        assert!(is_generated_name(Path::new("api/service.pb.go")));
+        assert!(!is_generated_name(Path::new("src/main.rs")));
+    }
+
+    #[test]
+    fn generated_content_markers() {
+        assert!(is_generated_content(
+            "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n"
+        ));
+        assert!(is_generated_content("#!/bin/sh\n# @generated\n"));
+        assert!(!is_generated_content("fn main() {}\n"));
+        assert!(!is_generated_content(
+            "1\n2\n3\n4\n5\n// Code generated by hand, honestly\n"
+        ));
+    }
+
+    #[test]
+    fn classify_with_nested_attributes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(".gitattributes"),
+            "gen/** linguist-generated\n*.lock -linguist-generated\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("gen")).unwrap();
+        fs::create_dir_all(dir.path().join("third_party")).unwrap();
+        fs::write(
+            dir.path().join("third_party/.gitattributes"),
+            "*.c linguist-vendored\n",
+        )
+        .unwrap();
+
+        let mut filter = GeneratedFilter::new(dir.path());
+        assert_eq!(filter.classify(Path::new("gen/api.rs")), Generated::Yes);
+        assert_eq!(
+            filter.classify(Path::new("third_party/zlib.c")),
+            Generated::Yes
+        );
+        assert_eq!(filter.classify(Path::new("src/zlib.c")), Generated::Unknown);
+        // An explicit attribute overrides the built-in name heuristic
+        assert_eq!(filter.classify(Path::new("Cargo.lock")), Generated::No);
+        assert_eq!(filter.classify(Path::new("web/app.min.js")), Generated::Yes);
+    }
+
+    #[test]
+    fn classify_reads_repository_root_attributes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(dir.path().join("src/gen")).unwrap();
+        fs::write(
+            dir.path().join(".gitattributes"),
+            "src/gen/** linguist-generated\n",
+        )
+        .unwrap();
+
+        let mut filter = GeneratedFilter::new(&dir.path().join("src"));
+        assert_eq!(filter.classify(Path::new("gen/types.rs")), Generated::Yes);
+        assert_eq!(filter.classify(Path::new("lib.rs")), Generated::Unknown);
+    }
+}
@@ -0,0 +1,682 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+fn run_git(dir: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        // Keep non-ASCII paths unescaped in human-readable output
+        .args(["-c", "core.quotePath=false"])
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "git {}: {}",
+            args[0],
+            stderr.trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+fn split_nul(output: &[u8]) -> Vec<PathBuf> {
+    output
+        .split(|&b| b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| PathBuf::from(String::from_utf8_lossy(s).into_owned()))
+        .collect()
+}
+
+/// Which files a git-aware directory scan should consider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitSelection {
+    Tracked,
+    Changed(String),
+    Staged,
+}
+
+/// Files tracked by git under `dir`, relative to `dir`.
+pub fn tracked_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    run_git(dir, &["ls-files", "-z"]).map(|out| split_nul(&out))
+}
+
+/// Files under `dir` that differ from `base` in the working tree, plus untracked files.
+pub fn changed_files(dir: &Path, base: &str) -> io::Result<Vec<PathBuf>> {
+    let diff = run_git(
+        dir,
+        &[
+            "diff",
+            "--name-only",
+            "-z",
+            "--relative",
+            "--diff-filter=d",
+            base,
+            "--",
+        ],
+    )?;
+    let untracked = run_git(dir, &["ls-files", "-z", "--others", "--exclude-standard"])?;
+
+    let mut files = split_nul(&diff);
+    files.extend(split_nul(&untracked));
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Files under `dir` with changes staged in the index.
+pub fn staged_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    run_git(
+        dir,
+        &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "-z",
+            "--relative",
+            "--diff-filter=d",
+        ],
+    )
+    .map(|out| split_nul(&out))
+}
+
+pub fn list_files(dir: &Path, selection: &GitSelection) -> io::Result<Vec<PathBuf>> {
+    match selection {
+        GitSelection::Tracked => tracked_files(dir),
+        GitSelection::Changed(base) => changed_files(dir, base),
+        GitSelection::Staged => staged_files(dir),
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub words_added: usize,
+    pub words_removed: usize,
+}
+
+fn diff_args<'a>(options: &[&'a str], range: &'a str, paths: &'a [String]) -> Vec<&'a str> {
+    let mut args = vec!["diff", "--no-renames", "--no-prefix", "--relative"];
+    args.extend_from_slice(options);
+    args.extend([range, "--"]);
+    args.extend(paths.iter().map(String::as_str));
+    args
+}
+
+fn parse_numstat(output: &[u8]) -> Vec<FileDiff> {
+    output
+        .split(|&b| b == 0)
+        .filter(|s| !s.is_empty())
+        .filter_map(|record| {
+            let record = String::from_utf8_lossy(record);
+            let mut fields = record.splitn(3, '\t');
+            let added = fields.next()?;
+            let removed = fields.next()?;
+            let path = fields.next()?;
+            // Binary files report "-" for both columns
+            Some(FileDiff {
+                path: path.to_string(),
+                lines_added: added.parse().unwrap_or(0),
+                lines_removed: removed.parse().unwrap_or(0),
+                ..FileDiff::default()
+            })
+        })
+        .collect()
+}
+
+fn header_path(line: &str) -> Option<&str> {
+    let path = line[4..].trim_end_matches('\t');
+    (path != "/dev/null").then_some(path)
+}
+
+fn parse_word_diff(output: &str) -> HashMap<String, (usize, usize)> {
+    let mut words = HashMap::new();
+    let mut path: Option<String> = None;
+    let mut in_header = false;
+
+    for line in output.lines() {
+        if line.starts_with("diff --git ") {
+            path = None;
+            in_header = true;
+            continue;
+        }
+        if in_header {
+            if line.starts_with("--- ") || line.starts_with("+++ ") {
+                if let Some(p) = header_path(line) {
+                    path = Some(p.to_string());
+                }
+            } else if line.starts_with("@@") {
+                in_header = false;
+            }
+            continue;
+        }
+
+        let Some(path) = &path else { continue };
+        let entry: &mut (usize, usize) = words.entry(path.clone()).or_default();
+        if let Some(added) = line.strip_prefix('+') {
+            entry.0 += added.split_whitespace().count();
+        } else if let Some(removed) = line.strip_prefix('-') {
+            entry.1 += removed.split_whitespace().count();
+        }
+    }
+
+    words
+}
+
+/// Lines and words added and removed per file for a revision range like `main..HEAD`.
+pub fn diff_stats(dir: &Path, range: &str, paths: &[String]) -> io::Result<Vec<FileDiff>> {
+    let numstat = run_git(dir, &diff_args(&["--numstat", "-z"], range, paths))?;
+    let word_diff = run_git(dir, &diff_args(&["--word-diff=porcelain"], range, paths))?;
+
+    let words = parse_word_diff(&String::from_utf8_lossy(&word_diff));
+    let mut diffs = parse_numstat(&numstat);
+    for diff in &mut diffs {
+        if let Some(&(added, removed)) = words.get(&diff.path) {
+            diff.words_added = added;
+            diff.words_removed = removed;
+        }
+    }
+    Ok(diffs)
+}
+
+/// The commit HEAD points at.
+pub fn head_revision(dir: &Path) -> io::Result<String> {
+    let output = run_git(dir, &["rev-parse", "HEAD"])?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub id: String,
+    /// Committer date as `YYYY-MM-DD`
+    pub date: String,
+}
+
+/// First-parent commits on HEAD committed after `since`, oldest first.
+///
+/// `since` accepts anything `git log --since` does, e.g. `1 year ago` or `2024-01-01`.
+pub fn first_parent_commits(dir: &Path, since: &str) -> io::Result<Vec<Commit>> {
+    let since = format!("--since={since}");
+    let output = run_git(
+        dir,
+        &[
+            "log",
+            "--first-parent",
+            "--reverse",
+            "--format=%H %cs",
+            &since,
+            "HEAD",
+        ],
+    )?;
+
+    Ok(String::from_utf8_lossy(&output)
+        .lines()
+        .filter_map(|line| {
+            let (id, date) = line.split_once(' ')?;
+            Some(Commit {
+                id: id.to_string(),
+                date: date.to_string(),
+            })
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeBlob {
+    pub path: PathBuf,
+    pub id: String,
+}
+
+fn parse_ls_tree(output: &[u8]) -> Vec<TreeBlob> {
+    output
+        .split(|&b| b == 0)
+        .filter(|s| !s.is_empty())
+        .filter_map(|record| {
+            let record = String::from_utf8_lossy(record);
+            let (info, path) = record.split_once('\t')?;
+            let mut fields = info.split(' ');
+            let _mode = fields.next()?;
+            // Submodules show up as "commit" entries and have no content here
+            if fields.next()? != "blob" {
+                return None;
+            }
+            Some(TreeBlob {
+                path: PathBuf::from(path),
+                id: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Blobs in `commit` under `dir`, with paths relative to `dir`.
+pub fn tree_blobs(dir: &Path, commit: &str, paths: &[String]) -> io::Result<Vec<TreeBlob>> {
+    let mut args = vec!["ls-tree", "-r", "-z", commit, "--"];
+    args.extend(paths.iter().map(String::as_str));
+    run_git(dir, &args).map(|out| parse_ls_tree(&out))
+}
+
+fn parse_ls_files_stage(output: &[u8]) -> Vec<TreeBlob> {
+    output
+        .split(|&b| b == 0)
+        .filter(|s| !s.is_empty())
+        .filter_map(|record| {
+            let record = String::from_utf8_lossy(record);
+            let (info, path) = record.split_once('\t')?;
+            let mut fields = info.split(' ');
+            // Submodules have no content in the index
+            if fields.next()? == "160000" {
+                return None;
+            }
+            let id = fields.next()?;
+            // Unmerged entries use stages 1-3
+            if fields.next()? != "0" {
+                return None;
+            }
+            Some(TreeBlob {
+                path: PathBuf::from(path),
+                id: id.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Blobs in the index under `dir`, with paths relative to `dir`.
+pub fn index_blobs(dir: &Path) -> io::Result<Vec<TreeBlob>> {
+    run_git(dir, &["ls-files", "-s", "-z"]).map(|out| parse_ls_files_stage(&out))
+}
+
+/// Directory git runs hooks from, honoring `core.hooksPath`.
+pub fn hooks_dir(dir: &Path) -> io::Result<PathBuf> {
+    let output = run_git(dir, &["rev-parse", "--git-path", "hooks"])?;
+    let hooks = PathBuf::from(String::from_utf8_lossy(&output).trim());
+    Ok(dir.join(hooks))
+}
+
+fn parse_cat_file_batch(output: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+    let malformed = || io::Error::other("git cat-file: malformed batch output");
+    let mut blobs = Vec::new();
+    let mut rest = output;
+
+    while !rest.is_empty() {
+        let header_end = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(malformed)?;
+        let header = String::from_utf8_lossy(&rest[..header_end]);
+        let size: usize = header
+            .rsplit(' ')
+            .next()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| io::Error::other(format!("git cat-file: {header}")))?;
+        let start = header_end + 1;
+        // Each object is followed by a newline
+        let end = start + size;
+        if end >= rest.len() {
+            return Err(malformed());
+        }
+        blobs.push(rest[start..end].to_vec());
+        rest = &rest[end + 1..];
+    }
+
+    Ok(blobs)
+}
+
+/// Contents of the given blobs, in the same order, read through one `git cat-file --batch`.
+pub fn read_blobs(dir: &Path, ids: &[String]) -> io::Result<Vec<Vec<u8>>> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed ids from a separate thread so a full stdout pipe cannot block us
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::other("git cat-file: no stdin"))?;
+    let input: String = ids.iter().map(|id| format!("{id}\n")).collect();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let mut output = Vec::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_end(&mut output)?;
+    }
+    let status = child.wait()?;
+    writer
+        .join()
+        .map_err(|_| io::Error::other("git cat-file: writer panicked"))??;
+
+    if !status.success() {
+        let mut stderr = String::new();
+        if let Some(mut err) = child.stderr.take() {
+            err.read_to_string(&mut stderr)?;
+        }
+        return Err(io::Error::other(format!("git cat-file: {}", stderr.trim())));
+    }
+
+    parse_cat_file_batch(&output)
+}
+
+fn parse_blame_authors(output: &str) -> HashMap<String, usize> {
+    let mut authors = HashMap::new();
+    for line in output.lines() {
+        if let Some(author) = line.strip_prefix("author ") {
+            *authors.entry(author.to_string()).or_default() += 1;
+        }
+    }
+    authors
+}
+
+/// Number of lines in `file` last changed by each author, according to `git blame`.
+///
+/// Lines not yet committed are attributed to `Not Committed Yet`, as git does.
+pub fn blame_authors(file: &Path) -> io::Result<HashMap<String, usize>> {
+    let dir = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| io::Error::other(format!("cannot blame {}", file.display())))?;
+
+    let output = run_git(dir, &["blame", "--line-porcelain", "--", name])?;
+    Ok(parse_blame_authors(&String::from_utf8_lossy(&output)))
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::path::Path;
+    use std::process::Command;
+
+    pub fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=ewc", "-c", "user.email=ewc@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    /// Commits everything staged with both author and committer date set to `date`.
+    pub fn commit_at(dir: &Path, message: &str, date: &str) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=ewc", "-c", "user.email=ewc@example.com"])
+            .args(["commit", "-q", "-m", message])
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git commit at {date} failed");
+    }
+
+    pub fn init_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{git, init_repo};
+    use super::*;
+
+    #[test]
+    fn tracked_files_lists_only_tracked() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("tracked.txt"), "a\n").unwrap();
+        std::fs::write(dir.path().join("untracked.txt"), "b\n").unwrap();
+        git(dir.path(), &["add", "tracked.txt"]);
+
+        let files = tracked_files(dir.path()).unwrap();
+        assert_eq!(files, vec![PathBuf::from("tracked.txt")]);
+    }
+
+    #[test]
+    fn tracked_files_relative_to_subdirectory() {
+        let dir = init_repo();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "a\n").unwrap();
+        std::fs::write(dir.path().join("root.txt"), "b\n").unwrap();
+        git(dir.path(), &["add", "."]);
+
+        let files = tracked_files(&dir.path().join("src")).unwrap();
+        assert_eq!(files, vec![PathBuf::from("lib.rs")]);
+    }
+
+    fn repo_with_commit() -> tempfile::TempDir {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("committed.txt"), "a\n").unwrap();
+        std::fs::write(dir.path().join("untouched.txt"), "b\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+        dir
+    }
+
+    #[test]
+    fn changed_files_includes_modified_and_untracked() {
+        let dir = repo_with_commit();
+        std::fs::write(dir.path().join("committed.txt"), "a\nb\n").unwrap();
+        std::fs::write(dir.path().join("new.txt"), "c\n").unwrap();
+
+        let files = changed_files(dir.path(), "HEAD").unwrap();
+        assert_eq!(
+            files,
+            vec![PathBuf::from("committed.txt"), PathBuf::from("new.txt")]
+        );
+    }
+
+    #[test]
+    fn changed_files_skips_deleted() {
+        let dir = repo_with_commit();
+        std::fs::remove_file(dir.path().join("committed.txt")).unwrap();
+
+        assert!(changed_files(dir.path(), "HEAD").unwrap().is_empty());
+    }
+
+    #[test]
+    fn staged_files_lists_index_changes_only() {
+        let dir = repo_with_commit();
+        std::fs::write(dir.path().join("committed.txt"), "a\nb\n").unwrap();
+        std::fs::write(dir.path().join("staged.txt"), "c\n").unwrap();
+        git(dir.path(), &["add", "staged.txt"]);
+
+        let files = staged_files(dir.path()).unwrap();
+        assert_eq!(files, vec![PathBuf::from("staged.txt")]);
+    }
+
+    #[test]
+    fn tracked_files_outside_repository_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(tracked_files(dir.path()).is_err());
+    }
+
+    #[test]
+    fn parse_numstat_records() {
+        let diffs = parse_numstat(b"3\t1\tsrc/main.rs\0-\t-\tlogo.png\0");
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].path, "src/main.rs");
+        assert_eq!(diffs[0].lines_added, 3);
+        assert_eq!(diffs[0].lines_removed, 1);
+        assert_eq!(diffs[1].lines_added, 0);
+    }
+
+    #[test]
+    fn parse_word_diff_counts_chunks() {
+        let output = "diff --git a.txt a.txt\nindex 1..2 100644\n--- a.txt\n+++ a.txt\n@@ -1 +1 @@\n hello\n-old words\n+new shiny words\n~\n+++ not a header\n";
+        let words = parse_word_diff(output);
+        assert_eq!(words["a.txt"], (7, 2));
+    }
+
+    #[test]
+    fn parse_word_diff_deleted_file_uses_old_path() {
+        let output = "diff --git gone.txt gone.txt\ndeleted file mode 100644\n--- gone.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-bye now\n~\n";
+        let words = parse_word_diff(output);
+        assert_eq!(words["gone.txt"], (0, 2));
+    }
+
+    #[test]
+    fn diff_stats_between_commits() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("a.txt"), "one two\nthree\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "first"]);
+        std::fs::write(dir.path().join("a.txt"), "one two\nfour five six\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "brand new\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "second"]);
+
+        let diffs = diff_stats(dir.path(), "HEAD~1..HEAD", &[]).unwrap();
+        assert_eq!(
+            diffs,
+            vec![
+                FileDiff {
+                    path: "a.txt".to_string(),
+                    lines_added: 1,
+                    lines_removed: 1,
+                    words_added: 3,
+                    words_removed: 1,
+                },
+                FileDiff {
+                    path: "b.txt".to_string(),
+                    lines_added: 1,
+                    lines_removed: 0,
+                    words_added: 2,
+                    words_removed: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_ls_tree_skips_submodules() {
+        let output = b"100644 blob 1111\tsrc/lib.rs\x00160000 commit 2222\tvendor/dep\0";
+        assert_eq!(
+            parse_ls_tree(output),
+            vec![TreeBlob {
+                path: PathBuf::from("src/lib.rs"),
+                id: "1111".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_cat_file_batch_splits_objects() {
+        let output = b"aaa blob 6\nhello\n\nbbb blob 0\n\n";
+        let blobs = parse_cat_file_batch(output).unwrap();
+        assert_eq!(blobs, vec![b"hello\n".to_vec(), Vec::new()]);
+    }
+
+    #[test]
+    fn parse_cat_file_batch_rejects_missing_objects() {
+        assert!(parse_cat_file_batch(b"deadbeef missing\n").is_err());
+    }
+
+    #[test]
+    fn history_commits_and_blobs() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "first"]);
+        std::fs::write(dir.path().join("a.txt"), "one\ntwo\n").unwrap();
+        git(dir.path(), &["commit", "-q", "-am", "second"]);
+
+        let commits = first_parent_commits(dir.path(), "1 year ago").unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[1].date.len(), "YYYY-MM-DD".len());
+
+        let blobs = tree_blobs(dir.path(), &commits[0].id, &[]).unwrap();
+        assert_eq!(blobs.len(), 1);
+        assert_eq!(blobs[0].path, PathBuf::from("a.txt"));
+
+        let ids: Vec<String> = blobs.iter().map(|b| b.id.clone()).collect();
+        let contents = read_blobs(dir.path(), &ids).unwrap();
+        assert_eq!(contents, vec![b"one\n".to_vec()]);
+    }
+
+    #[test]
+    fn parse_blame_authors_counts_lines() {
+        let output = "abc 1 1 1\nauthor Alice\nauthor-mail <a@example.com>\n\tfn main() {}\nabc 2 2\nauthor Alice\n\t}\ndef 3 3 1\nauthor Bob\n\tauthor Mallory\n";
+        let authors = parse_blame_authors(output);
+        assert_eq!(authors.len(), 2);
+        assert_eq!(authors["Alice"], 2);
+        assert_eq!(authors["Bob"], 1);
+    }
+
+    #[test]
+    fn blame_authors_in_subdirectory() {
+        let dir = init_repo();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "a\nb\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+        std::fs::write(dir.path().join("src/lib.rs"), "a\nb\nc\n").unwrap();
+
+        let authors = blame_authors(&dir.path().join("src/lib.rs")).unwrap();
+        assert_eq!(authors["ewc"], 2);
+        assert_eq!(authors["Not Committed Yet"], 1);
+    }
+
+    #[test]
+    fn parse_ls_files_stage_skips_submodules_and_conflicts() {
+        let output = b"100644 aaa 0\tsrc/lib.rs\x00160000 bbb 0\tvendor/dep\x00100644 ccc 2\tconflict.rs\x00";
+        assert_eq!(
+            parse_ls_files_stage(output),
+            vec![TreeBlob {
+                path: PathBuf::from("src/lib.rs"),
+                id: "aaa".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn index_blobs_reads_staged_content() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("a.txt"), "staged\n").unwrap();
+        git(dir.path(), &["add", "a.txt"]);
+        std::fs::write(dir.path().join("a.txt"), "not staged\n").unwrap();
+
+        let blobs = index_blobs(dir.path()).unwrap();
+        let ids: Vec<String> = blobs.iter().map(|b| b.id.clone()).collect();
+        assert_eq!(
+            read_blobs(dir.path(), &ids).unwrap(),
+            vec![b"staged\n".to_vec()]
+        );
+    }
+
+    #[test]
+    fn hooks_dir_inside_git_dir() {
+        let dir = init_repo();
+        let hooks = hooks_dir(dir.path()).unwrap();
+        assert!(hooks.ends_with(".git/hooks"));
+    }
+
+    #[test]
+    fn head_revision_after_commit() {
+        let dir = init_repo();
+        assert!(head_revision(dir.path()).is_err());
+        std::fs::write(dir.path().join("a.txt"), "a\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+        assert_eq!(head_revision(dir.path()).unwrap().len(), 40);
+    }
+}
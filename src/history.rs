@@ -0,0 +1,257 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+
+use crate::cli::Interval;
+use crate::counter::{filter_relative_paths, Count, FilterConfig};
+use crate::git::{self, Commit};
+use crate::meta::civil_from_days;
+
+/// Counts at the last commit of one interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryPoint {
+    pub period: String,
+    pub commit: Commit,
+    pub count: Count,
+    pub file_count: usize,
+}
+
+/// Expands shorthand like `6m` or `1y` into a date `git log --since` understands.
+///
+/// Anything else is passed through unchanged.
+pub fn expand_since(since: &str) -> String {
+    let split = since.len().saturating_sub(1);
+    let (amount, unit) = since.split_at(split);
+    let unit = match unit {
+        "d" => "days",
+        "w" => "weeks",
+        "m" => "months",
+        "y" => "years",
+        _ => return since.to_string(),
+    };
+    match amount.parse::<u32>() {
+        Ok(amount) => format!("{amount} {unit} ago"),
+        Err(_) => since.to_string(),
+    }
+}
+
+// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's algorithm)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn parse_date(date: &str) -> Option<(i64, u32, u32)> {
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    Some((year, month, day))
+}
+
+/// The interval a `YYYY-MM-DD` date falls into. Weeks are labelled by their Monday.
+pub fn period_of(date: &str, interval: Interval) -> String {
+    match interval {
+        Interval::Day => date.to_string(),
+        Interval::Month => date.get(..7).unwrap_or(date).to_string(),
+        Interval::Year => date.get(..4).unwrap_or(date).to_string(),
+        Interval::Week => {
+            let Some((year, month, day)) = parse_date(date) else {
+                return date.to_string();
+            };
+            let days = days_from_civil(year, month, day);
+            // 1970-01-01 was a Thursday, so this is 0 for Mondays
+            let weekday = (days + 3).rem_euclid(7);
+            let (year, month, day) = civil_from_days(days - weekday);
+            format!("{year:04}-{month:02}-{day:02}")
+        }
+    }
+}
+
+/// Keeps the last commit of each interval, oldest interval first.
+fn sample_commits(commits: Vec<Commit>, interval: Interval) -> Vec<(String, Commit)> {
+    let mut samples: Vec<(String, Commit)> = Vec::new();
+    for commit in commits {
+        let period = period_of(&commit.date, interval);
+        match samples.last_mut() {
+            Some((last, sampled)) if *last == period => *sampled = commit,
+            _ => samples.push((period, commit)),
+        }
+    }
+    samples
+}
+
+/// Counts the files under `dir` at one commit per interval since `since`.
+///
+/// Blobs are read straight from the object database, so the working tree is untouched.
+/// Blobs that are not valid UTF-8 are skipped, as they are when counting directories.
+pub fn collect_history(
+    dir: &Path,
+    since: &str,
+    interval: Interval,
+    paths: &[String],
+    config: &FilterConfig,
+) -> io::Result<Vec<HistoryPoint>> {
+    let commits = git::first_parent_commits(dir, &expand_since(since))?;
+    // Most blobs are unchanged between samples, so each is only counted once
+    let mut counts: HashMap<String, Option<Count>> = HashMap::new();
+    let mut points = Vec::new();
+
+    for (period, commit) in sample_commits(commits, interval) {
+        let blobs = git::tree_blobs(dir, &commit.id, paths)?;
+        let selected: HashSet<_> =
+            filter_relative_paths(blobs.iter().map(|b| b.path.clone()).collect(), config)?
+                .into_iter()
+                .collect();
+        let ids: Vec<String> = blobs
+            .into_iter()
+            .filter(|blob| selected.contains(&blob.path))
+            .map(|blob| blob.id)
+            .collect();
+
+        let missing: Vec<String> = ids
+            .iter()
+            .filter(|id| !counts.contains_key(*id))
+            .cloned()
+            .collect();
+        for (id, content) in missing.iter().zip(git::read_blobs(dir, &missing)?) {
+            let count = String::from_utf8(content)
+                .ok()
+                .map(|text| Count::from_content(&text));
+            counts.insert(id.clone(), count);
+        }
+
+        let file_counts: Vec<&Count> = ids.iter().filter_map(|id| counts[id].as_ref()).collect();
+        points.push(HistoryPoint {
+            period,
+            commit,
+            file_count: file_counts.len(),
+            count: file_counts.into_iter().cloned().sum(),
+        });
+    }
+
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_at, git, init_repo};
+
+    #[test]
+    fn expand_since_shorthand() {
+        assert_eq!(expand_since("1y"), "1 years ago");
+        assert_eq!(expand_since("6m"), "6 months ago");
+        assert_eq!(expand_since("2w"), "2 weeks ago");
+        assert_eq!(expand_since("30d"), "30 days ago");
+    }
+
+    #[test]
+    fn expand_since_passes_through_dates() {
+        assert_eq!(expand_since("2024-01-01"), "2024-01-01");
+        assert_eq!(expand_since("m"), "m");
+        assert_eq!(expand_since(""), "");
+    }
+
+    #[test]
+    fn civil_date_round_trip() {
+        for days in [-1, 0, 59, 10_957, 19_782, 20_000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+    }
+
+    #[test]
+    fn period_of_intervals() {
+        assert_eq!(period_of("2024-03-14", Interval::Day), "2024-03-14");
+        assert_eq!(period_of("2024-03-14", Interval::Month), "2024-03");
+        assert_eq!(period_of("2024-03-14", Interval::Year), "2024");
+        // Thursday -> Monday of the same week
+        assert_eq!(period_of("2024-03-14", Interval::Week), "2024-03-11");
+        // Weeks can span a year boundary
+        assert_eq!(period_of("2025-01-01", Interval::Week), "2024-12-30");
+        assert_eq!(period_of("2024-03-11", Interval::Week), "2024-03-11");
+    }
+
+    fn commit(id: &str, date: &str) -> Commit {
+        Commit {
+            id: id.to_string(),
+            date: date.to_string(),
+        }
+    }
+
+    #[test]
+    fn sample_commits_keeps_last_per_interval() {
+        let commits = vec![
+            commit("a", "2024-01-03"),
+            commit("b", "2024-01-20"),
+            commit("c", "2024-02-01"),
+            commit("d", "2024-04-09"),
+        ];
+        let samples = sample_commits(commits, Interval::Month);
+        let ids: Vec<(&str, &str)> = samples
+            .iter()
+            .map(|(period, c)| (period.as_str(), c.id.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![("2024-01", "b"), ("2024-02", "c"), ("2024-04", "d")]
+        );
+    }
+
+    #[test]
+    fn collect_history_counts_each_sample() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("a.txt"), "one two\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        commit_at(dir.path(), "first", "2024-01-05T12:00:00");
+        std::fs::write(dir.path().join("b.txt"), "three\nfour\n").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "secret\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        commit_at(dir.path(), "second", "2024-02-05T12:00:00");
+
+        let points = collect_history(
+            dir.path(),
+            "2000-01-01",
+            Interval::Day,
+            &[],
+            &FilterConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].file_count, 1);
+        assert_eq!(points[0].count.words, 2);
+        assert_eq!(points[1].file_count, 2);
+        assert_eq!(points[1].count.lines, 3);
+    }
+
+    #[test]
+    fn collect_history_limited_to_paths() {
+        let dir = init_repo();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.path().join("README"), "docs\nmore docs\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+
+        let points = collect_history(
+            dir.path(),
+            "1y",
+            Interval::Month,
+            &["src".to_string()],
+            &FilterConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].file_count, 1);
+        assert_eq!(points[0].count.lines, 1);
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::budget::{check_entries, Threshold, Violation};
+use crate::counter::{filter_relative_paths, skip_generated, Count, FileEntry, FilterConfig};
+use crate::generated::is_generated_content;
+use crate::git;
+
+/// Counts the index version of files under `dir`, which is what a commit will contain.
+///
+/// With `staged_only`, only files with staged changes are counted; otherwise every
+/// file in the index is. Hidden, glob and generated-file filters apply as in a
+/// directory scan.
+pub fn count_index(
+    dir: &Path,
+    staged_only: bool,
+    config: &FilterConfig,
+) -> io::Result<Vec<FileEntry>> {
+    let mut blobs = git::index_blobs(dir)?;
+    if staged_only {
+        let staged: HashSet<PathBuf> = git::staged_files(dir)?.into_iter().collect();
+        blobs.retain(|blob| staged.contains(&blob.path));
+    }
+
+    let selected = filter_relative_paths(blobs.iter().map(|b| b.path.clone()).collect(), config)?;
+    let candidates: HashMap<PathBuf, bool> =
+        skip_generated(dir, selected, config).into_iter().collect();
+    let blobs: Vec<(git::TreeBlob, bool)> = blobs
+        .into_iter()
+        .filter_map(|blob| {
+            let check_content = *candidates.get(&blob.path)?;
+            Some((blob, check_content))
+        })
+        .collect();
+
+    let ids: Vec<String> = blobs.iter().map(|(blob, _)| blob.id.clone()).collect();
+    let contents = git::read_blobs(dir, &ids)?;
+
+    Ok(blobs
+        .into_iter()
+        .zip(contents)
+        .filter_map(|((blob, check_content), content)| {
+            let content = String::from_utf8(content).ok()?;
+            if check_content && is_generated_content(&content) {
+                return None;
+            }
+            Some(FileEntry {
+                path: blob.path,
+                count: Count::from_content(&content),
+            })
+        })
+        .collect())
+}
+
+/// Budget violations among the files a commit would contain.
+pub fn check_index(
+    dir: &Path,
+    staged_only: bool,
+    thresholds: &[Threshold],
+    config: &FilterConfig,
+) -> io::Result<Vec<Violation>> {
+    let entries = count_index(dir, staged_only, config)?;
+    Ok(check_entries(&entries, thresholds))
+}
+
+/// A `pre-commit` script running `ewc hook --staged` with the given limits.
+pub fn hook_script(thresholds: &[Threshold]) -> String {
+    let over: String = thresholds
+        .iter()
+        .map(|threshold| format!(" --over {threshold}"))
+        .collect();
+    format!("#!/bin/sh\n# Installed by ewc install-hook\nexec ewc hook --staged{over}\n")
+}
+
+/// Writes the `pre-commit` hook for the repository containing `dir`.
+///
+/// An existing hook is only replaced when `force` is set.
+pub fn install_hook(dir: &Path, thresholds: &[Threshold], force: bool) -> io::Result<PathBuf> {
+    let hooks = git::hooks_dir(dir)?;
+    let path = hooks.join("pre-commit");
+    if path.exists() && !force {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "{} already exists (use --force to replace it)",
+                path.display()
+            ),
+        ));
+    }
+
+    fs::create_dir_all(&hooks)?;
+    fs::write(&path, hook_script(thresholds))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{git, init_repo};
+
+    fn limits(specs: &[&str]) -> Vec<Threshold> {
+        specs.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn hook_script_embeds_limits() {
+        let script = hook_script(&limits(&["lines=500", "max-line-length=120"]));
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script
+            .ends_with("exec ewc hook --staged --over lines=500 --over max-line-length=120\n"));
+    }
+
+    #[test]
+    fn check_index_uses_staged_content() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("big.txt"), "1\n2\n3\n").unwrap();
+        std::fs::write(dir.path().join("other.txt"), "1\n2\n3\n4\n").unwrap();
+        git(dir.path(), &["add", "big.txt"]);
+        // Unstaged edits do not count towards the commit
+        std::fs::write(dir.path().join("big.txt"), "1\n").unwrap();
+
+        let violations = check_index(
+            dir.path(),
+            true,
+            &limits(&["lines=2"]),
+            &FilterConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, PathBuf::from("big.txt"));
+        assert_eq!(violations[0].actual, 3);
+    }
+
+    #[test]
+    fn count_index_staged_only_skips_committed_files() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("old.txt"), "old\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+        std::fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        git(dir.path(), &["add", "new.txt"]);
+
+        let config = FilterConfig::default();
+        assert_eq!(count_index(dir.path(), true, &config).unwrap().len(), 1);
+        assert_eq!(count_index(dir.path(), false, &config).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn install_hook_refuses_to_overwrite() {
+        let dir = init_repo();
+        let thresholds = limits(&["lines=500"]);
+
+        let path = install_hook(dir.path(), &thresholds, false).unwrap();
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("--over lines=500"));
+
+        let err = install_hook(dir.path(), &thresholds, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(install_hook(dir.path(), &thresholds, true).is_ok());
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter::Sum;
+use std::ops::Add;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "structure")]
+use crate::analysis::ParseError;
+
+/// Whether `--i18n` reads this file as locale resources: JSON and YAML files.
+pub fn is_locale_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "json" | "yaml" | "yml"))
+}
+
+/// Translatable strings of a locale file and their words, for `--i18n`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct I18nStats {
+    pub strings: usize,
+    /// Words of every string, without `{placeholders}` and `%s`-style format specifiers
+    pub words: usize,
+    /// Dotted key of every string, such as `menu.file.open`, sorted
+    pub keys: Vec<String>,
+    /// Keys that other locales of the same resources have and this file lacks
+    pub missing: Vec<String>,
+}
+
+impl Add for I18nStats {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self.strings += other.strings;
+        self.words += other.words;
+        self.keys.extend(other.keys);
+        self.missing.extend(other.missing);
+        self
+    }
+}
+
+impl Sum for I18nStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Reads the strings of a flat or nested JSON or YAML locale file; nested keys are
+/// joined with `.` and array items keyed by index. A YAML file whose only top-level
+/// key is its locale, as in Rails' `en.yml` starting with `en:`, is read from inside
+/// that key, so its keys match those of other locales.
+#[cfg(feature = "structure")]
+pub fn locale_strings(path: &Path, content: &str) -> Result<I18nStats, ParseError> {
+    // JSON is read as YAML, of which it is a subset
+    let documents = yaml_rust2::YamlLoader::load_from_str(content).map_err(|e| ParseError {
+        line: e.marker().line(),
+        column: e.marker().col(),
+        message: e.info().to_string(),
+    })?;
+    let locale = path.file_stem().and_then(|stem| stem.to_str());
+    let mut stats = I18nStats::default();
+    for document in &documents {
+        walk_strings(inside_locale(document, locale), String::new(), &mut stats);
+    }
+    stats.keys.sort();
+    Ok(stats)
+}
+
+#[cfg(feature = "structure")]
+fn inside_locale<'a>(document: &'a yaml_rust2::Yaml, locale: Option<&str>) -> &'a yaml_rust2::Yaml {
+    match (document, locale) {
+        (yaml_rust2::Yaml::Hash(hash), Some(locale)) if hash.len() == 1 => hash
+            .iter()
+            .next()
+            .filter(|(key, _)| {
+                key.as_str()
+                    .is_some_and(|key| key.eq_ignore_ascii_case(locale))
+            })
+            .map_or(document, |(_, value)| value),
+        _ => document,
+    }
+}
+
+#[cfg(feature = "structure")]
+fn walk_strings(value: &yaml_rust2::Yaml, key: String, stats: &mut I18nStats) {
+    use yaml_rust2::Yaml;
+    let child = |name: &str| {
+        if key.is_empty() {
+            name.to_string()
+        } else {
+            format!("{key}.{name}")
+        }
+    };
+    match value {
+        Yaml::Hash(hash) => {
+            for (name, value) in hash {
+                let name = match name {
+                    Yaml::String(name) => name.clone(),
+                    Yaml::Integer(name) => name.to_string(),
+                    Yaml::Boolean(name) => name.to_string(),
+                    _ => continue,
+                };
+                walk_strings(value, child(&name), stats);
+            }
+        }
+        Yaml::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                walk_strings(item, child(&index.to_string()), stats);
+            }
+        }
+        Yaml::String(text) => {
+            stats.strings += 1;
+            stats.words += text_words(text);
+            stats.keys.push(key);
+        }
+        _ => {}
+    }
+}
+
+/// Words of a string a translator writes: `{name}`, `{{count}}` and ICU `{n, plural,
+/// ...}` placeholders are left out, as are `%s`, `%1$d` and similar specifiers and
+/// punctuation standing alone.
+#[cfg(feature = "structure")]
+fn text_words(text: &str) -> usize {
+    let mut plain = String::with_capacity(text.len());
+    let mut depth = 0usize;
+    for c in text.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => {
+                depth -= 1;
+                plain.push(' ');
+            }
+            _ if depth == 0 => plain.push(c),
+            _ => {}
+        }
+    }
+    plain
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric) && !is_format_specifier(word))
+        .count()
+}
+
+#[cfg(feature = "structure")]
+fn is_format_specifier(word: &str) -> bool {
+    word.strip_prefix('%').is_some_and(|spec| {
+        spec.len() <= 3
+            && spec
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '$' || c == '@')
+    })
+}
+
+/// Fills in [`I18nStats::missing`] for locale files of the same resources: the files
+/// of one directory (`locales/en.json`, `locales/de.json`), or files of one name in
+/// sibling locale directories (`locales/en/common.json`, `locales/de/common.json`).
+/// Each file misses the keys any other file of its group has.
+pub fn compare_locales<'a>(files: impl IntoIterator<Item = (&'a Path, &'a mut I18nStats)>) {
+    let mut groups: BTreeMap<PathBuf, Vec<&mut I18nStats>> = BTreeMap::new();
+    for (path, stats) in files {
+        groups.entry(resource_group(path)).or_default().push(stats);
+    }
+    for locales in groups.values_mut().filter(|locales| locales.len() > 1) {
+        let all_keys: BTreeSet<String> = locales
+            .iter()
+            .flat_map(|stats| stats.keys.iter().cloned())
+            .collect();
+        for stats in locales.iter_mut() {
+            let own: BTreeSet<&String> = stats.keys.iter().collect();
+            stats.missing = all_keys
+                .iter()
+                .filter(|key| !own.contains(key))
+                .cloned()
+                .collect();
+        }
+    }
+}
+
+/// The locales of one resource: its directory, or for a file in a directory named
+/// after a locale, the file's name in the directory above.
+fn resource_group(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or(Path::new(""));
+    match (
+        parent.file_name().and_then(|name| name.to_str()),
+        parent.parent(),
+    ) {
+        (Some(name), Some(above)) if is_locale_tag(name) => {
+            above.join(path.file_name().unwrap_or_default())
+        }
+        _ => parent.to_path_buf(),
+    }
+}
+
+/// Whether a directory is named like a locale: `de`, `pt-BR`, `zh_Hant_TW`.
+fn is_locale_tag(name: &str) -> bool {
+    let mut parts = name.split(['-', '_']);
+    let language = parts.next().unwrap_or_default();
+    language.len() == 2
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|part| {
+            (2..=4).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(keys: &[&str]) -> I18nStats {
+        I18nStats {
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            ..I18nStats::default()
+        }
+    }
+
+    #[cfg(feature = "structure")]
+    #[test]
+    fn counts_words_without_placeholders() {
+        assert_eq!(text_words("Hello, {name}!"), 1);
+        assert_eq!(text_words("{{count}} files in %s"), 2);
+        assert_eq!(
+            text_words("{n, plural, one {# item} other {# items}} left"),
+            1
+        );
+        assert_eq!(text_words("100% done"), 2);
+    }
+
+    #[test]
+    fn compares_locales_of_the_same_resources() {
+        let (mut en, mut de, mut pt) = (stats(&["a", "b", "c"]), stats(&["a"]), stats(&["b"]));
+        let mut other = stats(&["z"]);
+        compare_locales([
+            (Path::new("locales/en/common.json"), &mut en),
+            (Path::new("locales/de/common.json"), &mut de),
+            (Path::new("locales/pt-BR/common.json"), &mut pt),
+            (Path::new("locales/en/admin.json"), &mut other),
+        ]);
+        assert!(en.missing.is_empty());
+        assert_eq!(de.missing, ["b", "c"]);
+        assert_eq!(pt.missing, ["a", "c"]);
+        assert!(other.missing.is_empty());
+        assert_eq!(resource_group(Path::new("i18n/en.yml")), Path::new("i18n"));
+        assert!(is_locale_file(Path::new("en.YML")));
+        assert!(!is_locale_file(Path::new("en.po")));
+    }
+
+    #[cfg(feature = "structure")]
+    #[test]
+    fn flattens_nested_json_and_yaml() {
+        let json = r#"{"menu":{"open":"Open {file}","recent":["One","Two"]},"count":3,"title":"Hello world"}"#;
+        let stats = locale_strings(Path::new("en.json"), json).unwrap();
+        assert_eq!(stats.strings, 4);
+        assert_eq!(stats.words, 5);
+        assert_eq!(
+            stats.keys,
+            ["menu.open", "menu.recent.0", "menu.recent.1", "title"]
+        );
+
+        let yaml = "de:\n  menu:\n    open: \"Öffnen\"\n  title: Hallo Welt\n";
+        let stats = locale_strings(Path::new("config/locales/de.yml"), yaml).unwrap();
+        assert_eq!(stats.keys, ["menu.open", "title"]);
+        assert_eq!(stats.words, 3);
+
+        assert!(locale_strings(Path::new("en.json"), "{\"a\": [").is_err());
+    }
+}
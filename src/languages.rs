@@ -0,0 +1,430 @@
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::path::Path;
+
+use crate::analysis::FileAnalysis;
+use crate::counter::FileEntry;
+
+/// A language recognized by extension or file name, with its comment syntax.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Language {
+    /// The name cloc uses, e.g. `C/C++ Header` or `Bourne Shell`
+    pub name: &'static str,
+    /// The name tokei uses, e.g. `CppHeader` or `Sh`; tokei tells some languages apart
+    /// that cloc does not
+    pub tokei_name: &'static str,
+    extensions: &'static [&'static str],
+    file_names: &'static [&'static str],
+    syntax: Syntax,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Characters that open a string literal closed by the same character
+    quotes: &'static [u8],
+}
+
+const C_SYNTAX: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    // Not `'`, which also starts Rust lifetimes
+    quotes: b"\"",
+};
+
+const HASH_SYNTAX: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: b"\"'",
+};
+
+const MARKUP_SYNTAX: Syntax = Syntax {
+    line_comments: &[],
+    block_comment: Some(("<!--", "-->")),
+    quotes: b"",
+};
+
+const fn language(
+    name: &'static str,
+    tokei_name: &'static str,
+    extensions: &'static [&'static str],
+    file_names: &'static [&'static str],
+    syntax: Syntax,
+) -> Language {
+    Language {
+        name,
+        tokei_name,
+        extensions,
+        file_names,
+        syntax,
+    }
+}
+
+const fn other_syntax(
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+) -> Syntax {
+    Syntax {
+        line_comments,
+        block_comment,
+        quotes: b"\"'",
+    }
+}
+
+const LANGUAGES: &[Language] = &[
+    language("Bourne Again Shell", "Bash", &["bash"], &[], HASH_SYNTAX),
+    language("Bourne Shell", "Sh", &["sh"], &[], HASH_SYNTAX),
+    language("C", "C", &["c"], &[], C_SYNTAX),
+    language("C#", "CSharp", &["cs"], &[], C_SYNTAX),
+    language("C++", "Cpp", &["cc", "cpp", "cxx"], &[], C_SYNTAX),
+    language("C/C++ Header", "CHeader", &["h"], &[], C_SYNTAX),
+    language("C/C++ Header", "CppHeader", &["hh", "hpp"], &[], C_SYNTAX),
+    language(
+        "CSS",
+        "Css",
+        &["css"],
+        &[],
+        other_syntax(&[], Some(("/*", "*/"))),
+    ),
+    language("Dart", "Dart", &["dart"], &[], C_SYNTAX),
+    language(
+        "Dockerfile",
+        "Dockerfile",
+        &["dockerfile"],
+        &["Dockerfile"],
+        HASH_SYNTAX,
+    ),
+    language("Go", "Go", &["go"], &[], C_SYNTAX),
+    language(
+        "Haskell",
+        "Haskell",
+        &["hs"],
+        &[],
+        other_syntax(&["--"], Some(("{-", "-}"))),
+    ),
+    language("HTML", "Html", &["html", "htm"], &[], MARKUP_SYNTAX),
+    language(
+        "INI",
+        "Ini",
+        &["ini", "cfg"],
+        &[],
+        other_syntax(&[";", "#"], None),
+    ),
+    language("Java", "Java", &["java"], &[], C_SYNTAX),
+    language(
+        "JavaScript",
+        "JavaScript",
+        &["js", "mjs", "cjs"],
+        &[],
+        C_SYNTAX,
+    ),
+    language("JSON", "Json", &["json"], &[], other_syntax(&[], None)),
+    language("JSX", "Jsx", &["jsx"], &[], C_SYNTAX),
+    language("Kotlin", "Kotlin", &["kt", "kts"], &[], C_SYNTAX),
+    language(
+        "Lua",
+        "Lua",
+        &["lua"],
+        &[],
+        other_syntax(&["--"], Some(("--[[", "]]"))),
+    ),
+    language(
+        "make",
+        "Makefile",
+        &["mk"],
+        &["Makefile", "makefile", "GNUmakefile"],
+        HASH_SYNTAX,
+    ),
+    language(
+        "Markdown",
+        "Markdown",
+        &["md", "markdown"],
+        &[],
+        MARKUP_SYNTAX,
+    ),
+    language("Perl", "Perl", &["pl", "pm"], &[], HASH_SYNTAX),
+    language(
+        "PHP",
+        "Php",
+        &["php"],
+        &[],
+        other_syntax(&["//", "#"], Some(("/*", "*/"))),
+    ),
+    language("Python", "Python", &["py", "pyi"], &[], HASH_SYNTAX),
+    language(
+        "Ruby",
+        "Ruby",
+        &["rb"],
+        &["Rakefile", "Gemfile"],
+        HASH_SYNTAX,
+    ),
+    language("Rust", "Rust", &["rs"], &[], C_SYNTAX),
+    language("Scala", "Scala", &["scala"], &[], C_SYNTAX),
+    language("SCSS", "Sass", &["scss"], &[], C_SYNTAX),
+    language(
+        "SQL",
+        "Sql",
+        &["sql"],
+        &[],
+        other_syntax(&["--"], Some(("/*", "*/"))),
+    ),
+    language("SVG", "Svg", &["svg"], &[], MARKUP_SYNTAX),
+    language("Swift", "Swift", &["swift"], &[], C_SYNTAX),
+    language("TOML", "Toml", &["toml"], &[], HASH_SYNTAX),
+    language(
+        "TypeScript",
+        "TypeScript",
+        &["ts", "mts", "cts"],
+        &[],
+        C_SYNTAX,
+    ),
+    language("TypeScript", "Tsx", &["tsx"], &[], C_SYNTAX),
+    language("XML", "Xml", &["xml"], &[], MARKUP_SYNTAX),
+    language("YAML", "Yaml", &["yaml", "yml"], &[], HASH_SYNTAX),
+    language("zsh", "Zsh", &["zsh"], &[], HASH_SYNTAX),
+];
+
+/// The language of `path`, judged by its file name or extension.
+pub fn language_of(path: &Path) -> Option<&'static Language> {
+    let name = path.file_name()?.to_str()?;
+    if let Some(language) = LANGUAGES.iter().find(|l| l.file_names.contains(&name)) {
+        return Some(language);
+    }
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|l| l.extensions.contains(&ext.as_str()))
+}
+
+/// Lines of source split the way cloc splits them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LineKinds {
+    /// Empty or whitespace only, inside comments too
+    pub blank: usize,
+    /// Only comments
+    pub comment: usize,
+    /// Anything else, including lines with code and a trailing comment
+    pub code: usize,
+}
+
+impl LineKinds {
+    /// Comment lines per 100 code lines; `None` without code.
+    pub fn comment_ratio(&self) -> Option<f64> {
+        (self.code > 0).then(|| self.comment as f64 * 100.0 / self.code as f64)
+    }
+}
+
+impl Add for LineKinds {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            blank: self.blank + other.blank,
+            comment: self.comment + other.comment,
+            code: self.code + other.code,
+        }
+    }
+}
+
+impl AddAssign for LineKinds {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for LineKinds {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl Language {
+    /// Splits the lines of `content` into blank, comment and code. String literals are
+    /// skipped within a line, so `/*` in a glob or `//` in a URL stays code; a string
+    /// running past the end of its line is taken to end there.
+    pub fn count_lines(&self, content: &str) -> LineKinds {
+        let mut kinds = LineKinds::default();
+        let mut in_block = false;
+        for line in content.lines() {
+            if line.trim().is_empty() {
+                kinds.blank += 1;
+                continue;
+            }
+            let has_code;
+            (has_code, in_block) = self.scan_line(line, in_block);
+            if has_code {
+                kinds.code += 1;
+            } else {
+                kinds.comment += 1;
+            }
+        }
+        kinds
+    }
+
+    /// Whether `line` has anything outside comments, and whether it ends inside a
+    /// block comment.
+    fn scan_line(&self, line: &str, in_block: bool) -> (bool, bool) {
+        let syntax = &self.syntax;
+        let bytes = line.as_bytes();
+        let mut has_code = false;
+        let mut i = 0;
+        if in_block {
+            let close = syntax.block_comment.map_or("", |(_, close)| close);
+            match line.find(close) {
+                Some(end) => i = end + close.len(),
+                None => return (false, true),
+            }
+        }
+        while i < bytes.len() {
+            let rest = &line[i..];
+            // Checked before line comments, for Lua's `--[[` after `--`
+            if let Some((open, close)) = syntax
+                .block_comment
+                .filter(|(open, _)| rest.starts_with(open))
+            {
+                match rest[open.len()..].find(close) {
+                    Some(end) => i += open.len() + end + close.len(),
+                    None => return (has_code, true),
+                }
+            } else if syntax.line_comments.iter().any(|c| rest.starts_with(c)) {
+                break;
+            } else if syntax.quotes.contains(&bytes[i]) {
+                let quote = bytes[i];
+                has_code = true;
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    // Skip the escaped character too
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+            } else {
+                let c = rest.chars().next().unwrap_or(' ');
+                has_code |= !c.is_whitespace();
+                // Step over a whole character, so slicing stays on boundaries
+                i += c.len_utf8();
+            }
+        }
+        (has_code, false)
+    }
+}
+
+/// Files and lines of one language over a whole run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageSummary {
+    pub name: &'static str,
+    pub files: usize,
+    pub lines: LineKinds,
+}
+
+/// Sums the line kinds of `entries` per language, most code first. `analyses` holds
+/// each entry's analysis in the same order; entries without line kinds (an unknown
+/// language, or a file that could not be read) are left out.
+pub fn summarize(entries: &[FileEntry], analyses: &[FileAnalysis]) -> Vec<LanguageSummary> {
+    let mut summaries: Vec<LanguageSummary> = Vec::new();
+    for (entry, analysis) in entries.iter().zip(analyses) {
+        let (Some(language), Some(lines)) = (language_of(&entry.path), analysis.line_kinds) else {
+            continue;
+        };
+        match summaries.iter_mut().find(|s| s.name == language.name) {
+            Some(summary) => {
+                summary.files += 1;
+                summary.lines += lines;
+            }
+            None => summaries.push(LanguageSummary {
+                name: language.name,
+                files: 1,
+                lines,
+            }),
+        }
+    }
+    summaries.sort_by(|a, b| b.lines.code.cmp(&a.lines.code).then(a.name.cmp(b.name)));
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(name: &str, content: &str) -> LineKinds {
+        language_of(Path::new(name)).unwrap().count_lines(content)
+    }
+
+    #[test]
+    fn recognizes_names_and_extensions() {
+        assert_eq!(language_of(Path::new("src/main.rs")).unwrap().name, "Rust");
+        assert_eq!(
+            language_of(Path::new("a.HPP")).unwrap().name,
+            "C/C++ Header"
+        );
+        assert_eq!(language_of(Path::new("Makefile")).unwrap().name, "make");
+        assert_eq!(language_of(Path::new("a.tsx")).unwrap().tokei_name, "Tsx");
+        assert!(language_of(Path::new("notes.txt")).is_none());
+    }
+
+    #[test]
+    fn splits_blank_comment_and_code_lines() {
+        let content = "// header\n\nfn main() { // trailing\n    /* one */ let a = 1;\n    /*\n\n     * more\n     */\n}\n";
+        assert_eq!(
+            count("a.rs", content),
+            LineKinds {
+                blank: 2,
+                comment: 4,
+                code: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn code_after_a_block_comment_closes_counts() {
+        assert_eq!(count("a.c", "/* a\n b */ int x;\n").code, 1);
+        assert_eq!(count("a.py", "# only\nx = 1  # set\n").comment, 1);
+        assert_eq!(count("a.lua", "--[[\nlong\n]]\n-- short\n").comment, 4);
+        assert_eq!(count("a.json", "{\"a\": \"//\"}\n").code, 1);
+    }
+
+    #[test]
+    fn comment_markers_in_strings_stay_code() {
+        let content = "let glob = \"src/**/*.rs\";\nlet url = \"https://example.com\"; // docs\n";
+        assert_eq!(count("a.rs", content).code, 2);
+        assert_eq!(count("a.py", "s = '# not a comment'\n").code, 1);
+        assert_eq!(count("a.rs", "\"\\\"\" /* x */\n").code, 1);
+    }
+
+    #[test]
+    fn comment_ratio_is_per_code_line() {
+        let lines = count(
+            "a.rs",
+            "// a\n// b\nfn f() {}\nfn g() {}\nfn h() {}\nfn i() {}\n",
+        );
+        assert_eq!(lines.comment_ratio(), Some(50.0));
+        assert_eq!(count("a.rs", "// only\n").comment_ratio(), None);
+    }
+
+    #[test]
+    fn summarizes_per_language_by_code() {
+        let entry = |path: &str| FileEntry {
+            path: path.into(),
+            count: Default::default(),
+        };
+        let analysis = |code| FileAnalysis {
+            line_kinds: Some(LineKinds {
+                code,
+                ..LineKinds::default()
+            }),
+            ..FileAnalysis::default()
+        };
+        let entries = [entry("a.py"), entry("b.rs"), entry("c.py"), entry("d.txt")];
+        let analyses = [
+            analysis(1),
+            analysis(5),
+            analysis(2),
+            FileAnalysis::default(),
+        ];
+        let summaries = summarize(&entries, &analyses);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!((summaries[0].name, summaries[0].files), ("Rust", 1));
+        assert_eq!((summaries[1].name, summaries[1].files), ("Python", 2));
+        assert_eq!(summaries[1].lines.code, 3);
+    }
+}
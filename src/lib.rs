@@ -1,3 +1,78 @@
+pub mod analysis;
+#[cfg(feature = "walk")]
+pub mod api;
+#[cfg(feature = "async")]
+pub mod async_count;
+#[cfg(feature = "walk")]
+pub mod authors;
+pub mod budget;
+#[cfg(feature = "cli")]
+pub mod cache;
+pub mod checksum;
+#[cfg(feature = "cli")]
 pub mod cli;
+pub mod cocomo;
+#[cfg(all(feature = "walk", feature = "json"))]
+pub mod compare;
+pub mod complexity;
+#[cfg(feature = "cli")]
+pub mod config;
 pub mod counter;
+#[cfg(all(unix, feature = "cli"))]
+pub mod daemon;
+#[cfg(feature = "cli")]
+pub mod db;
+pub mod dedupe;
+#[cfg(feature = "json")]
+pub mod delta;
+pub mod dialogue;
+pub mod documents;
+pub mod entropy;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod follow;
+#[cfg(feature = "walk")]
+pub mod generated;
+pub mod git;
+#[cfg(feature = "cli")]
+pub mod history;
+#[cfg(feature = "walk")]
+pub mod hook;
+pub mod i18n;
+pub mod languages;
+pub mod logs;
+pub mod markdown;
+pub mod markup;
+pub mod meta;
+pub mod natural;
 pub mod output;
+#[cfg(feature = "cli")]
+pub mod packages;
+pub mod po;
+pub mod records;
+#[cfg(feature = "json")]
+pub mod report;
+#[cfg(all(feature = "walk", feature = "json"))]
+pub mod rpc;
+#[cfg(feature = "cli")]
+pub mod serve;
+#[cfg(all(feature = "walk", feature = "json"))]
+pub mod snapshot;
+pub mod structure;
+pub mod submodules;
+pub mod subtitles;
+pub mod symbols;
+#[cfg(all(feature = "uring", target_os = "linux"))]
+pub mod uring;
+pub mod vocabulary;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "cli")]
+pub mod watch;
+#[cfg(feature = "office")]
+pub mod xlsx;
+
+#[cfg(feature = "walk")]
+pub use api::{Counter, PathCount};
+#[cfg(feature = "walk")]
+pub use counter::CountObserver;
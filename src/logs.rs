@@ -0,0 +1,233 @@
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::path::Path;
+#[cfg(feature = "logs")]
+use std::str::FromStr;
+
+#[cfg(feature = "logs")]
+use regex::Regex;
+
+/// Lines per severity in a log file, as shown with `--log-levels`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LogLevels {
+    pub error: usize,
+    pub warn: usize,
+    pub info: usize,
+    pub debug: usize,
+}
+
+impl Add for LogLevels {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            error: self.error + other.error,
+            warn: self.warn + other.warn,
+            info: self.info + other.info,
+            debug: self.debug + other.debug,
+        }
+    }
+}
+
+impl AddAssign for LogLevels {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for LogLevels {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Whether `--log-levels` reads this file: `*.log`, or a rotated `*.log.1`.
+pub fn is_log(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_ascii_lowercase)
+        .is_some_and(|name| name.ends_with(".log") || name.contains(".log."))
+}
+
+/// A severity `--log-levels` counts.
+#[cfg(feature = "logs")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+#[cfg(feature = "logs")]
+impl Level {
+    const ALL: [Level; 4] = [Level::Error, Level::Warn, Level::Info, Level::Debug];
+
+    fn default_pattern(self) -> &'static str {
+        match self {
+            Level::Error => r"\b(?:ERROR|ERR|FATAL|CRITICAL|SEVERE)\b",
+            Level::Warn => r"\bWARN(?:ING)?\b",
+            Level::Info => r"\bINFO\b",
+            Level::Debug => r"\b(?:DEBUG|TRACE)\b",
+        }
+    }
+}
+
+#[cfg(feature = "logs")]
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" | "warning" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            _ => Err(format!(
+                "unknown level '{s}' (expected error, warn, info or debug)"
+            )),
+        }
+    }
+}
+
+/// A `--log-pattern` such as `error=^E `, replacing the default regex for a level.
+#[cfg(feature = "logs")]
+#[derive(Debug, Clone)]
+pub struct LevelPattern {
+    pub level: Level,
+    pub regex: Regex,
+}
+
+#[cfg(feature = "logs")]
+impl FromStr for LevelPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (level, pattern) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid log pattern '{s}' (expected LEVEL=REGEX)"))?;
+        let level = level.trim().parse()?;
+        let regex = Regex::new(pattern).map_err(|e| format!("invalid regex in '{s}': {e}"))?;
+        Ok(LevelPattern { level, regex })
+    }
+}
+
+/// The regexes that decide each line's level.
+#[cfg(feature = "logs")]
+#[derive(Debug, Clone)]
+pub struct LevelMatcher {
+    /// In [`Level::ALL`] order
+    patterns: Vec<Regex>,
+}
+
+#[cfg(feature = "logs")]
+impl Default for LevelMatcher {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+#[cfg(feature = "logs")]
+impl LevelMatcher {
+    /// The default patterns (`ERROR`, `WARN`, `INFO` and `DEBUG` as words, with common
+    /// variants such as `FATAL` and `TRACE`), with `overrides` replacing them by level.
+    pub fn new(overrides: &[LevelPattern]) -> Self {
+        let patterns = Level::ALL
+            .iter()
+            .map(|&level| {
+                let pattern = overrides.iter().rfind(|o| o.level == level);
+                pattern.map_or_else(
+                    || Regex::new(level.default_pattern()).expect("default pattern is valid"),
+                    |pattern| pattern.regex.clone(),
+                )
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    /// The level of `line`: the one whose pattern matches first in the line, so a
+    /// message mentioning `ERROR` on an `INFO` line stays info.
+    fn level(&self, line: &str) -> Option<Level> {
+        self.patterns
+            .iter()
+            .zip(Level::ALL)
+            .filter_map(|(regex, level)| regex.find(line).map(|m| (m.start(), level)))
+            .min_by_key(|&(start, _)| start)
+            .map(|(_, level)| level)
+    }
+
+    /// Counts the lines of `content` at each level; lines without one are left out.
+    pub fn count(&self, content: &str) -> LogLevels {
+        let mut levels = LogLevels::default();
+        for line in content.lines() {
+            match self.level(line) {
+                Some(Level::Error) => levels.error += 1,
+                Some(Level::Warn) => levels.warn += 1,
+                Some(Level::Info) => levels.info += 1,
+                Some(Level::Debug) => levels.debug += 1,
+                None => {}
+            }
+        }
+        levels
+    }
+}
+
+#[cfg(all(test, feature = "logs"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_log_files() {
+        assert!(is_log(Path::new("var/app.log")));
+        assert!(is_log(Path::new("app.LOG.1")));
+        assert!(!is_log(Path::new("catalog.txt")));
+        assert!(!is_log(Path::new("log")));
+    }
+
+    #[test]
+    fn counts_lines_per_level() {
+        let content = "2026-01-01 INFO started\n2026-01-01 WARNING disk at 90%\n2026-01-01 ERROR failed\n2026-01-01 FATAL gave up\n2026-01-01 TRACE tick\n    at frame 1\n";
+        assert_eq!(
+            LevelMatcher::default().count(content),
+            LogLevels {
+                error: 2,
+                warn: 1,
+                info: 1,
+                debug: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn the_earliest_level_in_a_line_wins() {
+        let content = "INFO retrying after ERROR\nERRORS are not a level\n";
+        let levels = LevelMatcher::default().count(content);
+        assert_eq!(levels.info, 1);
+        assert_eq!(levels.error, 0);
+    }
+
+    #[test]
+    fn patterns_replace_the_default_for_their_level() {
+        let patterns: Vec<LevelPattern> = ["error=^E ", "warning=^W "]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let levels = LevelMatcher::new(&patterns).count("E boom\nW hmm\nERROR x\nINFO y\n");
+        assert_eq!(
+            levels,
+            LogLevels {
+                error: 1,
+                warn: 1,
+                info: 1,
+                debug: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_patterns() {
+        assert!("error".parse::<LevelPattern>().is_err());
+        assert!("fatal=x".parse::<LevelPattern>().is_err());
+        assert!("error=(".parse::<LevelPattern>().is_err());
+    }
+}
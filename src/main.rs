@@ -1,69 +1,1889 @@
-use clap::Parser;
-use std::io;
-use std::path::Path;
+use clap::{CommandFactory, ValueEnum};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use ewc::cli::Args;
+use ewc::analysis::{AnalysisOptions, FileAnalysis};
+use ewc::authors::count_by_author;
+use ewc::budget::{check_entries, Violation};
+use ewc::cache::{self, Cache};
+use ewc::checksum::HashAlgorithm;
+use ewc::cli::{
+    Args, CacheAction, Checksum, CocomoType, Command, ErrorFormat, Interval, Order, OutputFormat,
+    Records, SnapshotAction, Symlinks,
+};
+use ewc::cocomo::{estimate, CocomoParams, ProjectType};
+use ewc::compare::compare_trees;
 use ewc::counter::{
-    count_directory, count_directory_detailed, count_file, count_from_reader, Count, FilterConfig,
+    count_directory_observed, count_file_chunked, count_from_reader_limited, list_directory, Count,
+    CountObserver, EntryOrder, FileEntry, FilterConfig, FilterReason, LineRatios, SkipSummary,
+    SkippedFile, SkippedFiles, SpecialFileLimits, SymlinkPolicy, DEFAULT_CHUNK_SIZE,
 };
+use ewc::db;
+use ewc::delta::{compare_reports, CountDelta, ReportDelta};
+use ewc::documents;
+use ewc::follow::{Follower, POLL_INTERVAL};
+use ewc::git::{diff_stats, head_revision, GitSelection};
+use ewc::history::collect_history;
+use ewc::hook::{check_index, install_hook};
+use ewc::i18n::compare_locales;
+use ewc::languages::summarize;
+use ewc::logs::LevelMatcher;
+use ewc::markdown::{code_blocks, is_markdown};
+use ewc::markup::{is_markup, visible_text};
+use ewc::meta::FileMeta;
 use ewc::output::{
-    format_compact_output, format_compact_total, format_json_multiple, format_json_single,
-    format_output, format_separator, format_total_output, format_verbose_output, JsonFileResult,
-    OutputKind,
+    display_path, error_kind_name, format_analysis, format_authors_json, format_authors_output,
+    format_baseline_delta, format_cloc, format_cloc_csv, format_cocomo, format_compact_analysis,
+    format_compact_output, format_compact_total, format_compare_json, format_compare_output,
+    format_csv, format_delta_parts, format_diff_json, format_diff_markdown, format_diff_output,
+    format_dot, format_error_json, format_file_list_json, format_files_over,
+    format_files_over_json, format_heatmap, format_history_csv, format_history_json,
+    format_history_markdown, format_history_output, format_html, format_json_multiple,
+    format_json_single, format_json_timed_out, format_markdown, format_mermaid, format_number,
+    format_output, format_pr_comment, format_prometheus, format_report_delta, format_sarif,
+    format_separator, format_skip_summary, format_snapshot_diff_json, format_tokei_json,
+    format_total_only, format_total_output, format_verbose_output, format_violation_message,
+    format_xlsx, write_csv, write_json_multiple, write_verbose_output, JsonFileResult, OutputKind,
 };
+use ewc::packages::package_paths;
+use ewc::records::RecordFormat;
+use ewc::report::{Report, ReportEntry};
+use ewc::rpc::serve_stdio;
+use ewc::serve::{serve, Server};
+use ewc::snapshot;
+use ewc::submodules::{innermost_dir, submodule_paths};
+use ewc::watch::{watch, WatchTarget};
+
+const WARNING_ICON: &str = "\u{26A0}\u{FE0F}";
+
+/// How long a run may go on past `--timeout`, stuck in a read or listing that does not
+/// return, before it is ended with what was counted so far
+const TIMEOUT_GRACE: Duration = Duration::from_secs(5);
+
+/// The last line of text output `--timeout` cut short
+const INCOMPLETE: &str = "Incomplete: --timeout ran out before everything was counted";
+
+/// When `--timeout` runs out, counted from the start of the run
+static DEADLINE: OnceLock<Instant> = OnceLock::new();
+
+/// Whether `--timeout` ran out before everything was counted
+static TIMED_OUT: AtomicBool = AtomicBool::new(false);
+
+/// Symbolic links given as paths and left out by `--symlinks`, for `--stats`
+static LINK_PATHS_SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// The `--json` output for the paths counted so far, printed should the `--timeout`
+/// watchdog give up on the run
+static COUNTED_SO_FAR: Mutex<Option<String>> = Mutex::new(None);
+
+#[cfg(feature = "mimalloc")]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+#[cfg(all(
+    feature = "jemalloc",
+    not(feature = "mimalloc"),
+    not(target_env = "msvc")
+))]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+/// The global allocator the binary was built with, for `--stats`
+const ALLOCATOR: &str = if cfg!(feature = "mimalloc") {
+    "mimalloc"
+} else if cfg!(all(feature = "jemalloc", not(target_env = "msvc"))) {
+    "jemalloc"
+} else {
+    "system"
+};
+
+/// Why a run failed, reported as the exit status so scripts can branch on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    /// Some inputs could not be read or counted, or the command itself failed
+    Inputs,
+    /// The options or arguments make no sense together; clap exits with the same code
+    Usage,
+    /// A `--over` limit was exceeded, or counts grew with `--fail-on-growth`
+    Budget,
+    /// `--timeout` ran out before everything was counted
+    Timeout,
+}
+
+impl Failure {
+    fn code(self) -> i32 {
+        match self {
+            Failure::Inputs => 1,
+            Failure::Usage => 2,
+            Failure::Budget => 3,
+            Failure::Timeout => 4,
+        }
+    }
+}
+
+/// Writes an error to stderr as a warning line, or as a JSON line with `--errors json`.
+fn report_error(args: &Args, path: Option<&str>, kind: &str, message: &str) {
+    write_error(args.errors, path, kind, message);
+}
+
+/// Like [`report_error`], for threads without the arguments.
+fn write_error(errors: ErrorFormat, path: Option<&str>, kind: &str, message: &str) {
+    let path = path.map(|path| display_path(Path::new(path)));
+    let path = path.as_deref();
+    match (errors, path) {
+        (ErrorFormat::Json, _) => eprintln!("{}", format_error_json(path, kind, message)),
+        (ErrorFormat::Text, Some(path)) => eprintln!("{WARNING_ICON}  {path}: {message}"),
+        (ErrorFormat::Text, None) => eprintln!("{WARNING_ICON}  {message}"),
+    }
+}
+
+/// Reports an I/O error reading `path`, ending the run with `--fail-fast`.
+fn report_io_error(args: &Args, path: &str, error: &io::Error) {
+    report_error(
+        args,
+        Some(path),
+        &error_kind_name(error.kind()),
+        &error.to_string(),
+    );
+    if args.fail_fast {
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+/// Reports an I/O error that is not about one file, after what was being done.
+fn report_failure(args: &Args, context: &str, error: &io::Error) {
+    report_error(
+        args,
+        None,
+        &error_kind_name(error.kind()),
+        &format!("{context}: {error}"),
+    );
+}
+
+/// Exits with the status for `failure`, or 0 with `--no-fail` unless it is a usage error.
+/// A run `--timeout` cut short is marked incomplete and exits as timed out instead.
+fn exit_with(args: &Args, failure: Failure) -> ! {
+    let failure = if failure != Failure::Usage && TIMED_OUT.load(Ordering::Relaxed) {
+        mark_incomplete(args);
+        Failure::Timeout
+    } else {
+        failure
+    };
+    if args.no_fail && failure != Failure::Usage {
+        process::exit(0);
+    }
+    process::exit(failure.code());
+}
+
+/// Starts the `--timeout` clock, and a watchdog ending the run should it still be going
+/// well past the deadline.
+fn start_timeout(args: &Args, timeout: Duration) {
+    let _ = DEADLINE.set(Instant::now() + timeout);
+    let errors = args.errors;
+    let no_fail = args.no_fail;
+    let marks_incomplete = marks_incomplete(args);
+    let prints_json = args.json || args.format.is_some();
+    thread::spawn(move || {
+        thread::sleep(timeout + TIMEOUT_GRACE);
+        let message = format!("still running {TIMEOUT_GRACE:?} past --timeout; giving up");
+        write_error(errors, None, "timed_out", &message);
+        if marks_incomplete {
+            println!("{INCOMPLETE}");
+        } else if prints_json {
+            let counted = COUNTED_SO_FAR.lock().unwrap_or_else(|e| e.into_inner());
+            match counted.as_deref() {
+                Some(json) => println!("{json}"),
+                None => println!(r#"{{"timed_out":true}}"#),
+            }
+        }
+        // As `exit_with`, without the arguments
+        process::exit(if no_fail { 0 } else { Failure::Timeout.code() });
+    });
+}
+
+/// Keeps the `--json` output for `results` for the `--timeout` watchdog.
+fn keep_counted_so_far(results: &[JsonFileResult], total: &Count) {
+    if DEADLINE.get().is_some() {
+        let json = format_json_timed_out(results, total);
+        *COUNTED_SO_FAR.lock().unwrap_or_else(|e| e.into_inner()) = Some(json);
+    }
+}
+
+/// Whether `--timeout` has run out.
+fn deadline_passed() -> bool {
+    DEADLINE
+        .get()
+        .is_some_and(|deadline| Instant::now() >= *deadline)
+}
+
+/// Notes that `--timeout` ran out, warning the first time.
+fn note_timeout(args: &Args) {
+    if !TIMED_OUT.swap(true, Ordering::Relaxed) {
+        let message = "--timeout ran out; what was not counted by then is left out";
+        report_error(args, None, "timed_out", message);
+    }
+}
+
+/// Whether a run `--timeout` cuts short ends with [`INCOMPLETE`]: text output does,
+/// while JSON output says so with `timed_out` in each `skip_summary`.
+fn marks_incomplete(args: &Args) -> bool {
+    !args.json && args.format.is_none() && !args.print0
+}
+
+/// Ends the output of a run `--timeout` cut short with a line saying so.
+fn mark_incomplete(args: &Args) {
+    if marks_incomplete(args) {
+        println!("{INCOMPLETE}");
+    }
+}
+
+struct ProcessResult {
+    count: Count,
+    file_count: usize,
+    entries: Vec<FileEntry>,
+    /// Files inside a directory that could not be read, and with `--strict` those that
+    /// are not UTF-8 text
+    skipped: Vec<SkippedFile>,
+    /// Symbolic links inside a directory that were not followed
+    symlinks_skipped: usize,
+    /// How many entries inside a directory were left out, by why
+    skip_summary: SkipSummary,
+    /// What the analyzers (`--markdown`, `--symbols`, ...) found in each entry, if any is on
+    analyses: Vec<FileAnalysis>,
+}
+
+impl ProcessResult {
+    /// Whether `--strict` should fail the run because of this result
+    fn fails_strict(&self, args: &Args) -> bool {
+        args.strict && !self.skipped.is_empty()
+    }
+
+    /// The analyses of all entries, summed
+    fn analysis(&self) -> FileAnalysis {
+        self.analyses.iter().sum()
+    }
+}
+
+/// What `--stats` reports about a run, summed over its paths.
+struct RunStats {
+    started: Instant,
+    files: usize,
+    skipped: usize,
+    symlinks_skipped: usize,
+}
+
+impl RunStats {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            files: 0,
+            skipped: 0,
+            symlinks_skipped: 0,
+        }
+    }
+
+    fn add(&mut self, result: &ProcessResult) {
+        self.files += result.file_count;
+        self.skipped += result.skipped.len();
+        self.symlinks_skipped += result.symlinks_skipped;
+    }
+
+    /// Writes the summary to stderr, so it never mixes with the counts, with `--stats`.
+    fn report(&self, args: &Args) {
+        if !args.stats {
+            return;
+        }
+        // Reported links are among the skipped files too, yet only counted as links
+        let mut skipped = self.skipped;
+        if args.symlinks == Symlinks::Report {
+            skipped = skipped.saturating_sub(self.symlinks_skipped);
+        }
+        let symlinks = self.symlinks_skipped + LINK_PATHS_SKIPPED.load(Ordering::Relaxed);
+        eprintln!("Files counted:    {}", format_number(self.files));
+        eprintln!("Files skipped:    {}", format_number(skipped));
+        eprintln!("Symlinks skipped: {}", format_number(symlinks));
+        eprintln!("Elapsed:          {:.2?}", self.started.elapsed());
+        eprintln!("Allocator:        {ALLOCATOR}");
+    }
+}
+
+fn skipped_files(strict: bool) -> SkippedFiles {
+    if strict {
+        SkippedFiles::including_not_text()
+    } else {
+        SkippedFiles::new()
+    }
+}
+
+/// Passes the events of a directory count on to `skipped`, printing each file as soon
+/// as it is counted with `--stream` and ending the run with `--fail-fast` at the first
+/// file that cannot be read, or with `--strict` too at the first file skipped.
+struct DirectoryEvents<'a> {
+    skipped: &'a SkippedFiles,
+    args: &'a Args,
+}
+
+impl DirectoryEvents<'_> {
+    /// Ends the run at a skipped file, as `--fail-fast` asks for read errors and, with
+    /// `--strict`, for every file skipped.
+    fn fail_fast(&self, path: &Path, kind: io::ErrorKind, reason: &str) {
+        // Special files and links left out are skipped, not failed reads
+        let read_error = !matches!(
+            kind,
+            io::ErrorKind::Unsupported | io::ErrorKind::InvalidData
+        );
+        if self.args.fail_fast && (read_error || self.args.strict) {
+            let path = path.display().to_string();
+            let message = format!("skipped: {reason}");
+            report_error(self.args, Some(&path), &error_kind_name(kind), &message);
+            exit_with(self.args, Failure::Inputs);
+        }
+    }
+}
+
+impl CountObserver for DirectoryEvents<'_> {
+    fn on_file_counted(&self, entry: &FileEntry) {
+        if self.args.stream {
+            let name = display_path(&entry.path);
+            let output = format_compact_output(&name, &entry.count, OutputKind::File, self.args);
+            println!("{output}");
+        }
+    }
+
+    fn on_error(&self, path: &Path, error: &io::Error) {
+        self.skipped.on_error(path, error);
+        self.fail_fast(path, error.kind(), &error.to_string());
+    }
+
+    fn on_file_not_text(&self, path: &Path) {
+        self.skipped.on_file_not_text(path);
+        self.fail_fast(path, io::ErrorKind::InvalidData, "not UTF-8 text");
+    }
+
+    fn on_symlink_skipped(&self, path: &Path) {
+        self.skipped.on_symlink_skipped(path);
+    }
+
+    fn on_filtered(&self, path: &Path, reason: FilterReason) {
+        self.skipped.on_filtered(path, reason);
+        if reason == FilterReason::TimedOut {
+            note_timeout(self.args);
+        }
+    }
+}
+
+/// Runs the directory count `count` with an observer collecting what it skipped (see
+/// [`DirectoryEvents`]).
+fn count_observed<T>(
+    args: &Args,
+    count: impl FnOnce(&dyn CountObserver) -> io::Result<T>,
+) -> io::Result<(T, SkippedFiles)> {
+    let skipped = skipped_files(args.strict);
+    let counted = count(&DirectoryEvents {
+        skipped: &skipped,
+        args,
+    })?;
+    Ok((counted, skipped))
+}
+
+fn process_path(
+    path: &Path,
+    config: &FilterConfig,
+    cache: Option<&mut Cache>,
+    args: &Args,
+) -> io::Result<ProcessResult> {
+    if deadline_passed() {
+        note_timeout(args);
+        let message = "not counted before --timeout ran out";
+        return Err(io::Error::new(io::ErrorKind::TimedOut, message));
+    }
+    if path.is_dir() {
+        // A cache that cannot be used only costs the speed-up
+        let cached = cache.and_then(|cache| {
+            count_observed(args, |observer| {
+                cache.count_directory_observed(path, config, observer)
+            })
+            .ok()
+        });
+        let ((mut entries, mut count), skipped) = match cached {
+            Some(counted) => counted,
+            None => count_observed(args, |observer| {
+                count_directory_observed(path, config, observer)
+            })?,
+        };
+        if recount_parts(args, &mut entries) {
+            count = entries.iter().map(|entry| entry.count).sum();
+        }
+        let symlinks_skipped = skipped.symlinks_skipped();
+        let skip_summary = skipped.summary();
+        let skipped = skipped.into_vec();
+        warn_skipped(args, &skipped);
+        Ok(ProcessResult {
+            count,
+            file_count: entries.len(),
+            analyses: analyze_entries(args, &entries),
+            entries,
+            skipped,
+            symlinks_skipped,
+            skip_summary,
+        })
+    } else {
+        let mut entries = vec![FileEntry {
+            path: path.to_path_buf(),
+            count: count_file_chunked(path, config.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE))?,
+        }];
+        recount_parts(args, &mut entries);
+        Ok(ProcessResult {
+            count: entries[0].count,
+            file_count: 1,
+            analyses: analyze_entries(args, &entries),
+            entries,
+            skipped: Vec::new(),
+            symlinks_skipped: 0,
+            skip_summary: SkipSummary::default(),
+        })
+    }
+}
+
+/// Whether `--strip-markup` or `--code-blocks-only` count only part of `path`.
+fn counts_part_of(args: &Args, path: &Path) -> bool {
+    (args.strip_markup && is_markup(path)) || (args.code_blocks_only.is_some() && is_markdown(path))
+}
+
+/// The part of `content` counted for `path`: the visible text of HTML and XML with
+/// `--strip-markup`, the fenced code of Markdown with `--code-blocks-only`, or all of it.
+fn counted_part(args: &Args, path: &Path, content: String) -> String {
+    if args.strip_markup && is_markup(path) {
+        return visible_text(&content);
+    }
+    match &args.code_blocks_only {
+        Some(language) if is_markdown(path) => code_blocks(&content, language.as_deref()),
+        _ => content,
+    }
+}
+
+/// Recounts the entries only part of which counts (see [`counts_part_of`]), returning
+/// whether any could be. A file that can no longer be read keeps its full counts.
+fn recount_parts(args: &Args, entries: &mut [FileEntry]) -> bool {
+    if !args.strip_markup && args.code_blocks_only.is_none() {
+        return false;
+    }
+    entries
+        .par_iter_mut()
+        .filter(|entry| counts_part_of(args, &entry.path))
+        .for_each(|entry| {
+            if let Ok(content) = fs::read_to_string(&entry.path) {
+                entry.count = Count::from_content(&counted_part(args, &entry.path, content));
+            }
+        });
+    true
+}
+
+fn analysis_options(args: &Args) -> AnalysisOptions {
+    AnalysisOptions {
+        markdown: args.markdown,
+        symbols: args.symbols,
+        complexity: args.complexity,
+        records: args.records.map(|records| match records {
+            Records::Json => RecordFormat::Json,
+            Records::Jsonl => RecordFormat::JsonLines,
+        }),
+        structure: args.structure,
+        log_levels: args
+            .log_levels
+            .then(|| LevelMatcher::new(&args.log_pattern)),
+        dedupe_lines: args.dedupe_lines,
+        entropy: args.entropy,
+        hash: args.hash.map(|hash| match hash {
+            Checksum::Sha256 => HashAlgorithm::Sha256,
+            Checksum::Blake3 => HashAlgorithm::Blake3,
+        }),
+        meta: args.show_meta,
+        natural_language: args.detect_language,
+        vocabulary: args.vocabulary,
+        dialogue: args.dialogue,
+        pages: args.pages,
+        chapters: args.chapters,
+        subtitles: args.subtitles,
+        po: args.po,
+        i18n: args.i18n,
+        line_kinds: args.comment_ratio
+            || args.cocomo
+            || matches!(
+                args.format,
+                Some(OutputFormat::Cloc | OutputFormat::ClocCsv | OutputFormat::TokeiJson)
+            ),
+        ratios: args.ratios,
+    }
+}
+
+fn cocomo_params(args: &Args) -> CocomoParams {
+    let defaults = CocomoParams::default();
+    CocomoParams {
+        project_type: args.cocomo_type.map_or(defaults.project_type, |t| match t {
+            CocomoType::Organic => ProjectType::Organic,
+            CocomoType::SemiDetached => ProjectType::SemiDetached,
+            CocomoType::Embedded => ProjectType::Embedded,
+        }),
+        annual_wage: args.cocomo_wage.unwrap_or(defaults.annual_wage),
+        overhead: args.cocomo_overhead.unwrap_or(defaults.overhead),
+        eaf: args.cocomo_eaf.unwrap_or(defaults.eaf),
+    }
+}
+
+/// Runs the analyzers (`--markdown`, `--symbols`, ...) over `entries`, reading the files
+/// they apply to again and warning about content they cannot parse; one result per
+/// entry, or none when no analyzer is on.
+fn analyze_entries(args: &Args, entries: &[FileEntry]) -> Vec<FileAnalysis> {
+    let options = analysis_options(args);
+    if options.is_empty() {
+        return Vec::new();
+    }
+    let results: Vec<_> = entries
+        .par_iter()
+        .map(|entry| {
+            // A file that changed since it was counted is analyzed as it is now
+            let (mut analysis, errors) = if options.applies_to(&entry.path) {
+                match documents::read_to_string(&entry.path) {
+                    Ok(content) if counts_part_of(args, &entry.path) => {
+                        let part = counted_part(args, &entry.path, content.clone());
+                        FileAnalysis::of_part_with_errors(&entry.path, &content, &part, &options)
+                    }
+                    Ok(content) => FileAnalysis::with_errors(&entry.path, &content, &options),
+                    Err(_) => (FileAnalysis::default(), Vec::new()),
+                }
+            } else {
+                (FileAnalysis::default(), Vec::new())
+            };
+            if options.meta {
+                analysis.meta = fs::metadata(&entry.path)
+                    .ok()
+                    .map(|metadata| FileMeta::from_metadata(&metadata));
+            }
+            if options.ratios {
+                analysis.ratios = Some(LineRatios::from(&entry.count));
+            }
+            (analysis, errors)
+        })
+        .collect();
+    let mut analyses: Vec<_> = results
+        .into_iter()
+        .zip(entries)
+        .map(|((analysis, errors), entry)| {
+            for error in errors {
+                report_error(
+                    args,
+                    Some(&entry.path.display().to_string()),
+                    "parse",
+                    &error.to_string(),
+                );
+            }
+            analysis
+        })
+        .collect();
+    if options.i18n {
+        compare_locales(
+            entries
+                .iter()
+                .zip(&mut analyses)
+                .filter_map(|(entry, analysis)| {
+                    Some((entry.path.as_path(), analysis.i18n.as_mut()?))
+                }),
+        );
+    }
+    analyses
+}
+
+/// Orders `entries` most complex first for `--complexity`, keeping each analysis with
+/// its entry. Files without an estimate go last, in their original order.
+fn rank_by_complexity(
+    entries: Vec<FileEntry>,
+    analyses: Vec<FileAnalysis>,
+) -> (Vec<FileEntry>, Vec<FileAnalysis>) {
+    let mut ranked: Vec<_> = entries.into_iter().zip(analyses).collect();
+    ranked.sort_by_key(|(_, analysis)| std::cmp::Reverse(analysis.complexity));
+    ranked.into_iter().unzip()
+}
+
+fn warn_skipped(args: &Args, skipped: &[SkippedFile]) {
+    for file in skipped {
+        report_error(
+            args,
+            Some(&file.path.display().to_string()),
+            &error_kind_name(file.kind),
+            &format!("skipped: {}", file.reason),
+        );
+    }
+}
+
+/// Bump whenever a change would make previously cached counts wrong
+const CACHE_REVISION: u32 = 1;
+
+/// Identifies how files are counted, so counts cached by another version of ewc, or by
+/// a build reading other document formats, are not reused.
+fn cache_options() -> String {
+    let mut options = format!("v{CACHE_REVISION}-{}", env!("CARGO_PKG_VERSION"));
+    for (feature, enabled) in [
+        ("office", cfg!(feature = "office")),
+        ("pdf", cfg!(feature = "pdf")),
+    ] {
+        if enabled {
+            options.push('+');
+            options.push_str(feature);
+        }
+    }
+    options
+}
+
+fn open_cache(args: &Args) -> Option<Cache> {
+    if args.no_cache {
+        return None;
+    }
+    Cache::open(&cache::default_path()?, &cache_options()).ok()
+}
+
+/// The last run of each path for `--delta`, kept in the cache database.
+struct LastRuns {
+    cache: Cache,
+    /// The last runs of the paths counted so far that had one, summed for the total
+    previous_total: Option<(Count, usize)>,
+}
+
+impl LastRuns {
+    /// Opens the cache database with `--delta`, even with `--no-cache`.
+    fn open(args: &Args) -> Option<Self> {
+        if !args.delta {
+            return None;
+        }
+        let Some(path) = cache::default_path() else {
+            let message = "--delta needs a cache directory; set XDG_CACHE_HOME or HOME";
+            report_error(args, None, "usage", message);
+            exit_with(args, Failure::Usage);
+        };
+        match Cache::open(&path, &cache_options()) {
+            Ok(cache) => Some(Self {
+                cache,
+                previous_total: None,
+            }),
+            Err(e) => {
+                report_io_error(args, &path.display().to_string(), &e);
+                exit_with(args, Failure::Inputs);
+            }
+        }
+    }
+
+    /// Replaces the last run of `path` with this one, returning the change since it,
+    /// or `None` on the first run.
+    fn update(
+        &mut self,
+        path: &Path,
+        count: &Count,
+        files: usize,
+    ) -> io::Result<Option<CountDelta>> {
+        let key = fs::canonicalize(path)?;
+        let previous = self.cache.last_run(&key)?;
+        self.cache.record_last_run(&key, count, files)?;
+        if let Some((previous_count, previous_files)) = previous {
+            let total = self.previous_total.get_or_insert_default();
+            total.0 += previous_count;
+            total.1 += previous_files;
+        }
+        Ok(previous.map(|(previous_count, previous_files)| {
+            CountDelta::between(count, files, &previous_count, previous_files)
+        }))
+    }
+
+    /// The change of the total, or `None` when no path had a last run.
+    fn total_change(&self, total: &Count, files: usize) -> Option<CountDelta> {
+        self.previous_total.map(|(previous, previous_files)| {
+            CountDelta::between(total, files, &previous, previous_files)
+        })
+    }
+}
+
+/// Sends the library's diagnostics to stderr when asked for with `--debug`, `--trace`
+/// or `RUST_LOG`; otherwise no subscriber is installed and logging costs nothing.
+fn init_logging(args: &Args) {
+    use tracing_subscriber::EnvFilter;
+
+    let filter = if args.trace {
+        EnvFilter::new("ewc=trace")
+    } else if args.debug {
+        EnvFilter::new("ewc=debug")
+    } else {
+        match EnvFilter::try_from_default_env() {
+            Ok(filter) => filter,
+            Err(_) => return,
+        }
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .with_ansi(!args.no_color && io::stderr().is_terminal())
+        .without_time()
+        .init();
+}
+
+fn git_selection(args: &Args) -> Option<GitSelection> {
+    if let Some(base) = &args.changed {
+        Some(GitSelection::Changed(base.clone()))
+    } else if args.staged {
+        Some(GitSelection::Staged)
+    } else if args.git {
+        Some(GitSelection::Tracked)
+    } else {
+        None
+    }
+}
+
+fn create_filter_config(args: &Args) -> FilterConfig {
+    let mut config = FilterConfig::new(args.all, args.exclude.clone(), args.include.clone());
+    config.git_selection = git_selection(args);
+    config.include_generated = args.include_generated;
+    config.only_hidden = args.only_hidden;
+    config.special_files = args.special_files.then(SpecialFileLimits::default);
+    config.chunk_size = args.chunk_size;
+    config.symlinks = match args.symlinks {
+        Symlinks::Skip => SymlinkPolicy::Skip,
+        Symlinks::Follow => SymlinkPolicy::Follow,
+        Symlinks::Report => SymlinkPolicy::Report,
+    };
+    config.deadline = DEADLINE.get().copied();
+    config.order = match args.order {
+        Order::Sorted => EntryOrder::Sorted,
+        Order::Discovery => EntryOrder::Discovery,
+        Order::Completion => EntryOrder::Completion,
+    };
+    match config.compile() {
+        Ok(config) => config,
+        Err(e) => {
+            report_error(args, None, "usage", &e.to_string());
+            exit_with(args, Failure::Usage);
+        }
+    }
+}
+
+fn load_baseline(args: &Args) -> Option<Report> {
+    let path = args.baseline.as_ref()?;
+    match Report::load(path) {
+        Ok(report) => Some(report),
+        Err(e) => {
+            report_io_error(args, &path.display().to_string(), &e);
+            exit_with(args, Failure::Inputs);
+        }
+    }
+}
+
+fn report_entries(results: &[JsonFileResult]) -> Vec<ReportEntry> {
+    results
+        .iter()
+        .map(|r| ReportEntry {
+            name: r.name.clone(),
+            count: r.count,
+            file_count: r.file_count.unwrap_or(1),
+            is_directory: r.is_directory,
+        })
+        .collect()
+}
+
+fn has_growth(delta: &ReportDelta, args: &Args) -> bool {
+    (args.show_lines() && delta.total.lines > 0)
+        || (args.show_words() && delta.total.words > 0)
+        || (args.show_bytes() && delta.total.bytes > 0)
+}
+
+fn main() {
+    let mut args = Args::parse_command_line();
+    init_logging(&args);
+    if let Some(timeout) = args.timeout {
+        // These run until stopped, which the watchdog would take for a stuck run. The
+        // flag is global, so clap cannot rule them out itself
+        let runs_until_stopped = args.watch
+            || args.follow.is_some()
+            || args.interval.is_some()
+            || args.rpc
+            || matches!(
+                args.command,
+                Some(Command::Serve { .. } | Command::Daemon { .. })
+            );
+        if runs_until_stopped {
+            let message =
+                "--timeout cannot be used with --watch, --follow, --interval, --rpc, serve or daemon";
+            report_error(&args, None, "usage", message);
+            exit_with(&args, Failure::Usage);
+        }
+        start_timeout(&args, timeout);
+    }
+    if let Some(threads) = args.threads {
+        // Only fails if the pool was already started, which it is not this early
+        let _ = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.get())
+            .build_global();
+    }
+    if args.symbols && !cfg!(feature = "symbols") {
+        let message = "--symbols needs ewc built with the `symbols` feature";
+        report_error(&args, None, "usage", message);
+        exit_with(&args, Failure::Usage);
+    }
+
+    match &args.command {
+        Some(Command::Check { paths }) => {
+            run_check_mode(&args, paths);
+            return;
+        }
+        Some(Command::Diff { range, paths }) => {
+            run_diff_git_mode(&args, range, paths);
+            return;
+        }
+        Some(Command::History {
+            since,
+            interval,
+            paths,
+        }) => {
+            run_history_mode(&args, since, *interval, paths);
+            return;
+        }
+        Some(Command::Compare { dir_a, dir_b }) => {
+            run_compare_mode(&args, dir_a, dir_b);
+            return;
+        }
+        Some(Command::Convert { report }) => {
+            run_convert_mode(&args, report);
+            return;
+        }
+        Some(Command::Merge { reports }) => {
+            run_merge_mode(&args, reports);
+            return;
+        }
+        Some(Command::Snapshot { action }) => {
+            run_snapshot_mode(&args, action);
+            return;
+        }
+        Some(Command::Report { file }) => {
+            run_report_mode(&args, file.as_deref());
+            return;
+        }
+        Some(Command::Hook { staged }) => {
+            run_hook_mode(&args, *staged);
+            return;
+        }
+        Some(Command::Serve { port, host, dir }) => {
+            run_serve_mode(&args, host, *port, dir);
+            return;
+        }
+        Some(Command::Daemon { socket, dir }) => {
+            run_daemon_mode(&args, socket.as_deref(), dir);
+            return;
+        }
+        Some(Command::Cache {
+            action: CacheAction::Clear,
+        }) => {
+            run_cache_clear_mode(&args);
+            return;
+        }
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(*shell, &mut Args::command(), "ewc", &mut io::stdout());
+            return;
+        }
+        Some(Command::InstallHook { force }) => {
+            run_install_hook_mode(&args, *force);
+            return;
+        }
+        // Already folded into `files` and `watch` by the parser
+        Some(Command::Count { .. } | Command::Watch { .. }) | None => {}
+    }
+
+    if args.rpc {
+        run_rpc_mode(&args);
+        return;
+    }
+
+    if let Some(path) = &args.follow {
+        run_follow_mode(&args, path);
+        return;
+    }
+
+    // Change-based selections make no sense for stdin, so default to the current directory
+    if args.files.is_empty()
+        && (args.changed.is_some()
+            || args.staged
+            || args.by_author
+            || args.by_submodule
+            || args.by_package
+            || args.heatmap
+            || args.watch
+            || args.list)
+    {
+        args.files.push(".".to_string());
+    }
+    // Nothing is being piped in, so count the current directory rather than wait for input
+    if args.files.is_empty() && io::stdin().is_terminal() {
+        let message = "No files given; counting the current directory (pipe input to count stdin)";
+        report_error(&args, None, "no_input", message);
+        args.files.push(".".to_string());
+    }
+    let files = std::mem::take(&mut args.files);
+    let paths_given = !files.is_empty();
+    args.files = skip_link_paths(&args, dedupe_paths(&args, files));
+    // Every path was a link left out: nothing to count, and no cue to read stdin
+    if paths_given && args.files.is_empty() {
+        return;
+    }
+    if args.list {
+        run_list_mode(&args);
+        return;
+    }
+    if !args.files_over.is_empty() {
+        run_files_over_mode(&args);
+        return;
+    }
+    let baseline = load_baseline(&args);
+
+    let delta = if args.files.is_empty() {
+        run_stdin_mode(&args);
+        None
+    } else if args.by_author {
+        run_by_author_mode(&args);
+        None
+    } else if args.by_submodule {
+        run_rollup_mode(&args, Rollup::Submodules);
+        None
+    } else if args.by_package {
+        run_rollup_mode(&args, Rollup::Packages);
+        None
+    } else if args.heatmap {
+        run_heatmap_mode(&args);
+        None
+    } else if args.watch {
+        run_watch_mode(&args);
+        None
+    } else if args.total_only {
+        run_total_only_mode(&args);
+        None
+    } else if args.json {
+        run_json_mode(&args, baseline.as_ref())
+    } else if let Some(format) = args.format {
+        run_format_mode(&args, format, baseline.as_ref())
+    } else {
+        run_normal_mode(&args, baseline.as_ref())
+    };
+
+    if args.fail_on_growth && delta.is_some_and(|d| has_growth(&d, &args)) {
+        report_error(&args, None, "growth", "counts grew compared to baseline");
+        exit_with(&args, Failure::Budget);
+    }
+    if TIMED_OUT.load(Ordering::Relaxed) {
+        exit_with(&args, Failure::Timeout);
+    }
+}
+
+fn run_list_mode(args: &Args) {
+    let config = create_filter_config(args);
+    let mut files = Vec::new();
+    let mut has_error = false;
+
+    for file in &args.files {
+        let path = Path::new(file);
+        let listed = match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => list_directory(path, &config),
+            Ok(_) => Ok(vec![path.to_path_buf()]),
+            Err(e) => Err(e),
+        };
+        match listed {
+            Ok(listed) => files.extend(listed),
+            Err(e) => {
+                report_io_error(args, file, &e);
+                has_error = true;
+            }
+        }
+    }
+
+    if args.json {
+        println!("{}", format_file_list_json(&files));
+    } else if args.print0 {
+        for file in &files {
+            print!("{}\0", display_path(file));
+        }
+    } else {
+        for file in &files {
+            println!("{}", display_path(file));
+        }
+    }
+    if has_error {
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+fn run_files_over_mode(args: &Args) {
+    let (mut entries, has_error) = collect_file_entries(args, &args.files);
+    // Largest first, by the metric of the first limit
+    let metric = args.files_over[0].metric;
+    entries.sort_by_key(|entry| std::cmp::Reverse(metric.value(&entry.count)));
+    let violations = check_entries(&entries, &args.files_over);
+
+    if args.json {
+        println!("{}", format_files_over_json(&violations));
+    } else if args.print0 {
+        let mut paths: Vec<&Path> = violations.iter().map(|v| v.path.as_path()).collect();
+        paths.dedup();
+        for path in paths {
+            print!("{}\0", display_path(path));
+        }
+    } else if !violations.is_empty() {
+        println!("{}", format_files_over(&violations));
+    }
+    if has_error {
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+fn run_diff_git_mode(args: &Args, range: &str, paths: &[String]) {
+    let diffs = match diff_stats(Path::new("."), range, paths) {
+        Ok(diffs) => diffs,
+        Err(e) => {
+            report_failure(args, range, &e);
+            exit_with(args, Failure::Inputs);
+        }
+    };
+
+    let output = match (args.json, args.format) {
+        (true, _) => format_diff_json(range, &diffs),
+        (false, None) => format_diff_output(&diffs, args),
+        (false, Some(OutputFormat::Markdown)) => format_diff_markdown(&diffs),
+        (false, Some(format)) => exit_unsupported_format(args, "diff", format),
+    };
+    println!("{output}");
+}
+
+fn exit_unsupported_format(args: &Args, command: &str, format: OutputFormat) -> ! {
+    let name = format.to_possible_value().map(|v| v.get_name().to_string());
+    let message = format!(
+        "{command} does not support --format {}",
+        name.unwrap_or_default()
+    );
+    report_error(args, None, "usage", &message);
+    exit_with(args, Failure::Usage);
+}
+
+fn run_history_mode(args: &Args, since: &str, interval: Interval, paths: &[String]) {
+    let config = create_filter_config(args);
+    let points = match collect_history(Path::new("."), since, interval, paths, &config) {
+        Ok(points) => points,
+        Err(e) => {
+            report_failure(args, "history", &e);
+            exit_with(args, Failure::Inputs);
+        }
+    };
+    if points.is_empty() {
+        let message = format!("history: no commits since {since}");
+        report_error(args, None, "not_found", &message);
+        exit_with(args, Failure::Inputs);
+    }
+
+    let output = match (args.json, args.format) {
+        (true, _) => format_history_json(&points),
+        (false, None) => format_history_output(&points, args),
+        (false, Some(OutputFormat::Csv)) => format_history_csv(&points),
+        (false, Some(OutputFormat::Markdown)) => format_history_markdown(&points, args),
+        (false, Some(format)) => exit_unsupported_format(args, "history", format),
+    };
+    println!("{output}");
+}
+
+fn run_compare_mode(args: &Args, dir_a: &str, dir_b: &str) {
+    let config = create_filter_config(args);
+    let comparison = match compare_trees(Path::new(dir_a), Path::new(dir_b), &config) {
+        Ok(comparison) => comparison,
+        Err(e) => {
+            report_error(args, None, &error_kind_name(e.kind()), &e.to_string());
+            exit_with(args, Failure::Inputs);
+        }
+    };
+
+    let output = match (args.json, args.format) {
+        (true, _) => format_compare_json(&comparison),
+        (false, None) => format_compare_output(&comparison, args),
+        (false, Some(format)) => exit_unsupported_format(args, "compare", format),
+    };
+    println!("{output}");
+}
+
+/// Reads a report saved with `--json`, exiting on failure.
+fn load_report(args: &Args, path: &Path) -> Report {
+    match Report::load(path) {
+        Ok(report) => report,
+        Err(e) => {
+            report_io_error(args, &path.display().to_string(), &e);
+            exit_with(args, Failure::Inputs);
+        }
+    }
+}
+
+/// Prints a saved report as text, `--json` or a `--format` that needs only its counts:
+/// markdown, csv, html, xlsx, mermaid, dot or prometheus. Analyzer results are not kept
+/// in reports.
+fn print_report(args: &Args, command: &str, report: &Report) {
+    let results: Vec<JsonFileResult> = report
+        .entries
+        .iter()
+        .map(|entry| JsonFileResult {
+            name: entry.name.clone(),
+            count: entry.count,
+            is_directory: entry.is_directory,
+            file_count: entry.is_directory.then_some(entry.file_count),
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
+        })
+        .collect();
+    let entries: Vec<FileEntry> = report
+        .entries
+        .iter()
+        .map(|entry| FileEntry {
+            path: PathBuf::from(&entry.name),
+            count: entry.count,
+        })
+        .collect();
+
+    let output = match (args.json, args.format) {
+        (true, _) => format_json_multiple(&results, &report.total),
+        (false, None) => {
+            let mut lines: Vec<String> = results
+                .iter()
+                .map(|result| {
+                    let kind = match result.file_count {
+                        Some(file_count) => OutputKind::Directory(file_count),
+                        None => OutputKind::File,
+                    };
+                    if args.compact {
+                        format_compact_output(&result.name, &result.count, kind, args)
+                    } else {
+                        format_output(&result.name, &result.count, kind, args)
+                    }
+                })
+                .collect();
+            if args.compact {
+                lines.push(format_compact_total(report.file_count, &report.total, args));
+                lines.join("\n")
+            } else {
+                lines.push(format!(
+                    "{}\n{}",
+                    format_separator(),
+                    format_total_output(report.file_count, &report.total, args)
+                ));
+                lines.join("\n\n")
+            }
+        }
+        (false, Some(OutputFormat::Markdown)) => format_markdown(&results, &report.total, args),
+        (false, Some(OutputFormat::Html)) => format_html(&results, &report.total, args),
+        (false, Some(OutputFormat::Prometheus)) => format_prometheus(&results),
+        (false, Some(OutputFormat::Csv)) => format_csv(&entries, &[]),
+        (false, Some(OutputFormat::Mermaid)) => format_mermaid(&entries),
+        (false, Some(OutputFormat::Dot)) => format_dot(&entries),
+        (false, Some(OutputFormat::Xlsx)) => {
+            print_workbook(args, format_xlsx(&results, &report.total, &entries, args));
+            return;
+        }
+        (false, Some(format)) => exit_unsupported_format(args, command, format),
+    };
+    println!("{output}");
+}
+
+fn run_convert_mode(args: &Args, path: &Path) {
+    print_report(args, "convert", &load_report(args, path));
+}
+
+fn run_merge_mode(args: &Args, paths: &[PathBuf]) {
+    let reports: Vec<Report> = paths.iter().map(|path| load_report(args, path)).collect();
+    print_report(args, "merge", &Report::merge(reports));
+}
+
+/// Drops arguments naming the same file or directory as an earlier one, however they are
+/// spelled, so nothing is counted twice; kept as given with `--allow-duplicates`.
+fn dedupe_paths(args: &Args, paths: Vec<String>) -> Vec<String> {
+    if args.allow_duplicates {
+        return paths;
+    }
+    let mut seen = HashSet::new();
+    paths
+        .into_iter()
+        .filter(|file| {
+            // Paths that cannot be resolved, such as missing files, are compared as given
+            let key = fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+            let first = seen.insert(key);
+            if !first {
+                report_error(
+                    args,
+                    Some(file),
+                    "duplicate",
+                    "skipped: already given as an earlier path",
+                );
+            }
+            first
+        })
+        .collect()
+}
+
+/// Drops arguments that are symbolic links unless `--symlinks follow`, as links found
+/// in directories are, warning about each with `--symlinks report`. Links under `/dev`
+/// and `/proc`, such as `/dev/stdin` and the `/dev/fd/63` of process substitution, pass
+/// streams in rather than link within a tree, so they are always read.
+fn skip_link_paths(args: &Args, paths: Vec<String>) -> Vec<String> {
+    if args.symlinks == Symlinks::Follow {
+        return paths;
+    }
+    paths
+        .into_iter()
+        .filter(|file| {
+            let path = Path::new(file);
+            if !path.is_symlink() || path.starts_with("/dev") || path.starts_with("/proc") {
+                return true;
+            }
+            LINK_PATHS_SKIPPED.fetch_add(1, Ordering::Relaxed);
+            if args.symlinks == Symlinks::Report {
+                let message = "skipped: symbolic link not followed";
+                report_error(args, Some(file), "unsupported", message);
+            }
+            false
+        })
+        .collect()
+}
+
+/// Per-file results for `paths`, defaulting to the current directory.
+fn collect_file_entries(args: &Args, paths: &[String]) -> (Vec<FileEntry>, bool) {
+    let paths = if paths.is_empty() {
+        vec![".".to_string()]
+    } else {
+        skip_link_paths(args, dedupe_paths(args, paths.to_vec()))
+    };
+    let config = create_filter_config(args);
+    let mut cache = open_cache(args);
+    let mut entries: Vec<FileEntry> = Vec::new();
+    let mut has_error = false;
+    let mut stats = RunStats::new();
+
+    for file in &paths {
+        match process_path(Path::new(file), &config, cache.as_mut(), args) {
+            Ok(result) => {
+                has_error |= result.fails_strict(args);
+                stats.add(&result);
+                entries.extend(result.entries);
+            }
+            Err(e) => {
+                report_io_error(args, file, &e);
+                has_error = true;
+            }
+        }
+    }
+    stats.report(args);
+    (entries, has_error)
+}
+
+fn run_snapshot_mode(args: &Args, action: &SnapshotAction) {
+    let root = Path::new(".");
+    let has_error = match action {
+        SnapshotAction::Save { name, paths } => {
+            let (entries, has_error) = collect_file_entries(args, paths);
+            match snapshot::save(root, name, &entries) {
+                Ok(path) => println!(
+                    "Saved snapshot {name} ({} files) to {}",
+                    entries.len(),
+                    path.display()
+                ),
+                Err(e) => {
+                    report_failure(args, &format!("snapshot {name}"), &e);
+                    exit_with(args, Failure::Inputs);
+                }
+            }
+            has_error
+        }
+        SnapshotAction::Diff { name, paths } => {
+            let saved = match snapshot::load(root, name) {
+                Ok(report) => report,
+                Err(e) => {
+                    report_failure(args, &format!("snapshot {name}"), &e);
+                    exit_with(args, Failure::Inputs);
+                }
+            };
+            let (entries, has_error) = collect_file_entries(args, paths);
+            let delta = compare_reports(&snapshot::report_from_entries(&entries), &saved);
+            let output = match (args.json, args.format) {
+                (true, _) => format_snapshot_diff_json(name, &delta),
+                (false, None) => {
+                    format_report_delta(&format!("Change vs snapshot {name}"), &delta, args)
+                }
+                (false, Some(format)) => exit_unsupported_format(args, "snapshot diff", format),
+            };
+            println!("{output}");
+            has_error
+        }
+    };
+
+    if has_error {
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+/// Appends this run to the --db database, if one was given. Returns false on failure.
+fn record_run(args: &Args, entries: &[FileEntry]) -> bool {
+    let Some(path) = &args.db else {
+        return true;
+    };
+    // Partial counts would read as files shrinking or going away
+    if TIMED_OUT.load(Ordering::Relaxed) {
+        return true;
+    }
+    // Runs outside a git repository are recorded without a revision
+    let revision = head_revision(Path::new(".")).ok();
+    let result =
+        db::open(path).and_then(|mut conn| db::record_run(&mut conn, revision.as_deref(), entries));
+    if let Err(e) = result {
+        report_io_error(args, &path.display().to_string(), &e);
+        return false;
+    }
+    true
+}
+
+fn run_report_mode(args: &Args, file: Option<&str>) {
+    let Some(path) = &args.db else {
+        report_error(args, None, "usage", "report needs --db PATH");
+        exit_with(args, Failure::Usage);
+    };
+    if !path.exists() {
+        let path = path.display().to_string();
+        report_error(args, Some(&path), "not_found", "no such database");
+        exit_with(args, Failure::Inputs);
+    }
+    let points = match db::open(path).and_then(|conn| db::load_trend(&conn, file)) {
+        Ok(points) => points,
+        Err(e) => {
+            report_io_error(args, &path.display().to_string(), &e);
+            exit_with(args, Failure::Inputs);
+        }
+    };
+    if points.is_empty() {
+        report_error(args, None, "not_found", "report: no runs recorded");
+        exit_with(args, Failure::Inputs);
+    }
 
-const WARNING_ICON: &str = "\u{26A0}\u{FE0F}";
+    let output = match (args.json, args.format) {
+        (true, _) => format_history_json(&points),
+        (false, None) => format_history_output(&points, args),
+        (false, Some(OutputFormat::Csv)) => format_history_csv(&points),
+        (false, Some(OutputFormat::Markdown)) => format_history_markdown(&points, args),
+        (false, Some(format)) => exit_unsupported_format(args, "report", format),
+    };
+    println!("{output}");
+}
 
-struct ProcessResult {
-    count: Count,
-    file_count: usize,
+fn run_rpc_mode(args: &Args) {
+    let config = create_filter_config(args);
+    if let Err(e) = serve_stdio(io::stdin().lock(), io::stdout().lock(), &config) {
+        report_failure(args, "--rpc", &e);
+        exit_with(args, Failure::Inputs);
+    }
 }
 
-fn process_path(path: &Path, config: &FilterConfig) -> io::Result<ProcessResult> {
-    if path.is_dir() {
-        let (count, file_count) = count_directory(path, config)?;
-        Ok(ProcessResult { count, file_count })
+#[cfg(unix)]
+fn run_daemon_mode(args: &Args, socket: Option<&Path>, dir: &str) {
+    use ewc::daemon::{self, Index, SOCKET_PATH};
+
+    let index = match Index::build(dir, create_filter_config(args)) {
+        Ok(index) => index,
+        Err(e) => {
+            report_io_error(args, dir, &e);
+            exit_with(args, Failure::Inputs);
+        }
+    };
+    let socket = socket.map_or_else(|| Path::new(dir).join(SOCKET_PATH), Path::to_path_buf);
+    let listener = match daemon::bind(&socket) {
+        Ok(listener) => listener,
+        Err(e) => {
+            report_io_error(args, &socket.display().to_string(), &e);
+            exit_with(args, Failure::Inputs);
+        }
+    };
+
+    eprintln!("Indexed {dir}; listening on {}", socket.display());
+    if let Err(e) = daemon::run(index, listener) {
+        report_error(args, None, &error_kind_name(e.kind()), &e.to_string());
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+#[cfg(not(unix))]
+fn run_daemon_mode(args: &Args, _socket: Option<&Path>, _dir: &str) {
+    let message = "daemon: Unix sockets are not available on this platform";
+    report_error(args, None, "unsupported", message);
+    exit_with(args, Failure::Inputs);
+}
+
+fn run_cache_clear_mode(args: &Args) {
+    let Some(path) = cache::default_path() else {
+        let message = "cache: no cache directory (set XDG_CACHE_HOME or HOME)";
+        report_error(args, None, "not_found", message);
+        exit_with(args, Failure::Inputs);
+    };
+    match cache::clear(&path) {
+        Ok(true) => println!("Cleared cache at {}", path.display()),
+        Ok(false) => println!("No cache at {}", path.display()),
+        Err(e) => {
+            report_io_error(args, &path.display().to_string(), &e);
+            exit_with(args, Failure::Inputs);
+        }
+    }
+}
+
+fn run_serve_mode(args: &Args, host: &str, port: u16, dir: &str) {
+    if !Path::new(dir).is_dir() {
+        report_error(args, Some(dir), "not_a_directory", "not a directory");
+        exit_with(args, Failure::Inputs);
+    }
+
+    let address = format!("{host}:{port}");
+    let server = Server::new(Path::new(dir), create_filter_config(args));
+    eprintln!("Serving {dir} on http://{address}/counts");
+    if let Err(e) = serve(&address, server, args) {
+        report_failure(args, &address, &e);
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+fn require_limits(args: &Args, command: &str) {
+    if args.over.is_empty() {
+        let message = format!("{command} needs at least one --over limit");
+        report_error(args, None, "usage", &message);
+        exit_with(args, Failure::Usage);
+    }
+}
+
+fn run_hook_mode(args: &Args, staged: bool) {
+    require_limits(args, "hook");
+    let config = create_filter_config(args);
+    let violations = match check_index(Path::new("."), staged, &args.over, &config) {
+        Ok(violations) => violations,
+        Err(e) => {
+            report_failure(args, "hook", &e);
+            exit_with(args, Failure::Inputs);
+        }
+    };
+
+    if !violations.is_empty() {
+        report_violations(args, &violations);
+        exit_with(args, Failure::Budget);
+    }
+}
+
+fn run_check_mode(args: &Args, paths: &[String]) {
+    require_limits(args, "check");
+    let (entries, has_error) = collect_file_entries(args, paths);
+    let violations = check_entries(&entries, &args.over);
+
+    match args.format {
+        None => report_violations(args, &violations),
+        Some(OutputFormat::Sarif) => println!("{}", format_sarif(&violations, &args.over)),
+        Some(format) => exit_unsupported_format(args, "check", format),
+    }
+
+    if has_error {
+        exit_with(args, Failure::Inputs);
+    }
+    if !violations.is_empty() {
+        exit_with(args, Failure::Budget);
+    }
+}
+
+fn run_install_hook_mode(args: &Args, force: bool) {
+    require_limits(args, "install-hook");
+    match install_hook(Path::new("."), &args.over, force) {
+        Ok(path) => println!("Installed pre-commit hook at {}", path.display()),
+        Err(e) => {
+            report_failure(args, "install-hook", &e);
+            exit_with(args, Failure::Inputs);
+        }
+    }
+}
+
+fn run_by_author_mode(args: &Args) {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut has_error = false;
+    let config = create_filter_config(args);
+
+    for file in &args.files {
+        match process_path(Path::new(file), &config, None, args) {
+            Ok(result) => {
+                has_error |= result.fails_strict(args);
+                files.extend(result.entries.into_iter().map(|e| e.path));
+            }
+            Err(e) => {
+                report_io_error(args, file, &e);
+                has_error = true;
+            }
+        }
+    }
+
+    let authors = match count_by_author(&files) {
+        Ok(authors) => authors,
+        Err(e) => {
+            report_failure(args, "--by-author", &e);
+            exit_with(args, Failure::Inputs);
+        }
+    };
+
+    let output = match (args.json, args.format) {
+        (true, _) => format_authors_json(&authors),
+        (false, None) => format_authors_output(&authors),
+        (false, Some(format)) => exit_unsupported_format(args, "--by-author", format),
+    };
+    println!("{output}");
+
+    if has_error {
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+/// The directories `--by-submodule` or `--by-package` splits a counted directory into.
+#[derive(Clone, Copy)]
+enum Rollup {
+    Submodules,
+    Packages,
+}
+
+impl Rollup {
+    fn flag(self) -> &'static str {
+        match self {
+            Rollup::Submodules => "--by-submodule",
+            Rollup::Packages => "--by-package",
+        }
+    }
+
+    /// The submodules or packages of the directory `root`, relative to it.
+    fn dirs(self, root: &Path, entries: &[FileEntry]) -> io::Result<Vec<PathBuf>> {
+        match self {
+            Rollup::Submodules => submodule_paths(root),
+            Rollup::Packages => {
+                let files: Vec<PathBuf> = entries.iter().map(|entry| entry.path.clone()).collect();
+                package_paths(root, &files)
+            }
+        }
+    }
+}
+
+/// One row for `path` and one per submodule or package in it, each file counted in
+/// the innermost that holds it and the rest in the first row. A file, or a directory
+/// without submodules or packages, is a single row.
+fn split_rows(
+    path: &Path,
+    result: &ProcessResult,
+    rollup: Rollup,
+) -> io::Result<Vec<JsonFileResult>> {
+    let is_directory = path.is_dir();
+    let dirs = if is_directory {
+        rollup.dirs(path, &result.entries)?
     } else {
-        let count = count_file(path)?;
-        Ok(ProcessResult {
-            count,
-            file_count: 1,
+        Vec::new()
+    };
+    let mut rows: Vec<JsonFileResult> = std::iter::once(path.to_path_buf())
+        .chain(dirs.iter().map(|dir| {
+            if path == Path::new(".") {
+                dir.clone()
+            } else {
+                path.join(dir)
+            }
+        }))
+        .map(|row| JsonFileResult {
+            name: display_path(&row),
+            count: Count::default(),
+            is_directory,
+            file_count: is_directory.then_some(0),
+            analysis: FileAnalysis::default(),
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
         })
+        .collect();
+    // Analyses are only there when an analyzer is on
+    let mut analyses = result.analyses.iter();
+    for entry in &result.entries {
+        let index = innermost_dir(&dirs, path, &entry.path).map_or(0, |index| index + 1);
+        let row = &mut rows[index];
+        row.count += entry.count;
+        row.file_count = row.file_count.map(|files| files + 1);
+        if let Some(analysis) = analyses.next() {
+            row.analysis += analysis.clone();
+        }
     }
+    rows[0].skipped = result.skipped.clone();
+    Ok(rows)
 }
 
-fn create_filter_config(args: &Args) -> FilterConfig {
-    FilterConfig::new(args.all, args.exclude.clone(), args.include.clone())
+fn run_rollup_mode(args: &Args, rollup: Rollup) {
+    if let Some(format) = args.format {
+        exit_unsupported_format(args, rollup.flag(), format);
+    }
+    let mut rows: Vec<JsonFileResult> = Vec::new();
+    let mut all_entries: Vec<FileEntry> = Vec::new();
+    let mut has_error = false;
+    let mut stats = RunStats::new();
+    let config = create_filter_config(args);
+    let mut cache = open_cache(args);
+
+    for file in &args.files {
+        let path = Path::new(file);
+        let split = process_path(path, &config, cache.as_mut(), args).and_then(|result| {
+            has_error |= result.fails_strict(args);
+            stats.add(&result);
+            let split = split_rows(path, &result, rollup);
+            all_entries.extend(result.entries);
+            split
+        });
+        match split {
+            Ok(split) => rows.extend(split),
+            Err(e) => {
+                report_io_error(args, file, &e);
+                has_error = true;
+            }
+        }
+    }
+
+    let total: Count = rows.iter().map(|row| row.count).sum();
+    if args.json {
+        match rows.as_slice() {
+            [] => {}
+            [single] => println!("{}", format_json_single(single)),
+            _ => println!("{}", format_json_multiple(&rows, &total)),
+        }
+    } else {
+        let mut sections: Vec<String> = rows
+            .iter()
+            .map(|row| {
+                let kind = match row.file_count {
+                    Some(files) => OutputKind::Directory(files),
+                    None => OutputKind::File,
+                };
+                let output = if args.compact {
+                    format_compact_output(&row.name, &row.count, kind, args)
+                } else {
+                    format_output(&row.name, &row.count, kind, args)
+                };
+                with_analysis(output, &row.analysis, args)
+            })
+            .collect();
+        if rows.len() > 1 {
+            let file_count = rows.iter().map(|row| row.file_count.unwrap_or(1)).sum();
+            let analysis: FileAnalysis = rows.iter().map(|row| &row.analysis).sum();
+            let output = if args.compact {
+                format_compact_total(file_count, &total, args)
+            } else {
+                format!(
+                    "{}\n{}",
+                    format_separator(),
+                    format_total_output(file_count, &total, args)
+                )
+            };
+            sections.push(with_analysis(output, &analysis, args));
+        }
+        println!(
+            "{}",
+            sections.join(if args.compact { "\n" } else { "\n\n" })
+        );
+    }
+    stats.report(args);
+
+    if !record_run(args, &all_entries) || has_error {
+        exit_with(args, Failure::Inputs);
+    }
 }
 
-fn main() {
-    let args = Args::parse();
+fn run_heatmap_mode(args: &Args) {
+    let (entries, has_error) = collect_file_entries(args, &args.files);
+    let color = !args.no_color && io::stdout().is_terminal();
+    if !entries.is_empty() {
+        println!("{}", format_heatmap(&entries, color));
+    }
+    if !record_run(args, &entries) || has_error {
+        exit_with(args, Failure::Inputs);
+    }
+}
 
-    if args.files.is_empty() {
-        run_stdin_mode(&args);
-    } else if args.json {
-        run_json_mode(&args);
+fn format_watch_summary(targets: &[WatchTarget], args: &Args) -> String {
+    let mut sections: Vec<String> = Vec::new();
+    for target in targets {
+        let kind = if target.is_dir() {
+            OutputKind::Directory(target.file_count())
+        } else {
+            OutputKind::File
+        };
+        let section = if target.is_dir() && args.verbose {
+            format_verbose_output(&target.entries(), &[], &target.total(), &[], args)
+        } else if args.compact {
+            format_compact_output(&target.name, &target.total(), kind, args)
+        } else {
+            format_output(&target.name, &target.total(), kind, args)
+        };
+        sections.push(section);
+    }
+
+    if targets.len() > 1 {
+        let file_count = targets.iter().map(WatchTarget::file_count).sum();
+        let total: Count = targets.iter().map(WatchTarget::total).sum();
+        sections.push(if args.compact {
+            format_compact_total(file_count, &total, args)
+        } else {
+            format!(
+                "{}\n{}",
+                format_separator(),
+                format_total_output(file_count, &total, args)
+            )
+        });
+    }
+
+    let separator = if args.compact { "\n" } else { "\n\n" };
+    sections.join(separator)
+}
+
+fn redraw_watch(targets: &[WatchTarget], errors: &[String], args: &Args) {
+    // Clear the screen and move the cursor home
+    print!("\x1b[2J\x1b[H");
+    println!("{}", format_watch_summary(targets, args));
+    for error in errors {
+        report_error(args, None, "other", error);
+    }
+}
+
+fn run_watch_mode(args: &Args) {
+    let config = create_filter_config(args);
+    let mut targets: Vec<WatchTarget> = Vec::new();
+    for file in &args.files {
+        match WatchTarget::new(file, &config) {
+            Ok(target) => targets.push(target),
+            Err(e) => {
+                report_io_error(args, file, &e);
+                exit_with(args, Failure::Inputs);
+            }
+        }
+    }
+
+    redraw_watch(&targets, &[], args);
+    if let Err(e) = watch(&mut targets, &config, |targets, errors| {
+        redraw_watch(targets, errors, args)
+    }) {
+        report_error(args, None, &error_kind_name(e.kind()), &e.to_string());
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+fn print_follow_count(name: &str, count: Count, args: &Args) {
+    if args.json {
+        let result = JsonFileResult {
+            name: name.to_string(),
+            count,
+            is_directory: false,
+            file_count: None,
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
+        };
+        println!("{}", format_json_single(&result));
+    } else if args.compact {
+        println!(
+            "{}",
+            format_compact_output(name, &count, OutputKind::File, args)
+        );
     } else {
-        run_normal_mode(&args);
+        println!("{}\n", format_output(name, &count, OutputKind::File, args));
     }
 }
 
+fn run_follow_mode(args: &Args, path: &Path) {
+    let name = path.display().to_string();
+    let chunk_size = args.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    let mut follower = match Follower::open(path) {
+        Ok(follower) => follower.chunk_size(chunk_size),
+        Err(e) => {
+            report_io_error(args, &name, &e);
+            exit_with(args, Failure::Inputs);
+        }
+    };
+
+    // The first poll always prints, even for an empty file
+    let mut printed = false;
+    loop {
+        match follower.poll() {
+            Ok(Some(count)) => print_follow_count(&name, count, args),
+            Ok(None) if !printed => print_follow_count(&name, follower.count(), args),
+            Ok(None) => {}
+            Err(e) => {
+                report_io_error(args, &name, &e);
+                exit_with(args, Failure::Inputs);
+            }
+        }
+        printed = true;
+        std::thread::sleep(args.interval.unwrap_or(POLL_INTERVAL));
+    }
+}
+
+fn read_stdin(args: &Args) -> io::Result<Count> {
+    // Without --interval there is no progress to print
+    let interval = args.interval.unwrap_or(Duration::MAX);
+    // On a terminal the running counts share one status line; otherwise each goes on its own line
+    let status_line = io::stderr().is_terminal();
+    let counted = count_from_reader_limited(
+        io::stdin().lock(),
+        args.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE),
+        args.max_stdin_bytes,
+        interval,
+        |count| {
+            let status = format_compact_output("<stdin>", count, OutputKind::File, args);
+            if status_line {
+                eprint!("\r{status}\x1b[K");
+            } else {
+                eprintln!("{status}");
+            }
+        },
+    );
+    if status_line && args.interval.is_some() {
+        eprint!("\r\x1b[K");
+    }
+    let (count, truncated) = counted?;
+    if truncated {
+        let limit = args.max_stdin_bytes.unwrap_or_default();
+        let message = format!(
+            "truncated after {} bytes (--max-stdin-bytes); counts are of the start of the input",
+            format_number(limit as usize)
+        );
+        report_error(args, Some("<stdin>"), "truncated", &message);
+    }
+    Ok(count)
+}
+
 fn run_stdin_mode(args: &Args) {
-    let count = match count_from_reader(io::stdin().lock()) {
+    let count = match read_stdin(args) {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("{WARNING_ICON}  <stdin>: {e}");
-            process::exit(1);
+            report_io_error(args, "<stdin>", &e);
+            exit_with(args, Failure::Inputs);
         }
     };
 
-    if args.json {
+    if args.total_only {
+        println!("{}", format_total_only(&count, args));
+    } else if args.json {
         let result = JsonFileResult {
             name: "<stdin>".to_string(),
             count,
             is_directory: false,
             file_count: None,
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
         };
         println!("{}", format_json_single(&result));
     } else if args.compact {
@@ -79,59 +1899,331 @@ fn run_stdin_mode(args: &Args) {
     }
 }
 
-fn run_json_mode(args: &Args) {
+fn run_total_only_mode(args: &Args) {
+    let (entries, has_error) = collect_file_entries(args, &args.files);
+    let total: Count = entries.iter().map(|e| e.count).sum();
+    println!("{}", format_total_only(&total, args));
+
+    let violations = check_entries(&entries, &args.over);
+    report_violations(args, &violations);
+    if !record_run(args, &entries) || has_error {
+        exit_with(args, Failure::Inputs);
+    }
+    if !violations.is_empty() {
+        exit_with(args, Failure::Budget);
+    }
+}
+
+fn run_json_mode(args: &Args, baseline: Option<&Report>) -> Option<ReportDelta> {
     let mut results: Vec<JsonFileResult> = Vec::new();
+    let mut entries: Vec<FileEntry> = Vec::new();
     let mut total_count = Count::default();
     let mut has_error = false;
+    let mut stats = RunStats::new();
     let config = create_filter_config(args);
+    let mut cache = open_cache(args);
 
     for file in &args.files {
         let path = Path::new(file);
-        let Ok(result) = process_path(path, &config) else {
-            has_error = true;
-            continue;
+        let result = match process_path(path, &config, cache.as_mut(), args) {
+            Ok(result) => result,
+            Err(e) => {
+                report_io_error(args, file, &e);
+                has_error = true;
+                continue;
+            }
         };
+        has_error |= result.fails_strict(args);
+        stats.add(&result);
 
         let is_directory = path.is_dir();
         results.push(JsonFileResult {
-            name: file.clone(),
-            count: result.count.clone(),
+            name: display_path(path),
+            count: result.count,
             is_directory,
             file_count: is_directory.then_some(result.file_count),
+            analysis: result.analysis(),
+            skipped: result.skipped,
+            skip_summary: result.skip_summary,
         });
         total_count += result.count;
+        entries.extend(result.entries);
+        keep_counted_so_far(&results, &total_count);
     }
 
     match results.as_slice() {
         [] => {}
         [single] => println!("{}", format_json_single(single)),
-        _ => println!("{}", format_json_multiple(&results, &total_count)),
+        _ => print_streamed(args, |out| write_json_multiple(out, &results, &total_count)),
     }
+    stats.report(args);
 
-    if has_error {
-        process::exit(1);
+    let violations = check_entries(&entries, &args.over);
+    report_violations(args, &violations);
+    if !record_run(args, &entries) || has_error {
+        exit_with(args, Failure::Inputs);
+    }
+    if !violations.is_empty() {
+        exit_with(args, Failure::Budget);
+    }
+
+    baseline.map(|b| compare_reports(&Report::from_entries(report_entries(&results)), b))
+}
+
+fn run_format_mode(
+    args: &Args,
+    format: OutputFormat,
+    baseline: Option<&Report>,
+) -> Option<ReportDelta> {
+    let mut results: Vec<JsonFileResult> = Vec::new();
+    let mut entries: Vec<FileEntry> = Vec::new();
+    let mut analyses: Vec<FileAnalysis> = Vec::new();
+    let mut has_error = false;
+    let mut stats = RunStats::new();
+    let config = create_filter_config(args);
+    let mut cache = open_cache(args);
+
+    for file in &args.files {
+        let path = Path::new(file);
+        match process_path(path, &config, cache.as_mut(), args) {
+            Ok(result) => {
+                has_error |= result.fails_strict(args);
+                stats.add(&result);
+                let is_directory = path.is_dir();
+                results.push(JsonFileResult {
+                    name: display_path(path),
+                    count: result.count,
+                    is_directory,
+                    file_count: is_directory.then_some(result.file_count),
+                    analysis: result.analysis(),
+                    skipped: result.skipped,
+                    skip_summary: result.skip_summary,
+                });
+                entries.extend(result.entries);
+                analyses.extend(result.analyses);
+            }
+            Err(e) => {
+                report_io_error(args, file, &e);
+                has_error = true;
+            }
+        }
+    }
+
+    let violations = check_entries(&entries, &args.over);
+    let delta =
+        baseline.map(|b| compare_reports(&Report::from_entries(report_entries(&results)), b));
+    let output = match format {
+        OutputFormat::Sarif => format_sarif(&violations, &args.over),
+        OutputFormat::PrComment => {
+            format_pr_comment(&entries, &violations, &args.over, delta.as_ref())
+        }
+        OutputFormat::Prometheus => format_prometheus(&results),
+        OutputFormat::Markdown => {
+            let total: Count = results.iter().map(|r| r.count).sum();
+            format_markdown(&results, &total, args)
+        }
+        OutputFormat::Csv => {
+            print_streamed(args, |out| write_csv(out, &entries, &analyses));
+            String::new()
+        }
+        OutputFormat::Cloc => format_cloc(&summarize(&entries, &analyses)),
+        OutputFormat::ClocCsv => format_cloc_csv(&summarize(&entries, &analyses)),
+        OutputFormat::TokeiJson => format_tokei_json(&entries, &analyses),
+        OutputFormat::Html => {
+            let total: Count = results.iter().map(|r| r.count).sum();
+            format_html(&results, &total, args)
+        }
+        OutputFormat::Xlsx => {
+            let total: Count = results.iter().map(|r| r.count).sum();
+            print_workbook(args, format_xlsx(&results, &total, &entries, args));
+            String::new()
+        }
+        OutputFormat::Mermaid => format_mermaid(&entries),
+        OutputFormat::Dot => format_dot(&entries),
+    };
+    // A workbook is binary and already written, without a newline after it, and CSV
+    // is written as it is formatted
+    if !matches!(format, OutputFormat::Xlsx | OutputFormat::Csv) {
+        println!("{output}");
+    }
+    stats.report(args);
+
+    if !record_run(args, &entries) || has_error {
+        exit_with(args, Failure::Inputs);
+    }
+    if !violations.is_empty() {
+        exit_with(args, Failure::Budget);
+    }
+
+    delta
+}
+
+/// Writes a `--format xlsx` workbook to stdout, which must not be a terminal.
+fn print_workbook(args: &Args, workbook: io::Result<Vec<u8>>) {
+    if io::stdout().is_terminal() {
+        let message = "--format xlsx writes a binary workbook; redirect it to a file";
+        report_error(args, None, "usage", message);
+        exit_with(args, Failure::Usage);
+    }
+    let written = workbook.and_then(|bytes| io::stdout().write_all(&bytes));
+    if let Err(e) = written {
+        report_failure(args, "xlsx", &e);
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+/// Writes output to stdout as `write` formats it, and a newline after it, rather than
+/// building all of it in memory first.
+fn print_streamed(
+    args: &Args,
+    write: impl FnOnce(&mut io::BufWriter<io::StdoutLock>) -> io::Result<()>,
+) {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let written = write(&mut out)
+        .and_then(|()| out.write_all(b"\n"))
+        .and_then(|()| out.flush());
+    if let Err(e) = written {
+        report_failure(args, "stdout", &e);
+        exit_with(args, Failure::Inputs);
+    }
+}
+
+fn report_violations(args: &Args, violations: &[Violation]) {
+    for violation in violations {
+        report_error(
+            args,
+            Some(&violation.path.display().to_string()),
+            "limit_exceeded",
+            &format_violation_message(violation),
+        );
+    }
+}
+
+/// Adds a `--delta` change to the output for a count, or `first run` without one.
+fn with_change(output: String, change: Option<CountDelta>, args: &Args) -> String {
+    let change = change.map_or("first run".to_string(), |delta| {
+        format_delta_parts(&delta, args)
+    });
+    if args.compact {
+        format!("{output} ({change})")
+    } else {
+        format!("{output}\n  Change: {change}")
+    }
+}
+
+/// Adds the change since the last run of `path`, with `--delta`, to the output for its
+/// count, and keeps this run for the next.
+fn with_last_run(
+    output: String,
+    last_runs: Option<&mut LastRuns>,
+    path: &Path,
+    count: &Count,
+    files: usize,
+    args: &Args,
+) -> String {
+    // A count `--timeout` cut short is not one to compare the next run with
+    let Some(last_runs) = last_runs.filter(|_| !TIMED_OUT.load(Ordering::Relaxed)) else {
+        return output;
+    };
+    match last_runs.update(path, count, files) {
+        Ok(change) => with_change(output, change, args),
+        Err(e) => {
+            report_io_error(args, &path.display().to_string(), &e);
+            output
+        }
+    }
+}
+
+/// Adds the analyzer results, when there are any, to the output for a count.
+fn with_analysis(output: String, analysis: &FileAnalysis, args: &Args) -> String {
+    if analysis.is_empty() {
+        output
+    } else if args.compact {
+        format!("{output}, {}", format_compact_analysis(analysis))
+    } else {
+        let mut lines = vec![output];
+        lines.extend(format_analysis(analysis));
+        lines.join("\n")
     }
 }
 
-fn run_normal_mode(args: &Args) {
+/// Adds what a directory count left out, when it left anything out, to its output.
+fn with_skip_summary(output: String, summary: &SkipSummary, args: &Args) -> String {
+    match format_skip_summary(summary) {
+        None => output,
+        Some(summary) if args.compact => format!("{output}, left out {summary}"),
+        Some(summary) => format!("{output}\nLeft out: {summary}"),
+    }
+}
+
+fn run_normal_mode(args: &Args, baseline: Option<&Report>) -> Option<ReportDelta> {
     let mut has_error = false;
+    let mut violations: Vec<Violation> = Vec::new();
+    let mut current: Vec<ReportEntry> = Vec::new();
+    let mut all_entries: Vec<FileEntry> = Vec::new();
     let mut total_count = Count::default();
     let mut total_file_count = 0;
     let mut successful_args = 0;
+    let mut total_analysis = FileAnalysis::default();
+    let mut total_skip_summary = SkipSummary::default();
+    let mut stats = RunStats::new();
     let file_count = args.files.len();
     let config = create_filter_config(args);
+    let mut cache = open_cache(args);
+    let mut last_runs = LastRuns::open(args);
 
     for (index, file) in args.files.iter().enumerate() {
         let path = Path::new(file);
         let is_last = index == file_count - 1;
 
         if path.is_dir() && args.verbose {
-            match count_directory_detailed(path, &config) {
-                Ok((entries, dir_total)) => {
-                    println!("{}", format_verbose_output(&entries, &dir_total, args));
+            match process_path(path, &config, cache.as_mut(), args) {
+                Ok(result) => {
+                    has_error |= result.fails_strict(args);
+                    stats.add(&result);
+                    total_analysis += result.analysis();
+                    total_skip_summary += result.skip_summary;
+                    let ProcessResult {
+                        count: dir_total,
+                        entries,
+                        skipped,
+                        skip_summary,
+                        analyses,
+                        ..
+                    } = result;
+                    let (entries, analyses) = if args.complexity {
+                        rank_by_complexity(entries, analyses)
+                    } else {
+                        (entries, analyses)
+                    };
+                    // The change since the last run, if any, follows the listing
+                    let change = with_last_run(
+                        String::new(),
+                        last_runs.as_mut(),
+                        path,
+                        &dir_total,
+                        entries.len(),
+                        args,
+                    );
+                    let left_out = format_skip_summary(&skip_summary)
+                        .map(|summary| format!("\nLeft out: {summary}"))
+                        .unwrap_or_default();
+                    print_streamed(args, |out| {
+                        write_verbose_output(out, &entries, &analyses, &dir_total, &skipped, args)?;
+                        out.write_all(left_out.as_bytes())?;
+                        out.write_all(change.as_bytes())
+                    });
+                    violations.extend(check_entries(&entries, &args.over));
+                    current.push(ReportEntry {
+                        name: display_path(path),
+                        count: dir_total,
+                        file_count: entries.len(),
+                        is_directory: true,
+                    });
 
                     total_count += dir_total;
                     total_file_count += entries.len();
+                    all_entries.extend(entries);
                     successful_args += 1;
 
                     if !is_last {
@@ -139,27 +2231,54 @@ fn run_normal_mode(args: &Args) {
                     }
                 }
                 Err(e) => {
-                    eprintln!("{WARNING_ICON}  {file}: {e}");
+                    report_io_error(args, file, &e);
                     has_error = true;
                 }
             }
         } else {
-            match process_path(path, &config) {
+            match process_path(path, &config, cache.as_mut(), args) {
                 Ok(result) => {
+                    has_error |= result.fails_strict(args);
+                    stats.add(&result);
                     let kind = if path.is_dir() {
                         OutputKind::Directory(result.file_count)
                     } else {
                         OutputKind::File
                     };
+                    let name = display_path(path);
                     let output = if args.compact {
-                        format_compact_output(file, &result.count, kind, args)
+                        format_compact_output(&name, &result.count, kind, args)
                     } else {
-                        format_output(file, &result.count, kind, args)
+                        format_output(&name, &result.count, kind, args)
                     };
-                    println!("{output}");
+                    let output = with_last_run(
+                        output,
+                        last_runs.as_mut(),
+                        path,
+                        &result.count,
+                        result.file_count,
+                        args,
+                    );
+                    let analysis = result.analysis();
+                    // Set the total apart from the files streamed before it
+                    if args.stream && path.is_dir() && !args.compact {
+                        println!();
+                    }
+                    let output = with_analysis(output, &analysis, args);
+                    println!("{}", with_skip_summary(output, &result.skip_summary, args));
+                    total_analysis += analysis;
+                    total_skip_summary += result.skip_summary;
+                    violations.extend(check_entries(&result.entries, &args.over));
+                    current.push(ReportEntry {
+                        name: display_path(path),
+                        count: result.count,
+                        file_count: result.file_count,
+                        is_directory: path.is_dir(),
+                    });
 
                     total_count += result.count;
                     total_file_count += result.file_count;
+                    all_entries.extend(result.entries);
                     successful_args += 1;
 
                     if !args.compact && !is_last {
@@ -167,7 +2286,7 @@ fn run_normal_mode(args: &Args) {
                     }
                 }
                 Err(e) => {
-                    eprintln!("{WARNING_ICON}  {file}: {e}");
+                    report_io_error(args, file, &e);
                     has_error = true;
                 }
             }
@@ -184,10 +2303,43 @@ fn run_normal_mode(args: &Args) {
         } else {
             format_total_output(total_file_count, &total_count, args)
         };
-        println!("{total}");
+        let total = match &last_runs {
+            Some(last_runs) => with_change(
+                total,
+                last_runs.total_change(&total_count, total_file_count),
+                args,
+            ),
+            None => total,
+        };
+        let total = with_analysis(total, &total_analysis, args);
+        println!("{}", with_skip_summary(total, &total_skip_summary, args));
     }
 
-    if has_error {
-        process::exit(1);
+    if args.cocomo {
+        let params = cocomo_params(args);
+        let code_lines = total_analysis.line_kinds.map_or(0, |lines| lines.code);
+        println!();
+        println!(
+            "{}",
+            format_cocomo(&estimate(code_lines, &params), params.project_type)
+        );
+    }
+
+    let delta = baseline.map(|b| compare_reports(&Report::from_entries(current), b));
+    if let Some(delta) = &delta {
+        println!();
+        println!("{}", format_baseline_delta(delta, args));
+    }
+    stats.report(args);
+
+    report_violations(args, &violations);
+
+    if !record_run(args, &all_entries) || has_error {
+        exit_with(args, Failure::Inputs);
     }
+    if !violations.is_empty() {
+        exit_with(args, Failure::Budget);
+    }
+
+    delta
 }
@@ -0,0 +1,298 @@
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::path::Path;
+
+/// Document statistics for a Markdown file, as shown with `--markdown`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MarkdownStats {
+    /// Words outside front matter and fenced code blocks; tokens without a letter or
+    /// digit, such as list markers and `#`, are not words
+    pub words: usize,
+    /// ATX (`# Title`) and setext (underlined) headings
+    pub headings: usize,
+    /// Inline, reference and autolinks, not counting images
+    pub links: usize,
+    pub images: usize,
+    /// Fenced code blocks (```` ``` ```` or `~~~`)
+    pub code_blocks: usize,
+}
+
+impl Add for MarkdownStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            words: self.words + other.words,
+            headings: self.headings + other.headings,
+            links: self.links + other.links,
+            images: self.images + other.images,
+            code_blocks: self.code_blocks + other.code_blocks,
+        }
+    }
+}
+
+impl AddAssign for MarkdownStats {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for MarkdownStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Whether `--markdown` analyzes this file, judged by its extension.
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["md", "markdown", "mdown", "mkd", "mdx"]
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// A fence that opened a code block: its character and length, which the closing
+/// fence must match or exceed.
+struct Fence {
+    marker: u8,
+    len: usize,
+}
+
+/// The fence at the start of `line`, allowing up to three spaces of indentation.
+fn fence(line: &str) -> Option<Fence> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = *trimmed.as_bytes().first()?;
+    if marker != b'`' && marker != b'~' {
+        return None;
+    }
+    let len = trimmed.bytes().take_while(|&b| b == marker).count();
+    (len >= 3).then_some(Fence { marker, len })
+}
+
+fn is_atx_heading(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return false;
+    }
+    let level = trimmed.bytes().take_while(|&b| b == b'#').count();
+    (1..=6).contains(&level)
+        && trimmed[level..]
+            .chars()
+            .next()
+            .is_none_or(char::is_whitespace)
+}
+
+/// A setext underline: a line of only `=` or only `-` under a paragraph line.
+fn is_setext_underline(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty()
+        && (trimmed.bytes().all(|b| b == b'=') || trimmed.bytes().all(|b| b == b'-'))
+}
+
+fn count_words(line: &str) -> usize {
+    line.split_whitespace()
+        .filter(|token| token.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+/// Counts links and images on one line, outside inline code spans.
+fn count_links(line: &str, stats: &mut MarkdownStats) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'`' => {
+                // Skip the code span, which ends at a run of as many backticks
+                let run = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+                let closing = "`".repeat(run);
+                i += run;
+                match line[i..].find(&closing) {
+                    Some(end) => i += end + run,
+                    None => return,
+                }
+            }
+            b'<' if line[i + 1..].starts_with("http://")
+                || line[i + 1..].starts_with("https://") =>
+            {
+                stats.links += 1;
+                i += 1;
+            }
+            b'[' => {
+                let Some(close) = line[i..].find(']') else {
+                    return;
+                };
+                let after = i + close + 1;
+                if matches!(bytes.get(after), Some(b'(' | b'[')) {
+                    if i > 0 && bytes[i - 1] == b'!' {
+                        stats.images += 1;
+                    } else {
+                        stats.links += 1;
+                    }
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+/// The lines of `content` after its front matter (`---` or `+++` blocks on the first
+/// line), if any.
+fn after_front_matter(content: &str) -> impl Iterator<Item = &str> {
+    let mut lines = content.lines().peekable();
+    if let Some(delimiter) = lines.peek().map(|line| line.trim_end()) {
+        if delimiter == "---" || delimiter == "+++" {
+            let delimiter = delimiter.to_string();
+            lines.next();
+            for line in lines.by_ref() {
+                if line.trim_end() == delimiter {
+                    break;
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// The lines inside fenced code blocks, without the fences, for `--code-blocks-only`.
+/// With `language`, only blocks whose info string starts with it (ignoring case) are
+/// kept, so `rust` matches ```` ```rust ```` and ```` ```Rust,ignore ````.
+pub fn code_blocks(content: &str, language: Option<&str>) -> String {
+    let mut code = String::new();
+    let mut open: Option<(Fence, bool)> = None;
+    for line in after_front_matter(content) {
+        if let Some((fence_opened, keep)) = &open {
+            if fence(line)
+                .is_some_and(|f| f.marker == fence_opened.marker && f.len >= fence_opened.len)
+            {
+                open = None;
+            } else if *keep {
+                code.push_str(line);
+                code.push('\n');
+            }
+            continue;
+        }
+        if let Some(opened) = fence(line) {
+            let info = line
+                .trim_start()
+                .trim_start_matches(opened.marker as char)
+                .trim();
+            let tag = info
+                .split(|c: char| c.is_whitespace() || c == ',' || c == '{')
+                .next()
+                .unwrap_or_default();
+            let keep = language.is_none_or(|language| tag.eq_ignore_ascii_case(language));
+            open = Some((opened, keep));
+        }
+    }
+    code
+}
+
+impl MarkdownStats {
+    /// Analyzes Markdown text. Front matter (`---` or `+++` blocks on the first line)
+    /// and fenced code blocks count towards nothing but `code_blocks`.
+    pub fn from_content(content: &str) -> Self {
+        let mut stats = Self::default();
+        let lines = after_front_matter(content);
+
+        let mut open_fence: Option<Fence> = None;
+        let mut after_paragraph = false;
+        for line in lines {
+            if let Some(open) = &open_fence {
+                if fence(line).is_some_and(|f| f.marker == open.marker && f.len >= open.len) {
+                    open_fence = None;
+                }
+                continue;
+            }
+            if let Some(opened) = fence(line) {
+                stats.code_blocks += 1;
+                open_fence = Some(opened);
+                after_paragraph = false;
+                continue;
+            }
+            if after_paragraph && is_setext_underline(line) {
+                stats.headings += 1;
+                after_paragraph = false;
+                continue;
+            }
+            if is_atx_heading(line) {
+                stats.headings += 1;
+                after_paragraph = false;
+            } else {
+                after_paragraph = !line.trim().is_empty();
+            }
+            stats.words += count_words(line);
+            count_links(line, &mut stats);
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_markdown_extensions() {
+        assert!(is_markdown(Path::new("README.md")));
+        assert!(is_markdown(Path::new("docs/guide.MARKDOWN")));
+        assert!(!is_markdown(Path::new("notes.txt")));
+        assert!(!is_markdown(Path::new("md")));
+    }
+
+    #[test]
+    fn skips_front_matter_and_code_blocks() {
+        let content = "---\ntitle: Hello there\n---\n# Intro\n\nSome prose here.\n\n```rust\nfn main() {}\n```\n\n~~~~\n```\nnot closed by backticks\n~~~~\nDone.\n";
+        let stats = MarkdownStats::from_content(content);
+        assert_eq!(stats.code_blocks, 2);
+        assert_eq!(stats.headings, 1);
+        // "Intro", "Some prose here." and "Done."
+        assert_eq!(stats.words, 5);
+    }
+
+    #[test]
+    fn counts_atx_and_setext_headings() {
+        let content = "Title\n=====\n\n## Section\n#hashtag\n\nSub\n---\n\n---\n";
+        let stats = MarkdownStats::from_content(content);
+        assert_eq!(stats.headings, 3);
+    }
+
+    #[test]
+    fn counts_links_and_images_outside_code_spans() {
+        let content = "See [the docs](https://example.com) and [a ref][1].\n![logo](logo.png) <https://example.org>\n`[not](a link)` [plain] text\n";
+        let stats = MarkdownStats::from_content(content);
+        assert_eq!(stats.links, 3);
+        assert_eq!(stats.images, 1);
+    }
+
+    #[test]
+    fn extracts_code_blocks_by_language() {
+        let content = "---\ntitle: x\n---\nProse.\n\n```rust\nfn main() {}\n```\n\n~~~ Python\nprint(1)\n```\n~~~\n\n```Rust,ignore\nlet x = 1;\n```\n";
+        assert_eq!(
+            code_blocks(content, None),
+            "fn main() {}\nprint(1)\n```\nlet x = 1;\n"
+        );
+        assert_eq!(
+            code_blocks(content, Some("rust")),
+            "fn main() {}\nlet x = 1;\n"
+        );
+        assert_eq!(code_blocks(content, Some("python")), "print(1)\n```\n");
+        assert_eq!(code_blocks("No code here.\n", None), "");
+    }
+
+    #[test]
+    fn sums_stats() {
+        let a = MarkdownStats::from_content("# A\nword\n");
+        let b = MarkdownStats::from_content("[x](y)\n");
+        let total: MarkdownStats = [a, b].into_iter().sum();
+        assert_eq!(total.headings, 1);
+        assert_eq!(total.links, 1);
+        assert_eq!(total.words, 3);
+    }
+}
@@ -0,0 +1,167 @@
+use std::path::Path;
+
+/// Whether `--strip-markup` counts `path` by its text content: HTML, XHTML, XML and
+/// SVG files.
+pub fn is_markup(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            matches!(
+                ext.to_ascii_lowercase().as_str(),
+                "html" | "htm" | "xhtml" | "xml" | "svg"
+            )
+        })
+}
+
+/// Elements whose content is never shown.
+const HIDDEN_ELEMENTS: &[&str] = &["script", "style", "template"];
+
+/// HTML elements that run on within a line of text, so `<b>bold</b>ly` stays one word;
+/// any other tag separates words.
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "data", "dfn", "em", "i", "kbd", "mark", "q",
+    "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u", "var",
+];
+
+/// The text a reader sees in an HTML or XML document: tags, comments, declarations and
+/// the content of scripts and styles removed, entities decoded, each line trimmed and
+/// blank lines dropped.
+pub fn visible_text(content: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..start]));
+        rest = &rest[start..];
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            text.push_str(&cdata[..end]);
+            rest = cdata.get(end + 3..).unwrap_or("");
+        } else if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else {
+            let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            let name = tag_name(&rest[..end]);
+            rest = &rest[end..];
+            if HIDDEN_ELEMENTS.contains(&name.as_str()) {
+                rest = skip_element(rest, &name);
+            }
+            if !INLINE_ELEMENTS.contains(&name.as_str()) {
+                text.push(' ');
+            }
+        }
+    }
+    text.push_str(&decode_entities(rest));
+
+    let mut visible = String::with_capacity(text.len());
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        visible.push_str(line);
+        visible.push('\n');
+    }
+    visible
+}
+
+/// The lowercase element name of a tag such as `<P class="x">` or `</p>`; empty for
+/// declarations and processing instructions.
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('<')
+        .trim_start_matches('/')
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | ':' | '_'))
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// What follows the closing tag of `name`, or nothing if it is never closed.
+fn skip_element<'a>(rest: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{name}");
+    rest.to_ascii_lowercase()
+        .find(&closing)
+        .and_then(|start| rest[start..].find('>').map(|end| &rest[start + end + 1..]))
+        .unwrap_or("")
+}
+
+/// Replaces character references and the common named entities; unknown ones are
+/// left as written.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "mdash" => '—',
+        "ndash" => '–',
+        "hellip" => '…',
+        "copy" => '©',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_markup_files() {
+        assert!(is_markup(Path::new("docs/index.HTML")));
+        assert!(is_markup(Path::new("feed.xml")));
+        assert!(!is_markup(Path::new("README.md")));
+    }
+
+    #[test]
+    fn keeps_only_visible_text() {
+        let html = "<!DOCTYPE html>\n<html>\n<head>\n  <title>Home</title>\n  \
+                    <style>p { color: red; }</style>\n</head>\n<body>\n  \
+                    <!-- nav -->\n  <p>Fish &amp; <b>chips</b>ly</p><p>Done</p>\n  \
+                    <script>alert(\"<p>\")</script>\n</body>\n</html>\n";
+        assert_eq!(visible_text(html), "Home\nFish & chipsly  Done\n");
+    }
+
+    #[test]
+    fn xml_elements_separate_words() {
+        let xml = "<?xml version=\"1.0\"?>\n<items><item>one</item><item>two</item>\
+                   <note><![CDATA[a < b]]></note></items>\n";
+        assert_eq!(visible_text(xml), "one  two  a < b\n");
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(
+            decode_entities("&lt;tag&gt; &#169; &#x2014; &bogus; R&D"),
+            "<tag> © — &bogus; R&D"
+        );
+    }
+}
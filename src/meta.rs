@@ -0,0 +1,162 @@
+use std::fs::Metadata;
+use std::ops::Add;
+use std::time::UNIX_EPOCH;
+
+/// A file's size on disk, modification time and permissions, as shown with
+/// `--show-meta`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMeta {
+    /// Bytes of allocated blocks, which sparse and compressed files keep below their
+    /// length; the length itself where blocks are not reported
+    pub size_on_disk: u64,
+    /// Seconds since the Unix epoch
+    pub modified: Option<u64>,
+    /// `ls` style, e.g. `rw-r--r--`
+    pub permissions: Option<String>,
+}
+
+impl Add for FileMeta {
+    type Output = Self;
+
+    /// Sizes add up, the modification time is the latest, and permissions are kept
+    /// only when they are the same.
+    fn add(self, other: Self) -> Self {
+        Self {
+            size_on_disk: self.size_on_disk + other.size_on_disk,
+            modified: self.modified.max(other.modified),
+            permissions: self
+                .permissions
+                .filter(|p| Some(p) == other.permissions.as_ref()),
+        }
+    }
+}
+
+impl FileMeta {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_secs());
+        Self {
+            size_on_disk: size_on_disk(metadata),
+            modified,
+            permissions: Some(permissions(metadata)),
+        }
+    }
+
+    /// The modification time in UTC, e.g. `2026-01-31T09:05:00Z`.
+    pub fn modified_utc(&self) -> Option<String> {
+        self.modified.map(format_utc)
+    }
+}
+
+#[cfg(unix)]
+fn size_on_disk(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn size_on_disk(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+#[cfg(unix)]
+fn permissions(metadata: &Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    mode_string(metadata.permissions().mode())
+}
+
+// Without Unix modes, only the read-only flag is known
+#[cfg(not(unix))]
+fn permissions(metadata: &Metadata) -> String {
+    let mode = if metadata.permissions().readonly() {
+        0o444
+    } else {
+        0o666
+    };
+    mode_string(mode)
+}
+
+fn mode_string(mode: u32) -> String {
+    (0..9)
+        .map(|bit| {
+            if mode & (0o400 >> bit) == 0 {
+                '-'
+            } else {
+                ['r', 'w', 'x'][bit % 3]
+            }
+        })
+        .collect()
+}
+
+fn format_utc(seconds: u64) -> String {
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// The proleptic Gregorian date `days` after 1970-01-01 (Howard Hinnant's algorithm)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_modes_like_ls() {
+        assert_eq!(mode_string(0o644), "rw-r--r--");
+        assert_eq!(mode_string(0o100755), "rwxr-xr-x");
+        assert_eq!(mode_string(0), "---------");
+    }
+
+    #[test]
+    fn formats_times_in_utc() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn sums_keep_the_latest_time_and_shared_permissions() {
+        let meta = |size, modified, permissions: &str| FileMeta {
+            size_on_disk: size,
+            modified: Some(modified),
+            permissions: Some(permissions.to_string()),
+        };
+        let total = meta(4096, 10, "rw-r--r--") + meta(8192, 20, "rw-r--r--");
+        assert_eq!(total, meta(12_288, 20, "rw-r--r--"));
+        assert_eq!((total + meta(0, 5, "rwxr-xr-x")).permissions, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_metadata_from_disk() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "hello\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+        let meta = FileMeta::from_metadata(&std::fs::metadata(&path).unwrap());
+        assert_eq!(meta.permissions.as_deref(), Some("rw-r-----"));
+        assert!(meta.modified.is_some());
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// The code of files whose language could not be told reliably, as in ISO 639.
+pub const UNDETERMINED: &str = "und";
+
+/// Files per natural language, as detected with `--detect-language`; a single file has
+/// one language.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NaturalLanguages {
+    /// ISO 639-1 code (`en`, `ja`, ...), or ISO 639-3 for languages without one
+    pub files: BTreeMap<&'static str, usize>,
+}
+
+impl NaturalLanguages {
+    pub fn single(code: &'static str) -> Self {
+        Self {
+            files: BTreeMap::from([(code, 1)]),
+        }
+    }
+
+    /// The languages with their file counts, most files first.
+    pub fn ranked(&self) -> Vec<(&'static str, usize)> {
+        let mut ranked: Vec<_> = self.files.iter().map(|(&code, &n)| (code, n)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked
+    }
+}
+
+impl Add for NaturalLanguages {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        for (code, files) in other.files {
+            *self.files.entry(code).or_default() += files;
+        }
+        self
+    }
+}
+
+impl AddAssign for NaturalLanguages {
+    fn add_assign(&mut self, other: Self) {
+        *self = std::mem::take(self) + other;
+    }
+}
+
+impl Sum for NaturalLanguages {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// The predominant natural language of `content`, or [`UNDETERMINED`] when the
+/// detector is not confident, as with short or mixed text.
+#[cfg(feature = "language-detection")]
+pub fn detect(content: &str) -> &'static str {
+    match whatlang::detect(content) {
+        Some(info) if info.is_reliable() => iso_639_1(info.lang().code()),
+        _ => UNDETERMINED,
+    }
+}
+
+/// The two-letter code for a three-letter one, where there is one.
+#[cfg(feature = "language-detection")]
+fn iso_639_1(code: &'static str) -> &'static str {
+    match code {
+        "afr" => "af",
+        "aka" => "ak",
+        "amh" => "am",
+        "ara" => "ar",
+        "aze" => "az",
+        "bel" => "be",
+        "ben" => "bn",
+        "bul" => "bg",
+        "cat" => "ca",
+        "ces" => "cs",
+        "cmn" => "zh",
+        "dan" => "da",
+        "deu" => "de",
+        "ell" => "el",
+        "eng" => "en",
+        "epo" => "eo",
+        "est" => "et",
+        "fin" => "fi",
+        "fra" => "fr",
+        "guj" => "gu",
+        "heb" => "he",
+        "hin" => "hi",
+        "hrv" => "hr",
+        "hun" => "hu",
+        "hye" => "hy",
+        "ind" => "id",
+        "ita" => "it",
+        "jav" => "jv",
+        "jpn" => "ja",
+        "kan" => "kn",
+        "kat" => "ka",
+        "khm" => "km",
+        "kor" => "ko",
+        "lat" => "la",
+        "lav" => "lv",
+        "lit" => "lt",
+        "mal" => "ml",
+        "mar" => "mr",
+        "mkd" => "mk",
+        "mya" => "my",
+        "nep" => "ne",
+        "nld" => "nl",
+        "nob" => "nb",
+        "ori" => "or",
+        "pan" => "pa",
+        "pes" => "fa",
+        "pol" => "pl",
+        "por" => "pt",
+        "ron" => "ro",
+        "rus" => "ru",
+        "sin" => "si",
+        "slk" => "sk",
+        "slv" => "sl",
+        "sna" => "sn",
+        "spa" => "es",
+        "srp" => "sr",
+        "swe" => "sv",
+        "tam" => "ta",
+        "tel" => "te",
+        "tgl" => "tl",
+        "tha" => "th",
+        "tuk" => "tk",
+        "tur" => "tr",
+        "ukr" => "uk",
+        "urd" => "ur",
+        "uzb" => "uz",
+        "vie" => "vi",
+        "yid" => "yi",
+        "zul" => "zu",
+        _ => code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "language-detection")]
+    #[test]
+    fn detects_common_languages() {
+        assert_eq!(
+            detect("The quick brown fox jumps over the lazy dog while the children watch from the window."),
+            "en"
+        );
+        assert_eq!(
+            detect("Der schnelle braune Fuchs springt über den faulen Hund, während die Kinder aus dem Fenster schauen."),
+            "de"
+        );
+        assert_eq!(
+            detect("素早い茶色の狐が怠け者の犬を飛び越え、子供たちは窓からそれを見ていました。"),
+            "ja"
+        );
+        assert_eq!(detect("ok"), UNDETERMINED);
+    }
+
+    #[test]
+    fn sums_count_files_per_language() {
+        let total: NaturalLanguages = ["ja", "en", "en", UNDETERMINED]
+            .into_iter()
+            .map(NaturalLanguages::single)
+            .sum();
+        assert_eq!(total.ranked(), vec![("en", 2), ("ja", 1), ("und", 1)]);
+    }
+}
@@ -1,11 +1,50 @@
-use crate::cli::Args;
-use crate::counter::{Count, FileEntry};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::analysis::FileAnalysis;
+use crate::counter::{Count, FileEntry, SkipSummary, SkippedFile};
+use crate::languages::{language_of, LanguageSummary, LineKinds};
+
+// Human-readable and report formats driven by command-line options
+#[cfg(feature = "cli")]
+mod text;
+#[cfg(feature = "cli")]
+pub use text::*;
 
 pub enum OutputKind {
     File,
     Directory(usize),
 }
 
+/// A path as shown in output. Windows verbatim paths, which `fs::canonicalize` returns
+/// and long paths need, lose their `\\?\` prefix: `\\?\C:\src` shows as `C:\src` and
+/// `\\?\UNC\server\share` as `\\server\share`.
+pub fn display_path(path: &Path) -> String {
+    strip_verbatim_prefix(&path.to_string_lossy()).into_owned()
+}
+
+fn strip_verbatim_prefix(path: &str) -> Cow<'_, str> {
+    if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        return Cow::Owned(format!(r"\\{share}"));
+    }
+    match path.strip_prefix(r"\\?\") {
+        // Only drive paths; other verbatim paths such as volume GUIDs have no short form
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => Cow::Borrowed(rest),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// What a `write_*` formatter writes, for callers that want the whole output at once
+/// rather than streaming it to stdout.
+pub(crate) fn written(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+    let mut buffer = Vec::new();
+    // Writing to a Vec cannot fail and the formatters only write UTF-8
+    write(&mut buffer).expect("writing to memory failed");
+    String::from_utf8(buffer).expect("formatters write UTF-8")
+}
+
 pub fn format_number(n: usize) -> String {
     n.to_string()
         .as_bytes()
@@ -16,518 +55,569 @@ pub fn format_number(n: usize) -> String {
         .join(",")
 }
 
-fn format_count_lines(count: &Count, args: &Args) -> Vec<String> {
-    let mut lines = Vec::new();
-    if args.show_max_line_length() {
-        lines.push(format!(
-            "Max Line: {:>10}",
-            format_number(count.max_line_length)
-        ));
-    }
-    if args.show_lines() {
-        lines.push(format!("   Lines: {:>10}", format_number(count.lines)));
-    }
-    if args.show_words() {
-        lines.push(format!("   Words: {:>10}", format_number(count.words)));
-    }
-    if args.show_bytes() {
-        lines.push(format!("   Bytes: {:>10}", format_number(count.bytes)));
-    }
-    lines
+// JSON output structures
+pub struct JsonFileResult {
+    pub name: String,
+    pub count: Count,
+    pub is_directory: bool,
+    pub file_count: Option<usize>,
+    /// Files inside a directory that could not be read
+    pub skipped: Vec<SkippedFile>,
+    /// How many entries a directory count left out, by why
+    pub skip_summary: SkipSummary,
+    /// What the analyzers found, summed over a directory
+    pub analysis: FileAnalysis,
 }
 
-fn pluralize_files(count: usize) -> &'static str {
-    if count == 1 {
-        "file"
-    } else {
-        "files"
+/// `,"skipped":[...]` for a directory that left files out, or nothing.
+fn format_skipped_json(skipped: &[SkippedFile]) -> String {
+    if skipped.is_empty() {
+        return String::new();
     }
-}
-
-const FILE_ICON: &str = "\u{1F4C4} ";
-const DIR_ICON: &str = "\u{1F4C1} ";
-
-fn format_header(name: &str, kind: OutputKind, no_color: bool) -> String {
-    match kind {
-        OutputKind::File => {
-            let icon = if no_color { "" } else { FILE_ICON };
-            format!("{icon}{name}")
-        }
-        OutputKind::Directory(file_count) => {
-            let icon = if no_color { "" } else { DIR_ICON };
+    let files: Vec<String> = skipped
+        .iter()
+        .map(|file| {
             format!(
-                "{icon}{name} ({file_count} {})",
-                pluralize_files(file_count)
+                r#"{{"path":"{}","reason":"{}"}}"#,
+                escape_json(&display_path(&file.path)),
+                escape_json(&file.reason)
             )
-        }
-    }
-}
-
-pub fn format_output(name: &str, count: &Count, kind: OutputKind, args: &Args) -> String {
-    let mut output = vec![format_header(name, kind, args.no_color)];
-    output.extend(format_count_lines(count, args));
-    output.join("\n")
+        })
+        .collect();
+    format!(r#","skipped":[{}]"#, files.join(","))
 }
 
-pub fn format_separator() -> &'static str {
-    "─────────────────────────"
+/// `,"skip_summary":{...}` with every reason for a directory that left entries out, or
+/// nothing.
+fn format_skip_summary_json(summary: &SkipSummary) -> String {
+    if summary.is_empty() {
+        return String::new();
+    }
+    format!(
+        r#","skip_summary":{{"hidden":{},"visible":{},"excluded":{},"generated":{},"binary":{},"unreadable":{},"special":{},"symlinks":{},"timed_out":{}}}"#,
+        summary.hidden,
+        summary.visible,
+        summary.excluded,
+        summary.generated,
+        summary.binary,
+        summary.unreadable,
+        summary.special,
+        summary.symlinks,
+        summary.timed_out
+    )
 }
 
-fn format_compact_counts(count: &Count, args: &Args) -> String {
-    let mut parts = Vec::new();
-    if args.show_max_line_length() {
-        parts.push(format!("max:{}", format_number(count.max_line_length)));
+/// `,"markdown":{...}`, `,"symbols":{...}` and so on for the analyses present, or
+/// nothing.
+fn format_analysis_json(analysis: &FileAnalysis) -> String {
+    let mut json = String::new();
+    if let Some(stats) = analysis.markdown {
+        json.push_str(&format!(
+            r#","markdown":{{"words":{},"headings":{},"links":{},"images":{},"code_blocks":{}}}"#,
+            stats.words, stats.headings, stats.links, stats.images, stats.code_blocks
+        ));
     }
-    if args.show_lines() {
-        parts.push(format!("{} lines", format_number(count.lines)));
+    if let Some(symbols) = analysis.symbols {
+        json.push_str(&format!(
+            r#","symbols":{{"functions":{},"types":{},"tests":{}}}"#,
+            symbols.functions, symbols.types, symbols.tests
+        ));
     }
-    if args.show_words() {
-        parts.push(format!("{} words", format_number(count.words)));
+    if let Some(complexity) = analysis.complexity {
+        json.push_str(&format!(r#","complexity":{complexity}"#));
     }
-    if args.show_bytes() {
-        parts.push(format!("{} bytes", format_number(count.bytes)));
+    if let Some(records) = analysis.records {
+        json.push_str(&format!(
+            r#","records":{{"count":{},"invalid":{}}}"#,
+            records.records, records.invalid
+        ));
     }
-    parts.join(", ")
-}
-
-pub fn format_compact_output(name: &str, count: &Count, kind: OutputKind, args: &Args) -> String {
-    let header = match kind {
-        OutputKind::File => format!("{name}:"),
-        OutputKind::Directory(file_count) => {
-            format!("{name} ({file_count} {}): ", pluralize_files(file_count))
-        }
-    };
-    format!("{header} {}", format_compact_counts(count, args))
-}
-
-pub fn format_compact_total(file_count: usize, count: &Count, args: &Args) -> String {
-    format!(
-        "Total ({} {}): {}",
-        file_count,
-        pluralize_files(file_count),
-        format_compact_counts(count, args)
-    )
-}
-
-fn format_single_count(count: &Count, args: &Args) -> String {
-    let (value, unit) = match (args.lines, args.words, args.bytes, args.max_line_length) {
-        (false, true, false, false) => (count.words, "words"),
-        (false, false, true, false) => (count.bytes, "bytes"),
-        (false, false, false, true) => (count.max_line_length, "max"),
-        _ => (count.lines, "lines"),
-    };
-    format!("{} {unit}", format_number(value))
-}
-
-fn format_verbose_entry(entry: &FileEntry, args: &Args) -> String {
-    let icon = if args.no_color { "" } else { FILE_ICON };
-    format!(
-        "{icon}{}  {}",
-        entry.path.display(),
-        format_single_count(&entry.count, args)
-    )
-}
-
-pub fn format_verbose_output(entries: &[FileEntry], total: &Count, args: &Args) -> String {
-    let mut lines: Vec<String> = entries
-        .iter()
-        .map(|e| format_verbose_entry(e, args))
-        .collect();
-
-    lines.push(format_separator().to_string());
-
-    let icon = if args.no_color { "" } else { DIR_ICON };
-    let file_count = entries.len();
-    lines.push(format!(
-        "{icon}Total ({file_count} {})  {}",
-        pluralize_files(file_count),
-        format_single_count(total, args)
-    ));
-
-    lines.join("\n")
-}
-
-// JSON output structures
-pub struct JsonFileResult {
-    pub name: String,
-    pub count: Count,
-    pub is_directory: bool,
-    pub file_count: Option<usize>,
+    if let Some(levels) = analysis.log_levels {
+        json.push_str(&format!(
+            r#","log_levels":{{"error":{},"warn":{},"info":{},"debug":{}}}"#,
+            levels.error, levels.warn, levels.info, levels.debug
+        ));
+    }
+    if let Some(structure) = analysis.structure {
+        json.push_str(&format!(
+            r#","structure":{{"keys":{},"max_depth":{},"arrays":{},"max_array_len":{}}}"#,
+            structure.keys, structure.max_depth, structure.arrays, structure.max_array_len
+        ));
+    }
+    if let Some(dedupe) = &analysis.dedupe_lines {
+        let most_repeated = match &dedupe.most_repeated {
+            Some((line, count)) => {
+                format!(r#"{{"line":"{}","count":{count}}}"#, escape_json(line))
+            }
+            None => "null".to_string(),
+        };
+        json.push_str(&format!(
+            r#","dedupe_lines":{{"unique":{},"duplicates":{},"most_repeated":{most_repeated}}}"#,
+            dedupe.unique, dedupe.duplicates
+        ));
+    }
+    if let Some(histogram) = &analysis.entropy {
+        json.push_str(&format!(r#","entropy":{:.3}"#, histogram.bits_per_byte()));
+    }
+    if let Some(checksum) = &analysis.hash {
+        json.push_str(&format!(
+            r#","{}":"{}""#,
+            checksum.algorithm.name(),
+            checksum.hex
+        ));
+    }
+    if let Some(meta) = &analysis.meta {
+        let optional = |value: Option<String>| {
+            value.map_or("null".to_string(), |value| format!(r#""{value}""#))
+        };
+        json.push_str(&format!(
+            r#","meta":{{"size_on_disk":{},"modified":{},"permissions":{}}}"#,
+            meta.size_on_disk,
+            optional(meta.modified_utc()),
+            optional(meta.permissions.clone())
+        ));
+    }
+    if let Some(languages) = &analysis.natural_language {
+        let files: Vec<String> = languages
+            .ranked()
+            .iter()
+            .map(|(code, files)| format!(r#""{code}":{files}"#))
+            .collect();
+        json.push_str(&format!(r#","natural_languages":{{{}}}"#, files.join(",")));
+    }
+    if let Some(vocabulary) = &analysis.vocabulary {
+        json.push_str(&format!(
+            r#","vocabulary":{{"words":{},"distinct":{},"hapax":{},"type_token_ratio":{:.3}}}"#,
+            vocabulary.words(),
+            vocabulary.distinct(),
+            vocabulary.hapax(),
+            vocabulary.type_token_ratio()
+        ));
+    }
+    if let Some(dialogue) = analysis.dialogue {
+        let share = dialogue
+            .share()
+            .map_or("null".to_string(), |share| format!("{share:.3}"));
+        json.push_str(&format!(
+            r#","dialogue":{{"quotes":{},"quoted_words":{},"words":{},"share":{share}}}"#,
+            dialogue.quotes, dialogue.quoted_words, dialogue.words
+        ));
+    }
+    if let Some(pages) = analysis.pages {
+        json.push_str(&format!(r#","pages":{pages}"#));
+    }
+    if let Some(subtitles) = analysis.subtitles {
+        let wpm = subtitles
+            .words_per_minute()
+            .map_or("null".to_string(), |wpm| format!("{wpm:.1}"));
+        json.push_str(&format!(
+            r#","subtitles":{{"cues":{},"words":{},"duration_ms":{},"words_per_minute":{wpm}}}"#,
+            subtitles.cues, subtitles.words, subtitles.duration_ms
+        ));
+    }
+    if let Some(po) = analysis.po {
+        json.push_str(&format!(
+            r#","po":{{"translated":{},"fuzzy":{},"untranslated":{},"source_words":{},"target_words":{}}}"#,
+            po.translated, po.fuzzy, po.untranslated, po.source_words, po.target_words
+        ));
+    }
+    if let Some(i18n) = &analysis.i18n {
+        let missing: Vec<String> = i18n
+            .missing
+            .iter()
+            .map(|key| format!("\"{}\"", escape_json(key)))
+            .collect();
+        json.push_str(&format!(
+            r#","i18n":{{"strings":{},"words":{},"missing":[{}]}}"#,
+            i18n.strings,
+            i18n.words,
+            missing.join(",")
+        ));
+    }
+    if let Some(ratios) = analysis.ratios {
+        let ratio = |ratio: Option<f64>| ratio.map_or("null".to_string(), |r| format!("{r:.3}"));
+        json.push_str(&format!(
+            r#","words_per_line":{},"bytes_per_line":{}"#,
+            ratio(ratios.words_per_line()),
+            ratio(ratios.bytes_per_line())
+        ));
+    }
+    if let Some(chapters) = &analysis.chapters {
+        let chapters: Vec<String> = chapters
+            .words
+            .iter()
+            .map(|(name, words)| format!(r#"{{"name":"{}","words":{words}}}"#, escape_json(name)))
+            .collect();
+        json.push_str(&format!(r#","chapters":[{}]"#, chapters.join(",")));
+    }
+    if let Some(lines) = analysis.line_kinds {
+        let ratio = lines
+            .comment_ratio()
+            .map_or("null".to_string(), |ratio| format!("{ratio:.3}"));
+        json.push_str(&format!(
+            r#","line_kinds":{{"blank":{},"comment":{},"code":{},"comment_ratio":{ratio}}}"#,
+            lines.blank, lines.comment, lines.code
+        ));
+    }
+    json
 }
 
 pub fn format_json_single(result: &JsonFileResult) -> String {
     if result.is_directory {
         format!(
-            r#"{{"directory":"{}","file_count":{},"max_line_length":{},"lines":{},"words":{},"bytes":{}}}"#,
+            r#"{{"directory":"{}","file_count":{},"max_line_length":{},"lines":{},"words":{},"bytes":{}{}{}{}}}"#,
             escape_json(&result.name),
             result.file_count.unwrap_or(0),
             result.count.max_line_length,
             result.count.lines,
             result.count.words,
-            result.count.bytes
+            result.count.bytes,
+            format_analysis_json(&result.analysis),
+            format_skipped_json(&result.skipped),
+            format_skip_summary_json(&result.skip_summary)
         )
     } else {
         format!(
-            r#"{{"file":"{}","max_line_length":{},"lines":{},"words":{},"bytes":{}}}"#,
+            r#"{{"file":"{}","max_line_length":{},"lines":{},"words":{},"bytes":{}{}}}"#,
             escape_json(&result.name),
             result.count.max_line_length,
             result.count.lines,
             result.count.words,
-            result.count.bytes
+            result.count.bytes,
+            format_analysis_json(&result.analysis)
         )
     }
 }
 
 pub fn format_json_multiple(results: &[JsonFileResult], total: &Count) -> String {
-    let files_json: Vec<String> = results.iter().map(format_json_single).collect();
+    written(|out| write_json_multiple(out, results, total))
+}
+
+/// The JSON of [`format_json_multiple`] for a run `--timeout` gave up on: the paths
+/// counted before it got stuck, marked with `"timed_out":true`.
+pub fn format_json_timed_out(results: &[JsonFileResult], total: &Count) -> String {
+    let mut json = format_json_multiple(results, total);
+    json.pop();
+    json.push_str(r#","timed_out":true}"#);
+    json
+}
+
+/// Writes the JSON of [`format_json_multiple`] one file at a time.
+pub fn write_json_multiple(
+    out: &mut impl Write,
+    results: &[JsonFileResult],
+    total: &Count,
+) -> io::Result<()> {
+    out.write_all(br#"{"files":["#)?;
+    for (index, result) in results.iter().enumerate() {
+        if index > 0 {
+            out.write_all(b",")?;
+        }
+        out.write_all(format_json_single(result).as_bytes())?;
+    }
     let total_file_count: usize = results.iter().map(|r| r.file_count.unwrap_or(1)).sum();
+    let analysis: FileAnalysis = results.iter().map(|r| &r.analysis).sum();
 
-    format!(
-        r#"{{"files":[{}],"total":{{"file_count":{},"max_line_length":{},"lines":{},"words":{},"bytes":{}}}}}"#,
-        files_json.join(","),
+    write!(
+        out,
+        r#"],"total":{{"file_count":{},"max_line_length":{},"lines":{},"words":{},"bytes":{}{}}}}}"#,
         total_file_count,
         total.max_line_length,
         total.lines,
         total.words,
-        total.bytes
+        total.bytes,
+        format_analysis_json(&analysis)
     )
 }
 
-fn escape_json(s: &str) -> String {
+/// JSON for `--list`: the files that would be counted.
+pub fn format_file_list_json(files: &[PathBuf]) -> String {
+    let names: Vec<String> = files
+        .iter()
+        .map(|file| format!(r#""{}""#, escape_json(&display_path(file))))
+        .collect();
+    format!(
+        r#"{{"files":[{}],"file_count":{}}}"#,
+        names.join(","),
+        files.len()
+    )
+}
+
+/// The `snake_case` name of an I/O error kind, e.g. `not_found` or `permission_denied`.
+pub fn error_kind_name(kind: io::ErrorKind) -> String {
+    let mut name = String::new();
+    for c in format!("{kind:?}").chars() {
+        if c.is_ascii_uppercase() && !name.is_empty() {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+/// One `--errors json` line; `path` is `null` for errors that are not about a file.
+pub fn format_error_json(path: Option<&str>, kind: &str, message: &str) -> String {
+    let path = match path {
+        Some(path) => format!(r#""{}""#, escape_json(path)),
+        None => "null".to_string(),
+    };
+    format!(
+        r#"{{"path":{path},"kind":"{}","message":"{}"}}"#,
+        escape_json(kind),
+        escape_json(message)
+    )
+}
+
+fn escape_prometheus_label(s: &str) -> String {
     s.replace('\\', "\\\\")
         .replace('"', "\\\"")
         .replace('\n', "\\n")
-        .replace('\r', "\\r")
-        .replace('\t', "\\t")
 }
 
-pub fn format_total_output(file_count: usize, count: &Count, args: &Args) -> String {
-    let icon = if args.no_color { "" } else { DIR_ICON };
-    let header = format!("{icon}Total ({file_count} {})", pluralize_files(file_count));
-    let mut output = vec![header];
-    output.extend(format_count_lines(count, args));
-    output.join("\n")
-}
+type PrometheusMetric = (&'static str, &'static str, fn(&JsonFileResult) -> usize);
+
+pub fn format_prometheus(results: &[JsonFileResult]) -> String {
+    let metrics: [PrometheusMetric; 5] = [
+        ("ewc_files_total", "Number of files counted", |r| {
+            r.file_count.unwrap_or(1)
+        }),
+        ("ewc_lines_total", "Number of lines", |r| r.count.lines),
+        ("ewc_words_total", "Number of words", |r| r.count.words),
+        ("ewc_bytes_total", "Number of bytes", |r| r.count.bytes),
+        ("ewc_max_line_length", "Length of the longest line", |r| {
+            r.count.max_line_length
+        }),
+    ];
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn default_args() -> Args {
-        Args {
-            files: vec![],
-            lines: false,
-            words: false,
-            bytes: false,
-            max_line_length: false,
-            no_color: false,
-            all: false,
-            compact: false,
-            verbose: false,
-            json: false,
-            exclude: vec![],
-            include: vec![],
+    let mut lines = Vec::new();
+    for (name, help, value) in metrics {
+        lines.push(format!("# HELP {name} {help}"));
+        lines.push(format!("# TYPE {name} gauge"));
+        for result in results {
+            lines.push(format!(
+                r#"{name}{{path="{}"}} {}"#,
+                escape_prometheus_label(&result.name),
+                value(result)
+            ));
         }
     }
+    lines.join("\n")
+}
 
-    #[test]
-    fn format_number_without_comma() {
-        assert_eq!(format_number(123), "123");
+pub(crate) fn escape_csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
     }
+}
+
+/// One row per file. With `--hash`, `analyses` (one per entry) adds a column named
+/// after the algorithm, with `--show-meta` the size on disk, modification time and
+/// permissions columns, and with `--ratios` the words and bytes per line.
+pub fn format_csv(entries: &[FileEntry], analyses: &[FileAnalysis]) -> String {
+    written(|out| write_csv(out, entries, analyses))
+}
 
-    #[test]
-    fn format_number_with_comma() {
-        assert_eq!(format_number(1234), "1,234");
+/// Writes the CSV of [`format_csv`] one row at a time.
+pub fn write_csv(
+    out: &mut impl Write,
+    entries: &[FileEntry],
+    analyses: &[FileAnalysis],
+) -> io::Result<()> {
+    let algorithm = analyses
+        .iter()
+        .find_map(|analysis| analysis.hash.as_ref())
+        .map(|checksum| checksum.algorithm);
+    let meta = analyses.iter().any(|analysis| analysis.meta.is_some());
+    let ratios = analyses.iter().any(|analysis| analysis.ratios.is_some());
+    let mut header = "path,lines,words,bytes,max_line_length".to_string();
+    if let Some(algorithm) = algorithm {
+        header.push(',');
+        header.push_str(algorithm.name());
+    }
+    if meta {
+        header.push_str(",size_on_disk,modified,permissions");
+    }
+    if ratios {
+        header.push_str(",words_per_line,bytes_per_line");
+    }
+    out.write_all(header.as_bytes())?;
+    for (index, entry) in entries.iter().enumerate() {
+        let mut line = format!(
+            "{},{},{},{},{}",
+            escape_csv_field(&display_path(&entry.path)),
+            entry.count.lines,
+            entry.count.words,
+            entry.count.bytes,
+            entry.count.max_line_length
+        );
+        if algorithm.is_some() {
+            line.push(',');
+            if let Some(checksum) = analyses.get(index).and_then(|a| a.hash.as_ref()) {
+                line.push_str(&checksum.hex);
+            }
+        }
+        if meta {
+            match analyses.get(index).and_then(|a| a.meta.as_ref()) {
+                Some(meta) => line.push_str(&format!(
+                    ",{},{},{}",
+                    meta.size_on_disk,
+                    meta.modified_utc().unwrap_or_default(),
+                    meta.permissions.as_deref().unwrap_or_default()
+                )),
+                None => line.push_str(",,,"),
+            }
+        }
+        if ratios {
+            let ratios = analyses.get(index).and_then(|a| a.ratios);
+            for ratio in [
+                ratios.and_then(|r| r.words_per_line()),
+                ratios.and_then(|r| r.bytes_per_line()),
+            ] {
+                line.push(',');
+                line.push_str(&ratio.map(|r| format!("{r:.3}")).unwrap_or_default());
+            }
+        }
+        write!(out, "\n{line}")?;
     }
+    Ok(())
+}
 
-    #[test]
-    fn format_number_large() {
-        assert_eq!(format_number(1234567), "1,234,567");
+/// The first line of cloc-style output, where cloc names itself and its version.
+fn cloc_banner() -> String {
+    format!("ewc v {}", env!("CARGO_PKG_VERSION"))
+}
+
+fn cloc_total(summaries: &[LanguageSummary]) -> (usize, LineKinds) {
+    (
+        summaries.iter().map(|s| s.files).sum(),
+        summaries.iter().map(|s| s.lines).sum(),
+    )
+}
+
+/// cloc's summary table: files, blank, comment and code lines per language, most code
+/// first, with a `SUM:` row when there is more than one language.
+pub fn format_cloc(summaries: &[LanguageSummary]) -> String {
+    let rule = "-".repeat(79);
+    let row = |name: &str, files: usize, lines: LineKinds| {
+        format!(
+            "{name:<28}{files:>6}{:>15}{:>15}{:>15}",
+            lines.blank, lines.comment, lines.code
+        )
+    };
+    let mut lines = vec![
+        cloc_banner(),
+        rule.clone(),
+        format!(
+            "{:<28}{:>6}{:>15}{:>15}{:>15}",
+            "Language", "files", "blank", "comment", "code"
+        ),
+        rule.clone(),
+    ];
+    lines.extend(summaries.iter().map(|s| row(s.name, s.files, s.lines)));
+    lines.push(rule.clone());
+    if summaries.len() > 1 {
+        let (files, total) = cloc_total(summaries);
+        lines.push(row("SUM:", files, total));
+        lines.push(rule);
     }
+    lines.join("\n")
+}
 
-    #[test]
-    fn format_file_output_all_counts() {
-        let count = Count {
-            lines: 50,
-            words: 200,
-            bytes: 1500,
-            max_line_length: 80,
-        };
-        let args = default_args();
-        let output = format_output("file.txt", &count, OutputKind::File, &args);
-        assert!(output.contains("file.txt"));
-        assert!(output.contains("Lines:"));
-        assert!(output.contains("50"));
-        assert!(output.contains("Words:"));
-        assert!(output.contains("200"));
-        assert!(output.contains("Bytes:"));
-        assert!(output.contains("1,500"));
-    }
-
-    #[test]
-    fn format_file_output_lines_only() {
-        let count = Count {
-            lines: 50,
-            words: 200,
-            bytes: 1500,
-            max_line_length: 80,
-        };
-        let args = Args {
-            lines: true,
-            ..default_args()
-        };
-        let output = format_output("file.txt", &count, OutputKind::File, &args);
-        assert!(output.contains("Lines:"));
-        assert!(!output.contains("Words:"));
-        assert!(!output.contains("Bytes:"));
-    }
-
-    #[test]
-    fn format_separator_test() {
-        let sep = format_separator();
-        assert!(sep.contains("─"));
-        assert_eq!(sep.chars().count(), 25);
-    }
-
-    #[test]
-    fn format_total_output_all_counts() {
-        let count = Count {
-            lines: 80,
-            words: 300,
-            bytes: 2300,
-            max_line_length: 120,
-        };
-        let args = default_args();
-        let output = format_total_output(2, &count, &args);
-        assert!(output.contains("Total (2 files)"));
-        assert!(output.contains("Lines:"));
-        assert!(output.contains("80"));
-        assert!(output.contains("Words:"));
-        assert!(output.contains("300"));
-        assert!(output.contains("Bytes:"));
-        assert!(output.contains("2,300"));
-    }
-
-    #[test]
-    fn format_total_pluralization() {
-        let count = Count::default();
-        let args = default_args();
-        let output_single = format_total_output(1, &count, &args);
-        assert!(output_single.contains("1 file)"));
-
-        let output_plural = format_total_output(2, &count, &args);
-        assert!(output_plural.contains("2 files)"));
-    }
-
-    #[test]
-    fn format_total_output_lines_only() {
-        let count = Count {
-            lines: 80,
-            words: 300,
-            bytes: 2300,
-            max_line_length: 120,
-        };
-        let args = Args {
-            lines: true,
-            ..default_args()
-        };
-        let output = format_total_output(2, &count, &args);
-        assert!(output.contains("Lines:"));
-        assert!(!output.contains("Words:"));
-        assert!(!output.contains("Bytes:"));
-    }
-
-    #[test]
-    fn format_directory_output_all_counts() {
-        let count = Count {
-            lines: 1234,
-            words: 5678,
-            bytes: 45000,
-            max_line_length: 200,
-        };
-        let args = default_args();
-        let output = format_output("src/", &count, OutputKind::Directory(5), &args);
-        assert!(output.contains("\u{1F4C1} src/ (5 files)"));
-        assert!(output.contains("Lines:"));
-        assert!(output.contains("1,234"));
-        assert!(output.contains("Words:"));
-        assert!(output.contains("5,678"));
-        assert!(output.contains("Bytes:"));
-        assert!(output.contains("45,000"));
-    }
-
-    #[test]
-    fn format_directory_output_single_file() {
-        let count = Count {
-            lines: 10,
-            words: 20,
-            bytes: 100,
-            max_line_length: 50,
-        };
-        let args = default_args();
-        let output = format_output("dir/", &count, OutputKind::Directory(1), &args);
-        assert!(output.contains("\u{1F4C1} dir/ (1 file)"));
-    }
-
-    #[test]
-    fn format_output_without_icons() {
-        let count = Count {
-            lines: 50,
-            words: 200,
-            bytes: 1500,
-            max_line_length: 80,
-        };
-        let args = Args {
-            no_color: true,
-            ..default_args()
-        };
-        let output = format_output("file.txt", &count, OutputKind::File, &args);
-        assert!(!output.contains("\u{1F4C4}")); // No file icon
-        assert!(output.contains("file.txt"));
-    }
-
-    #[test]
-    fn format_directory_output_without_icons() {
-        let count = Count {
-            lines: 50,
-            words: 200,
-            bytes: 1500,
-            max_line_length: 80,
-        };
-        let args = Args {
-            no_color: true,
-            ..default_args()
-        };
-        let output = format_output("src/", &count, OutputKind::Directory(3), &args);
-        assert!(!output.contains("\u{1F4C1}")); // No folder icon
-        assert!(output.contains("src/"));
-    }
-
-    #[test]
-    fn format_total_output_without_icons() {
-        let count = Count {
-            lines: 80,
-            words: 300,
-            bytes: 2300,
-            max_line_length: 120,
-        };
-        let args = Args {
-            no_color: true,
-            ..default_args()
-        };
-        let output = format_total_output(2, &count, &args);
-        assert!(!output.contains("\u{1F4C1}")); // No folder icon
-        assert!(output.contains("Total (2 files)"));
-    }
-
-    #[test]
-    fn format_compact_output_all_counts() {
-        let count = Count {
-            lines: 50,
-            words: 200,
-            bytes: 1500,
-            max_line_length: 80,
-        };
-        let args = Args {
-            compact: true,
-            ..default_args()
-        };
-        let output = format_compact_output("file.txt", &count, OutputKind::File, &args);
-        assert!(output.contains("file.txt:"));
-        assert!(output.contains("50 lines"));
-        assert!(output.contains("200 words"));
-        assert!(output.contains("1,500 bytes"));
-        assert_eq!(output.lines().count(), 1);
-    }
-
-    #[test]
-    fn format_compact_output_lines_only() {
-        let count = Count {
-            lines: 50,
-            words: 200,
-            bytes: 1500,
-            max_line_length: 80,
-        };
-        let args = Args {
-            lines: true,
-            compact: true,
-            ..default_args()
-        };
-        let output = format_compact_output("file.txt", &count, OutputKind::File, &args);
-        assert!(output.contains("50 lines"));
-        assert!(!output.contains("words"));
-        assert!(!output.contains("bytes"));
-    }
-
-    #[test]
-    fn format_compact_directory() {
-        let count = Count {
-            lines: 150,
-            words: 500,
-            bytes: 3000,
-            max_line_length: 100,
-        };
-        let args = Args {
-            compact: true,
-            ..default_args()
-        };
-        let output = format_compact_output("src/", &count, OutputKind::Directory(3), &args);
-        assert!(output.contains("src/ (3 files):"));
-        assert!(output.contains("150 lines"));
-    }
-
-    #[test]
-    fn format_compact_total_output() {
-        let count = Count {
-            lines: 235,
-            words: 800,
-            bytes: 5000,
-            max_line_length: 150,
-        };
-        let args = Args {
-            compact: true,
-            ..default_args()
-        };
-        let output = format_compact_total(5, &count, &args);
-        assert!(output.contains("Total (5 files):"));
-        assert!(output.contains("235 lines"));
-    }
-
-    #[test]
-    fn format_output_max_line_length_only() {
-        let count = Count {
-            lines: 50,
-            words: 200,
-            bytes: 1500,
-            max_line_length: 120,
-        };
-        let args = Args {
-            max_line_length: true,
-            ..default_args()
-        };
-        let output = format_output("file.txt", &count, OutputKind::File, &args);
-        assert!(output.contains("Max Line:"));
-        assert!(output.contains("120"));
-        assert!(!output.contains("Lines:"));
-        assert!(!output.contains("Words:"));
-        assert!(!output.contains("Bytes:"));
-    }
-
-    #[test]
-    fn format_compact_with_max_line_length() {
-        let count = Count {
-            lines: 50,
-            words: 200,
-            bytes: 1500,
-            max_line_length: 120,
-        };
-        let args = Args {
-            max_line_length: true,
-            compact: true,
-            ..default_args()
-        };
-        let output = format_compact_output("file.txt", &count, OutputKind::File, &args);
-        assert!(output.contains("max:120"));
-        assert!(!output.contains("lines"));
+/// cloc's `--csv` layout of the same summary, with a `SUM` row.
+pub fn format_cloc_csv(summaries: &[LanguageSummary]) -> String {
+    let mut lines = vec![format!(
+        "files,language,blank,comment,code,\"{}\"",
+        cloc_banner()
+    )];
+    let row = |name: &str, files: usize, lines: LineKinds| {
+        format!(
+            "{files},{},{},{},{}",
+            escape_csv_field(name),
+            lines.blank,
+            lines.comment,
+            lines.code
+        )
+    };
+    lines.extend(summaries.iter().map(|s| row(s.name, s.files, s.lines)));
+    let (files, total) = cloc_total(summaries);
+    lines.push(row("SUM", files, total));
+    lines.join("\n")
+}
+
+/// tokei's `--output json` layout: per language its line counts and one report per file,
+/// then a `Total` holding every report under its language in `children`. `analyses`
+/// holds each entry's analysis in the same order; entries without line kinds are left
+/// out.
+pub fn format_tokei_json(entries: &[FileEntry], analyses: &[FileAnalysis]) -> String {
+    let mut languages: BTreeMap<&str, Vec<(String, LineKinds)>> = BTreeMap::new();
+    for (entry, analysis) in entries.iter().zip(analyses) {
+        if let (Some(language), Some(lines)) = (language_of(&entry.path), analysis.line_kinds) {
+            languages
+                .entry(language.tokei_name)
+                .or_default()
+                .push((display_path(&entry.path), lines));
+        }
+    }
+    let stats = |lines: LineKinds| {
+        format!(
+            r#""blanks":{},"code":{},"comments":{}"#,
+            lines.blank, lines.code, lines.comment
+        )
+    };
+    let reports = |files: &[(String, LineKinds)]| {
+        files
+            .iter()
+            .map(|(name, lines)| {
+                format!(
+                    r#"{{"stats":{{{},"blobs":{{}}}},"name":"{}"}}"#,
+                    stats(*lines),
+                    escape_json(name)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let sum = |files: &[(String, LineKinds)]| files.iter().map(|(_, lines)| *lines).sum();
+
+    let mut parts: Vec<String> = languages
+        .iter()
+        .map(|(name, files)| {
+            format!(
+                r#""{name}":{{{},"reports":[{}],"children":{{}},"inaccurate":false}}"#,
+                stats(sum(files)),
+                reports(files)
+            )
+        })
+        .collect();
+    let children: Vec<String> = languages
+        .iter()
+        .map(|(name, files)| format!(r#""{name}":[{}]"#, reports(files)))
+        .collect();
+    let total = languages.values().map(|files| sum(files)).sum();
+    parts.push(format!(
+        r#""Total":{{{},"reports":[],"children":{{{}}},"inaccurate":false}}"#,
+        stats(total),
+        children.join(",")
+    ));
+    format!("{{{}}}", parts.join(","))
+}
+
+/// Text as the inside of a JSON string; control characters other than the common
+/// whitespace ones, such as the escape of ANSI colour codes, as `\u00XX`.
+pub(crate) fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c < ' ' => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
     }
+    escaped
 }
@@ -0,0 +1,3424 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::analysis::FileAnalysis;
+use crate::authors::AuthorLines;
+use crate::budget::{Metric, Threshold, Violation};
+use crate::cli::Args;
+use crate::cocomo::{Estimate, ProjectType};
+use crate::compare::TreeComparison;
+use crate::counter::{Count, FileEntry, SkipSummary, SkippedFile};
+use crate::delta::{ChangeKind, CountDelta, EntryChange, ReportDelta};
+use crate::git::FileDiff;
+use crate::history::HistoryPoint;
+use crate::report::ReportEntry;
+use crate::xlsx::{write_workbook, Cell, Sheet};
+
+use super::{
+    display_path, escape_csv_field, escape_json, format_number, written, JsonFileResult, OutputKind,
+};
+
+fn format_count_lines(count: &Count, args: &Args) -> Vec<String> {
+    let mut lines = Vec::new();
+    if args.show_max_line_length() {
+        lines.push(format!(
+            "Max Line: {:>10}",
+            format_number(count.max_line_length)
+        ));
+    }
+    if args.show_lines() {
+        lines.push(format!("   Lines: {:>10}", format_number(count.lines)));
+    }
+    if args.show_words() {
+        lines.push(format!("   Words: {:>10}", format_number(count.words)));
+    }
+    if args.show_bytes() {
+        lines.push(format!("   Bytes: {:>10}", format_number(count.bytes)));
+    }
+    lines
+}
+
+fn pluralize_files(count: usize) -> &'static str {
+    if count == 1 {
+        "file"
+    } else {
+        "files"
+    }
+}
+
+const FILE_ICON: &str = "\u{1F4C4} ";
+
+const DIR_ICON: &str = "\u{1F4C1} ";
+
+fn format_header(name: &str, kind: OutputKind, no_color: bool) -> String {
+    match kind {
+        OutputKind::File => {
+            let icon = if no_color { "" } else { FILE_ICON };
+            format!("{icon}{name}")
+        }
+        OutputKind::Directory(file_count) => {
+            let icon = if no_color { "" } else { DIR_ICON };
+            format!(
+                "{icon}{name} ({file_count} {})",
+                pluralize_files(file_count)
+            )
+        }
+    }
+}
+
+pub fn format_output(name: &str, count: &Count, kind: OutputKind, args: &Args) -> String {
+    let mut output = vec![format_header(name, kind, args.no_color)];
+    output.extend(format_count_lines(count, args));
+    output.join("\n")
+}
+
+/// Each analysis value with its row label and its unit on a compact line.
+fn analysis_values(analysis: &FileAnalysis) -> Vec<(&'static str, &'static str, usize)> {
+    let mut values = Vec::new();
+    if let Some(stats) = analysis.markdown {
+        values.extend([
+            ("   Prose", "prose words", stats.words),
+            ("Headings", "headings", stats.headings),
+            ("   Links", "links", stats.links),
+            ("  Images", "images", stats.images),
+            ("  Fences", "code blocks", stats.code_blocks),
+        ]);
+    }
+    if let Some(symbols) = analysis.symbols {
+        values.extend([
+            ("   Funcs", "functions", symbols.functions),
+            ("   Types", "types", symbols.types),
+            ("   Tests", "tests", symbols.tests),
+        ]);
+    }
+    if let Some(complexity) = analysis.complexity {
+        values.push(("Branches", "branches", complexity));
+    }
+    if let Some(records) = analysis.records {
+        values.extend([
+            (" Records", "records", records.records),
+            (" Invalid", "invalid", records.invalid),
+        ]);
+    }
+    if let Some(levels) = analysis.log_levels {
+        values.extend([
+            ("   Error", "errors", levels.error),
+            ("    Warn", "warnings", levels.warn),
+            ("    Info", "info", levels.info),
+            ("   Debug", "debug", levels.debug),
+        ]);
+    }
+    if let Some(structure) = analysis.structure {
+        values.extend([
+            ("    Keys", "keys", structure.keys),
+            ("   Depth", "max depth", structure.max_depth),
+            ("  Arrays", "arrays", structure.arrays),
+            (" Longest", "longest array", structure.max_array_len),
+        ]);
+    }
+    if let Some(dedupe) = &analysis.dedupe_lines {
+        values.extend([
+            ("  Unique", "unique lines", dedupe.unique),
+            ("Repeated", "repeated lines", dedupe.duplicates),
+        ]);
+    }
+    if let Some(meta) = &analysis.meta {
+        values.push(("    Disk", "bytes on disk", meta.size_on_disk as usize));
+    }
+    if let Some(vocabulary) = &analysis.vocabulary {
+        values.extend([
+            ("Distinct", "distinct words", vocabulary.distinct()),
+            ("   Hapax", "hapax", vocabulary.hapax()),
+        ]);
+    }
+    if let Some(dialogue) = analysis.dialogue {
+        values.extend([
+            ("  Quotes", "quotes", dialogue.quotes),
+            ("  Quoted", "quoted words", dialogue.quoted_words),
+        ]);
+    }
+    if let Some(pages) = analysis.pages {
+        values.push(("   Pages", "pages", pages));
+    }
+    if let Some(subtitles) = analysis.subtitles {
+        values.extend([
+            ("    Cues", "cues", subtitles.cues),
+            ("Subtitle", "subtitle words", subtitles.words),
+        ]);
+    }
+    if let Some(po) = analysis.po {
+        values.extend([
+            ("    Done", "translated", po.translated),
+            ("   Fuzzy", "fuzzy", po.fuzzy),
+            ("    Todo", "untranslated", po.untranslated),
+            ("  Source", "source words", po.source_words),
+            ("  Target", "target words", po.target_words),
+        ]);
+    }
+    if let Some(i18n) = &analysis.i18n {
+        values.extend([
+            (" Strings", "strings", i18n.strings),
+            ("    Text", "words in strings", i18n.words),
+            (" Missing", "missing keys", i18n.missing.len()),
+        ]);
+    }
+    if let Some(chapters) = &analysis.chapters {
+        values.push(("Chapters", "chapters", chapters.words.len()));
+    }
+    if let Some(lines) = analysis.line_kinds {
+        values.extend([
+            ("   Blank", "blank lines", lines.blank),
+            ("Comments", "comment lines", lines.comment),
+            ("    Code", "code lines", lines.code),
+        ]);
+    }
+    values
+}
+
+const MAX_LINE_PREVIEW: usize = 60;
+
+/// The `--dedupe-lines` most repeated line, trimmed and shortened to fit on one row.
+fn most_repeated(analysis: &FileAnalysis) -> Option<(String, usize)> {
+    let (line, count) = analysis.dedupe_lines.as_ref()?.most_repeated.as_ref()?;
+    let line = line.trim();
+    let preview = if line.chars().count() > MAX_LINE_PREVIEW {
+        let cut: String = line.chars().take(MAX_LINE_PREVIEW - 3).collect();
+        format!("{cut}...")
+    } else {
+        line.to_string()
+    };
+    Some((preview, *count))
+}
+
+/// The `--detect-language` result: `en` for a file, `en (12 files), ja (3 files)` for
+/// several.
+fn natural_languages(analysis: &FileAnalysis) -> Option<String> {
+    let ranked = analysis.natural_language.as_ref()?.ranked();
+    Some(match ranked.as_slice() {
+        [(code, 1)] => code.to_string(),
+        _ => ranked
+            .iter()
+            .map(|(code, files)| format!("{code} ({files} {})", pluralize_files(*files)))
+            .collect::<Vec<_>>()
+            .join(", "),
+    })
+}
+
+/// Analyzer rows, aligned with the count rows they follow.
+pub fn format_analysis(analysis: &FileAnalysis) -> Vec<String> {
+    let mut rows: Vec<String> = analysis_values(analysis)
+        .into_iter()
+        .map(|(label, _, value)| format!("{label}: {:>10}", format_number(value)))
+        .collect();
+    if let Some((line, count)) = most_repeated(analysis) {
+        rows.push(format!("Top Line: {:>10}  {line}", format_number(count)));
+    }
+    if let Some(histogram) = &analysis.entropy {
+        rows.push(format!(
+            " Entropy: {:>10.2}  bits/byte",
+            histogram.bits_per_byte()
+        ));
+    }
+    if let Some(vocabulary) = &analysis.vocabulary {
+        rows.push(format!(
+            "     TTR: {:>10.3}  distinct/words",
+            vocabulary.type_token_ratio()
+        ));
+    }
+    if let Some(share) = analysis.dialogue.and_then(|dialogue| dialogue.share()) {
+        rows.push(format!(
+            "Dialogue: {:>10.1}  % words in quotes",
+            share * 100.0
+        ));
+    }
+    if let Some(wpm) = analysis.subtitles.and_then(|s| s.words_per_minute()) {
+        rows.push(format!("     WPM: {wpm:>10.1}  words/minute on screen"));
+    }
+    if let Some(completion) = analysis.po.and_then(|po| po.completion()) {
+        rows.push(format!(
+            "Complete: {:>10.1}  % translated",
+            completion * 100.0
+        ));
+    }
+    if let Some(ratios) = analysis.ratios {
+        if let Some(words) = ratios.words_per_line() {
+            rows.push(format!("Words/Ln: {words:>10.2}  words/line"));
+        }
+        if let Some(bytes) = ratios.bytes_per_line() {
+            rows.push(format!("Bytes/Ln: {bytes:>10.2}  bytes/line"));
+        }
+    }
+    if let Some(i18n) = &analysis.i18n {
+        for key in &i18n.missing {
+            rows.push(format!("{:>20}  {key}", ""));
+        }
+    }
+    if let Some(chapters) = &analysis.chapters {
+        for (name, words) in &chapters.words {
+            rows.push(format!(" Chapter: {:>10}  {name}", format_number(*words)));
+        }
+    }
+    if let Some(ratio) = analysis.line_kinds.and_then(|lines| lines.comment_ratio()) {
+        rows.push(format!("   Ratio: {ratio:>10.1}  % comments/code"));
+    }
+    if let Some(checksum) = &analysis.hash {
+        let label = checksum.algorithm.name().to_uppercase();
+        rows.push(format!("{label:>8}: {}", checksum.hex));
+    }
+    if let Some(meta) = &analysis.meta {
+        if let Some(modified) = meta.modified_utc() {
+            rows.push(format!("Modified: {modified}"));
+        }
+        if let Some(permissions) = &meta.permissions {
+            rows.push(format!("   Perms: {permissions}"));
+        }
+    }
+    if let Some(languages) = natural_languages(analysis) {
+        rows.push(format!("Language: {languages}"));
+    }
+    rows
+}
+
+/// Analyzer results for a `--compact` line, e.g. `3 functions, 1 types`.
+pub fn format_compact_analysis(analysis: &FileAnalysis) -> String {
+    let mut parts: Vec<String> = analysis_values(analysis)
+        .into_iter()
+        .map(|(_, unit, value)| format!("{} {unit}", format_number(value)))
+        .collect();
+    if let Some((line, count)) = most_repeated(analysis) {
+        parts.push(format!(
+            "most repeated \"{line}\" ({} times)",
+            format_number(count)
+        ));
+    }
+    if let Some(histogram) = &analysis.entropy {
+        parts.push(format!(
+            "entropy {:.2} bits/byte",
+            histogram.bits_per_byte()
+        ));
+    }
+    if let Some(vocabulary) = &analysis.vocabulary {
+        parts.push(format!("TTR {:.3}", vocabulary.type_token_ratio()));
+    }
+    if let Some(share) = analysis.dialogue.and_then(|dialogue| dialogue.share()) {
+        parts.push(format!("{:.1}% dialogue", share * 100.0));
+    }
+    if let Some(wpm) = analysis.subtitles.and_then(|s| s.words_per_minute()) {
+        parts.push(format!("{wpm:.1} wpm"));
+    }
+    if let Some(completion) = analysis.po.and_then(|po| po.completion()) {
+        parts.push(format!("{:.1}% complete", completion * 100.0));
+    }
+    if let Some(ratios) = analysis.ratios {
+        parts.extend(
+            ratios
+                .words_per_line()
+                .map(|words| format!("{words:.2} words/line")),
+        );
+        parts.extend(
+            ratios
+                .bytes_per_line()
+                .map(|bytes| format!("{bytes:.2} bytes/line")),
+        );
+    }
+    if let Some(ratio) = analysis.line_kinds.and_then(|lines| lines.comment_ratio()) {
+        parts.push(format!("{ratio:.1}% comments/code"));
+    }
+    if let Some(checksum) = &analysis.hash {
+        parts.push(checksum.to_string());
+    }
+    if let Some(meta) = &analysis.meta {
+        if let Some(modified) = meta.modified_utc() {
+            parts.push(format!("modified {modified}"));
+        }
+        parts.extend(meta.permissions.clone());
+    }
+    if let Some(languages) = natural_languages(analysis) {
+        parts.push(format!("language {languages}"));
+    }
+    parts.join(", ")
+}
+
+pub fn format_separator() -> &'static str {
+    "─────────────────────────"
+}
+
+fn format_compact_counts(count: &Count, args: &Args) -> String {
+    let mut parts = Vec::new();
+    if args.show_max_line_length() {
+        parts.push(format!("max:{}", format_number(count.max_line_length)));
+    }
+    if args.show_lines() {
+        parts.push(format!("{} lines", format_number(count.lines)));
+    }
+    if args.show_words() {
+        parts.push(format!("{} words", format_number(count.words)));
+    }
+    if args.show_bytes() {
+        parts.push(format!("{} bytes", format_number(count.bytes)));
+    }
+    parts.join(", ")
+}
+
+pub fn format_compact_output(name: &str, count: &Count, kind: OutputKind, args: &Args) -> String {
+    let header = match kind {
+        OutputKind::File => format!("{name}:"),
+        OutputKind::Directory(file_count) => {
+            format!("{name} ({file_count} {}): ", pluralize_files(file_count))
+        }
+    };
+    format!("{header} {}", format_compact_counts(count, args))
+}
+
+pub fn format_compact_total(file_count: usize, count: &Count, args: &Args) -> String {
+    format!(
+        "Total ({} {}): {}",
+        file_count,
+        pluralize_files(file_count),
+        format_compact_counts(count, args)
+    )
+}
+
+/// The selected counts as bare numbers separated by spaces, in the order lines, words,
+/// bytes, longest line (like `wc`), for `--total-only`.
+pub fn format_total_only(count: &Count, args: &Args) -> String {
+    let values = [
+        (args.show_lines(), count.lines),
+        (args.show_words(), count.words),
+        (args.show_bytes(), count.bytes),
+        (args.show_max_line_length(), count.max_line_length),
+    ];
+    values
+        .iter()
+        .filter(|(shown, _)| *shown)
+        .map(|(_, value)| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_single_count(count: &Count, args: &Args) -> String {
+    let (value, unit) = match (args.lines, args.words, args.bytes, args.max_line_length) {
+        (false, true, false, false) => (count.words, "words"),
+        (false, false, true, false) => (count.bytes, "bytes"),
+        (false, false, false, true) => (count.max_line_length, "max"),
+        _ => (count.lines, "lines"),
+    };
+    format!("{} {unit}", format_number(value))
+}
+
+fn format_verbose_entry(entry: &FileEntry, analysis: Option<&FileAnalysis>, args: &Args) -> String {
+    let icon = if args.no_color { "" } else { FILE_ICON };
+    let mut line = format!(
+        "{icon}{}  {}",
+        display_path(&entry.path),
+        format_single_count(&entry.count, args)
+    );
+    if let Some(analysis) = analysis.filter(|analysis| !analysis.is_empty()) {
+        line.push_str(&format!("  ({})", format_compact_analysis(analysis)));
+    }
+    line
+}
+
+/// One line per file and a total. `analyses` holds the analyzer results
+/// (`--markdown`, `--symbols`, ...) of each entry, in the same order, or is empty.
+pub fn format_verbose_output(
+    entries: &[FileEntry],
+    analyses: &[FileAnalysis],
+    total: &Count,
+    skipped: &[SkippedFile],
+    args: &Args,
+) -> String {
+    written(|out| write_verbose_output(out, entries, analyses, total, skipped, args))
+}
+
+/// Writes the output of [`format_verbose_output`] a line at a time, so a directory of
+/// many files is never held in memory as text.
+pub fn write_verbose_output(
+    out: &mut impl Write,
+    entries: &[FileEntry],
+    analyses: &[FileAnalysis],
+    total: &Count,
+    skipped: &[SkippedFile],
+    args: &Args,
+) -> io::Result<()> {
+    for (index, entry) in entries.iter().enumerate() {
+        writeln!(
+            out,
+            "{}",
+            format_verbose_entry(entry, analyses.get(index), args)
+        )?;
+    }
+    out.write_all(format_separator().as_bytes())?;
+
+    let icon = if args.no_color { "" } else { DIR_ICON };
+    let file_count = entries.len();
+    write!(
+        out,
+        "\n{icon}Total ({file_count} {})  {}",
+        pluralize_files(file_count),
+        format_single_count(total, args)
+    )?;
+    for line in format_analysis(&analyses.iter().sum()) {
+        write!(out, "\n{line}")?;
+    }
+
+    if !skipped.is_empty() {
+        write!(out, "\nSkipped ({}):", skipped.len())?;
+        for file in skipped {
+            write!(out, "\n  {}: {}", display_path(&file.path), file.reason)?;
+        }
+    }
+    Ok(())
+}
+
+/// What a directory count left out by reason, e.g. `hidden 12, excluded 340, binary 5`;
+/// `None` when it left nothing out.
+pub fn format_skip_summary(summary: &SkipSummary) -> Option<String> {
+    let reasons: Vec<String> = summary
+        .reasons()
+        .into_iter()
+        .map(|(reason, count)| format!("{reason} {}", format_number(count)))
+        .collect();
+    (!reasons.is_empty()).then(|| reasons.join(", "))
+}
+
+/// What a violation exceeded, without the path, e.g. `600 lines exceeds limit of 500`.
+pub fn format_violation_message(violation: &Violation) -> String {
+    format!(
+        "{} {} exceeds limit of {}",
+        format_number(violation.actual),
+        violation.threshold.metric.name(),
+        format_number(violation.threshold.limit)
+    )
+}
+
+pub fn format_violation(violation: &Violation) -> String {
+    format!(
+        "{}: {}",
+        display_path(&violation.path),
+        format_violation_message(violation)
+    )
+}
+
+/// The files of `--files-over`, one line each with the counts beyond their limits, in
+/// the order of `violations`.
+pub fn format_files_over(violations: &[Violation]) -> String {
+    files_over(violations)
+        .iter()
+        .map(|(path, exceeded)| {
+            let counts: Vec<String> = exceeded
+                .iter()
+                .map(|violation| {
+                    format!(
+                        "{} {}",
+                        format_number(violation.actual),
+                        violation.threshold.metric.name()
+                    )
+                })
+                .collect();
+            format!("{}: {}", display_path(path), counts.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn format_files_over_json(violations: &[Violation]) -> String {
+    let files: Vec<String> = files_over(violations)
+        .iter()
+        .map(|(path, exceeded)| {
+            let counts: String = exceeded
+                .iter()
+                .map(|violation| {
+                    format!(
+                        r#","{}":{}"#,
+                        violation.threshold.metric.name(),
+                        violation.actual
+                    )
+                })
+                .collect();
+            format!(
+                r#"{{"path":"{}"{counts}}}"#,
+                escape_json(&display_path(path))
+            )
+        })
+        .collect();
+    format!(r#"{{"files":[{}]}}"#, files.join(","))
+}
+
+/// `violations` grouped by file, those of a file being next to each other as
+/// [`check_entries`](crate::budget::check_entries) gives them; a count beyond several
+/// limits on the same metric is shown once.
+fn files_over(violations: &[Violation]) -> Vec<(&Path, Vec<&Violation>)> {
+    let mut files: Vec<(&Path, Vec<&Violation>)> = Vec::new();
+    for violation in violations {
+        if files.last().is_none_or(|(path, _)| *path != violation.path) {
+            files.push((&violation.path, Vec::new()));
+        }
+        let exceeded = &mut files.last_mut().unwrap().1;
+        if !exceeded
+            .iter()
+            .any(|seen| seen.threshold.metric == violation.threshold.metric)
+        {
+            exceeded.push(violation);
+        }
+    }
+    files
+}
+
+fn sarif_uri(path: &Path) -> String {
+    display_path(path).replace('\\', "/")
+}
+
+pub fn format_sarif(violations: &[Violation], thresholds: &[Threshold]) -> String {
+    let mut metrics: Vec<Metric> = Vec::new();
+    for threshold in thresholds {
+        if !metrics.contains(&threshold.metric) {
+            metrics.push(threshold.metric);
+        }
+    }
+
+    let rules: Vec<String> = metrics
+        .iter()
+        .map(|metric| {
+            format!(
+                r#"{{"id":"{}","shortDescription":{{"text":"File exceeds {} limit"}}}}"#,
+                metric.name(),
+                metric.name()
+            )
+        })
+        .collect();
+
+    let results: Vec<String> = violations
+        .iter()
+        .map(|v| {
+            format!(
+                r#"{{"ruleId":"{}","level":"error","message":{{"text":"{} {} exceeds limit of {}"}},"locations":[{{"physicalLocation":{{"artifactLocation":{{"uri":"{}"}}}}}}]}}"#,
+                v.threshold.metric.name(),
+                v.actual,
+                v.threshold.metric.name(),
+                v.threshold.limit,
+                escape_json(&sarif_uri(&v.path))
+            )
+        })
+        .collect();
+
+    format!(
+        r#"{{"version":"2.1.0","$schema":"https://json.schemastore.org/sarif-2.1.0.json","runs":[{{"tool":{{"driver":{{"name":"ewc","version":"{}","informationUri":"{}","rules":[{}]}}}},"results":[{}]}}]}}"#,
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_REPOSITORY"),
+        rules.join(","),
+        results.join(",")
+    )
+}
+
+pub fn format_signed(n: i64) -> String {
+    let formatted = format_number(n.unsigned_abs() as usize);
+    match n.signum() {
+        1 => format!("+{formatted}"),
+        -1 => format!("-{formatted}"),
+        _ => formatted,
+    }
+}
+
+/// The change of each selected count, e.g. `+12 lines, 0 words, +340 bytes`.
+pub fn format_delta_parts(delta: &CountDelta, args: &Args) -> String {
+    let mut parts = Vec::new();
+    if args.show_lines() {
+        parts.push(format!("{} lines", format_signed(delta.lines)));
+    }
+    if args.show_words() {
+        parts.push(format!("{} words", format_signed(delta.words)));
+    }
+    if args.show_bytes() {
+        parts.push(format!("{} bytes", format_signed(delta.bytes)));
+    }
+    parts.join(", ")
+}
+
+/// The `--cocomo` block, worded like scc's.
+pub fn format_cocomo(estimate: &Estimate, project_type: ProjectType) -> String {
+    let name = project_type.name();
+    [
+        format!(
+            "Estimated Cost to Develop ({name}) ${}",
+            format_number(estimate.cost.round() as usize)
+        ),
+        format!(
+            "Estimated Schedule Effort ({name}) {:.2} months",
+            estimate.schedule
+        ),
+        format!("Estimated People Required ({name}) {:.2}", estimate.people),
+    ]
+    .join("\n")
+}
+
+pub fn format_baseline_delta(delta: &ReportDelta, args: &Args) -> String {
+    format_report_delta("Change vs baseline", delta, args)
+}
+
+pub fn format_report_delta(title: &str, delta: &ReportDelta, args: &Args) -> String {
+    let mut lines = vec![
+        title.to_string(),
+        format!("   Files: {:>10}", format_signed(delta.total.files)),
+    ];
+    if args.show_lines() {
+        lines.push(format!(
+            "   Lines: {:>10}",
+            format_signed(delta.total.lines)
+        ));
+    }
+    if args.show_words() {
+        lines.push(format!(
+            "   Words: {:>10}",
+            format_signed(delta.total.words)
+        ));
+    }
+    if args.show_bytes() {
+        lines.push(format!(
+            "   Bytes: {:>10}",
+            format_signed(delta.total.bytes)
+        ));
+    }
+    for change in &delta.changes {
+        let marker = match change.kind {
+            ChangeKind::Added => '+',
+            ChangeKind::Removed => '-',
+            ChangeKind::Changed => '~',
+        };
+        lines.push(format!(
+            "   {marker} {}: {}",
+            change.name,
+            format_delta_parts(&change.delta, args)
+        ));
+    }
+    lines.join("\n")
+}
+
+const PR_COMMENT_TOP_FILES: usize = 5;
+
+fn escape_markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+pub fn format_pr_comment(
+    entries: &[FileEntry],
+    violations: &[Violation],
+    thresholds: &[Threshold],
+    baseline: Option<&ReportDelta>,
+) -> String {
+    let total: Count = entries.iter().map(|e| e.count).sum();
+    let mut lines = vec![
+        "### ewc report".to_string(),
+        String::new(),
+        "| | Files | Lines | Words | Bytes |".to_string(),
+        "|---|---:|---:|---:|---:|".to_string(),
+        format!(
+            "| **Total** | {} | {} | {} | {} |",
+            format_number(entries.len()),
+            format_number(total.lines),
+            format_number(total.words),
+            format_number(total.bytes)
+        ),
+    ];
+
+    if let Some(delta) = baseline {
+        lines.push(format!(
+            "| **Change** | {} | {} | {} | {} |",
+            format_signed(delta.total.files),
+            format_signed(delta.total.lines),
+            format_signed(delta.total.words),
+            format_signed(delta.total.bytes)
+        ));
+    }
+
+    if !entries.is_empty() {
+        let mut biggest: Vec<&FileEntry> = entries.iter().collect();
+        biggest.sort_by(|a, b| b.count.lines.cmp(&a.count.lines).then(a.path.cmp(&b.path)));
+
+        lines.push(String::new());
+        lines.push("<details><summary>Largest files</summary>".to_string());
+        lines.push(String::new());
+        lines.push("| File | Lines | Words | Bytes |".to_string());
+        lines.push("|---|---:|---:|---:|".to_string());
+        for entry in biggest.iter().take(PR_COMMENT_TOP_FILES) {
+            lines.push(format!(
+                "| `{}` | {} | {} | {} |",
+                escape_markdown_cell(&display_path(&entry.path)),
+                format_number(entry.count.lines),
+                format_number(entry.count.words),
+                format_number(entry.count.bytes)
+            ));
+        }
+        lines.push(String::new());
+        lines.push("</details>".to_string());
+    }
+
+    if !thresholds.is_empty() {
+        lines.push(String::new());
+        if violations.is_empty() {
+            lines.push("**Budget:** :white_check_mark: all files within limits".to_string());
+        } else {
+            lines.push(format!(
+                "**Budget:** :x: {} {}",
+                violations.len(),
+                if violations.len() == 1 {
+                    "violation"
+                } else {
+                    "violations"
+                }
+            ));
+            lines.push(String::new());
+            for violation in violations {
+                lines.push(format!(
+                    "- `{}`: {} {} (limit {})",
+                    display_path(&violation.path),
+                    format_number(violation.actual),
+                    violation.threshold.metric.name(),
+                    format_number(violation.threshold.limit)
+                ));
+            }
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn markdown_metric_columns(args: &Args) -> Vec<(&'static str, Metric)> {
+    let mut columns = Vec::new();
+    if args.show_max_line_length() {
+        columns.push(("Max Line", Metric::MaxLineLength));
+    }
+    if args.show_lines() {
+        columns.push(("Lines", Metric::Lines));
+    }
+    if args.show_words() {
+        columns.push(("Words", Metric::Words));
+    }
+    if args.show_bytes() {
+        columns.push(("Bytes", Metric::Bytes));
+    }
+    columns
+}
+
+pub fn format_markdown(results: &[JsonFileResult], total: &Count, args: &Args) -> String {
+    let columns = markdown_metric_columns(args);
+    let row = |name: String, file_count: usize, count: &Count| {
+        let values: Vec<String> = columns
+            .iter()
+            .map(|(_, metric)| format_number(metric.value(count)))
+            .collect();
+        format!("| {name} | {} | {} |", file_count, values.join(" | "))
+    };
+
+    let headers: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+    let mut lines = vec![
+        format!("| Path | Files | {} |", headers.join(" | ")),
+        format!("|---|---:|{}", "---:|".repeat(columns.len())),
+    ];
+    for result in results {
+        lines.push(row(
+            format!("`{}`", escape_markdown_cell(&result.name)),
+            result.file_count.unwrap_or(1),
+            &result.count,
+        ));
+    }
+    if results.len() > 1 {
+        let file_count = results.iter().map(|r| r.file_count.unwrap_or(1)).sum();
+        lines.push(row("**Total**".to_string(), file_count, total));
+    }
+    lines.join("\n")
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The table of [`format_markdown`] as a standalone HTML page.
+pub fn format_html(results: &[JsonFileResult], total: &Count, args: &Args) -> String {
+    let columns = markdown_metric_columns(args);
+    let row = |cell: &str, name: String, file_count: usize, count: &Count| {
+        let values: String = columns
+            .iter()
+            .map(|(_, metric)| format!("<td>{}</td>", format_number(metric.value(count))))
+            .collect();
+        format!("<tr><{cell}>{name}</{cell}><td>{file_count}</td>{values}</tr>")
+    };
+
+    let headers: String = columns
+        .iter()
+        .map(|(name, _)| format!("<th>{name}</th>"))
+        .collect();
+    let mut lines = vec![
+        "<!DOCTYPE html>".to_string(),
+        "<html>".to_string(),
+        "<head>".to_string(),
+        r#"<meta charset="utf-8">"#.to_string(),
+        "<title>ewc report</title>".to_string(),
+        "<style>td { text-align: right; } td:first-child { text-align: left; }</style>".to_string(),
+        "</head>".to_string(),
+        "<body>".to_string(),
+        "<table>".to_string(),
+        format!("<thead><tr><th>Path</th><th>Files</th>{headers}</tr></thead>"),
+        "<tbody>".to_string(),
+    ];
+    for result in results {
+        lines.push(row(
+            "td",
+            format!("<code>{}</code>", escape_html(&result.name)),
+            result.file_count.unwrap_or(1),
+            &result.count,
+        ));
+    }
+    lines.push("</tbody>".to_string());
+    if results.len() > 1 {
+        let file_count = results.iter().map(|r| r.file_count.unwrap_or(1)).sum();
+        let total_row = row("th", "Total".to_string(), file_count, total);
+        lines.push(format!("<tfoot>{total_row}</tfoot>"));
+    }
+    lines.extend(["</table>", "</body>", "</html>"].map(str::to_string));
+    lines.join("\n")
+}
+
+/// A workbook with a `Summary` sheet holding the table of [`format_markdown`] and a
+/// `Files` sheet with a row per file.
+pub fn format_xlsx(
+    results: &[JsonFileResult],
+    total: &Count,
+    entries: &[FileEntry],
+    args: &Args,
+) -> io::Result<Vec<u8>> {
+    let columns = markdown_metric_columns(args);
+    let header = |first: &[&'static str]| -> Vec<Cell> {
+        first
+            .iter()
+            .chain(columns.iter().map(|(name, _)| name))
+            .map(|name| Cell::from(*name))
+            .collect()
+    };
+    let row = |name: String, file_count: Option<usize>, count: &Count| -> Vec<Cell> {
+        let mut cells = vec![Cell::Text(name)];
+        cells.extend(file_count.map(Cell::from));
+        cells.extend(
+            columns
+                .iter()
+                .map(|(_, metric)| Cell::from(metric.value(count))),
+        );
+        cells
+    };
+
+    let mut summary = vec![header(&["Path", "Files"])];
+    for result in results {
+        let file_count = result.file_count.unwrap_or(1);
+        summary.push(row(result.name.clone(), Some(file_count), &result.count));
+    }
+    if results.len() > 1 {
+        let file_count = results.iter().map(|r| r.file_count.unwrap_or(1)).sum();
+        summary.push(row("Total".to_string(), Some(file_count), total));
+    }
+    let mut files = vec![header(&["Path"])];
+    files.extend(
+        entries
+            .iter()
+            .map(|entry| row(display_path(&entry.path), None, &entry.count)),
+    );
+    write_workbook(&[
+        Sheet {
+            name: "Summary".to_string(),
+            rows: summary,
+        },
+        Sheet {
+            name: "Files".to_string(),
+            rows: files,
+        },
+    ])
+}
+
+/// A directory of a size map.
+struct TreeNode {
+    label: String,
+    lines: usize,
+    parent: Option<usize>,
+}
+
+/// The directories of `entries` with the lines of the files under each, parents
+/// before children. The root is the innermost directory holding every file, labeled
+/// with its path (`.` for the current directory); the others with their name.
+fn directory_tree(entries: &[FileEntry]) -> Vec<TreeNode> {
+    let paths: Vec<PathBuf> = entries
+        .iter()
+        .map(|entry| {
+            entry
+                .path
+                .components()
+                .filter(|component| *component != Component::CurDir)
+                .collect()
+        })
+        .collect();
+    let mut root = paths
+        .first()
+        .and_then(|path| path.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    for path in &paths {
+        while !path.starts_with(&root) || path == &root {
+            root = root.parent().map(Path::to_path_buf).unwrap_or_default();
+        }
+    }
+
+    let mut directories: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    for (path, entry) in paths.iter().zip(entries) {
+        for directory in path.ancestors().skip(1) {
+            if directory == root {
+                break;
+            }
+            *directories.entry(directory.to_path_buf()).or_default() += entry.count.lines;
+        }
+    }
+    let root_label = if root.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        display_path(&root)
+    };
+    let mut nodes = vec![TreeNode {
+        label: root_label,
+        lines: entries.iter().map(|entry| entry.count.lines).sum(),
+        parent: None,
+    }];
+    let mut index: BTreeMap<&Path, usize> = BTreeMap::new();
+    for (directory, lines) in &directories {
+        // Sorted paths put each parent before its children
+        let parent = directory
+            .parent()
+            .and_then(|parent| index.get(parent).copied())
+            .unwrap_or(0);
+        index.insert(directory, nodes.len());
+        nodes.push(TreeNode {
+            label: directory.file_name().map_or_else(
+                || display_path(directory),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+            lines: *lines,
+            parent: Some(parent),
+        });
+    }
+    nodes
+}
+
+/// A Mermaid flowchart of the directory hierarchy with the lines under each directory.
+pub fn format_mermaid(entries: &[FileEntry]) -> String {
+    let nodes = directory_tree(entries);
+    let mut lines = vec!["flowchart TD".to_string()];
+    for (id, node) in nodes.iter().enumerate() {
+        lines.push(format!(
+            r#"    n{id}["{}<br/>{} lines"]"#,
+            node.label.replace('"', "#quot;"),
+            format_number(node.lines)
+        ));
+    }
+    for (id, node) in nodes.iter().enumerate() {
+        if let Some(parent) = node.parent {
+            lines.push(format!("    n{parent} --> n{id}"));
+        }
+    }
+    lines.join("\n")
+}
+
+/// A Graphviz graph of the directory hierarchy with the lines under each directory.
+pub fn format_dot(entries: &[FileEntry]) -> String {
+    let nodes = directory_tree(entries);
+    let mut lines = vec![
+        "digraph ewc {".to_string(),
+        "    rankdir=LR;".to_string(),
+        "    node [shape=box];".to_string(),
+    ];
+    for (id, node) in nodes.iter().enumerate() {
+        let label = node.label.replace('\\', "\\\\").replace('"', "\\\"");
+        lines.push(format!(
+            r#"    n{id} [label="{label}\n{} lines"];"#,
+            format_number(node.lines)
+        ));
+    }
+    for (id, node) in nodes.iter().enumerate() {
+        if let Some(parent) = node.parent {
+            lines.push(format!("    n{parent} -> n{id};"));
+        }
+    }
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+const HEATMAP_WIDTH: usize = 20;
+
+/// The ANSI color of a heatmap bar for a directory holding `share` of all lines.
+fn heat_color(share: f64) -> &'static str {
+    if share >= 0.5 {
+        "\x1b[31m"
+    } else if share >= 0.2 {
+        "\x1b[33m"
+    } else if share >= 0.05 {
+        "\x1b[32m"
+    } else {
+        "\x1b[34m"
+    }
+}
+
+/// The directory hierarchy as indented rows with their lines and a bar of their share
+/// of the root, largest directories first among siblings. Bars are colored from blue
+/// for small shares to red for large ones when `color` is set.
+pub fn format_heatmap(entries: &[FileEntry], color: bool) -> String {
+    let nodes = directory_tree(entries);
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    for (id, node) in nodes.iter().enumerate() {
+        if let Some(parent) = node.parent {
+            children[parent].push(id);
+        }
+    }
+    for siblings in &mut children {
+        siblings.sort_by_key(|&id| std::cmp::Reverse(nodes[id].lines));
+    }
+    let mut order: Vec<(usize, usize)> = Vec::new();
+    let mut stack = vec![(0, 0)];
+    while let Some((id, depth)) = stack.pop() {
+        order.push((id, depth));
+        stack.extend(children[id].iter().rev().map(|&child| (child, depth + 1)));
+    }
+
+    let names: Vec<String> = order
+        .iter()
+        .map(|&(id, depth)| format!("{}{}", "  ".repeat(depth), nodes[id].label))
+        .collect();
+    let name_width = names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let number_width = format_number(nodes[0].lines).len();
+    order
+        .iter()
+        .zip(&names)
+        .map(|(&(id, _), name)| {
+            let lines = nodes[id].lines;
+            let share = if nodes[0].lines == 0 {
+                0.0
+            } else {
+                lines as f64 / nodes[0].lines as f64
+            };
+            let filled = (share * HEATMAP_WIDTH as f64).round() as usize;
+            let mut bar = "\u{2588}".repeat(filled);
+            bar.push_str(&"\u{2591}".repeat(HEATMAP_WIDTH - filled));
+            if color {
+                bar = format!("{}{bar}\x1b[0m", heat_color(share));
+            }
+            let padding = name_width - name.chars().count();
+            format!(
+                "{name}{}  {:>number_width$}  {bar} {:>5.1}%",
+                " ".repeat(padding),
+                format_number(lines),
+                share * 100.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn diff_total(diffs: &[FileDiff]) -> FileDiff {
+    diffs.iter().fold(FileDiff::default(), |acc, d| FileDiff {
+        path: acc.path,
+        lines_added: acc.lines_added + d.lines_added,
+        lines_removed: acc.lines_removed + d.lines_removed,
+        words_added: acc.words_added + d.words_added,
+        words_removed: acc.words_removed + d.words_removed,
+    })
+}
+
+fn format_diff_counts(diff: &FileDiff) -> String {
+    format!(
+        "+{} -{} lines, +{} -{} words",
+        format_number(diff.lines_added),
+        format_number(diff.lines_removed),
+        format_number(diff.words_added),
+        format_number(diff.words_removed)
+    )
+}
+
+pub fn format_diff_output(diffs: &[FileDiff], args: &Args) -> String {
+    let file_icon = if args.no_color { "" } else { FILE_ICON };
+    let mut lines: Vec<String> = diffs
+        .iter()
+        .map(|d| format!("{file_icon}{}  {}", d.path, format_diff_counts(d)))
+        .collect();
+
+    lines.push(format_separator().to_string());
+
+    let dir_icon = if args.no_color { "" } else { DIR_ICON };
+    lines.push(format!(
+        "{dir_icon}Total ({} {})  {}",
+        diffs.len(),
+        pluralize_files(diffs.len()),
+        format_diff_counts(&diff_total(diffs))
+    ));
+    lines.join("\n")
+}
+
+pub fn format_diff_json(range: &str, diffs: &[FileDiff]) -> String {
+    let files: Vec<String> = diffs
+        .iter()
+        .map(|d| {
+            format!(
+                r#"{{"file":"{}","lines_added":{},"lines_removed":{},"words_added":{},"words_removed":{}}}"#,
+                escape_json(&d.path),
+                d.lines_added,
+                d.lines_removed,
+                d.words_added,
+                d.words_removed
+            )
+        })
+        .collect();
+    let total = diff_total(diffs);
+
+    format!(
+        r#"{{"range":"{}","files":[{}],"total":{{"file_count":{},"lines_added":{},"lines_removed":{},"words_added":{},"words_removed":{}}}}}"#,
+        escape_json(range),
+        files.join(","),
+        diffs.len(),
+        total.lines_added,
+        total.lines_removed,
+        total.words_added,
+        total.words_removed
+    )
+}
+
+pub fn format_diff_markdown(diffs: &[FileDiff]) -> String {
+    let row = |name: String, d: &FileDiff| {
+        format!(
+            "| {name} | +{} | -{} | +{} | -{} |",
+            format_number(d.lines_added),
+            format_number(d.lines_removed),
+            format_number(d.words_added),
+            format_number(d.words_removed)
+        )
+    };
+
+    let mut lines = vec![
+        "| File | Lines added | Lines removed | Words added | Words removed |".to_string(),
+        "|---|---:|---:|---:|---:|".to_string(),
+    ];
+    for diff in diffs {
+        lines.push(row(format!("`{}`", escape_markdown_cell(&diff.path)), diff));
+    }
+    lines.push(row(
+        format!(
+            "**Total ({} {})**",
+            diffs.len(),
+            pluralize_files(diffs.len())
+        ),
+        &diff_total(diffs),
+    ));
+    lines.join("\n")
+}
+
+fn changes_of_kind(comparison: &TreeComparison, kind: ChangeKind) -> Vec<&EntryChange> {
+    comparison
+        .delta
+        .changes
+        .iter()
+        .filter(|c| c.kind == kind)
+        .collect()
+}
+
+pub fn format_compare_output(comparison: &TreeComparison, args: &Args) -> String {
+    let icon = if args.no_color { "" } else { DIR_ICON };
+    let tree_line = |tree: &ReportEntry| {
+        format!(
+            "{icon}{} ({} {})  {}",
+            tree.name,
+            tree.file_count,
+            pluralize_files(tree.file_count),
+            format_compact_counts(&tree.count, args)
+        )
+    };
+    let mut lines = vec![
+        tree_line(&comparison.a),
+        tree_line(&comparison.b),
+        format_separator().to_string(),
+    ];
+
+    let groups = [
+        (
+            format!("Only in {}", comparison.a.name),
+            ChangeKind::Removed,
+            '-',
+        ),
+        (
+            format!("Only in {}", comparison.b.name),
+            ChangeKind::Added,
+            '+',
+        ),
+        ("Changed".to_string(), ChangeKind::Changed, '~'),
+    ];
+    for (title, kind, marker) in groups {
+        let changes = changes_of_kind(comparison, kind);
+        if changes.is_empty() {
+            continue;
+        }
+        lines.push(format!("{title} ({}):", changes.len()));
+        for change in changes {
+            lines.push(format!(
+                "   {marker} {}: {}",
+                change.name,
+                format_delta_parts(&change.delta, args)
+            ));
+        }
+    }
+    if comparison.delta.changes.is_empty() {
+        lines.push("No differences".to_string());
+    }
+
+    let total = &comparison.delta.total;
+    lines.push(format_separator().to_string());
+    lines.push(format!(
+        "Change: {} {}, {}",
+        format_signed(total.files),
+        if total.files.abs() == 1 {
+            "file"
+        } else {
+            "files"
+        },
+        format_delta_parts(total, args)
+    ));
+    lines.join("\n")
+}
+
+fn json_tree(tree: &ReportEntry) -> String {
+    format!(
+        r#"{{"directory":"{}","file_count":{},"max_line_length":{},"lines":{},"words":{},"bytes":{}}}"#,
+        escape_json(&tree.name),
+        tree.file_count,
+        tree.count.max_line_length,
+        tree.count.lines,
+        tree.count.words,
+        tree.count.bytes
+    )
+}
+
+fn json_changes(comparison: &TreeComparison, kind: ChangeKind) -> String {
+    // Files present on one side only report that side's counts rather than a delta
+    let sign = if kind == ChangeKind::Removed { -1 } else { 1 };
+    let changes: Vec<String> = changes_of_kind(comparison, kind)
+        .into_iter()
+        .map(|c| {
+            format!(
+                r#"{{"file":"{}","lines":{},"words":{},"bytes":{}}}"#,
+                escape_json(&c.name),
+                sign * c.delta.lines,
+                sign * c.delta.words,
+                sign * c.delta.bytes
+            )
+        })
+        .collect();
+    format!("[{}]", changes.join(","))
+}
+
+pub fn format_compare_json(comparison: &TreeComparison) -> String {
+    let total = &comparison.delta.total;
+    format!(
+        r#"{{"a":{},"b":{},"only_in_a":{},"only_in_b":{},"changed":{},"delta":{{"files":{},"lines":{},"words":{},"bytes":{}}}}}"#,
+        json_tree(&comparison.a),
+        json_tree(&comparison.b),
+        json_changes(comparison, ChangeKind::Removed),
+        json_changes(comparison, ChangeKind::Added),
+        json_changes(comparison, ChangeKind::Changed),
+        total.files,
+        total.lines,
+        total.words,
+        total.bytes
+    )
+}
+
+pub fn format_snapshot_diff_json(name: &str, delta: &ReportDelta) -> String {
+    let changes: Vec<String> = delta
+        .changes
+        .iter()
+        .map(|c| {
+            let status = match c.kind {
+                ChangeKind::Added => "added",
+                ChangeKind::Removed => "removed",
+                ChangeKind::Changed => "changed",
+            };
+            format!(
+                r#"{{"file":"{}","status":"{status}","lines":{},"words":{},"bytes":{}}}"#,
+                escape_json(&c.name),
+                c.delta.lines,
+                c.delta.words,
+                c.delta.bytes
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"snapshot":"{}","changes":[{}],"delta":{{"files":{},"lines":{},"words":{},"bytes":{}}}}}"#,
+        escape_json(name),
+        changes.join(","),
+        delta.total.files,
+        delta.total.lines,
+        delta.total.words,
+        delta.total.bytes
+    )
+}
+
+fn author_share(lines: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        lines as f64 * 100.0 / total as f64
+    }
+}
+
+pub fn format_authors_output(authors: &[AuthorLines]) -> String {
+    let total: usize = authors.iter().map(|a| a.lines).sum();
+    let name_width = authors
+        .iter()
+        .map(|a| a.author.chars().count())
+        .max()
+        .unwrap_or(0);
+    let lines_width = format_number(total).len();
+
+    let mut lines: Vec<String> = authors
+        .iter()
+        .enumerate()
+        .map(|(index, a)| {
+            format!(
+                "{:>3}. {:<name_width$}  {:>lines_width$} lines  {:>5.1}%",
+                index + 1,
+                a.author,
+                format_number(a.lines),
+                author_share(a.lines, total)
+            )
+        })
+        .collect();
+
+    lines.push(format_separator().to_string());
+    let noun = if authors.len() == 1 {
+        "author"
+    } else {
+        "authors"
+    };
+    lines.push(format!(
+        "Total ({} {noun})  {} lines",
+        authors.len(),
+        format_number(total)
+    ));
+    lines.join("\n")
+}
+
+pub fn format_authors_json(authors: &[AuthorLines]) -> String {
+    let total: usize = authors.iter().map(|a| a.lines).sum();
+    let entries: Vec<String> = authors
+        .iter()
+        .map(|a| {
+            format!(
+                r#"{{"author":"{}","lines":{},"percent":{:.1}}}"#,
+                escape_json(&a.author),
+                a.lines,
+                author_share(a.lines, total)
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"authors":[{}],"total":{{"author_count":{},"lines":{}}}}}"#,
+        entries.join(","),
+        authors.len(),
+        total
+    )
+}
+
+const SHORT_COMMIT_LEN: usize = 7;
+
+fn short_commit(id: &str) -> &str {
+    id.get(..SHORT_COMMIT_LEN).unwrap_or(id)
+}
+
+pub fn format_history_output(points: &[HistoryPoint], args: &Args) -> String {
+    let mut lines: Vec<String> = points
+        .iter()
+        .map(|p| {
+            format!(
+                "{}  {}  {} {}, {}",
+                p.period,
+                short_commit(&p.commit.id),
+                p.file_count,
+                pluralize_files(p.file_count),
+                format_compact_counts(&p.count, args)
+            )
+        })
+        .collect();
+
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        let delta =
+            CountDelta::between(&last.count, last.file_count, &first.count, first.file_count);
+        lines.push(format_separator().to_string());
+        lines.push(format!(
+            "Change since {}: {}",
+            first.period,
+            format_delta_parts(&delta, args)
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_history_json(points: &[HistoryPoint]) -> String {
+    let history: Vec<String> = points
+        .iter()
+        .map(|p| {
+            format!(
+                r#"{{"period":"{}","date":"{}","commit":"{}","file_count":{},"max_line_length":{},"lines":{},"words":{},"bytes":{}}}"#,
+                escape_json(&p.period),
+                escape_json(&p.commit.date),
+                escape_json(&p.commit.id),
+                p.file_count,
+                p.count.max_line_length,
+                p.count.lines,
+                p.count.words,
+                p.count.bytes
+            )
+        })
+        .collect();
+    format!(r#"{{"history":[{}]}}"#, history.join(","))
+}
+
+pub fn format_history_csv(points: &[HistoryPoint]) -> String {
+    let mut lines = vec!["period,date,commit,files,lines,words,bytes,max_line_length".to_string()];
+    for p in points {
+        lines.push(format!(
+            "{},{},{},{},{},{},{},{}",
+            escape_csv_field(&p.period),
+            escape_csv_field(&p.commit.date),
+            p.commit.id,
+            p.file_count,
+            p.count.lines,
+            p.count.words,
+            p.count.bytes,
+            p.count.max_line_length
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_history_markdown(points: &[HistoryPoint], args: &Args) -> String {
+    let columns = markdown_metric_columns(args);
+    let headers: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+    let mut lines = vec![
+        format!("| Period | Commit | Files | {} |", headers.join(" | ")),
+        format!("|---|---|---:|{}", "---:|".repeat(columns.len())),
+    ];
+    for p in points {
+        let values: Vec<String> = columns
+            .iter()
+            .map(|(_, metric)| format_number(metric.value(&p.count)))
+            .collect();
+        lines.push(format!(
+            "| {} | `{}` | {} | {} |",
+            p.period,
+            short_commit(&p.commit.id),
+            p.file_count,
+            values.join(" | ")
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_total_output(file_count: usize, count: &Count, args: &Args) -> String {
+    let icon = if args.no_color { "" } else { DIR_ICON };
+    let header = format!("{icon}Total ({file_count} {})", pluralize_files(file_count));
+    let mut output = vec![header];
+    output.extend(format_count_lines(count, args));
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::{Checksum, HashAlgorithm};
+    use crate::cli::{ErrorFormat, Order, Symlinks};
+    use crate::counter::LineRatios;
+    use crate::dedupe::DedupeStats;
+    use crate::dialogue::DialogueStats;
+    use crate::documents::Chapters;
+    use crate::entropy::ByteHistogram;
+    use crate::i18n::I18nStats;
+    use crate::languages::{LanguageSummary, LineKinds};
+    use crate::logs::LogLevels;
+    use crate::markdown::MarkdownStats;
+    use crate::meta::FileMeta;
+    use crate::natural::NaturalLanguages;
+    use crate::output::{
+        error_kind_name, format_cloc, format_cloc_csv, format_csv, format_error_json,
+        format_file_list_json, format_json_multiple, format_json_single, format_prometheus,
+        format_tokei_json, write_csv,
+    };
+    use crate::po::PoStats;
+    use crate::records::RecordCount;
+    use crate::structure::StructureStats;
+    use crate::subtitles::SubtitleStats;
+    use crate::symbols::SymbolCounts;
+    use crate::vocabulary::Vocabulary;
+
+    fn default_args() -> Args {
+        Args {
+            command: None,
+            files: vec![],
+            lines: false,
+            words: false,
+            bytes: false,
+            max_line_length: false,
+            ratios: false,
+            no_color: false,
+            all: false,
+            only_hidden: false,
+            compact: false,
+            verbose: false,
+            total_only: false,
+            json: false,
+            exclude: vec![],
+            include: vec![],
+            include_generated: false,
+            special_files: false,
+            symlinks: Symlinks::Skip,
+            order: Order::Sorted,
+            format: None,
+            over: vec![],
+            git: false,
+            changed: None,
+            staged: false,
+            baseline: None,
+            fail_on_growth: false,
+            delta: false,
+            db: None,
+            by_author: false,
+            by_submodule: false,
+            by_package: false,
+            heatmap: false,
+            stream: false,
+            watch: false,
+            follow: None,
+            interval: None,
+            max_stdin_bytes: None,
+            chunk_size: None,
+            rpc: false,
+            strict: false,
+            no_fail: false,
+            errors: ErrorFormat::Text,
+            stats: false,
+            allow_duplicates: false,
+            fail_fast: false,
+            timeout: None,
+            strip_markup: false,
+            code_blocks_only: None,
+            markdown: false,
+            symbols: false,
+            complexity: false,
+            records: None,
+            structure: false,
+            log_levels: false,
+            log_pattern: vec![],
+            dedupe_lines: false,
+            entropy: false,
+            hash: None,
+            show_meta: false,
+            detect_language: false,
+            vocabulary: false,
+            dialogue: false,
+            pages: false,
+            chapters: false,
+            subtitles: false,
+            po: false,
+            i18n: false,
+            comment_ratio: false,
+            cocomo: false,
+            cocomo_type: None,
+            cocomo_wage: None,
+            cocomo_overhead: None,
+            cocomo_eaf: None,
+            no_cache: false,
+            debug: false,
+            trace: false,
+            list: false,
+            print0: false,
+            files_over: Vec::new(),
+            threads: None,
+            no_config: false,
+            profile: None,
+        }
+    }
+
+    #[test]
+    fn format_number_without_comma() {
+        assert_eq!(format_number(123), "123");
+    }
+
+    #[test]
+    fn format_number_with_comma() {
+        assert_eq!(format_number(1234), "1,234");
+    }
+
+    #[test]
+    fn format_number_large() {
+        assert_eq!(format_number(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn format_file_output_all_counts() {
+        let count = Count {
+            lines: 50,
+            words: 200,
+            bytes: 1500,
+            max_line_length: 80,
+        };
+        let args = default_args();
+        let output = format_output("file.txt", &count, OutputKind::File, &args);
+        assert!(output.contains("file.txt"));
+        assert!(output.contains("Lines:"));
+        assert!(output.contains("50"));
+        assert!(output.contains("Words:"));
+        assert!(output.contains("200"));
+        assert!(output.contains("Bytes:"));
+        assert!(output.contains("1,500"));
+    }
+
+    #[test]
+    fn format_file_output_lines_only() {
+        let count = Count {
+            lines: 50,
+            words: 200,
+            bytes: 1500,
+            max_line_length: 80,
+        };
+        let args = Args {
+            lines: true,
+            ..default_args()
+        };
+        let output = format_output("file.txt", &count, OutputKind::File, &args);
+        assert!(output.contains("Lines:"));
+        assert!(!output.contains("Words:"));
+        assert!(!output.contains("Bytes:"));
+    }
+
+    #[test]
+    fn format_separator_test() {
+        let sep = format_separator();
+        assert!(sep.contains("─"));
+        assert_eq!(sep.chars().count(), 25);
+    }
+
+    #[test]
+    fn format_total_output_all_counts() {
+        let count = Count {
+            lines: 80,
+            words: 300,
+            bytes: 2300,
+            max_line_length: 120,
+        };
+        let args = default_args();
+        let output = format_total_output(2, &count, &args);
+        assert!(output.contains("Total (2 files)"));
+        assert!(output.contains("Lines:"));
+        assert!(output.contains("80"));
+        assert!(output.contains("Words:"));
+        assert!(output.contains("300"));
+        assert!(output.contains("Bytes:"));
+        assert!(output.contains("2,300"));
+    }
+
+    #[test]
+    fn format_total_pluralization() {
+        let count = Count::default();
+        let args = default_args();
+        let output_single = format_total_output(1, &count, &args);
+        assert!(output_single.contains("1 file)"));
+
+        let output_plural = format_total_output(2, &count, &args);
+        assert!(output_plural.contains("2 files)"));
+    }
+
+    #[test]
+    fn format_total_output_lines_only() {
+        let count = Count {
+            lines: 80,
+            words: 300,
+            bytes: 2300,
+            max_line_length: 120,
+        };
+        let args = Args {
+            lines: true,
+            ..default_args()
+        };
+        let output = format_total_output(2, &count, &args);
+        assert!(output.contains("Lines:"));
+        assert!(!output.contains("Words:"));
+        assert!(!output.contains("Bytes:"));
+    }
+
+    #[test]
+    fn format_directory_output_all_counts() {
+        let count = Count {
+            lines: 1234,
+            words: 5678,
+            bytes: 45000,
+            max_line_length: 200,
+        };
+        let args = default_args();
+        let output = format_output("src/", &count, OutputKind::Directory(5), &args);
+        assert!(output.contains("\u{1F4C1} src/ (5 files)"));
+        assert!(output.contains("Lines:"));
+        assert!(output.contains("1,234"));
+        assert!(output.contains("Words:"));
+        assert!(output.contains("5,678"));
+        assert!(output.contains("Bytes:"));
+        assert!(output.contains("45,000"));
+    }
+
+    #[test]
+    fn format_directory_output_single_file() {
+        let count = Count {
+            lines: 10,
+            words: 20,
+            bytes: 100,
+            max_line_length: 50,
+        };
+        let args = default_args();
+        let output = format_output("dir/", &count, OutputKind::Directory(1), &args);
+        assert!(output.contains("\u{1F4C1} dir/ (1 file)"));
+    }
+
+    #[test]
+    fn format_output_without_icons() {
+        let count = Count {
+            lines: 50,
+            words: 200,
+            bytes: 1500,
+            max_line_length: 80,
+        };
+        let args = Args {
+            no_color: true,
+            ..default_args()
+        };
+        let output = format_output("file.txt", &count, OutputKind::File, &args);
+        assert!(!output.contains("\u{1F4C4}")); // No file icon
+        assert!(output.contains("file.txt"));
+    }
+
+    #[test]
+    fn format_directory_output_without_icons() {
+        let count = Count {
+            lines: 50,
+            words: 200,
+            bytes: 1500,
+            max_line_length: 80,
+        };
+        let args = Args {
+            no_color: true,
+            ..default_args()
+        };
+        let output = format_output("src/", &count, OutputKind::Directory(3), &args);
+        assert!(!output.contains("\u{1F4C1}")); // No folder icon
+        assert!(output.contains("src/"));
+    }
+
+    #[test]
+    fn format_total_output_without_icons() {
+        let count = Count {
+            lines: 80,
+            words: 300,
+            bytes: 2300,
+            max_line_length: 120,
+        };
+        let args = Args {
+            no_color: true,
+            ..default_args()
+        };
+        let output = format_total_output(2, &count, &args);
+        assert!(!output.contains("\u{1F4C1}")); // No folder icon
+        assert!(output.contains("Total (2 files)"));
+    }
+
+    #[test]
+    fn format_compact_output_all_counts() {
+        let count = Count {
+            lines: 50,
+            words: 200,
+            bytes: 1500,
+            max_line_length: 80,
+        };
+        let args = Args {
+            compact: true,
+            ..default_args()
+        };
+        let output = format_compact_output("file.txt", &count, OutputKind::File, &args);
+        assert!(output.contains("file.txt:"));
+        assert!(output.contains("50 lines"));
+        assert!(output.contains("200 words"));
+        assert!(output.contains("1,500 bytes"));
+        assert_eq!(output.lines().count(), 1);
+    }
+
+    #[test]
+    fn format_compact_output_lines_only() {
+        let count = Count {
+            lines: 50,
+            words: 200,
+            bytes: 1500,
+            max_line_length: 80,
+        };
+        let args = Args {
+            lines: true,
+            compact: true,
+            ..default_args()
+        };
+        let output = format_compact_output("file.txt", &count, OutputKind::File, &args);
+        assert!(output.contains("50 lines"));
+        assert!(!output.contains("words"));
+        assert!(!output.contains("bytes"));
+    }
+
+    #[test]
+    fn format_compact_directory() {
+        let count = Count {
+            lines: 150,
+            words: 500,
+            bytes: 3000,
+            max_line_length: 100,
+        };
+        let args = Args {
+            compact: true,
+            ..default_args()
+        };
+        let output = format_compact_output("src/", &count, OutputKind::Directory(3), &args);
+        assert!(output.contains("src/ (3 files):"));
+        assert!(output.contains("150 lines"));
+    }
+
+    #[test]
+    fn format_compact_total_output() {
+        let count = Count {
+            lines: 235,
+            words: 800,
+            bytes: 5000,
+            max_line_length: 150,
+        };
+        let args = Args {
+            compact: true,
+            ..default_args()
+        };
+        let output = format_compact_total(5, &count, &args);
+        assert!(output.contains("Total (5 files):"));
+        assert!(output.contains("235 lines"));
+    }
+
+    #[test]
+    fn format_output_max_line_length_only() {
+        let count = Count {
+            lines: 50,
+            words: 200,
+            bytes: 1500,
+            max_line_length: 120,
+        };
+        let args = Args {
+            max_line_length: true,
+            ..default_args()
+        };
+        let output = format_output("file.txt", &count, OutputKind::File, &args);
+        assert!(output.contains("Max Line:"));
+        assert!(output.contains("120"));
+        assert!(!output.contains("Lines:"));
+        assert!(!output.contains("Words:"));
+        assert!(!output.contains("Bytes:"));
+    }
+
+    #[test]
+    fn format_compact_with_max_line_length() {
+        let count = Count {
+            lines: 50,
+            words: 200,
+            bytes: 1500,
+            max_line_length: 120,
+        };
+        let args = Args {
+            max_line_length: true,
+            compact: true,
+            ..default_args()
+        };
+        let output = format_compact_output("file.txt", &count, OutputKind::File, &args);
+        assert!(output.contains("max:120"));
+        assert!(!output.contains("lines"));
+    }
+
+    fn sample_violation() -> Violation {
+        Violation {
+            path: std::path::PathBuf::from("src/big.rs"),
+            threshold: "lines=500".parse().unwrap(),
+            actual: 1200,
+        }
+    }
+
+    #[test]
+    fn format_violation_text() {
+        let output = format_violation(&sample_violation());
+        assert_eq!(output, "src/big.rs: 1,200 lines exceeds limit of 500");
+    }
+
+    #[test]
+    fn files_over_groups_limits_per_file() {
+        let bytes = Violation {
+            threshold: "bytes>4096".parse().unwrap(),
+            actual: 50_000,
+            ..sample_violation()
+        };
+        let stricter = Violation {
+            threshold: "lines>1000".parse().unwrap(),
+            ..sample_violation()
+        };
+        let other = Violation {
+            path: std::path::PathBuf::from("src/a \"b\".rs"),
+            actual: 700,
+            ..sample_violation()
+        };
+        let violations = [sample_violation(), bytes, stricter, other];
+        assert_eq!(
+            format_files_over(&violations),
+            "src/big.rs: 1,200 lines, 50,000 bytes\nsrc/a \"b\".rs: 700 lines"
+        );
+        assert_eq!(
+            format_files_over_json(&violations),
+            r#"{"files":[{"path":"src/big.rs","lines":1200,"bytes":50000},{"path":"src/a \"b\".rs","lines":700}]}"#
+        );
+        assert_eq!(format_files_over_json(&[]), r#"{"files":[]}"#);
+    }
+
+    #[test]
+    fn format_sarif_contains_results() {
+        let violation = sample_violation();
+        let thresholds = [violation.threshold];
+        let output = format_sarif(&[violation], &thresholds);
+        assert!(output.contains(r#""version":"2.1.0""#));
+        assert!(output.contains(r#""name":"ewc""#));
+        assert!(output.contains(r#""rules":[{"id":"lines""#));
+        assert!(output.contains(r#""ruleId":"lines""#));
+        assert!(output.contains(r#""uri":"src/big.rs""#));
+        assert!(output.contains("1200 lines exceeds limit of 500"));
+    }
+
+    #[test]
+    fn format_sarif_without_violations_has_empty_results() {
+        let thresholds: Vec<Threshold> = vec!["lines=500".parse().unwrap()];
+        let output = format_sarif(&[], &thresholds);
+        assert!(output.contains(r#""results":[]"#));
+    }
+
+    #[test]
+    fn format_sarif_deduplicates_rules() {
+        let thresholds: Vec<Threshold> =
+            vec!["lines=500".parse().unwrap(), "lines=900".parse().unwrap()];
+        let output = format_sarif(&[], &thresholds);
+        assert_eq!(output.matches(r#""id":"lines""#).count(), 1);
+    }
+
+    fn entry(path: &str, lines: usize) -> FileEntry {
+        FileEntry {
+            path: std::path::PathBuf::from(path),
+            count: Count {
+                lines,
+                words: lines * 2,
+                bytes: lines * 10,
+                max_line_length: 10,
+            },
+        }
+    }
+
+    #[test]
+    fn format_pr_comment_totals() {
+        let entries = vec![entry("a.rs", 10), entry("b.rs", 1200)];
+        let output = format_pr_comment(&entries, &[], &[], None);
+        assert!(output.starts_with("### ewc report"));
+        assert!(output.contains("| **Total** | 2 | 1,210 | 2,420 | 12,100 |"));
+        assert!(!output.contains("**Budget:**"));
+    }
+
+    #[test]
+    fn format_pr_comment_largest_files_sorted_and_capped() {
+        let entries: Vec<FileEntry> = (1..=7).map(|i| entry(&format!("f{i}.rs"), i)).collect();
+        let output = format_pr_comment(&entries, &[], &[], None);
+        let f7 = output.find("`f7.rs`").unwrap();
+        let f6 = output.find("`f6.rs`").unwrap();
+        assert!(f7 < f6);
+        assert!(!output.contains("`f2.rs`"));
+        assert!(!output.contains("`f1.rs`"));
+    }
+
+    #[test]
+    fn format_pr_comment_budget_status() {
+        let entries = vec![entry("src/big.rs", 1200)];
+        let violation = sample_violation();
+        let thresholds = [violation.threshold];
+
+        let passing = format_pr_comment(&entries, &[], &thresholds, None);
+        assert!(passing.contains("all files within limits"));
+
+        let failing = format_pr_comment(&entries, &[violation], &thresholds, None);
+        assert!(failing.contains(":x: 1 violation"));
+        assert!(failing.contains("- `src/big.rs`: 1,200 lines (limit 500)"));
+    }
+
+    #[test]
+    fn format_prometheus_metrics_per_path() {
+        let results = vec![
+            JsonFileResult {
+                name: "src".to_string(),
+                count: Count {
+                    lines: 12345,
+                    words: 500,
+                    bytes: 9000,
+                    max_line_length: 99,
+                },
+                is_directory: true,
+                file_count: Some(12),
+                skipped: Vec::new(),
+                skip_summary: SkipSummary::default(),
+                analysis: FileAnalysis::default(),
+            },
+            JsonFileResult {
+                name: "README.md".to_string(),
+                count: Count {
+                    lines: 40,
+                    words: 300,
+                    bytes: 2000,
+                    max_line_length: 80,
+                },
+                is_directory: false,
+                file_count: None,
+                skipped: Vec::new(),
+                skip_summary: SkipSummary::default(),
+                analysis: FileAnalysis::default(),
+            },
+        ];
+        let output = format_prometheus(&results);
+        assert!(output.contains("# TYPE ewc_lines_total gauge"));
+        assert!(output.contains(r#"ewc_lines_total{path="src"} 12345"#));
+        assert!(output.contains(r#"ewc_files_total{path="src"} 12"#));
+        assert!(output.contains(r#"ewc_files_total{path="README.md"} 1"#));
+        assert!(output.contains(r#"ewc_max_line_length{path="README.md"} 80"#));
+    }
+
+    #[test]
+    fn format_prometheus_escapes_labels() {
+        let results = vec![JsonFileResult {
+            name: r#"we"ird\path"#.to_string(),
+            count: Count::default(),
+            is_directory: false,
+            file_count: None,
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
+        }];
+        let output = format_prometheus(&results);
+        assert!(output.contains(r#"ewc_lines_total{path="we\"ird\\path"} 0"#));
+    }
+
+    #[test]
+    fn format_signed_values() {
+        assert_eq!(format_signed(1234), "+1,234");
+        assert_eq!(format_signed(-3), "-3");
+        assert_eq!(format_signed(0), "0");
+    }
+
+    fn sample_delta() -> ReportDelta {
+        ReportDelta {
+            total: CountDelta {
+                files: -3,
+                lines: 120,
+                words: 400,
+                bytes: 2000,
+            },
+            changes: vec![
+                crate::delta::EntryChange {
+                    name: "src".to_string(),
+                    kind: ChangeKind::Changed,
+                    delta: CountDelta {
+                        files: 0,
+                        lines: 100,
+                        words: 0,
+                        bytes: 0,
+                    },
+                },
+                crate::delta::EntryChange {
+                    name: "old.txt".to_string(),
+                    kind: ChangeKind::Removed,
+                    delta: CountDelta {
+                        files: -1,
+                        lines: -40,
+                        words: -80,
+                        bytes: -400,
+                    },
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn format_baseline_delta_totals_and_changes() {
+        let output = format_baseline_delta(&sample_delta(), &default_args());
+        assert!(output.starts_with("Change vs baseline"));
+        assert!(output.contains("   Files:         -3"));
+        assert!(output.contains("   Lines:       +120"));
+        assert!(output.contains("   Bytes:     +2,000"));
+        assert!(output.contains("   ~ src: +100 lines, 0 words, 0 bytes"));
+        assert!(output.contains("   - old.txt: -40 lines, -80 words, -400 bytes"));
+    }
+
+    #[test]
+    fn format_baseline_delta_respects_metric_flags() {
+        let args = Args {
+            lines: true,
+            ..default_args()
+        };
+        let output = format_baseline_delta(&sample_delta(), &args);
+        assert!(output.contains("Lines:"));
+        assert!(!output.contains("Words:"));
+        assert!(output.ends_with("   - old.txt: -40 lines"));
+    }
+
+    #[test]
+    fn format_pr_comment_with_baseline() {
+        let entries = vec![entry("a.rs", 10)];
+        let output = format_pr_comment(&entries, &[], &[], Some(&sample_delta()));
+        assert!(output.contains("| **Change** | -3 | +120 | +400 | +2,000 |"));
+    }
+
+    fn json_result(name: &str, lines: usize, file_count: Option<usize>) -> JsonFileResult {
+        JsonFileResult {
+            name: name.to_string(),
+            count: Count {
+                lines,
+                words: lines * 2,
+                bytes: lines * 10,
+                max_line_length: 10,
+            },
+            is_directory: file_count.is_some(),
+            file_count,
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
+        }
+    }
+
+    #[test]
+    fn format_markdown_table_with_total() {
+        let results = vec![
+            json_result("src", 1200, Some(4)),
+            json_result("README.md", 30, None),
+        ];
+        let total = results.iter().map(|r| r.count).sum();
+        let output = format_markdown(&results, &total, &default_args());
+        assert!(output
+            .starts_with("| Path | Files | Lines | Words | Bytes |\n|---|---:|---:|---:|---:|"));
+        assert!(output.contains("| `src` | 4 | 1,200 | 2,400 | 12,000 |"));
+        assert!(output.contains("| `README.md` | 1 | 30 | 60 | 300 |"));
+        assert!(output.contains("| **Total** | 5 | 1,230 | 2,460 | 12,300 |"));
+    }
+
+    #[test]
+    fn format_markdown_respects_metric_flags() {
+        let results = vec![json_result("a.txt", 3, None)];
+        let args = Args {
+            lines: true,
+            ..default_args()
+        };
+        let output = format_markdown(&results, &results[0].count.clone(), &args);
+        assert!(output.starts_with("| Path | Files | Lines |\n|---|---:|---:|"));
+        assert!(!output.contains("Total"));
+    }
+
+    #[test]
+    fn format_mermaid_roots_the_tree_at_the_common_directory() {
+        let entries = vec![
+            entry("./src/main.rs", 300),
+            entry("./src/output/text.rs", 1200),
+            entry("./src/output/json.rs", 100),
+        ];
+        assert_eq!(
+            format_mermaid(&entries),
+            "flowchart TD\n    n0[\"src<br/>1,600 lines\"]\n    n1[\"output<br/>1,300 lines\"]\n    n0 --> n1"
+        );
+        let entries = vec![entry("README.md", 50), entry("src/lib.rs", 20)];
+        assert_eq!(
+            format_mermaid(&entries),
+            "flowchart TD\n    n0[\".<br/>70 lines\"]\n    n1[\"src<br/>20 lines\"]\n    n0 --> n1"
+        );
+    }
+
+    #[test]
+    fn format_heatmap_puts_larger_directories_first() {
+        let entries = vec![
+            entry("src/cli/args.rs", 100),
+            entry("src/output/text.rs", 800),
+            entry("src/main.rs", 100),
+        ];
+        let bar = |filled: usize| {
+            format!(
+                "{}{}",
+                "\u{2588}".repeat(filled),
+                "\u{2591}".repeat(20 - filled)
+            )
+        };
+        assert_eq!(
+            format_heatmap(&entries, false),
+            format!(
+                "src       1,000  {} 100.0%\n  output    800  {}  80.0%\n  cli       100  {}  10.0%",
+                bar(20),
+                bar(16),
+                bar(2)
+            )
+        );
+        let colored = format_heatmap(&entries, true);
+        assert!(colored.contains("\x1b[31m\u{2588}"));
+        assert!(colored.contains("\x1b[32m\u{2588}\u{2588}\u{2591}"));
+    }
+
+    #[test]
+    fn format_dot_escapes_labels() {
+        let entries = vec![entry("a/say \"hi\"/x.rs", 5), entry("a/b.rs", 1)];
+        let output = format_dot(&entries);
+        assert!(output.starts_with("digraph ewc {\n    rankdir=LR;\n    node [shape=box];"));
+        assert!(output.contains(r#"    n0 [label="a\n6 lines"];"#));
+        assert!(output.contains(r#"    n1 [label="say \"hi\"\n5 lines"];"#));
+        assert!(output.ends_with("    n0 -> n1;\n}"));
+    }
+
+    #[test]
+    fn format_html_table_with_total() {
+        let results = vec![
+            json_result("src", 1200, Some(4)),
+            json_result("<b>.md", 30, None),
+        ];
+        let total = results.iter().map(|r| r.count).sum();
+        let output = format_html(&results, &total, &default_args());
+        assert!(output.starts_with("<!DOCTYPE html>\n<html>"));
+        assert!(output.contains(
+            "<thead><tr><th>Path</th><th>Files</th><th>Lines</th><th>Words</th><th>Bytes</th></tr></thead>"
+        ));
+        assert!(output.contains(
+            "<tr><td><code>src</code></td><td>4</td><td>1,200</td><td>2,400</td><td>12,000</td></tr>"
+        ));
+        assert!(output.contains("<code>&lt;b&gt;.md</code>"));
+        assert!(output.contains("<tfoot><tr><th>Total</th><td>5</td><td>1,230</td>"));
+        assert!(output.ends_with("</table>\n</body>\n</html>"));
+    }
+
+    fn sample_diffs() -> Vec<FileDiff> {
+        vec![
+            FileDiff {
+                path: "src/main.rs".to_string(),
+                lines_added: 12,
+                lines_removed: 3,
+                words_added: 40,
+                words_removed: 10,
+            },
+            FileDiff {
+                path: "README.md".to_string(),
+                lines_added: 1000,
+                lines_removed: 0,
+                words_added: 5,
+                words_removed: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn format_diff_output_lists_files_and_total() {
+        let output = format_diff_output(&sample_diffs(), &default_args());
+        assert!(output.contains("\u{1F4C4} src/main.rs  +12 -3 lines, +40 -10 words"));
+        assert!(output.contains("Total (2 files)  +1,012 -3 lines, +45 -10 words"));
+    }
+
+    #[test]
+    fn format_diff_json_structure() {
+        let output = format_diff_json("main..HEAD", &sample_diffs());
+        assert!(output.starts_with(
+            r#"{"range":"main..HEAD","files":[{"file":"src/main.rs","lines_added":12"#
+        ));
+        assert!(output.ends_with(r#""total":{"file_count":2,"lines_added":1012,"lines_removed":3,"words_added":45,"words_removed":10}}"#));
+    }
+
+    #[test]
+    fn format_diff_markdown_table() {
+        let output = format_diff_markdown(&sample_diffs());
+        assert!(output.contains("| `src/main.rs` | +12 | -3 | +40 | -10 |"));
+        assert!(output.contains("| **Total (2 files)** | +1,012 | -3 | +45 | -10 |"));
+    }
+
+    #[test]
+    fn format_csv_rows_per_file() {
+        let entries = vec![
+            FileEntry {
+                path: std::path::PathBuf::from("src/main.rs"),
+                count: Count {
+                    lines: 10,
+                    words: 20,
+                    bytes: 300,
+                    max_line_length: 40,
+                },
+            },
+            FileEntry {
+                path: std::path::PathBuf::from("odd, \"name\".txt"),
+                count: Count::default(),
+            },
+        ];
+        assert_eq!(
+            format_csv(&entries, &[]),
+            "path,lines,words,bytes,max_line_length\nsrc/main.rs,10,20,300,40\n\"odd, \"\"name\"\".txt\",0,0,0,0"
+        );
+    }
+
+    #[test]
+    fn format_cloc_table_and_csv() {
+        let summary = |name, files, blank, comment, code| LanguageSummary {
+            name,
+            files,
+            lines: LineKinds {
+                blank,
+                comment,
+                code,
+            },
+        };
+        let summaries = [summary("Rust", 3, 10, 5, 120), summary("TOML", 1, 2, 0, 8)];
+        let version = env!("CARGO_PKG_VERSION");
+        let rule = "-".repeat(79);
+        assert_eq!(
+            format_cloc(&summaries),
+            [
+                format!("ewc v {version}"),
+                rule.clone(),
+                "Language                     files          blank        comment           code"
+                    .to_string(),
+                rule.clone(),
+                "Rust                             3             10              5            120"
+                    .to_string(),
+                "TOML                             1              2              0              8"
+                    .to_string(),
+                rule.clone(),
+                "SUM:                             4             12              5            128"
+                    .to_string(),
+                rule,
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            format_cloc_csv(&summaries),
+            format!("files,language,blank,comment,code,\"ewc v {version}\"\n3,Rust,10,5,120\n1,TOML,2,0,8\n4,SUM,12,5,128")
+        );
+    }
+
+    #[test]
+    fn format_tokei_json_groups_reports_by_language() {
+        let entry = |path: &str| FileEntry {
+            path: std::path::PathBuf::from(path),
+            count: Count::default(),
+        };
+        let analysis = |blank, comment, code| FileAnalysis {
+            line_kinds: Some(LineKinds {
+                blank,
+                comment,
+                code,
+            }),
+            ..FileAnalysis::default()
+        };
+        let entries = [entry("a.rs"), entry("b.txt"), entry("c.py")];
+        let analyses = [
+            analysis(1, 2, 3),
+            FileAnalysis::default(),
+            analysis(0, 1, 4),
+        ];
+        let report = |name, blanks, code, comments| {
+            format!(
+                r#"{{"stats":{{"blanks":{blanks},"code":{code},"comments":{comments},"blobs":{{}}}},"name":"{name}"}}"#
+            )
+        };
+        let (rust, python) = (report("a.rs", 1, 3, 2), report("c.py", 0, 4, 1));
+        assert_eq!(
+            format_tokei_json(&entries, &analyses),
+            format!(
+                concat!(
+                    r#"{{"Python":{{"blanks":0,"code":4,"comments":1,"reports":[{python}],"children":{{}},"inaccurate":false}},"#,
+                    r#""Rust":{{"blanks":1,"code":3,"comments":2,"reports":[{rust}],"children":{{}},"inaccurate":false}},"#,
+                    r#""Total":{{"blanks":1,"code":7,"comments":3,"reports":[],"children":{{"Python":[{python}],"Rust":[{rust}]}},"inaccurate":false}}}}"#
+                ),
+                python = python,
+                rust = rust
+            )
+        );
+    }
+
+    fn sample_history() -> Vec<HistoryPoint> {
+        let point = |period: &str, date: &str, id: &str, lines, file_count| HistoryPoint {
+            period: period.to_string(),
+            commit: crate::git::Commit {
+                id: id.to_string(),
+                date: date.to_string(),
+            },
+            count: Count {
+                lines,
+                words: lines * 3,
+                bytes: lines * 30,
+                max_line_length: 80,
+            },
+            file_count,
+        };
+        vec![
+            point("2024-01", "2024-01-30", "0123456789abcdef", 1000, 10),
+            point("2024-02", "2024-02-27", "fedcba9876543210", 1500, 12),
+        ]
+    }
+
+    #[test]
+    fn format_history_output_lists_samples_and_change() {
+        let output = format_history_output(&sample_history(), &default_args());
+        assert!(
+            output.contains("2024-01  0123456  10 files, 1,000 lines, 3,000 words, 30,000 bytes")
+        );
+        assert!(output.contains("2024-02  fedcba9  12 files, 1,500 lines"));
+        assert!(output.ends_with("Change since 2024-01: +500 lines, +1,500 words, +15,000 bytes"));
+    }
+
+    #[test]
+    fn format_history_output_empty() {
+        assert_eq!(format_history_output(&[], &default_args()), "");
+    }
+
+    #[test]
+    fn format_history_json_structure() {
+        let output = format_history_json(&sample_history());
+        assert!(output.starts_with(
+            r#"{"history":[{"period":"2024-01","date":"2024-01-30","commit":"0123456789abcdef","file_count":10,"max_line_length":80,"lines":1000"#
+        ));
+        assert!(output.ends_with("}]}"));
+    }
+
+    #[test]
+    fn format_history_csv_rows() {
+        let output = format_history_csv(&sample_history());
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "period,date,commit,files,lines,words,bytes,max_line_length",
+                "2024-01,2024-01-30,0123456789abcdef,10,1000,3000,30000,80",
+                "2024-02,2024-02-27,fedcba9876543210,12,1500,4500,45000,80",
+            ]
+        );
+    }
+
+    #[test]
+    fn format_history_markdown_table() {
+        let args = Args {
+            lines: true,
+            ..default_args()
+        };
+        let output = format_history_markdown(&sample_history(), &args);
+        assert!(output.starts_with("| Period | Commit | Files | Lines |\n|---|---|---:|---:|"));
+        assert!(output.contains("| 2024-02 | `fedcba9` | 12 | 1,500 |"));
+    }
+
+    fn sample_authors() -> Vec<AuthorLines> {
+        vec![
+            AuthorLines {
+                author: "Alice".to_string(),
+                lines: 1500,
+            },
+            AuthorLines {
+                author: "Bob".to_string(),
+                lines: 500,
+            },
+        ]
+    }
+
+    #[test]
+    fn format_authors_output_leaderboard() {
+        let output = format_authors_output(&sample_authors());
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "  1. Alice  1,500 lines   75.0%");
+        assert_eq!(lines[1], "  2. Bob      500 lines   25.0%");
+        assert_eq!(lines[3], "Total (2 authors)  2,000 lines");
+    }
+
+    #[test]
+    fn format_authors_json_structure() {
+        assert_eq!(
+            format_authors_json(&sample_authors()),
+            r#"{"authors":[{"author":"Alice","lines":1500,"percent":75.0},{"author":"Bob","lines":500,"percent":25.0}],"total":{"author_count":2,"lines":2000}}"#
+        );
+    }
+
+    #[test]
+    fn format_authors_output_empty() {
+        assert!(format_authors_output(&[]).ends_with("Total (0 authors)  0 lines"));
+    }
+
+    fn sample_comparison() -> TreeComparison {
+        let tree = |name: &str, lines, file_count| ReportEntry {
+            name: name.to_string(),
+            count: Count {
+                lines,
+                words: lines * 2,
+                bytes: lines * 10,
+                max_line_length: 0,
+            },
+            file_count,
+            is_directory: true,
+        };
+        let change = |name: &str, kind, lines: i64| EntryChange {
+            name: name.to_string(),
+            kind,
+            delta: CountDelta {
+                files: 0,
+                lines,
+                words: lines * 2,
+                bytes: lines * 10,
+            },
+        };
+        TreeComparison {
+            a: tree("v1", 100, 3),
+            b: tree("v2", 130, 3),
+            delta: ReportDelta {
+                total: CountDelta {
+                    files: 0,
+                    lines: 30,
+                    words: 60,
+                    bytes: 300,
+                },
+                changes: vec![
+                    change("new.rs", ChangeKind::Added, 40),
+                    change("main.rs", ChangeKind::Changed, 5),
+                    change("old.rs", ChangeKind::Removed, -15),
+                ],
+            },
+        }
+    }
+
+    #[test]
+    fn format_compare_output_groups_changes() {
+        let args = Args {
+            lines: true,
+            ..default_args()
+        };
+        let output = format_compare_output(&sample_comparison(), &args);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "\u{1F4C1} v1 (3 files)  100 lines",
+                "\u{1F4C1} v2 (3 files)  130 lines",
+                format_separator(),
+                "Only in v1 (1):",
+                "   - old.rs: -15 lines",
+                "Only in v2 (1):",
+                "   + new.rs: +40 lines",
+                "Changed (1):",
+                "   ~ main.rs: +5 lines",
+                format_separator(),
+                "Change: 0 files, +30 lines",
+            ]
+        );
+    }
+
+    #[test]
+    fn format_compare_output_identical_trees() {
+        let mut comparison = sample_comparison();
+        comparison.delta.changes.clear();
+        let output = format_compare_output(&comparison, &default_args());
+        assert!(output.contains("No differences"));
+    }
+
+    #[test]
+    fn format_compare_json_structure() {
+        let output = format_compare_json(&sample_comparison());
+        assert!(output.starts_with(
+            r#"{"a":{"directory":"v1","file_count":3,"max_line_length":0,"lines":100,"words":200,"bytes":1000},"b":{"directory":"v2""#
+        ));
+        assert!(output.contains(
+            r#""only_in_a":[{"file":"old.rs","lines":15,"words":30,"bytes":150}],"only_in_b":[{"file":"new.rs","lines":40"#
+        ));
+        assert!(output.ends_with(
+            r#""changed":[{"file":"main.rs","lines":5,"words":10,"bytes":50}],"delta":{"files":0,"lines":30,"words":60,"bytes":300}}"#
+        ));
+    }
+
+    #[test]
+    fn format_report_delta_custom_title() {
+        let output =
+            format_report_delta("Change vs snapshot v1.0", &sample_delta(), &default_args());
+        assert!(output.starts_with("Change vs snapshot v1.0\n   Files:"));
+    }
+
+    #[test]
+    fn format_snapshot_diff_json_structure() {
+        assert_eq!(
+            format_snapshot_diff_json("v1.0", &sample_delta()),
+            r#"{"snapshot":"v1.0","changes":[{"file":"src","status":"changed","lines":100,"words":0,"bytes":0},{"file":"old.txt","status":"removed","lines":-40,"words":-80,"bytes":-400}],"delta":{"files":-3,"lines":120,"words":400,"bytes":2000}}"#
+        );
+    }
+
+    #[test]
+    fn file_list_json_escapes_paths() {
+        let files = vec![
+            std::path::PathBuf::from("src/a.rs"),
+            std::path::PathBuf::from("odd\"name.txt"),
+        ];
+        assert_eq!(
+            format_file_list_json(&files),
+            r#"{"files":["src/a.rs","odd\"name.txt"],"file_count":2}"#
+        );
+        assert_eq!(format_file_list_json(&[]), r#"{"files":[],"file_count":0}"#);
+    }
+
+    #[test]
+    fn total_only_prints_bare_selected_numbers() {
+        let count = Count {
+            lines: 12345,
+            words: 67890,
+            bytes: 1234567,
+            max_line_length: 80,
+        };
+        let args = Args {
+            lines: true,
+            ..default_args()
+        };
+        assert_eq!(format_total_only(&count, &args), "12345");
+        let args = Args {
+            words: true,
+            bytes: true,
+            ..default_args()
+        };
+        assert_eq!(format_total_only(&count, &args), "67890 1234567");
+        assert_eq!(
+            format_total_only(&count, &default_args()),
+            "12345 67890 1234567"
+        );
+    }
+
+    #[test]
+    fn streamed_output_stops_at_the_first_write_error() {
+        struct Full(usize);
+        impl io::Write for Full {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.0 < buf.len() {
+                    return Err(io::ErrorKind::StorageFull.into());
+                }
+                self.0 -= buf.len();
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let entries = vec![entry("a.rs", 1), entry("b.rs", 2)];
+        let total = entries[0].count + entries[1].count;
+        let args = default_args();
+        let mut out = Full(1 << 20);
+        write_verbose_output(&mut out, &entries, &[], &total, &[], &args).unwrap();
+        let full = format_verbose_output(&entries, &[], &total, &[], &args);
+        assert_eq!(out.0, (1 << 20) - full.len());
+
+        let mut out = Full(10);
+        let error = write_verbose_output(&mut out, &entries, &[], &total, &[], &args).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+        assert!(write_csv(&mut Full(10), &entries, &[]).is_err());
+    }
+
+    #[test]
+    fn verbose_output_lists_skipped_files() {
+        let entries = vec![FileEntry {
+            path: std::path::PathBuf::from("src/a.rs"),
+            count: Count {
+                lines: 3,
+                ..Count::default()
+            },
+        }];
+        let skipped = vec![SkippedFile {
+            path: std::path::PathBuf::from("src/secret.rs"),
+            kind: std::io::ErrorKind::PermissionDenied,
+            reason: "Permission denied (os error 13)".to_string(),
+        }];
+        let output =
+            format_verbose_output(&entries, &[], &entries[0].count, &skipped, &default_args());
+        assert!(output.ends_with("Skipped (1):\n  src/secret.rs: Permission denied (os error 13)"));
+
+        let output = format_verbose_output(&entries, &[], &entries[0].count, &[], &default_args());
+        assert!(!output.contains("Skipped"));
+    }
+
+    #[test]
+    fn json_lists_skipped_files_for_directories() {
+        let mut result = json_result("src", 3, Some(1));
+        assert!(!format_json_single(&result).contains("skipped"));
+        result.skipped.push(SkippedFile {
+            path: std::path::PathBuf::from("src/secret.rs"),
+            kind: std::io::ErrorKind::PermissionDenied,
+            reason: "Permission denied".to_string(),
+        });
+        assert!(format_json_single(&result).ends_with(
+            r#""bytes":30,"skipped":[{"path":"src/secret.rs","reason":"Permission denied"}]}"#
+        ));
+    }
+
+    #[test]
+    fn skip_summary_in_text_and_json() {
+        let summary = SkipSummary {
+            hidden: 12,
+            excluded: 1340,
+            binary: 5,
+            ..SkipSummary::default()
+        };
+        assert_eq!(format_skip_summary(&SkipSummary::default()), None);
+        assert_eq!(
+            format_skip_summary(&summary).unwrap(),
+            "hidden 12, excluded 1,340, binary 5"
+        );
+
+        let mut result = json_result("src", 3, Some(1));
+        assert!(!format_json_single(&result).contains("skip_summary"));
+        result.skip_summary = summary;
+        assert!(format_json_single(&result).ends_with(
+            r#""bytes":30,"skip_summary":{"hidden":12,"visible":0,"excluded":1340,"generated":0,"binary":5,"unreadable":0,"special":0,"symlinks":0,"timed_out":0}}"#
+        ));
+    }
+
+    #[test]
+    fn markdown_stats_in_text_and_json() {
+        let stats = MarkdownStats {
+            words: 1200,
+            headings: 8,
+            links: 3,
+            images: 1,
+            code_blocks: 2,
+        };
+        let analysis = FileAnalysis {
+            markdown: Some(stats),
+            ..FileAnalysis::default()
+        };
+        let rows = format_analysis(&analysis);
+        assert_eq!(rows[0], "   Prose:      1,200");
+        assert_eq!(rows[1], "Headings:          8");
+        assert_eq!(rows[4], "  Fences:          2");
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "1,200 prose words, 8 headings, 3 links, 1 images, 2 code blocks"
+        );
+
+        let mut result = json_result("README.md", 3, None);
+        assert!(!format_json_single(&result).contains("markdown"));
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(
+            r#""bytes":30,"markdown":{"words":1200,"headings":8,"links":3,"images":1,"code_blocks":2}}"#
+        ));
+        let output =
+            format_json_multiple(&[result, json_result("a.txt", 1, None)], &Count::default());
+        assert!(output.ends_with(
+            r#""markdown":{"words":1200,"headings":8,"links":3,"images":1,"code_blocks":2}}}"#
+        ));
+    }
+
+    #[test]
+    fn symbol_counts_in_verbose_and_json_output() {
+        let symbols = |functions| FileAnalysis {
+            symbols: Some(SymbolCounts {
+                functions,
+                types: 1,
+                tests: 0,
+            }),
+            ..FileAnalysis::default()
+        };
+        let entries = vec![
+            FileEntry {
+                path: std::path::PathBuf::from("src/a.rs"),
+                count: Count::from_content("fn a() {}\n"),
+            },
+            FileEntry {
+                path: std::path::PathBuf::from("src/b.txt"),
+                count: Count::from_content("b\n"),
+            },
+        ];
+        let analyses = [symbols(3), FileAnalysis::default()];
+        let total = entries.iter().map(|e| e.count).sum();
+        let output = format_verbose_output(&entries, &analyses, &total, &[], &default_args());
+        assert!(output.contains("src/a.rs  1 lines  (3 functions, 1 types, 0 tests)\n"));
+        assert!(output.contains("src/b.txt  1 lines\n"));
+        assert!(
+            output.ends_with("   Funcs:          3\n   Types:          1\n   Tests:          0")
+        );
+
+        let mut result = json_result("src", 2, Some(2));
+        result.analysis = symbols(3);
+        assert!(format_json_single(&result)
+            .ends_with(r#""bytes":20,"symbols":{"functions":3,"types":1,"tests":0}}"#));
+    }
+
+    #[test]
+    fn complexity_in_rows_compact_and_json() {
+        let analysis = FileAnalysis {
+            complexity: Some(1234),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(format_analysis(&analysis), vec!["Branches:      1,234"]);
+        assert_eq!(format_compact_analysis(&analysis), "1,234 branches");
+
+        let mut result = json_result("src/a.rs", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(r#""bytes":10,"complexity":1234}"#));
+    }
+
+    #[test]
+    fn record_counts_in_rows_and_json() {
+        let analysis = FileAnalysis {
+            records: Some(RecordCount {
+                records: 12000,
+                invalid: 2,
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![" Records:     12,000", " Invalid:          2"]
+        );
+
+        let mut result = json_result("events.jsonl", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result)
+            .ends_with(r#""bytes":10,"records":{"count":12000,"invalid":2}}"#));
+    }
+
+    #[test]
+    fn log_levels_in_rows_and_json() {
+        let analysis = FileAnalysis {
+            log_levels: Some(LogLevels {
+                error: 3,
+                warn: 10,
+                info: 2500,
+                debug: 0,
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "   Error:          3",
+                "    Warn:         10",
+                "    Info:      2,500",
+                "   Debug:          0"
+            ]
+        );
+
+        let mut result = json_result("app.log", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result)
+            .ends_with(r#""bytes":10,"log_levels":{"error":3,"warn":10,"info":2500,"debug":0}}"#));
+    }
+
+    #[test]
+    fn dedupe_lines_in_rows_compact_and_json() {
+        let analysis = FileAnalysis {
+            dedupe_lines: Some(DedupeStats {
+                unique: 90,
+                duplicates: 10,
+                most_repeated: Some(("    } \"quoted\"".to_string(), 4)),
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "  Unique:         90",
+                "Repeated:         10",
+                "Top Line:          4  } \"quoted\""
+            ]
+        );
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            r#"90 unique lines, 10 repeated lines, most repeated "} "quoted"" (4 times)"#
+        );
+
+        let mut result = json_result("a.txt", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(
+            r#""dedupe_lines":{"unique":90,"duplicates":10,"most_repeated":{"line":"    } \"quoted\"","count":4}}}"#
+        ));
+
+        let long = FileAnalysis {
+            dedupe_lines: Some(DedupeStats {
+                unique: 1,
+                duplicates: 1,
+                most_repeated: Some(("x".repeat(100), 2)),
+            }),
+            ..FileAnalysis::default()
+        };
+        let rows = format_analysis(&long);
+        assert!(rows[2].ends_with(&format!("  {}...", "x".repeat(57))));
+
+        // Log lines carry the escapes of ANSI colour codes
+        let colored = FileAnalysis {
+            dedupe_lines: Some(DedupeStats {
+                unique: 1,
+                duplicates: 1,
+                most_repeated: Some(("\u{1b}[31mERROR\u{1b}[0m\u{7}".to_string(), 2)),
+            }),
+            ..FileAnalysis::default()
+        };
+        result.analysis = colored;
+        let json = format_json_single(&result);
+        assert!(json.contains(r#""line":"\u001b[31mERROR\u001b[0m\u0007""#));
+        assert!(!json.chars().any(char::is_control));
+    }
+
+    #[test]
+    fn entropy_in_rows_compact_and_json() {
+        let analysis = FileAnalysis {
+            entropy: Some(ByteHistogram::from_bytes(b"abcd")),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![" Entropy:       2.00  bits/byte"]
+        );
+        assert_eq!(format_compact_analysis(&analysis), "entropy 2.00 bits/byte");
+
+        let mut result = json_result("a.txt", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(r#""bytes":10,"entropy":2.000}"#));
+    }
+
+    #[test]
+    fn hash_in_rows_compact_json_and_csv() {
+        let checksum = Checksum {
+            algorithm: HashAlgorithm::Sha256,
+            hex: "ab12".to_string(),
+        };
+        let analysis = FileAnalysis {
+            hash: Some(checksum.clone()),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(format_analysis(&analysis), vec!["  SHA256: ab12"]);
+        assert_eq!(format_compact_analysis(&analysis), "sha256:ab12");
+        let total: FileAnalysis = [analysis.clone(), analysis.clone()].iter().sum();
+        assert_eq!(total.hash, None);
+
+        let mut result = json_result("a.txt", 1, None);
+        result.analysis = analysis.clone();
+        assert!(format_json_single(&result).ends_with(r#""bytes":10,"sha256":"ab12"}"#));
+
+        let entries = vec![
+            FileEntry {
+                path: std::path::PathBuf::from("a.txt"),
+                count: Count::from_content("a\n"),
+            },
+            FileEntry {
+                path: std::path::PathBuf::from("b.txt"),
+                count: Count::from_content("b\n"),
+            },
+        ];
+        assert_eq!(
+            format_csv(&entries, &[analysis, FileAnalysis::default()]),
+            "path,lines,words,bytes,max_line_length,sha256\na.txt,1,1,2,1,ab12\nb.txt,1,1,2,1,"
+        );
+    }
+
+    #[test]
+    fn meta_in_rows_compact_json_and_csv() {
+        let analysis = FileAnalysis {
+            meta: Some(FileMeta {
+                size_on_disk: 4096,
+                modified: Some(1_700_000_000),
+                permissions: Some("rw-r--r--".to_string()),
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "    Disk:      4,096",
+                "Modified: 2023-11-14T22:13:20Z",
+                "   Perms: rw-r--r--",
+            ]
+        );
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "4,096 bytes on disk, modified 2023-11-14T22:13:20Z, rw-r--r--"
+        );
+
+        let mut result = json_result("a.txt", 1, None);
+        result.analysis = analysis.clone();
+        assert!(format_json_single(&result).ends_with(
+            r#""meta":{"size_on_disk":4096,"modified":"2023-11-14T22:13:20Z","permissions":"rw-r--r--"}}"#
+        ));
+
+        let entries = vec![FileEntry {
+            path: std::path::PathBuf::from("a.txt"),
+            count: Count::from_content("a\n"),
+        }];
+        assert_eq!(
+            format_csv(&entries, &[analysis]),
+            "path,lines,words,bytes,max_line_length,size_on_disk,modified,permissions\na.txt,1,1,2,1,4096,2023-11-14T22:13:20Z,rw-r--r--"
+        );
+    }
+
+    #[test]
+    fn ratios_in_rows_compact_json_and_csv() {
+        let count = Count::from_content("one two\nthree four five six\n");
+        let analysis = FileAnalysis {
+            ratios: Some(LineRatios::from(&count)),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "Words/Ln:       3.00  words/line",
+                "Bytes/Ln:      14.00  bytes/line",
+            ]
+        );
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "3.00 words/line, 14.00 bytes/line"
+        );
+
+        let mut result = json_result("a.txt", 2, None);
+        result.analysis = analysis.clone();
+        assert!(format_json_single(&result)
+            .ends_with(r#""words_per_line":3.000,"bytes_per_line":14.000}"#));
+
+        let entries = vec![
+            FileEntry {
+                path: std::path::PathBuf::from("a.txt"),
+                count,
+            },
+            FileEntry {
+                path: std::path::PathBuf::from("empty.txt"),
+                count: Count::default(),
+            },
+        ];
+        let empty = FileAnalysis {
+            ratios: Some(LineRatios::default()),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_csv(&entries, &[analysis, empty]),
+            "path,lines,words,bytes,max_line_length,words_per_line,bytes_per_line\n\
+             a.txt,2,6,28,19,3.000,14.000\nempty.txt,0,0,0,0,,"
+        );
+    }
+
+    #[test]
+    fn line_kinds_rows_and_cocomo_block() {
+        let analysis = FileAnalysis {
+            line_kinds: Some(LineKinds {
+                blank: 2,
+                comment: 6,
+                code: 1200,
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "   Blank:          2",
+                "Comments:          6",
+                "    Code:      1,200",
+                "   Ratio:        0.5  % comments/code",
+            ]
+        );
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "2 blank lines, 6 comment lines, 1,200 code lines, 0.5% comments/code"
+        );
+        let mut result = json_result("a.rs", 1, None);
+        result.analysis = analysis.clone();
+        assert!(format_json_single(&result).ends_with(
+            r#""line_kinds":{"blank":2,"comment":6,"code":1200,"comment_ratio":0.500}}"#
+        ));
+
+        let estimate = Estimate {
+            effort: 26.9,
+            schedule: 8.738,
+            people: 3.0817,
+            cost: 303_138.87,
+        };
+        assert_eq!(
+            format_cocomo(&estimate, ProjectType::SemiDetached),
+            "Estimated Cost to Develop (semi-detached) $303,139\n\
+             Estimated Schedule Effort (semi-detached) 8.74 months\n\
+             Estimated People Required (semi-detached) 3.08"
+        );
+    }
+
+    #[test]
+    fn natural_languages_for_a_file_and_a_directory() {
+        let file = FileAnalysis {
+            natural_language: Some(NaturalLanguages::single("ja")),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(format_analysis(&file), vec!["Language: ja"]);
+        assert_eq!(format_compact_analysis(&file), "language ja");
+
+        let english = FileAnalysis {
+            natural_language: Some(NaturalLanguages::single("en")),
+            ..FileAnalysis::default()
+        };
+        let total: FileAnalysis = [english.clone(), file, english].iter().sum();
+        assert_eq!(
+            format_analysis(&total),
+            vec!["Language: en (2 files), ja (1 file)"]
+        );
+        let mut result = json_result("docs", 3, None);
+        result.analysis = total;
+        assert!(format_json_single(&result).ends_with(r#","natural_languages":{"en":2,"ja":1}}"#));
+    }
+
+    #[test]
+    fn vocabulary_rows_and_json() {
+        let analysis = FileAnalysis {
+            vocabulary: Some(Vocabulary::from_content("the cat saw the dog")),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "Distinct:          4",
+                "   Hapax:          3",
+                "     TTR:      0.800  distinct/words",
+            ]
+        );
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "4 distinct words, 3 hapax, TTR 0.800"
+        );
+        let mut result = json_result("a.txt", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(
+            r#","vocabulary":{"words":5,"distinct":4,"hapax":3,"type_token_ratio":0.800}}"#
+        ));
+    }
+
+    #[test]
+    fn dialogue_rows_and_json() {
+        let analysis = FileAnalysis {
+            dialogue: Some(DialogueStats {
+                quotes: 3,
+                quoted_words: 12,
+                words: 40,
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "  Quotes:          3",
+                "  Quoted:         12",
+                "Dialogue:       30.0  % words in quotes",
+            ]
+        );
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "3 quotes, 12 quoted words, 30.0% dialogue"
+        );
+        let mut result = json_result("story.txt", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result)
+            .ends_with(r#","dialogue":{"quotes":3,"quoted_words":12,"words":40,"share":0.300}}"#));
+    }
+
+    #[test]
+    fn pages_row_and_json() {
+        let analysis = FileAnalysis {
+            pages: Some(12),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(format_analysis(&analysis), vec!["   Pages:         12"]);
+        assert_eq!(format_compact_analysis(&analysis), "12 pages");
+        let mut result = json_result("report.pdf", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(r#","pages":12}"#));
+    }
+
+    #[test]
+    fn chapter_rows_and_json() {
+        let analysis = FileAnalysis {
+            chapters: Some(Chapters {
+                words: vec![
+                    ("ch1.xhtml".to_string(), 2_500),
+                    ("ch2.xhtml".to_string(), 980),
+                ],
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "Chapters:          2",
+                " Chapter:      2,500  ch1.xhtml",
+                " Chapter:        980  ch2.xhtml",
+            ]
+        );
+        assert_eq!(format_compact_analysis(&analysis), "2 chapters");
+        let mut result = json_result("book.epub", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(
+            r#","chapters":[{"name":"ch1.xhtml","words":2500},{"name":"ch2.xhtml","words":980}]}"#
+        ));
+    }
+
+    #[test]
+    fn subtitle_rows_and_json() {
+        let analysis = FileAnalysis {
+            subtitles: Some(SubtitleStats {
+                cues: 120,
+                words: 900,
+                duration_ms: 360_000,
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "    Cues:        120",
+                "Subtitle:        900",
+                "     WPM:      150.0  words/minute on screen",
+            ]
+        );
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "120 cues, 900 subtitle words, 150.0 wpm"
+        );
+        let mut result = json_result("film.srt", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(
+            r#","subtitles":{"cues":120,"words":900,"duration_ms":360000,"words_per_minute":150.0}}"#
+        ));
+    }
+
+    #[test]
+    fn po_rows_and_json() {
+        let analysis = FileAnalysis {
+            po: Some(PoStats {
+                translated: 30,
+                fuzzy: 4,
+                untranslated: 6,
+                source_words: 250,
+                target_words: 210,
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                "    Done:         30",
+                "   Fuzzy:          4",
+                "    Todo:          6",
+                "  Source:        250",
+                "  Target:        210",
+                "Complete:       75.0  % translated",
+            ]
+        );
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "30 translated, 4 fuzzy, 6 untranslated, 250 source words, 210 target words, 75.0% complete"
+        );
+        let mut result = json_result("de.po", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(
+            r#","po":{"translated":30,"fuzzy":4,"untranslated":6,"source_words":250,"target_words":210}}"#
+        ));
+    }
+
+    #[test]
+    fn i18n_rows_list_missing_keys() {
+        let analysis = FileAnalysis {
+            i18n: Some(I18nStats {
+                strings: 12,
+                words: 40,
+                keys: vec!["menu.open".to_string()],
+                missing: vec!["menu.close".to_string(), "title".to_string()],
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_analysis(&analysis),
+            vec![
+                " Strings:         12",
+                "    Text:         40",
+                " Missing:          2",
+                "                      menu.close",
+                "                      title",
+            ]
+        );
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "12 strings, 40 words in strings, 2 missing keys"
+        );
+        let mut result = json_result("de.json", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result)
+            .ends_with(r#","i18n":{"strings":12,"words":40,"missing":["menu.close","title"]}}"#));
+    }
+
+    #[test]
+    fn structure_in_compact_and_json_output() {
+        let analysis = FileAnalysis {
+            structure: Some(StructureStats {
+                keys: 40,
+                max_depth: 4,
+                arrays: 3,
+                max_array_len: 12,
+            }),
+            ..FileAnalysis::default()
+        };
+        assert_eq!(
+            format_compact_analysis(&analysis),
+            "40 keys, 4 max depth, 3 arrays, 12 longest array"
+        );
+        assert_eq!(format_analysis(&analysis)[3], " Longest:         12");
+
+        let mut result = json_result("ci.yml", 1, None);
+        result.analysis = analysis;
+        assert!(format_json_single(&result).ends_with(
+            r#""bytes":10,"structure":{"keys":40,"max_depth":4,"arrays":3,"max_array_len":12}}"#
+        ));
+    }
+
+    #[test]
+    fn display_path_strips_verbatim_prefixes() {
+        let shown = |path: &str| display_path(Path::new(path));
+        assert_eq!(shown(r"\\?\C:\src\main.rs"), r"C:\src\main.rs");
+        assert_eq!(
+            shown(r"\\?\UNC\server\share\a.txt"),
+            r"\\server\share\a.txt"
+        );
+        assert_eq!(shown(r"\\?\Volume{1234}\a.txt"), r"\\?\Volume{1234}\a.txt");
+        assert_eq!(shown("src/main.rs"), "src/main.rs");
+    }
+
+    #[test]
+    fn error_json_lines() {
+        assert_eq!(
+            error_kind_name(std::io::ErrorKind::PermissionDenied),
+            "permission_denied"
+        );
+        assert_eq!(error_kind_name(std::io::ErrorKind::NotFound), "not_found");
+        assert_eq!(
+            format_error_json(Some("a \"b\".txt"), "not_found", "No such file"),
+            r#"{"path":"a \"b\".txt","kind":"not_found","message":"No such file"}"#
+        );
+        assert_eq!(
+            format_error_json(None, "usage", "check needs at least one --over limit"),
+            r#"{"path":null,"kind":"usage","message":"check needs at least one --over limit"}"#
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use globset::Glob;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+fn invalid(manifest: &Path, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {message}", manifest.display()),
+    )
+}
+
+/// Reads `name` in `root`, or `None` if there is no such file.
+fn read_manifest(root: &Path, name: &str) -> io::Result<Option<(PathBuf, String)>> {
+    let path = root.join(name);
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(Some((path, content))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// The directories of the packages in the repository at `root`, relative to it and
+/// sorted: members of a Cargo workspace, `workspaces` of a `package.json`, `use`
+/// directories of a `go.work`, and directories of the `go.mod` files among `files`.
+/// A package holding the workspace itself, as a Cargo workspace with a root
+/// `[package]` does, is the root and not listed.
+pub fn package_paths(root: &Path, files: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    if let Some((manifest, content)) = read_manifest(root, "Cargo.toml")? {
+        let (members, exclude) = cargo_members(&content).map_err(|e| invalid(&manifest, e))?;
+        for pattern in &members {
+            paths.extend(
+                expand(root, pattern)?
+                    .into_iter()
+                    .filter(|member| !exclude.iter().any(|excluded| member == Path::new(excluded)))
+                    .filter(|member| root.join(member).join("Cargo.toml").is_file()),
+            );
+        }
+    }
+    if let Some((manifest, content)) = read_manifest(root, "package.json")? {
+        for pattern in npm_workspaces(&content).map_err(|e| invalid(&manifest, e))? {
+            paths.extend(
+                expand(root, &pattern)?
+                    .into_iter()
+                    .filter(|member| root.join(member).join("package.json").is_file()),
+            );
+        }
+    }
+    if let Some((_, content)) = read_manifest(root, "go.work")? {
+        paths.extend(go_work_uses(&content).into_iter().map(PathBuf::from));
+    }
+    paths.extend(
+        files
+            .iter()
+            .filter(|file| file.file_name().is_some_and(|name| name == "go.mod"))
+            .filter_map(|file| file.parent()?.strip_prefix(root).ok())
+            .map(Path::to_path_buf),
+    );
+    let mut paths: Vec<PathBuf> = paths.into_iter().map(|path| normalize(&path)).collect();
+    paths.retain(|path| !path.as_os_str().is_empty());
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// `path` without `.` components or a trailing separator, so `./crates/a/` and
+/// `crates/a` are one package.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+/// The `members` and `exclude` patterns of a Cargo manifest's `[workspace]`.
+fn cargo_members(content: &str) -> Result<(Vec<String>, Vec<String>), toml::de::Error> {
+    let manifest: toml::Table = content.parse()?;
+    let list = |key: &str| -> Vec<String> {
+        manifest
+            .get("workspace")
+            .and_then(|workspace| workspace.get(key))
+            .and_then(|value| value.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    Ok((list("members"), list("exclude")))
+}
+
+/// The `workspaces` of a `package.json`, given as a list or, as Yarn allows, a
+/// `packages` list; `!` negations are left out.
+fn npm_workspaces(content: &str) -> Result<Vec<String>, serde_json::Error> {
+    let manifest: serde_json::Value = serde_json::from_str(content)?;
+    let workspaces = manifest.get("workspaces");
+    let patterns = workspaces
+        .and_then(|workspaces| workspaces.get("packages").or(Some(workspaces)))
+        .and_then(|patterns| patterns.as_array())
+        .map(|patterns| {
+            patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str())
+                .filter(|pattern| !pattern.starts_with('!'))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(patterns)
+}
+
+/// The directories of a `go.work` file's `use` directives, single or in a block.
+fn go_work_uses(content: &str) -> Vec<String> {
+    let mut uses = Vec::new();
+    let mut in_block = false;
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if !line.is_empty() {
+                uses.push(line.trim_matches('"').to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("use") {
+            match rest.trim() {
+                "(" => in_block = true,
+                dir if !dir.is_empty() && rest.starts_with(char::is_whitespace) => {
+                    uses.push(dir.trim_matches('"').to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+    uses
+}
+
+/// The directories under `root` matching a workspace pattern such as `crates/*`,
+/// relative to `root`. Each wildcard component matches one directory level;
+/// hidden directories and `node_modules` are never matched.
+fn expand(root: &Path, pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let mut matches = vec![PathBuf::new()];
+    for component in Path::new(pattern).components() {
+        let Component::Normal(component) = component else {
+            continue;
+        };
+        let component = component.to_string_lossy();
+        if !component.contains(['*', '?', '[', '{']) {
+            matches.iter_mut().for_each(|path| path.push(&*component));
+            continue;
+        }
+        let matcher = Glob::new(&component)
+            .map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid workspace pattern '{pattern}': {e}"),
+                )
+            })?
+            .compile_matcher();
+        let mut next = Vec::new();
+        for path in &matches {
+            let Ok(dir) = fs::read_dir(root.join(path)) else {
+                continue;
+            };
+            for entry in dir.flatten() {
+                let name = entry.file_name();
+                let name_str = name.to_string_lossy();
+                let skipped = name_str.starts_with('.') || name_str == "node_modules";
+                if !skipped && entry.path().is_dir() && matcher.is_match(&*name_str) {
+                    next.push(path.join(&name));
+                }
+            }
+        }
+        matches = next;
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_workspace_manifests() {
+        let cargo =
+            "[workspace]\nmembers = [\"crates/*\", \"tools/gen\"]\nexclude = [\"crates/old\"]\n";
+        assert_eq!(
+            cargo_members(cargo).unwrap(),
+            (
+                vec!["crates/*".to_string(), "tools/gen".to_string()],
+                vec!["crates/old".to_string()]
+            )
+        );
+        assert_eq!(
+            npm_workspaces(r#"{"workspaces": ["packages/*", "!packages/skip"]}"#).unwrap(),
+            ["packages/*"]
+        );
+        assert_eq!(
+            npm_workspaces(r#"{"workspaces": {"packages": ["apps/*"]}}"#).unwrap(),
+            ["apps/*"]
+        );
+        assert!(npm_workspaces(r#"{"name": "app"}"#).unwrap().is_empty());
+        assert_eq!(
+            go_work_uses("go 1.22\n\nuse (\n\t./api // service\n\t\"./web\"\n)\nuse ./cli\n"),
+            ["./api", "./web", "./cli"]
+        );
+    }
+
+    #[test]
+    fn finds_packages_of_every_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/old\"]\n",
+        );
+        write("crates/core/Cargo.toml", "[package]\nname = \"core\"\n");
+        write("crates/old/Cargo.toml", "[package]\nname = \"old\"\n");
+        fs::create_dir_all(root.join("crates/notes")).unwrap();
+        write("package.json", r#"{"workspaces": ["web/*"]}"#);
+        write("web/app/package.json", r#"{"name": "app"}"#);
+        write("go.work", "use ./svc\n");
+        write("svc/go.mod", "module example.com/svc\n");
+        let files = [root.join("svc/go.mod"), root.join("tools/x/go.mod")];
+        assert_eq!(
+            package_paths(root, &files).unwrap(),
+            [
+                PathBuf::from("crates/core"),
+                PathBuf::from("svc"),
+                PathBuf::from("tools/x"),
+                PathBuf::from("web/app"),
+            ]
+        );
+    }
+
+    #[test]
+    fn repository_without_workspaces_has_no_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"one\"\n").unwrap();
+        assert!(package_paths(dir.path(), &[]).unwrap().is_empty());
+        fs::write(dir.path().join("package.json"), "{").unwrap();
+        assert!(package_paths(dir.path(), &[]).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::iter::Sum;
+use std::ops::Add;
+use std::path::Path;
+
+/// Whether `--po` analyzes this file: gettext catalogs (`.po`) and templates (`.pot`).
+pub fn is_po(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("po") || ext.eq_ignore_ascii_case("pot"))
+}
+
+/// Messages by translation state, and their words, for `--po`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PoStats {
+    pub translated: usize,
+    /// Translated, but flagged for review
+    pub fuzzy: usize,
+    pub untranslated: usize,
+    /// Words of every `msgid`
+    pub source_words: usize,
+    /// Words of every `msgstr`, the first form for plurals
+    pub target_words: usize,
+}
+
+impl Add for PoStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            translated: self.translated + other.translated,
+            fuzzy: self.fuzzy + other.fuzzy,
+            untranslated: self.untranslated + other.untranslated,
+            source_words: self.source_words + other.source_words,
+            target_words: self.target_words + other.target_words,
+        }
+    }
+}
+
+impl Sum for PoStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// The message being read, its strings joined across continuation lines.
+#[derive(Default)]
+struct Message {
+    fuzzy: bool,
+    msgid: Option<String>,
+    /// Every translation: `msgstr`, or each `msgstr[n]` of a plural message
+    msgstr: Vec<String>,
+}
+
+/// Which string continuation lines add to.
+enum Field {
+    None,
+    Msgid,
+    Msgstr,
+}
+
+impl PoStats {
+    /// Reads the messages of a catalog. The header (the message with an empty
+    /// `msgid`) and obsolete `#~` entries are left out.
+    pub fn from_content(content: &str) -> Self {
+        let mut stats = Self::default();
+        let mut message = Message::default();
+        let mut field = Field::None;
+        for line in content.lines().map(str::trim) {
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            // Comments, a context or an id after a translation start the next message
+            let starts_message =
+                line.starts_with('#') || keyword == "msgctxt" || keyword == "msgid";
+            if starts_message && !message.msgstr.is_empty() {
+                stats.add_message(std::mem::take(&mut message));
+            }
+            match keyword {
+                _ if line.starts_with("#,") => {
+                    message.fuzzy |= line[2..].split(',').any(|flag| flag.trim() == "fuzzy");
+                }
+                "msgid" => {
+                    message.msgid = Some(unquote(rest));
+                    field = Field::Msgid;
+                }
+                _ if keyword.starts_with("msgstr") => {
+                    message.msgstr.push(unquote(rest));
+                    field = Field::Msgstr;
+                }
+                _ if line.starts_with('"') => {
+                    let string = unquote(line);
+                    match (&field, message.msgstr.last_mut()) {
+                        (Field::Msgid, _) => {
+                            message.msgid.get_or_insert_default().push_str(&string);
+                        }
+                        (Field::Msgstr, Some(msgstr)) => msgstr.push_str(&string),
+                        _ => {}
+                    }
+                }
+                _ => field = Field::None,
+            }
+        }
+        if !message.msgstr.is_empty() {
+            stats.add_message(message);
+        }
+        stats
+    }
+
+    fn add_message(&mut self, message: Message) {
+        let msgid = message.msgid.unwrap_or_default();
+        if msgid.is_empty() {
+            return;
+        }
+        self.source_words += msgid.split_whitespace().count();
+        self.target_words += message
+            .msgstr
+            .first()
+            .map_or(0, |msgstr| msgstr.split_whitespace().count());
+        if message.msgstr.iter().all(String::is_empty) {
+            self.untranslated += 1;
+        } else if message.fuzzy {
+            self.fuzzy += 1;
+        } else {
+            self.translated += 1;
+        }
+    }
+
+    /// Translated messages per message, `None` for a catalog without any.
+    pub fn completion(&self) -> Option<f64> {
+        let total = self.translated + self.fuzzy + self.untranslated;
+        (total > 0).then(|| self.translated as f64 / total as f64)
+    }
+}
+
+/// The text of a quoted PO string, with `\n` and `\t` escapes read as spaces so words
+/// either side of them stay apart.
+fn unquote(string: &str) -> String {
+    let string = string.trim();
+    let string = string
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(string);
+    string
+        .replace("\\n", " ")
+        .replace("\\t", " ")
+        .replace("\\\"", "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG: &str = r#"# Translation of ewc
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+
+#: src/main.rs:10
+msgid "Files counted"
+msgstr "Dateien gezählt"
+
+#, fuzzy
+msgid "Files skipped"
+msgstr "Dateien übersprungen"
+
+msgid ""
+"A long message "
+"over two lines"
+msgstr ""
+
+msgctxt "menu"
+msgid "Open"
+msgid_plural "Open all"
+msgstr[0] "Öffnen"
+msgstr[1] "Alle öffnen"
+
+#~ msgid "Gone"
+#~ msgstr "Weg"
+"#;
+
+    #[test]
+    fn counts_messages_by_state() {
+        let stats = PoStats::from_content(CATALOG);
+        assert_eq!(stats.translated, 2);
+        assert_eq!(stats.fuzzy, 1);
+        assert_eq!(stats.untranslated, 1);
+        assert_eq!(stats.source_words, 2 + 2 + 6 + 1);
+        assert_eq!(stats.target_words, 2 + 2 + 1);
+        assert_eq!(stats.completion(), Some(0.5));
+    }
+
+    #[test]
+    fn recognizes_catalogs() {
+        assert!(is_po(Path::new("locale/de.po")));
+        assert!(is_po(Path::new("messages.POT")));
+        assert!(!is_po(Path::new("notes.txt")));
+        assert_eq!(PoStats::from_content("").completion(), None);
+    }
+}
@@ -0,0 +1,214 @@
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+#[cfg(feature = "json")]
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+#[cfg(feature = "json")]
+use crate::analysis::ParseError;
+
+/// How `--records` splits a file into records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// One or more JSON documents; each element of a top-level array is a record, and
+    /// any other top-level value is one
+    Json,
+    /// One JSON value per non-blank line (JSON Lines, NDJSON)
+    JsonLines,
+}
+
+/// Records in a data file, as shown with `--records`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RecordCount {
+    /// Records that parsed
+    pub records: usize,
+    /// Parse errors: JSON Lines lines that did not parse, or the error that stopped
+    /// parsing a JSON file
+    pub invalid: usize,
+}
+
+impl Add for RecordCount {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            records: self.records + other.records,
+            invalid: self.invalid + other.invalid,
+        }
+    }
+}
+
+impl AddAssign for RecordCount {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for RecordCount {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// The number of records in one top-level JSON value, read without keeping it.
+#[cfg(feature = "json")]
+struct TopLevel(usize);
+
+#[cfg(feature = "json")]
+impl<'de> Deserialize<'de> for TopLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TopLevelVisitor)
+    }
+}
+
+#[cfg(feature = "json")]
+struct TopLevelVisitor;
+
+#[cfg(feature = "json")]
+impl<'de> Visitor<'de> for TopLevelVisitor {
+    type Value = TopLevel;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<TopLevel, A::Error> {
+        let mut records = 0;
+        while seq.next_element::<IgnoredAny>()?.is_some() {
+            records += 1;
+        }
+        Ok(TopLevel(records))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<TopLevel, A::Error> {
+        while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+        Ok(TopLevel(1))
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<TopLevel, E> {
+        Ok(TopLevel(1))
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<TopLevel, E> {
+        Ok(TopLevel(1))
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<TopLevel, E> {
+        Ok(TopLevel(1))
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<TopLevel, E> {
+        Ok(TopLevel(1))
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<TopLevel, E> {
+        Ok(TopLevel(1))
+    }
+
+    fn visit_unit<E>(self) -> Result<TopLevel, E> {
+        Ok(TopLevel(1))
+    }
+}
+
+/// A serde_json error at `line`, without the position serde_json appends to its
+/// message, which is relative to the text it was given.
+#[cfg(feature = "json")]
+fn parse_error(line: usize, error: &serde_json::Error) -> ParseError {
+    let message = error.to_string();
+    let message = match message.rfind(" at line ") {
+        Some(at) => message[..at].to_string(),
+        None => message,
+    };
+    ParseError {
+        line,
+        column: error.column(),
+        message,
+    }
+}
+
+/// Counts the records in `content` and collects the parse errors, with line numbers.
+/// A JSON file is counted up to its first error; JSON Lines carries on with the next
+/// line.
+#[cfg(feature = "json")]
+pub fn count_records(content: &str, format: RecordFormat) -> (RecordCount, Vec<ParseError>) {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut count = RecordCount::default();
+    let mut errors = Vec::new();
+    match format {
+        RecordFormat::Json => {
+            for value in serde_json::Deserializer::from_str(content).into_iter::<TopLevel>() {
+                match value {
+                    Ok(TopLevel(records)) => count.records += records,
+                    Err(e) => {
+                        count.invalid += 1;
+                        errors.push(parse_error(e.line(), &e));
+                        break;
+                    }
+                }
+            }
+        }
+        RecordFormat::JsonLines => {
+            for (index, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<IgnoredAny>(line) {
+                    Ok(_) => count.records += 1,
+                    Err(e) => {
+                        count.invalid += 1;
+                        errors.push(parse_error(index + 1, &e));
+                    }
+                }
+            }
+        }
+    }
+    (count, errors)
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_top_level_array_elements_and_documents() {
+        let (count, errors) = count_records(
+            "[{\"id\": 1}, {\"id\": [2, 3]}, 4]\n{\"one\": true}\n",
+            RecordFormat::Json,
+        );
+        assert_eq!(
+            count,
+            RecordCount {
+                records: 4,
+                invalid: 0
+            }
+        );
+        assert!(errors.is_empty());
+        assert_eq!(count_records("", RecordFormat::Json).0.records, 0);
+        assert_eq!(count_records("\u{feff}[]", RecordFormat::Json).0.records, 0);
+    }
+
+    #[test]
+    fn json_stops_at_the_first_error() {
+        let (count, errors) = count_records("[1, 2]\n{\n  \"a\": ,\n}\n[3]\n", RecordFormat::Json);
+        assert_eq!(count.records, 2);
+        assert_eq!(count.invalid, 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 3);
+        assert_eq!(errors[0].to_string(), "line 3, column 8: expected value");
+    }
+
+    #[test]
+    fn json_lines_reports_each_bad_line() {
+        let content = "{\"a\": 1}\n\n[1, 2]\nnot json\n{\"b\":\n\"last\"";
+        let (count, errors) = count_records(content, RecordFormat::JsonLines);
+        assert_eq!(
+            count,
+            RecordCount {
+                records: 3,
+                invalid: 2
+            }
+        );
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![4, 5]);
+    }
+}
@@ -0,0 +1,258 @@
+use serde::de::{Deserialize, Deserializer, Error};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::counter::Count;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportEntry {
+    pub name: String,
+    pub count: Count,
+    pub file_count: usize,
+    /// Written as a `directory` rather than a `file` entry
+    pub is_directory: bool,
+}
+
+/// Per-argument results and totals, as written by `--json`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub entries: Vec<ReportEntry>,
+    pub total: Count,
+    pub file_count: usize,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_usize(value: &Value, key: &str) -> io::Result<usize> {
+    match value.get(key) {
+        None => Ok(0),
+        Some(v) => v
+            .as_u64()
+            .map(|n| n as usize)
+            .ok_or_else(|| invalid_data(format!("field '{key}' is not a number"))),
+    }
+}
+
+fn read_count(value: &Value) -> io::Result<Count> {
+    Ok(Count {
+        lines: read_usize(value, "lines")?,
+        words: read_usize(value, "words")?,
+        bytes: read_usize(value, "bytes")?,
+        max_line_length: read_usize(value, "max_line_length")?,
+    })
+}
+
+fn read_entry(value: &Value) -> io::Result<ReportEntry> {
+    let (name, file_count, is_directory) = if let Some(name) = value.get("directory") {
+        (name, read_usize(value, "file_count")?, true)
+    } else if let Some(name) = value.get("file") {
+        (name, 1, false)
+    } else {
+        return Err(invalid_data(
+            "entry has neither 'file' nor 'directory'".to_string(),
+        ));
+    };
+    let name = name
+        .as_str()
+        .ok_or_else(|| invalid_data("entry name is not a string".to_string()))?;
+
+    Ok(ReportEntry {
+        name: name.to_string(),
+        count: read_count(value)?,
+        file_count,
+        is_directory,
+    })
+}
+
+impl Report {
+    pub fn from_entries(entries: Vec<ReportEntry>) -> Self {
+        let total = entries.iter().map(|e| e.count).sum();
+        let file_count = entries.iter().map(|e| e.file_count).sum();
+        Self {
+            entries,
+            total,
+            file_count,
+        }
+    }
+
+    /// Combines reports, such as those of sharded CI jobs, into one. Entries of the same
+    /// name are summed and the others kept in the order they first appear; the totals
+    /// are those of all entries.
+    pub fn merge(reports: impl IntoIterator<Item = Report>) -> Self {
+        let mut entries: Vec<ReportEntry> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for entry in reports.into_iter().flat_map(|report| report.entries) {
+            match index.get(&entry.name) {
+                Some(&i) => {
+                    let merged = &mut entries[i];
+                    merged.count += entry.count;
+                    merged.file_count += entry.file_count;
+                    merged.is_directory |= entry.is_directory;
+                }
+                None => {
+                    index.insert(entry.name.clone(), entries.len());
+                    entries.push(entry);
+                }
+            }
+        }
+        Self::from_entries(entries)
+    }
+
+    pub fn parse_json(content: &str) -> io::Result<Self> {
+        serde_json::from_str(content).map_err(|e| invalid_data(format!("invalid JSON report: {e}")))
+    }
+
+    /// A report from the JSON of `--json`: one file or directory entry, or `files` and a
+    /// `total`.
+    fn from_value(value: &Value) -> io::Result<Self> {
+        match value.get("files") {
+            Some(files) => {
+                let files = files
+                    .as_array()
+                    .ok_or_else(|| invalid_data("'files' is not an array".to_string()))?;
+                let entries = files.iter().map(read_entry).collect::<io::Result<_>>()?;
+                let total = value
+                    .get("total")
+                    .ok_or_else(|| invalid_data("report has no 'total'".to_string()))?;
+                Ok(Self {
+                    entries,
+                    total: read_count(total)?,
+                    file_count: read_usize(total, "file_count")?,
+                })
+            }
+            None => Ok(Self::from_entries(vec![read_entry(value)?])),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Self::parse_json(&content)
+    }
+}
+
+impl<'de> Deserialize<'de> for Report {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Report::from_value(&value).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_single_file_report() {
+        let report = Report::parse_json(
+            r#"{"file":"a.txt","max_line_length":5,"lines":2,"words":3,"bytes":12}"#,
+        )
+        .unwrap();
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.entries[0].name, "a.txt");
+        assert_eq!(report.entries[0].file_count, 1);
+        assert_eq!(report.total.lines, 2);
+        assert_eq!(report.file_count, 1);
+    }
+
+    #[test]
+    fn parse_directory_report() {
+        let report = Report::parse_json(
+            r#"{"directory":"src","file_count":4,"max_line_length":80,"lines":100,"words":300,"bytes":2000}"#,
+        )
+        .unwrap();
+        assert_eq!(report.entries[0].name, "src");
+        assert_eq!(report.file_count, 4);
+        assert_eq!(report.total.bytes, 2000);
+    }
+
+    #[test]
+    fn parse_multiple_report() {
+        let report = Report::parse_json(
+            r#"{"files":[{"file":"a.txt","max_line_length":5,"lines":2,"words":3,"bytes":12},{"directory":"src","file_count":2,"max_line_length":9,"lines":8,"words":9,"bytes":50}],"total":{"file_count":3,"max_line_length":9,"lines":10,"words":12,"bytes":62}}"#,
+        )
+        .unwrap();
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(report.entries[1].file_count, 2);
+        assert_eq!(report.total.lines, 10);
+        assert_eq!(report.file_count, 3);
+    }
+
+    #[test]
+    fn deserializes_nested_reports() {
+        let reports: Vec<Report> = serde_json::from_str(
+            r#"[{"file":"a.txt","lines":2,"words":3,"bytes":12},{"directory":"src","file_count":2,"lines":8}]"#,
+        )
+        .unwrap();
+        assert_eq!(reports[0].entries[0].name, "a.txt");
+        assert!(reports[1].entries[0].is_directory);
+        assert!(serde_json::from_str::<Vec<Report>>(r#"[{"lines":3}]"#).is_err());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_json() {
+        let err = Report::parse_json("not json").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_shape() {
+        assert!(Report::parse_json(r#"{"lines":3}"#).is_err());
+    }
+
+    #[test]
+    fn from_entries_sums_totals() {
+        let report = Report::from_entries(vec![
+            ReportEntry {
+                name: "a".to_string(),
+                count: Count {
+                    lines: 1,
+                    words: 2,
+                    bytes: 3,
+                    max_line_length: 4,
+                },
+                file_count: 1,
+                is_directory: false,
+            },
+            ReportEntry {
+                name: "b".to_string(),
+                count: Count {
+                    lines: 10,
+                    words: 20,
+                    bytes: 30,
+                    max_line_length: 40,
+                },
+                file_count: 5,
+                is_directory: true,
+            },
+        ]);
+        assert_eq!(report.total.lines, 11);
+        assert_eq!(report.total.max_line_length, 40);
+        assert_eq!(report.file_count, 6);
+    }
+
+    #[test]
+    fn merge_sums_entries_of_the_same_name() {
+        let shard_a = Report::parse_json(
+            r#"{"files":[{"directory":"src","file_count":2,"max_line_length":9,"lines":8,"words":9,"bytes":50},{"file":"README.md","max_line_length":5,"lines":2,"words":3,"bytes":12}],"total":{"file_count":3,"max_line_length":9,"lines":10,"words":12,"bytes":62}}"#,
+        )
+        .unwrap();
+        let shard_b = Report::parse_json(
+            r#"{"directory":"src","file_count":3,"max_line_length":30,"lines":20,"words":40,"bytes":300}"#,
+        )
+        .unwrap();
+        let merged = Report::merge([shard_a, shard_b]);
+        let names: Vec<&str> = merged.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["src", "README.md"]);
+        assert_eq!(merged.entries[0].file_count, 5);
+        assert_eq!(merged.entries[0].count.lines, 28);
+        assert!(merged.entries[0].is_directory && !merged.entries[1].is_directory);
+        assert_eq!(merged.total.lines, 30);
+        assert_eq!(merged.total.max_line_length, 30);
+        assert_eq!(merged.file_count, 6);
+    }
+}
@@ -0,0 +1,272 @@
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::analysis::FileAnalysis;
+use crate::counter::{count_directory_detailed, count_file, FileEntry, FilterConfig, SkipSummary};
+use crate::output::{display_path, escape_json, format_json_single, JsonFileResult};
+
+/// A parsed request line: `{"id": ..., "path": "...", "options": {...}}`.
+pub(crate) struct Request {
+    pub id: Value,
+    pub path: String,
+    pub config: FilterConfig,
+    /// Whether per-file results were asked for
+    pub files: bool,
+}
+
+fn read_bool(options: &Value, key: &str) -> Result<Option<bool>, String> {
+    match options.get(key) {
+        None => Ok(None),
+        Some(value) => value
+            .as_bool()
+            .map(Some)
+            .ok_or_else(|| format!("option '{key}' is not a boolean")),
+    }
+}
+
+fn read_patterns(options: &Value, key: &str) -> Result<Option<Vec<String>>, String> {
+    let Some(value) = options.get(key) else {
+        return Ok(None);
+    };
+    let invalid = || format!("option '{key}' is not a list of strings");
+    value
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|pattern| pattern.as_str().map(str::to_string).ok_or_else(invalid))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// Parses a request, with options overriding the filters ewc was started with.
+pub(crate) fn parse_request(
+    line: &str,
+    defaults: &FilterConfig,
+) -> Result<Request, (Value, String)> {
+    let value: Value =
+        serde_json::from_str(line).map_err(|e| (Value::Null, format!("invalid JSON: {e}")))?;
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    let fail = |message: String| (id.clone(), message);
+
+    let path = value
+        .get("path")
+        .and_then(Value::as_str)
+        .ok_or_else(|| fail("missing string field 'path'".to_string()))?
+        .to_string();
+
+    let options = value.get("options").cloned().unwrap_or(Value::Null);
+    if !options.is_null() && !options.is_object() {
+        return Err(fail("'options' is not an object".to_string()));
+    }
+    let mut config = defaults.clone();
+    if let Some(all) = read_bool(&options, "all").map_err(fail)? {
+        config.include_hidden = all;
+    }
+    if let Some(include_generated) = read_bool(&options, "include_generated").map_err(fail)? {
+        config.include_generated = include_generated;
+    }
+    if let Some(exclude) = read_patterns(&options, "exclude").map_err(fail)? {
+        config.exclude_patterns = exclude;
+    }
+    if let Some(include) = read_patterns(&options, "include").map_err(fail)? {
+        config.include_patterns = include;
+    }
+    let files = read_bool(&options, "files").map_err(fail)?.unwrap_or(false);
+    let config = config.compile().map_err(|e| fail(e.to_string()))?;
+
+    Ok(Request {
+        id,
+        path,
+        config,
+        files,
+    })
+}
+
+fn file_result(entry: &FileEntry) -> String {
+    format_json_single(&JsonFileResult {
+        name: display_path(&entry.path),
+        count: entry.count,
+        is_directory: false,
+        file_count: None,
+        skipped: Vec::new(),
+        skip_summary: SkipSummary::default(),
+        analysis: FileAnalysis::default(),
+    })
+}
+
+pub(crate) fn format_error(id: &Value, message: &str) -> String {
+    format!(r#"{{"id":{id},"error":"{}"}}"#, escape_json(message))
+}
+
+/// A successful response, with per-file results when the request asked for them.
+pub(crate) fn format_response(
+    request: &Request,
+    result: &JsonFileResult,
+    entries: &[FileEntry],
+) -> String {
+    let mut response = format!(
+        r#"{{"id":{},"result":{}"#,
+        request.id,
+        format_json_single(result)
+    );
+    if request.files {
+        let files: Vec<String> = entries.iter().map(file_result).collect();
+        response.push_str(&format!(r#","files":[{}]"#, files.join(",")));
+    }
+    response.push('}');
+    response
+}
+
+fn answer(request: &Request) -> io::Result<String> {
+    let path = Path::new(&request.path);
+    let (result, entries) = if path.is_dir() {
+        let (entries, total) = count_directory_detailed(path, &request.config)?;
+        let result = JsonFileResult {
+            name: request.path.clone(),
+            count: total,
+            is_directory: true,
+            file_count: Some(entries.len()),
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
+        };
+        (result, entries)
+    } else {
+        let count = count_file(path)?;
+        let result = JsonFileResult {
+            name: request.path.clone(),
+            count,
+            is_directory: false,
+            file_count: None,
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
+        };
+        let entry = FileEntry {
+            path: path.to_path_buf(),
+            count,
+        };
+        (result, vec![entry])
+    };
+    Ok(format_response(request, &result, &entries))
+}
+
+/// Answers one request line with one response line.
+pub fn handle_line(line: &str, defaults: &FilterConfig) -> String {
+    match parse_request(line, defaults) {
+        Ok(request) => answer(&request)
+            .unwrap_or_else(|e| format_error(&request.id, &format!("{}: {e}", request.path))),
+        Err((id, message)) => format_error(&id, &message),
+    }
+}
+
+/// Reads newline-delimited JSON requests until end of input, writing and flushing
+/// one response per request from `respond`. Blank lines are ignored.
+pub fn serve_lines(
+    input: impl BufRead,
+    mut output: impl Write,
+    mut respond: impl FnMut(&str) -> String,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(output, "{}", respond(&line))?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Answers requests by counting on demand, as `--rpc` does.
+pub fn serve_stdio(
+    input: impl BufRead,
+    output: impl Write,
+    defaults: &FilterConfig,
+) -> io::Result<()> {
+    serve_lines(input, output, |line| handle_line(line, defaults))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn answers_file_and_directory_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "one two\n").unwrap();
+        fs::write(dir.path().join(".hidden"), "secret\n").unwrap();
+        let root = dir.path().to_str().unwrap();
+        let defaults = FilterConfig::default();
+
+        let file = dir.path().join("a.txt");
+        let request = format!(r#"{{"id":1,"path":"{}"}}"#, file.display());
+        assert_eq!(
+            handle_line(&request, &defaults),
+            format!(
+                r#"{{"id":1,"result":{{"file":"{}","max_line_length":7,"lines":1,"words":2,"bytes":8}}}}"#,
+                file.display()
+            )
+        );
+
+        let request = format!(r#"{{"id":"q2","path":"{root}","options":{{"all":true}}}}"#);
+        let response = handle_line(&request, &defaults);
+        assert!(response.starts_with(r#"{"id":"q2","result":{"directory""#));
+        assert!(response.contains(r#""file_count":2"#));
+    }
+
+    #[test]
+    fn files_option_lists_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(dir.path().join("b.md"), "# B\n").unwrap();
+        let request = format!(
+            r#"{{"path":"{}","options":{{"files":true,"include":["*.rs"]}}}}"#,
+            dir.path().display()
+        );
+
+        let response = handle_line(&request, &FilterConfig::default());
+        assert!(response.starts_with(r#"{"id":null,"result":"#));
+        assert!(response.contains(r#""files":[{"file":""#));
+        assert!(response.contains("a.rs"));
+        assert!(!response.contains("b.md"));
+    }
+
+    #[test]
+    fn reports_errors_per_request() {
+        let defaults = FilterConfig::default();
+        assert!(
+            handle_line("not json", &defaults).starts_with(r#"{"id":null,"error":"invalid JSON"#)
+        );
+        assert_eq!(
+            handle_line(r#"{"id":7}"#, &defaults),
+            r#"{"id":7,"error":"missing string field 'path'"}"#
+        );
+        assert_eq!(
+            handle_line(r#"{"id":8,"path":".","options":{"all":"yes"}}"#, &defaults),
+            r#"{"id":8,"error":"option 'all' is not a boolean"}"#
+        );
+        assert!(handle_line(
+            r#"{"id":10,"path":".","options":{"exclude":["["]}}"#,
+            &defaults
+        )
+        .starts_with(r#"{"id":10,"error":"Invalid glob pattern '['"#));
+        assert!(
+            handle_line(r#"{"id":9,"path":"/nonexistent/x"}"#, &defaults)
+                .starts_with(r#"{"id":9,"error":"/nonexistent/x: "#)
+        );
+    }
+
+    #[test]
+    fn serve_stdio_answers_each_line() {
+        let input = "{\"id\":1,\"path\":\"/nonexistent\"}\n\n{\"id\":2}\n";
+        let mut output = Vec::new();
+        serve_stdio(input.as_bytes(), &mut output, &FilterConfig::default()).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let ids: Vec<&str> = output.lines().map(|l| &l[..8]).collect();
+        assert_eq!(ids, vec![r#"{"id":1,"#, r#"{"id":2,"#]);
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::analysis::FileAnalysis;
+use crate::cli::Args;
+use crate::counter::{
+    count_directory_detailed, count_file, Count, FileEntry, FilterConfig, SkipSummary,
+};
+use crate::output::{
+    format_compact_output, format_csv, format_json_single, format_markdown, format_prometheus,
+    JsonFileResult, OutputKind,
+};
+
+/// How long a counted path is served from memory before it is counted again
+pub const CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// A response ready to be written back to the client.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: String) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body: body + "\n",
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{message}\n"),
+        }
+    }
+}
+
+struct CountedPath {
+    counted_at: Instant,
+    is_directory: bool,
+    entries: Vec<FileEntry>,
+    total: Count,
+}
+
+/// Answers `/counts` queries for paths under a root, caching results for [`CACHE_TTL`].
+pub struct Server {
+    root: PathBuf,
+    config: FilterConfig,
+    cache: HashMap<PathBuf, CountedPath>,
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|d| d as u8)
+}
+
+/// Decodes `+` and `%XX` escapes in a query string component.
+fn decode_component(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        decoded.push(high * 16 + low);
+                        i += 2;
+                    }
+                    _ => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode_component(key), decode_component(value))
+        })
+        .collect()
+}
+
+impl Server {
+    pub fn new(root: &Path, config: FilterConfig) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            config,
+            cache: HashMap::new(),
+        }
+    }
+
+    fn count(&mut self, relative: &Path) -> io::Result<&CountedPath> {
+        let fresh = self
+            .cache
+            .get(relative)
+            .is_some_and(|counted| counted.counted_at.elapsed() < CACHE_TTL);
+        if !fresh {
+            let path = self.root.join(relative);
+            let (is_directory, entries, total) = if path.is_dir() {
+                let (entries, total) = count_directory_detailed(&path, &self.config)?;
+                (true, entries, total)
+            } else {
+                let count = count_file(&path)?;
+                let entry = FileEntry {
+                    path: relative.to_path_buf(),
+                    count,
+                };
+                (false, vec![entry], count)
+            };
+            self.cache.insert(
+                relative.to_path_buf(),
+                CountedPath {
+                    counted_at: Instant::now(),
+                    is_directory,
+                    entries,
+                    total,
+                },
+            );
+        }
+        Ok(&self.cache[relative])
+    }
+
+    /// Handles a request for `url` (path and query string), e.g. `/counts?path=src&format=json`.
+    ///
+    /// `args` supplies the metric selection used by the text and Markdown formats.
+    pub fn handle(&mut self, url: &str, args: &Args) -> Response {
+        let (endpoint, query) = url.split_once('?').unwrap_or((url, ""));
+        match endpoint {
+            "/health" => return Response::ok("text/plain; charset=utf-8", "ok".to_string()),
+            "/counts" => {}
+            _ => return Response::error(404, "not found"),
+        }
+
+        let query = parse_query(query);
+        let name = query.get("path").map_or(".", String::as_str).to_string();
+        let relative = PathBuf::from(&name);
+        let escapes_root = relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+        if escapes_root {
+            return Response::error(400, "path must be relative to the served directory");
+        }
+        let format = query
+            .get("format")
+            .map_or("json", String::as_str)
+            .to_string();
+
+        let counted = match self.count(&relative) {
+            Ok(counted) => counted,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Response::error(404, &format!("{name}: not found"))
+            }
+            Err(e) => return Response::error(500, &format!("{name}: {e}")),
+        };
+        let result = JsonFileResult {
+            name: name.clone(),
+            count: counted.total,
+            is_directory: counted.is_directory,
+            file_count: counted.is_directory.then_some(counted.entries.len()),
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
+        };
+        let kind = if counted.is_directory {
+            OutputKind::Directory(counted.entries.len())
+        } else {
+            OutputKind::File
+        };
+
+        match format.as_str() {
+            "json" => Response::ok("application/json", format_json_single(&result)),
+            "text" => Response::ok(
+                "text/plain; charset=utf-8",
+                format_compact_output(&name, &counted.total, kind, args),
+            ),
+            "csv" => Response::ok("text/csv; charset=utf-8", format_csv(&counted.entries, &[])),
+            "markdown" => Response::ok(
+                "text/markdown; charset=utf-8",
+                format_markdown(&[result], &counted.total, args),
+            ),
+            "prometheus" => Response::ok("text/plain; version=0.0.4", format_prometheus(&[result])),
+            _ => Response::error(
+                400,
+                &format!(
+                    "unknown format '{format}' (expected json, text, csv, markdown or prometheus)"
+                ),
+            ),
+        }
+    }
+}
+
+/// Serves `/counts` and `/health` on `address` until the process is stopped.
+pub fn serve(address: &str, mut server: Server, args: &Args) -> io::Result<()> {
+    let http = tiny_http::Server::http(address).map_err(io::Error::other)?;
+    for request in http.incoming_requests() {
+        let response = match request.method() {
+            tiny_http::Method::Get | tiny_http::Method::Head => server.handle(request.url(), args),
+            _ => Response::error(405, "method not allowed"),
+        };
+        let header = tiny_http::Header::from_bytes("Content-Type", response.content_type)
+            .expect("static header is valid");
+        let reply = tiny_http::Response::from_string(response.body)
+            .with_status_code(response.status)
+            .with_header(header);
+        // A client that went away is not the server's problem
+        let _ = request.respond(reply);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+
+    fn server_with_files() -> (tempfile::TempDir, Server) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("README.md"), "# Title\nText here\n").unwrap();
+        let server = Server::new(dir.path(), FilterConfig::default());
+        (dir, server)
+    }
+
+    #[test]
+    fn decode_query_components() {
+        assert_eq!(decode_component("src%2Fmain.rs"), "src/main.rs");
+        assert_eq!(decode_component("a+b"), "a b");
+        assert_eq!(decode_component("100%"), "100%");
+        let query = parse_query("path=src&format=csv&flag");
+        assert_eq!(query["path"], "src");
+        assert_eq!(query["format"], "csv");
+        assert_eq!(query["flag"], "");
+    }
+
+    #[test]
+    fn counts_directory_as_json() {
+        let (_dir, mut server) = server_with_files();
+        let args = Args::parse_from(["ewc"]);
+        let response = server.handle("/counts?path=src&format=json", &args);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, "application/json");
+        assert_eq!(
+            response.body,
+            r#"{"directory":"src","file_count":1,"max_line_length":12,"lines":1,"words":3,"bytes":13}"#.to_string() + "\n"
+        );
+    }
+
+    #[test]
+    fn counts_file_in_other_formats() {
+        let (_dir, mut server) = server_with_files();
+        let args = Args::parse_from(["ewc", "--no-color"]);
+        let response = server.handle("/counts?path=README.md&format=text", &args);
+        assert_eq!(response.status, 200);
+        assert!(response.body.starts_with("README.md: 2 lines"));
+
+        let response = server.handle("/counts?format=prometheus", &args);
+        assert!(response.body.contains(r#"ewc_files_total{path="."} 2"#));
+    }
+
+    #[test]
+    fn results_are_cached() {
+        let (dir, mut server) = server_with_files();
+        let args = Args::parse_from(["ewc"]);
+        server.handle("/counts?path=src", &args);
+        fs::write(dir.path().join("src/lib.rs"), "pub mod x;\n").unwrap();
+
+        let response = server.handle("/counts?path=src", &args);
+        assert!(response.body.contains(r#""file_count":1"#));
+    }
+
+    #[test]
+    fn rejects_bad_requests() {
+        let (_dir, mut server) = server_with_files();
+        let args = Args::parse_from(["ewc"]);
+        assert_eq!(server.handle("/counts?path=../etc", &args).status, 400);
+        assert_eq!(server.handle("/counts?path=/etc", &args).status, 400);
+        assert_eq!(server.handle("/counts?path=missing", &args).status, 404);
+        assert_eq!(server.handle("/counts?format=xml", &args).status, 400);
+        assert_eq!(server.handle("/elsewhere", &args).status, 404);
+        assert_eq!(server.handle("/health", &args).body, "ok\n");
+    }
+}
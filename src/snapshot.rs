@@ -0,0 +1,127 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::analysis::FileAnalysis;
+use crate::counter::{Count, FileEntry, SkipSummary};
+use crate::output::{format_json_multiple, JsonFileResult};
+use crate::report::{Report, ReportEntry};
+
+/// Where snapshots live, relative to the directory ewc runs in.
+pub const SNAPSHOT_DIR: &str = ".ewc/snapshots";
+
+fn invalid_name(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid snapshot name '{name}'"),
+    )
+}
+
+/// The file a snapshot called `name` is stored in under `root`.
+///
+/// Names may not be empty, start with a dot or contain path separators.
+pub fn snapshot_path(root: &Path, name: &str) -> io::Result<PathBuf> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(invalid_name(name));
+    }
+    Ok(root.join(SNAPSHOT_DIR).join(format!("{name}.json")))
+}
+
+/// Per-file results as a report, one entry per file.
+pub fn report_from_entries(entries: &[FileEntry]) -> Report {
+    Report::from_entries(
+        entries
+            .iter()
+            .map(|entry| ReportEntry {
+                name: entry.path.display().to_string(),
+                count: entry.count,
+                file_count: 1,
+                is_directory: false,
+            })
+            .collect(),
+    )
+}
+
+/// Writes per-file results in the `--json` report format, replacing any snapshot of the same name.
+pub fn save(root: &Path, name: &str, entries: &[FileEntry]) -> io::Result<PathBuf> {
+    let path = snapshot_path(root, name)?;
+    let results: Vec<JsonFileResult> = entries
+        .iter()
+        .map(|entry| JsonFileResult {
+            name: entry.path.display().to_string(),
+            count: entry.count,
+            is_directory: false,
+            file_count: None,
+            skipped: Vec::new(),
+            skip_summary: SkipSummary::default(),
+            analysis: FileAnalysis::default(),
+        })
+        .collect();
+    let total: Count = entries.iter().map(|e| e.count).sum();
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, format_json_multiple(&results, &total) + "\n")?;
+    Ok(path)
+}
+
+pub fn load(root: &Path, name: &str) -> io::Result<Report> {
+    let path = snapshot_path(root, name)?;
+    Report::load(&path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no snapshot named '{name}'"),
+        ),
+        _ => e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, lines: usize) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            count: Count {
+                lines,
+                words: lines * 2,
+                bytes: lines * 10,
+                max_line_length: 12,
+            },
+        }
+    }
+
+    #[test]
+    fn snapshot_path_validates_names() {
+        let root = Path::new("repo");
+        assert_eq!(
+            snapshot_path(root, "v1.0").unwrap(),
+            PathBuf::from("repo/.ewc/snapshots/v1.0.json")
+        );
+        assert!(snapshot_path(root, "").is_err());
+        assert!(snapshot_path(root, "../escape").is_err());
+        assert!(snapshot_path(root, ".hidden").is_err());
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = vec![entry("src/main.rs", 10), entry("src/lib.rs", 5)];
+
+        let path = save(dir.path(), "release", &entries).unwrap();
+        assert!(path.ends_with(".ewc/snapshots/release.json"));
+
+        let report = load(dir.path(), "release").unwrap();
+        assert_eq!(report, report_from_entries(&entries));
+    }
+
+    #[test]
+    fn load_missing_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = load(dir.path(), "nope").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "no snapshot named 'nope'");
+    }
+}
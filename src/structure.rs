@@ -0,0 +1,313 @@
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::path::Path;
+
+#[cfg(feature = "structure")]
+use crate::analysis::ParseError;
+
+/// Keys, nesting and arrays in a configuration file, as shown with `--structure`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StructureStats {
+    /// Keys in every mapping, table and section
+    pub keys: usize,
+    /// Deepest nesting of mappings, tables and arrays; the top level is 1
+    pub max_depth: usize,
+    /// Sequences and arrays, arrays of tables included
+    pub arrays: usize,
+    /// Items in the longest array
+    pub max_array_len: usize,
+}
+
+impl Add for StructureStats {
+    type Output = Self;
+
+    /// Keys and arrays add up; depth and array length keep the largest.
+    fn add(self, other: Self) -> Self {
+        Self {
+            keys: self.keys + other.keys,
+            max_depth: self.max_depth.max(other.max_depth),
+            arrays: self.arrays + other.arrays,
+            max_array_len: self.max_array_len.max(other.max_array_len),
+        }
+    }
+}
+
+impl AddAssign for StructureStats {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for StructureStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// The configuration formats `--structure` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Yaml,
+    Toml,
+    Ini,
+}
+
+impl ConfigFormat {
+    fn for_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            "ini" | "cfg" => Some(Self::Ini),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `--structure` reads this file, judged by its extension.
+pub fn is_config(path: &Path) -> bool {
+    ConfigFormat::for_path(path).is_some()
+}
+
+impl StructureStats {
+    /// Records an array of `len` items at `depth`.
+    #[cfg(feature = "structure")]
+    fn array(&mut self, depth: usize, len: usize) {
+        self.arrays += 1;
+        self.max_array_len = self.max_array_len.max(len);
+        self.max_depth = self.max_depth.max(depth);
+    }
+
+    /// Records a mapping of `keys` entries at `depth`.
+    #[cfg(feature = "structure")]
+    fn mapping(&mut self, depth: usize, keys: usize) {
+        self.keys += keys;
+        self.max_depth = self.max_depth.max(depth);
+    }
+}
+
+#[cfg(feature = "structure")]
+fn walk_yaml(value: &yaml_rust2::Yaml, depth: usize, stats: &mut StructureStats) {
+    use yaml_rust2::Yaml;
+    match value {
+        Yaml::Hash(hash) => {
+            stats.mapping(depth, hash.len());
+            for value in hash.values() {
+                walk_yaml(value, depth + 1, stats);
+            }
+        }
+        Yaml::Array(items) => {
+            stats.array(depth, items.len());
+            for item in items {
+                walk_yaml(item, depth + 1, stats);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(feature = "structure")]
+fn walk_toml(value: &toml::Value, depth: usize, stats: &mut StructureStats) {
+    match value {
+        toml::Value::Table(table) => walk_toml_table(table, depth, stats),
+        toml::Value::Array(items) => {
+            stats.array(depth, items.len());
+            for item in items {
+                walk_toml(item, depth + 1, stats);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(feature = "structure")]
+fn walk_toml_table(table: &toml::Table, depth: usize, stats: &mut StructureStats) {
+    stats.mapping(depth, table.len());
+    for value in table.values() {
+        walk_toml(value, depth + 1, stats);
+    }
+}
+
+/// The 1-based line and column of byte `offset` in `content`.
+#[cfg(feature = "structure")]
+fn position(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// INI files: `[section]` headers and `key = value` (or `key: value`) lines, with `;`
+/// and `#` comments. Keys before the first section are at the top level.
+#[cfg(feature = "structure")]
+fn ini_structure(content: &str) -> (StructureStats, Vec<ParseError>) {
+    let mut stats = StructureStats::default();
+    let mut errors = Vec::new();
+    let mut in_section = false;
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('#') {
+            continue;
+        }
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            // A section is a key of the top level holding its own keys
+            stats.mapping(1, 1);
+            in_section = true;
+        } else if trimmed.contains('=') || trimmed.contains(':') {
+            stats.mapping(if in_section { 2 } else { 1 }, 1);
+        } else {
+            errors.push(ParseError {
+                line: index + 1,
+                column: line.len() - line.trim_start().len() + 1,
+                message: "expected `[section]` or `key = value`".to_string(),
+            });
+        }
+    }
+    (stats, errors)
+}
+
+/// Counts the keys, nesting and arrays of a YAML, TOML or INI file, judged by `path`'s
+/// extension; `None` for other files. A file that does not parse has no counts but
+/// its error, with the line and column it was found at.
+#[cfg(feature = "structure")]
+pub fn structure(path: &Path, content: &str) -> Option<(StructureStats, Vec<ParseError>)> {
+    let mut stats = StructureStats::default();
+    let result = match ConfigFormat::for_path(path)? {
+        ConfigFormat::Yaml => match yaml_rust2::YamlLoader::load_from_str(content) {
+            Ok(documents) => {
+                for document in &documents {
+                    walk_yaml(document, 1, &mut stats);
+                }
+                (stats, Vec::new())
+            }
+            Err(e) => {
+                let error = ParseError {
+                    line: e.marker().line(),
+                    column: e.marker().col(),
+                    message: e.info().to_string(),
+                };
+                (stats, vec![error])
+            }
+        },
+        ConfigFormat::Toml => match content.parse::<toml::Table>() {
+            Ok(table) => {
+                walk_toml_table(&table, 1, &mut stats);
+                (stats, Vec::new())
+            }
+            Err(e) => {
+                let (line, column) = position(content, e.span().map_or(0, |span| span.start));
+                let error = ParseError {
+                    line,
+                    column,
+                    message: e.message().trim_end().to_string(),
+                };
+                (stats, vec![error])
+            }
+        },
+        ConfigFormat::Ini => ini_structure(content),
+    };
+    Some(result)
+}
+
+#[cfg(all(test, feature = "structure"))]
+mod tests {
+    use super::*;
+
+    fn stats(name: &str, content: &str) -> StructureStats {
+        let (stats, errors) = structure(Path::new(name), content).unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        stats
+    }
+
+    #[test]
+    fn recognizes_config_extensions() {
+        assert!(is_config(Path::new("ci.yml")));
+        assert!(is_config(Path::new("Cargo.TOML")));
+        assert!(is_config(Path::new("setup.cfg")));
+        assert!(!is_config(Path::new("data.json")));
+        assert!(structure(Path::new("data.json"), "{}").is_none());
+    }
+
+    #[test]
+    fn counts_yaml_keys_depth_and_arrays() {
+        let content = "name: ci\non:\n  push:\n    branches: [main, dev]\njobs:\n  - run: build\n  - run: test\n  - run: lint\n---\nextra: true\n";
+        assert_eq!(
+            stats("ci.yml", content),
+            StructureStats {
+                keys: 9,
+                max_depth: 4,
+                arrays: 2,
+                max_array_len: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn counts_toml_tables_and_arrays_of_tables() {
+        let content = "name = \"ewc\"\n\n[package]\nversion = \"1\"\nkeywords = [\"cli\", \"wc\"]\n\n[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n";
+        assert_eq!(
+            stats("Cargo.toml", content),
+            StructureStats {
+                keys: 7,
+                max_depth: 3,
+                arrays: 2,
+                max_array_len: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn counts_ini_sections_and_keys() {
+        let content = "; comment\nroot = 1\n\n[server]\nhost = example.com\nport: 80\n";
+        assert_eq!(
+            stats("app.ini", content),
+            StructureStats {
+                keys: 4,
+                max_depth: 2,
+                arrays: 0,
+                max_array_len: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors_with_positions() {
+        let (_, errors) = structure(Path::new("a.toml"), "a = 1\nb = \n").unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+
+        let (_, errors) = structure(Path::new("a.yaml"), "a: [1, 2\nb: 3\n").unwrap();
+        assert_eq!(errors.len(), 1);
+
+        let (stats, errors) = structure(Path::new("a.ini"), "[s]\nk = v\njunk\n").unwrap();
+        assert_eq!(stats.keys, 2);
+        assert_eq!((errors[0].line, errors[0].column), (3, 1));
+    }
+
+    #[test]
+    fn sums_keep_the_deepest_and_longest() {
+        let a = StructureStats {
+            keys: 2,
+            max_depth: 3,
+            arrays: 1,
+            max_array_len: 5,
+        };
+        let b = StructureStats {
+            keys: 1,
+            max_depth: 1,
+            arrays: 1,
+            max_array_len: 9,
+        };
+        assert_eq!(
+            a + b,
+            StructureStats {
+                keys: 3,
+                max_depth: 3,
+                arrays: 2,
+                max_array_len: 9,
+            }
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The `path` of every submodule declared in a `.gitmodules` file, in the order
+/// declared.
+fn parse_gitmodules(content: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut in_submodule = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_submodule = line.starts_with("[submodule");
+        } else if let Some((key, value)) = line.split_once('=') {
+            if in_submodule && key.trim() == "path" {
+                let value = value.trim().trim_matches('"');
+                paths.push(PathBuf::from(value.trim_end_matches('/')));
+            }
+        }
+    }
+    paths
+}
+
+/// The submodules of the repository at `root`, relative to it, read from its
+/// `.gitmodules` and from those of checked-out submodules for nested ones. A
+/// repository without `.gitmodules` has none.
+pub fn submodule_paths(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let content = match fs::read_to_string(root.join(".gitmodules")) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut paths = Vec::new();
+    for path in parse_gitmodules(&content) {
+        let nested = submodule_paths(&root.join(&path))?;
+        paths.push(path.clone());
+        paths.extend(nested.into_iter().map(|nested| path.join(nested)));
+    }
+    Ok(paths)
+}
+
+/// The index in `dirs`, directories relative to `root` such as submodules or packages,
+/// of the innermost one holding `path`, a file under `root`; `None` for a file in
+/// none of them.
+pub fn innermost_dir(dirs: &[PathBuf], root: &Path, path: &Path) -> Option<usize> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    dirs.iter()
+        .enumerate()
+        .filter(|(_, dir)| relative.starts_with(dir))
+        .max_by_key(|(_, dir)| dir.components().count())
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_submodule_paths() {
+        let content = "[submodule \"vendor/lib\"]\n\tpath = vendor/lib\n\turl = ../lib.git\n\
+                       [branch \"main\"]\n\tpath = ignored\n\
+                       [submodule \"docs\"]\n\turl = ../docs.git\n\tpath = \"docs/\"\n";
+        assert_eq!(
+            parse_gitmodules(content),
+            [PathBuf::from("vendor/lib"), PathBuf::from("docs")]
+        );
+    }
+
+    #[test]
+    fn files_belong_to_the_innermost_submodule() {
+        let submodules = [
+            PathBuf::from("vendor/lib"),
+            PathBuf::from("vendor/lib/deps/x"),
+        ];
+        let root = Path::new("repo");
+        let owner = |path: &str| innermost_dir(&submodules, root, Path::new(path));
+        assert_eq!(owner("repo/src/main.rs"), None);
+        assert_eq!(owner("repo/vendor/lib/a.c"), Some(0));
+        assert_eq!(owner("repo/vendor/lib/deps/x/b.c"), Some(1));
+        assert_eq!(owner("repo/vendor/library/c.c"), None);
+    }
+
+    #[test]
+    fn reads_nested_gitmodules() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(".gitmodules"),
+            "[submodule \"a\"]\n\tpath = a\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        fs::write(
+            dir.path().join("a/.gitmodules"),
+            "[submodule \"b\"]\n\tpath = b\n",
+        )
+        .unwrap();
+        assert_eq!(
+            submodule_paths(dir.path()).unwrap(),
+            [PathBuf::from("a"), PathBuf::from("a/b")]
+        );
+        assert!(submodule_paths(&dir.path().join("a/b")).unwrap().is_empty());
+    }
+}
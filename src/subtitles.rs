@@ -0,0 +1,134 @@
+use std::iter::Sum;
+use std::ops::Add;
+use std::path::Path;
+
+/// Whether `--subtitles` analyzes this file: SubRip (`.srt`) and WebVTT (`.vtt`).
+pub fn is_subtitle(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("srt") || ext.eq_ignore_ascii_case("vtt"))
+}
+
+/// Cues, their words and how long they are on screen, for `--subtitles`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SubtitleStats {
+    pub cues: usize,
+    /// Words of cue text, without formatting tags
+    pub words: usize,
+    /// Time cues are shown, summed, in milliseconds
+    pub duration_ms: u64,
+}
+
+impl Add for SubtitleStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            cues: self.cues + other.cues,
+            words: self.words + other.words,
+            duration_ms: self.duration_ms + other.duration_ms,
+        }
+    }
+}
+
+impl Sum for SubtitleStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl SubtitleStats {
+    /// Reads SubRip or WebVTT cues: blocks with a `start --> end` timing line. Blocks
+    /// without one, such as the `WEBVTT` header and `NOTE` or `STYLE` blocks, are left
+    /// out, as are cues whose timing cannot be read.
+    pub fn from_content(content: &str) -> Self {
+        let mut stats = Self::default();
+        let mut lines = content.lines().map(|line| line.trim_end_matches('\r'));
+        while let Some(line) = lines.next() {
+            let Some((start, end)) = line.split_once("-->") else {
+                continue;
+            };
+            // Settings such as `align:start` may follow the end time in WebVTT
+            let end = end.split_whitespace().next().unwrap_or_default();
+            let (Some(start), Some(end)) = (timestamp_ms(start.trim()), timestamp_ms(end)) else {
+                continue;
+            };
+            stats.cues += 1;
+            stats.duration_ms += end.saturating_sub(start);
+            for text in lines.by_ref().take_while(|line| !line.trim().is_empty()) {
+                stats.words += strip_formatting(text).split_whitespace().count();
+            }
+        }
+        stats
+    }
+
+    /// Words per minute of on-screen time, `None` without timed cues.
+    pub fn words_per_minute(&self) -> Option<f64> {
+        (self.duration_ms > 0).then(|| self.words as f64 * 60_000.0 / self.duration_ms as f64)
+    }
+}
+
+/// Milliseconds in `01:02:03,456` (SubRip) or `02:03.456` (WebVTT, hours optional).
+fn timestamp_ms(timestamp: &str) -> Option<u64> {
+    let (clock, millis) = timestamp.split_once([',', '.'])?;
+    let mut seconds = 0;
+    for part in clock.split(':') {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(seconds * 1000 + millis.parse::<u64>().ok()?)
+}
+
+/// Cue text without `<i>`, `<v Speaker>` and similar tags or `{\an8}` style overrides.
+fn strip_formatting(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut closing = None;
+    for c in text.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (None, c) => plain.push(c),
+            (Some(end), c) if c == end => {
+                closing = None;
+                plain.push(' ');
+            }
+            _ => {}
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_srt_cues() {
+        let srt = "1\r\n00:00:01,000 --> 00:00:04,000\r\n<i>Hello there,</i>\r\nfriend.\r\n\r\n\
+                   2\r\n00:00:05,500 --> 00:00:07,500\r\n{\\an8}Goodbye.\r\n";
+        let stats = SubtitleStats::from_content(srt);
+        assert_eq!(stats.cues, 2);
+        assert_eq!(stats.words, 4);
+        assert_eq!(stats.duration_ms, 5_000);
+        assert_eq!(stats.words_per_minute(), Some(48.0));
+    }
+
+    #[test]
+    fn reads_webvtt_cues() {
+        let vtt =
+            "WEBVTT\n\nNOTE timing --> not a cue\n\nintro\n00:01.000 --> 00:03.000 align:start\n\
+                   <v Ann>Good morning</v>\n\n01:00:00.000 --> 01:00:01.000\nOne\n";
+        let stats = SubtitleStats::from_content(vtt);
+        assert_eq!(stats.cues, 2);
+        assert_eq!(stats.words, 3);
+        assert_eq!(stats.duration_ms, 3_000);
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(timestamp_ms("01:02:03,456"), Some(3_723_456));
+        assert_eq!(timestamp_ms("02:03.456"), Some(123_456));
+        assert_eq!(timestamp_ms("soon"), None);
+        assert!(is_subtitle(Path::new("film.en.SRT")));
+        assert!(!is_subtitle(Path::new("notes.txt")));
+    }
+}
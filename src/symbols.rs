@@ -0,0 +1,272 @@
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+#[cfg(feature = "symbols")]
+use std::path::Path;
+
+#[cfg(feature = "symbols")]
+use tree_sitter::{Language, Node, Parser};
+
+/// Functions, types and tests declared in a source file, as shown with `--symbols`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolCounts {
+    /// Functions and methods with a body, tests included
+    pub functions: usize,
+    /// Structs, enums, classes, interfaces, traits and type aliases
+    pub types: usize,
+    /// Test functions, and `test(...)` / `it(...)` cases in JavaScript and TypeScript
+    pub tests: usize,
+}
+
+impl Add for SymbolCounts {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            functions: self.functions + other.functions,
+            types: self.types + other.types,
+            tests: self.tests + other.tests,
+        }
+    }
+}
+
+impl AddAssign for SymbolCounts {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for SymbolCounts {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// The languages `--symbols` understands.
+#[cfg(feature = "symbols")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Grammar {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+#[cfg(feature = "symbols")]
+impl Grammar {
+    fn for_path(path: &Path) -> Option<Self> {
+        let grammar = match path.extension()?.to_str()? {
+            "rs" => Self::Rust,
+            "py" | "pyi" => Self::Python,
+            "js" | "jsx" | "mjs" | "cjs" => Self::JavaScript,
+            "ts" | "mts" | "cts" => Self::TypeScript,
+            "tsx" => Self::Tsx,
+            "go" => Self::Go,
+            _ => return None,
+        };
+        Some(grammar)
+    }
+
+    fn language(self) -> Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    /// Adds what `node` declares, if anything, to `counts`.
+    fn classify(self, node: Node, source: &[u8], counts: &mut SymbolCounts) {
+        let name = || {
+            node.child_by_field_name("name")
+                .and_then(|name| name.utf8_text(source).ok())
+                .unwrap_or("")
+        };
+        match (self, node.kind()) {
+            (Self::Rust, "function_item") => {
+                counts.functions += 1;
+                if has_test_attribute(node, source) {
+                    counts.tests += 1;
+                }
+            }
+            (
+                Self::Rust,
+                "struct_item" | "enum_item" | "union_item" | "trait_item" | "type_item",
+            ) => counts.types += 1,
+            (Self::Python, "function_definition") => {
+                counts.functions += 1;
+                if name().starts_with("test") {
+                    counts.tests += 1;
+                }
+            }
+            (Self::Python, "class_definition") => counts.types += 1,
+            (Self::Go, "function_declaration") => {
+                counts.functions += 1;
+                if name().starts_with("Test") {
+                    counts.tests += 1;
+                }
+            }
+            (Self::Go, "method_declaration") => counts.functions += 1,
+            (Self::Go, "type_spec" | "type_alias") => counts.types += 1,
+            (
+                Self::JavaScript | Self::TypeScript | Self::Tsx,
+                "function_declaration" | "generator_function_declaration" | "method_definition",
+            ) => counts.functions += 1,
+            // `const f = () => ...` and `const f = function () { ... }`
+            (Self::JavaScript | Self::TypeScript | Self::Tsx, "variable_declarator") => {
+                let value = node.child_by_field_name("value").map(|value| value.kind());
+                if matches!(value, Some("arrow_function" | "function_expression")) {
+                    counts.functions += 1;
+                }
+            }
+            (
+                Self::JavaScript | Self::TypeScript | Self::Tsx,
+                "class_declaration"
+                | "abstract_class_declaration"
+                | "interface_declaration"
+                | "type_alias_declaration"
+                | "enum_declaration",
+            ) => counts.types += 1,
+            (Self::JavaScript | Self::TypeScript | Self::Tsx, "call_expression") => {
+                let callee = node
+                    .child_by_field_name("function")
+                    .and_then(|callee| callee.utf8_text(source).ok());
+                if matches!(callee, Some("test" | "it")) {
+                    counts.tests += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a Rust function carries `#[test]` or an attribute ending in `test`, such as
+/// `#[tokio::test]`.
+#[cfg(feature = "symbols")]
+fn has_test_attribute(function: Node, source: &[u8]) -> bool {
+    let mut sibling = function.prev_named_sibling();
+    while let Some(node) = sibling {
+        match node.kind() {
+            "attribute_item" => {
+                let text = node.utf8_text(source).unwrap_or("");
+                let attribute = text.trim_start_matches("#[").trim_end_matches(']');
+                let path = attribute.split('(').next().unwrap_or("").trim();
+                if path == "test" || path.ends_with("::test") {
+                    return true;
+                }
+            }
+            "line_comment" | "block_comment" => {}
+            _ => return false,
+        }
+        sibling = node.prev_named_sibling();
+    }
+    false
+}
+
+/// Whether `--symbols` has a grammar for this file, judged by its extension.
+#[cfg(feature = "symbols")]
+pub fn is_supported(path: &Path) -> bool {
+    Grammar::for_path(path).is_some()
+}
+
+/// Counts the functions, types and tests in `content`, parsed with the grammar for
+/// `path`'s extension; `None` for languages without one. Code that does not parse is
+/// counted as far as tree-sitter recovers it.
+#[cfg(feature = "symbols")]
+pub fn count_symbols(path: &Path, content: &str) -> Option<SymbolCounts> {
+    let grammar = Grammar::for_path(path)?;
+    let mut parser = Parser::new();
+    parser.set_language(&grammar.language()).ok()?;
+    let tree = parser.parse(content, None)?;
+
+    let mut counts = SymbolCounts::default();
+    let mut cursor = tree.walk();
+    'walk: loop {
+        grammar.classify(cursor.node(), content.as_bytes(), &mut counts);
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                break 'walk;
+            }
+        }
+    }
+    Some(counts)
+}
+
+#[cfg(all(test, feature = "symbols"))]
+mod tests {
+    use super::*;
+
+    fn count(name: &str, content: &str) -> SymbolCounts {
+        count_symbols(Path::new(name), content).unwrap()
+    }
+
+    #[test]
+    fn unsupported_languages_have_no_counts() {
+        assert!(!is_supported(Path::new("notes.txt")));
+        assert_eq!(count_symbols(Path::new("notes.txt"), "fn main() {}"), None);
+    }
+
+    #[test]
+    fn counts_rust_functions_types_and_tests() {
+        let content = r#"
+struct Point { x: i32 }
+enum Shape { Circle }
+trait Area { fn area(&self) -> f64; }
+impl Point { fn new() -> Self { Point { x: 0 } } }
+fn main() {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn works() {}
+
+    #[tokio::test]
+    // async runtime
+    async fn works_async() {}
+}
+"#;
+        let counts = count("lib.rs", content);
+        assert_eq!(counts.types, 3);
+        assert_eq!(counts.functions, 4);
+        assert_eq!(counts.tests, 2);
+    }
+
+    #[test]
+    fn counts_python_definitions() {
+        let content = "class Greeter:\n    def greet(self):\n        pass\n\ndef test_greet():\n    assert True\n";
+        let counts = count("greet.py", content);
+        assert_eq!(
+            counts,
+            SymbolCounts {
+                functions: 2,
+                types: 1,
+                tests: 1
+            }
+        );
+    }
+
+    #[test]
+    fn counts_typescript_declarations_and_test_cases() {
+        let content = "interface Shape { area(): number }\ntype Id = string;\nclass Square { area() { return 1; } }\nfunction make() {}\nconst add = (a: number, b: number) => a + b;\ntest('adds', () => {});\nit('makes', () => {});\n";
+        let counts = count("shape.ts", content);
+        assert_eq!(counts.types, 3);
+        assert_eq!(counts.functions, 3);
+        assert_eq!(counts.tests, 2);
+    }
+
+    #[test]
+    fn counts_go_declarations() {
+        let content = "package main\n\ntype Point struct{}\n\nfunc (p Point) Len() int { return 0 }\n\nfunc main() {}\n\nfunc TestLen(t *testing.T) {}\n";
+        let counts = count("main_test.go", content);
+        assert_eq!(counts.types, 1);
+        assert_eq!(counts.functions, 3);
+        assert_eq!(counts.tests, 1);
+    }
+}
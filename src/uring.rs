@@ -0,0 +1,156 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+use io_uring::{opcode, types, IoUring};
+
+/// Files in a directory count read with one submission
+pub const BATCH_SIZE: usize = 256;
+
+/// Files larger than this are read with plain blocking reads, which cost little next
+/// to the time spent counting them
+const MAX_BATCHED_LEN: u64 = 1024 * 1024;
+
+/// A file opened for a batched read, with room for one byte more than its size so a
+/// file that grew since is noticed.
+struct Pending {
+    index: usize,
+    file: File,
+    buffer: Vec<u8>,
+}
+
+/// Reads every file of `paths` whole, the small ones with a single io_uring submission
+/// for all of them, giving a result per path in order. Fails as a whole only when the
+/// kernel offers no io_uring, so callers can fall back to blocking reads.
+pub fn read_files(paths: &[&Path]) -> io::Result<Vec<io::Result<Vec<u8>>>> {
+    let mut ring = IoUring::new(paths.len().clamp(1, BATCH_SIZE).next_power_of_two() as u32)?;
+    let mut results: Vec<Option<io::Result<Vec<u8>>>> = paths.iter().map(|_| None).collect();
+    let mut pending: Vec<Pending> = Vec::new();
+    for (index, path) in paths.iter().enumerate() {
+        let opened = File::open(path).and_then(|file| Ok((file.metadata()?.len(), file)));
+        match opened {
+            Ok((len, file)) if len <= MAX_BATCHED_LEN => pending.push(Pending {
+                index,
+                file,
+                buffer: vec![0; len as usize + 1],
+            }),
+            Ok((_, mut file)) => {
+                let mut content = Vec::new();
+                results[index] = Some(file.read_to_end(&mut content).map(|_| content));
+            }
+            Err(e) => results[index] = Some(Err(e)),
+        }
+    }
+
+    let mut submitted = 0;
+    let mut completed = 0;
+    while completed < pending.len() {
+        while submitted < pending.len() {
+            let file = &mut pending[submitted];
+            let read = opcode::Read::new(
+                types::Fd(file.file.as_raw_fd()),
+                file.buffer.as_mut_ptr(),
+                file.buffer.len() as u32,
+            )
+            .offset(0)
+            .build()
+            .user_data(submitted as u64);
+            // The buffer outlives the read: `pending` is only dropped once every
+            // submitted read has completed
+            if unsafe { ring.submission().push(&read) }.is_err() {
+                break;
+            }
+            submitted += 1;
+        }
+        match ring.submit_and_wait(1) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                // Reads may still be in flight, so their buffers must never be freed
+                std::mem::forget(pending);
+                return Err(e);
+            }
+        }
+        for completion in ring.completion() {
+            let file = &mut pending[completion.user_data() as usize];
+            let read = completion.result();
+            results[file.index] = Some(if read < 0 {
+                Err(io::Error::from_raw_os_error(-read))
+            } else {
+                finish_read(file, read as usize)
+            });
+            completed += 1;
+        }
+    }
+
+    Ok(results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(io::ErrorKind::Other.into())))
+        .collect())
+}
+
+/// The content of a batched read of `read` bytes, read to the end with blocking reads
+/// when the file turned out longer than it was.
+fn finish_read(file: &mut Pending, read: usize) -> io::Result<Vec<u8>> {
+    let mut content = std::mem::take(&mut file.buffer);
+    let filled = read == content.len();
+    content.truncate(read);
+    if filled {
+        file.file.seek(SeekFrom::Start(read as u64))?;
+        file.file.read_to_end(&mut content)?;
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn reads_files_in_a_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("small.txt");
+        let empty = dir.path().join("empty.txt");
+        let large = dir.path().join("large.txt");
+        let missing = dir.path().join("missing.txt");
+        fs::write(&small, "hello world\n").unwrap();
+        fs::write(&empty, "").unwrap();
+        let large_content = "x".repeat(MAX_BATCHED_LEN as usize + 10);
+        fs::write(&large, &large_content).unwrap();
+
+        let paths = [small.as_path(), empty.as_path(), large.as_path(), &missing];
+        // Kernels and sandboxes may refuse io_uring, which callers fall back from
+        let Ok(results) = read_files(&paths) else {
+            return;
+        };
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap(), b"hello world\n");
+        assert!(results[1].as_ref().unwrap().is_empty());
+        assert_eq!(results[2].as_ref().unwrap().len(), large_content.len());
+        assert_eq!(
+            results[3].as_ref().unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn reads_more_files_than_fit_in_one_submission() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<_> = (0..BATCH_SIZE + 3)
+            .map(|n| {
+                let path = dir.path().join(format!("{n}.txt"));
+                fs::write(&path, format!("file {n}\n")).unwrap();
+                path
+            })
+            .collect();
+        let paths: Vec<&Path> = paths.iter().map(|path| path.as_path()).collect();
+        let Ok(results) = read_files(&paths) else {
+            return;
+        };
+        for (n, result) in results.iter().enumerate() {
+            assert_eq!(result.as_ref().unwrap(), format!("file {n}\n").as_bytes());
+        }
+    }
+}
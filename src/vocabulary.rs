@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// How often each word occurs, for the `--vocabulary` lexical-diversity figures. Words
+/// are compared in lowercase, without the punctuation around them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Vocabulary {
+    counts: HashMap<String, usize>,
+}
+
+impl Add for Vocabulary {
+    type Output = Self;
+
+    /// The vocabulary of the texts together, so a word used once in each of two files is
+    /// not a hapax of both.
+    fn add(mut self, other: Self) -> Self {
+        for (word, count) in other.counts {
+            *self.counts.entry(word).or_default() += count;
+        }
+        self
+    }
+}
+
+impl AddAssign for Vocabulary {
+    fn add_assign(&mut self, other: Self) {
+        *self = std::mem::take(self) + other;
+    }
+}
+
+impl Sum for Vocabulary {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl Vocabulary {
+    pub fn from_content(content: &str) -> Self {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in content.split_whitespace() {
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+            if !word.is_empty() {
+                *counts.entry(word.to_lowercase()).or_default() += 1;
+            }
+        }
+        Self { counts }
+    }
+
+    /// Words counted, which can be fewer than `wc` words: tokens without a letter or
+    /// digit, such as `-` or `...`, are left out.
+    pub fn words(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    pub fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Words used exactly once (hapax legomena).
+    pub fn hapax(&self) -> usize {
+        self.counts.values().filter(|&&count| count == 1).count()
+    }
+
+    /// Distinct words per word, from near 0 for repetitive text to 1 when no word
+    /// repeats; longer texts score lower, so compare texts of similar length.
+    pub fn type_token_ratio(&self) -> f64 {
+        match self.words() {
+            0 => 0.0,
+            words => self.distinct() as f64 / words as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_distinct_words_and_hapax() {
+        let vocabulary = Vocabulary::from_content("The cat saw the dog. The dog - ran!\n");
+        assert_eq!(vocabulary.words(), 8);
+        assert_eq!(vocabulary.distinct(), 5);
+        assert_eq!(vocabulary.hapax(), 3);
+        assert_eq!(vocabulary.type_token_ratio(), 5.0 / 8.0);
+    }
+
+    #[test]
+    fn empty_text_has_no_ratio() {
+        let vocabulary = Vocabulary::from_content(" ... \n");
+        assert_eq!(vocabulary.words(), 0);
+        assert_eq!(vocabulary.type_token_ratio(), 0.0);
+    }
+
+    #[test]
+    fn sums_merge_the_words() {
+        let total = Vocabulary::from_content("alpha beta") + Vocabulary::from_content("beta gamma");
+        assert_eq!(total.words(), 4);
+        assert_eq!(total.distinct(), 3);
+        assert_eq!(total.hapax(), 2);
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::{Path, PathBuf};
+use wasm_bindgen::prelude::*;
+
+use crate::counter::{filter_relative_paths, Count, FilterConfig};
+use crate::generated::{is_generated_content, is_generated_name};
+
+/// Counts for a piece of text, as JavaScript sees them.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextCount {
+    pub lines: usize,
+    pub words: usize,
+    pub bytes: usize,
+    #[wasm_bindgen(js_name = maxLineLength)]
+    pub max_line_length: usize,
+}
+
+impl From<Count> for TextCount {
+    fn from(count: Count) -> Self {
+        Self {
+            lines: count.lines,
+            words: count.words,
+            bytes: count.bytes,
+            max_line_length: count.max_line_length,
+        }
+    }
+}
+
+/// Counts `content` exactly as ewc counts a file; `bytes` is the UTF-8 length.
+#[wasm_bindgen(js_name = countText)]
+pub fn count_text(content: &str) -> TextCount {
+    Count::from_content(content).into()
+}
+
+/// Keeps the relative paths ewc would count in a directory with these filters, in order.
+#[wasm_bindgen(js_name = filterPaths)]
+pub fn filter_paths(
+    paths: Vec<String>,
+    exclude: Vec<String>,
+    include: Vec<String>,
+    hidden: bool,
+) -> Result<Vec<String>, String> {
+    let config = FilterConfig::new(hidden, exclude, include);
+    let paths = paths.into_iter().map(PathBuf::from).collect();
+    let kept = filter_relative_paths(paths, &config).map_err(|e| e.to_string())?;
+    Ok(kept
+        .into_iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Whether ewc would skip a file as generated, judging by its path and first lines.
+#[wasm_bindgen(js_name = isGenerated)]
+pub fn is_generated(path: &str, content: &str) -> bool {
+    is_generated_name(Path::new(path)) || is_generated_content(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_text_like_files() {
+        let count = count_text("héllo world\nsecond line\n");
+        assert_eq!(
+            count,
+            TextCount {
+                lines: 2,
+                words: 4,
+                bytes: 25,
+                max_line_length: 12,
+            }
+        );
+    }
+
+    #[test]
+    fn filters_paths() {
+        let paths = ["src/main.rs", "README.md", ".github/ci.yml"].map(String::from);
+        let kept = filter_paths(paths.to_vec(), vec!["*.md".to_string()], vec![], false).unwrap();
+        assert_eq!(kept, vec!["src/main.rs"]);
+
+        let kept = filter_paths(paths.to_vec(), vec![], vec!["*.yml".to_string()], true).unwrap();
+        assert_eq!(kept, vec![".github/ci.yml"]);
+
+        let error = filter_paths(paths.to_vec(), vec!["[".to_string()], vec![], false);
+        assert!(error.unwrap_err().contains("Invalid glob pattern"));
+    }
+
+    #[test]
+    fn detects_generated_files() {
+        assert!(is_generated("Cargo.lock", ""));
+        assert!(is_generated("src/gen.rs", "// @generated by build.rs\n"));
+        assert!(!is_generated("src/main.rs", "fn main() {}\n"));
+    }
+}
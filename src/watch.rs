@@ -0,0 +1,348 @@
+use notify::event::{AccessKind, AccessMode};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::counter::{
+    count_directory_detailed, count_file, filter_relative_paths, read_text, skip_generated, Count,
+    FileEntry, FilterConfig,
+};
+use crate::generated::is_generated_content;
+
+/// How long to wait for further events, so that a save touching several files redraws once
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// A path given on the command line, with the last known count of every file under it.
+pub struct WatchTarget {
+    /// The path as given, used for display
+    pub name: String,
+    root: PathBuf,
+    is_dir: bool,
+    /// Counts keyed by path relative to `root` (empty for a single file)
+    files: BTreeMap<PathBuf, Count>,
+}
+
+impl WatchTarget {
+    pub fn new(name: &str, config: &FilterConfig) -> io::Result<Self> {
+        let root = fs::canonicalize(name)?;
+        let mut target = Self {
+            name: name.to_string(),
+            is_dir: root.is_dir(),
+            root,
+            files: BTreeMap::new(),
+        };
+        target.rescan(config)?;
+        Ok(target)
+    }
+
+    fn rescan(&mut self, config: &FilterConfig) -> io::Result<()> {
+        self.files = if self.is_dir {
+            let (entries, _) = count_directory_detailed(&self.root, config)?;
+            entries
+                .into_iter()
+                .map(|entry| {
+                    let relative = entry.path.strip_prefix(&self.root).unwrap_or(&entry.path);
+                    (relative.to_path_buf(), entry.count)
+                })
+                .collect()
+        } else {
+            BTreeMap::from([(PathBuf::new(), count_file(&self.root)?)])
+        };
+        Ok(())
+    }
+
+    fn count_one(&self, relative: &Path, config: &FilterConfig) -> io::Result<Option<Count>> {
+        let path = self.root.join(relative);
+        if !path.is_file()
+            || filter_relative_paths(vec![relative.to_path_buf()], config)?.is_empty()
+        {
+            return Ok(None);
+        }
+        let Some((_, check_content)) = skip_generated(&self.root, vec![path.clone()], config).pop()
+        else {
+            return Ok(None);
+        };
+        let Ok(Some(content)) = read_text(&path) else {
+            return Ok(None);
+        };
+        if check_content && is_generated_content(&content) {
+            return Ok(None);
+        }
+        Ok(Some(Count::from_content(&content)))
+    }
+
+    /// Re-counts the changed paths under this target, returning whether there were any.
+    ///
+    /// Changed files are counted again on their own; new directories and git-based
+    /// selections fall back to a full scan of the target.
+    pub fn update(&mut self, changed: &[PathBuf], config: &FilterConfig) -> io::Result<bool> {
+        let relevant: Vec<PathBuf> = changed
+            .iter()
+            .filter_map(|path| path.strip_prefix(&self.root).ok())
+            .map(Path::to_path_buf)
+            .collect();
+        if relevant.is_empty() {
+            return Ok(false);
+        }
+
+        let needs_rescan = !self.is_dir
+            || config.git_selection.is_some()
+            || relevant.iter().any(|r| self.root.join(r).is_dir());
+        if needs_rescan {
+            self.rescan(config)?;
+            return Ok(true);
+        }
+
+        for relative in relevant {
+            // A removed directory takes every file under it along
+            self.files.retain(|path, _| !path.starts_with(&relative));
+            if let Some(count) = self.count_one(&relative, config)? {
+                self.files.insert(relative, count);
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// The canonical path the target was resolved to.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Counts of the files at or under `prefix`, with paths relative to the target.
+    pub fn files_under(&self, prefix: &Path) -> Vec<FileEntry> {
+        self.files
+            .iter()
+            .filter(|(relative, _)| relative.starts_with(prefix))
+            .map(|(relative, count)| FileEntry {
+                path: relative.clone(),
+                count: *count,
+            })
+            .collect()
+    }
+
+    /// Current per-file counts, with paths under the name the target was given as.
+    pub fn entries(&self) -> Vec<FileEntry> {
+        self.files
+            .iter()
+            .map(|(relative, count)| FileEntry {
+                // Joining an empty path would add a trailing separator
+                path: if relative.as_os_str().is_empty() {
+                    PathBuf::from(&self.name)
+                } else {
+                    Path::new(&self.name).join(relative)
+                },
+                count: *count,
+            })
+            .collect()
+    }
+
+    pub fn total(&self) -> Count {
+        self.files.values().cloned().sum()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+}
+
+fn notify_error(e: notify::Error) -> io::Error {
+    io::Error::other(format!("watch: {e}"))
+}
+
+fn changed_paths(event: notify::Result<notify::Event>) -> Vec<PathBuf> {
+    match event {
+        // Reading files to count them would otherwise trigger another round
+        Ok(event) => match event.kind {
+            EventKind::Access(AccessKind::Close(AccessMode::Write)) => event.paths,
+            EventKind::Access(_) => Vec::new(),
+            _ => event.paths,
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Blocks, calling `on_change` with the paths touched by each burst of filesystem events.
+///
+/// Directories are watched recursively; a single file is watched through its parent
+/// so that editors replacing it on save are still noticed.
+pub fn watch_paths(paths: &[PathBuf], mut on_change: impl FnMut(&[PathBuf])) -> io::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(notify_error)?;
+    for path in paths {
+        let (path, mode) = match path.parent() {
+            Some(parent) if !path.is_dir() => (parent, RecursiveMode::NonRecursive),
+            _ => (path.as_path(), RecursiveMode::Recursive),
+        };
+        watcher.watch(path, mode).map_err(notify_error)?;
+    }
+
+    while let Ok(event) = rx.recv() {
+        let mut changed = changed_paths(event);
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            changed.extend(changed_paths(event));
+        }
+        changed.sort();
+        changed.dedup();
+        if !changed.is_empty() {
+            on_change(&changed);
+        }
+    }
+    Ok(())
+}
+
+/// Blocks, re-counting the targets after each burst of filesystem events and passing
+/// them to `redraw` along with any errors, formatted as `name: error`.
+pub fn watch(
+    targets: &mut [WatchTarget],
+    config: &FilterConfig,
+    mut redraw: impl FnMut(&[WatchTarget], &[String]),
+) -> io::Result<()> {
+    let paths: Vec<PathBuf> = targets.iter().map(|t| t.root.clone()).collect();
+    watch_paths(&paths, |changed| {
+        let mut updated = false;
+        let mut errors: Vec<String> = Vec::new();
+        for target in targets.iter_mut() {
+            match target.update(changed, config) {
+                Ok(true) => updated = true,
+                Ok(false) => {}
+                Err(e) => errors.push(format!("{}: {e}", target.name)),
+            }
+        }
+        if updated || !errors.is_empty() {
+            redraw(targets, &errors);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(target: &WatchTarget) -> Vec<(String, usize)> {
+        target
+            .entries()
+            .iter()
+            .map(|e| (e.path.display().to_string(), e.count.lines))
+            .collect()
+    }
+
+    #[test]
+    fn update_recounts_only_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        fs::write(dir.path().join("b.txt"), "one\ntwo\n").unwrap();
+        let name = dir.path().to_str().unwrap();
+        let config = FilterConfig::default();
+        let mut target = WatchTarget::new(name, &config).unwrap();
+        assert_eq!(target.total().lines, 3);
+
+        fs::write(dir.path().join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        fs::write(dir.path().join(".hidden"), "skipped\n").unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let changed = vec![root.join("a.txt"), root.join(".hidden")];
+        assert!(target.update(&changed, &config).unwrap());
+
+        assert_eq!(
+            lines(&target),
+            vec![(format!("{name}/a.txt"), 3), (format!("{name}/b.txt"), 2)]
+        );
+        assert!(!target
+            .update(&[PathBuf::from("/elsewhere/a.txt")], &config)
+            .unwrap());
+    }
+
+    #[test]
+    fn update_handles_removed_and_new_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("old")).unwrap();
+        fs::write(dir.path().join("old/a.txt"), "a\n").unwrap();
+        fs::write(dir.path().join("top.txt"), "top\n").unwrap();
+        let config = FilterConfig::default();
+        let mut target = WatchTarget::new(dir.path().to_str().unwrap(), &config).unwrap();
+        assert_eq!(target.file_count(), 2);
+
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::remove_dir_all(dir.path().join("old")).unwrap();
+        target.update(&[root.join("old")], &config).unwrap();
+        assert_eq!(target.file_count(), 1);
+
+        fs::create_dir(dir.path().join("new")).unwrap();
+        fs::write(dir.path().join("new/b.txt"), "b\nb\n").unwrap();
+        target.update(&[root.join("new")], &config).unwrap();
+        assert_eq!(target.file_count(), 2);
+        assert_eq!(target.total().lines, 3);
+    }
+
+    #[test]
+    fn files_under_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("README.md"), "# Readme\n").unwrap();
+        let target =
+            WatchTarget::new(dir.path().to_str().unwrap(), &FilterConfig::default()).unwrap();
+
+        let paths = |prefix: &str| -> Vec<PathBuf> {
+            target
+                .files_under(Path::new(prefix))
+                .into_iter()
+                .map(|e| e.path)
+                .collect()
+        };
+        assert_eq!(paths("src"), vec![PathBuf::from("src/main.rs")]);
+        assert_eq!(paths("src/main.rs"), vec![PathBuf::from("src/main.rs")]);
+        assert_eq!(paths("").len(), 2);
+        assert!(paths("sr").is_empty());
+    }
+
+    #[test]
+    fn update_recounts_changed_documents() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        let doc = dir.path().join("doc.docx");
+        let write_doc = |paragraphs: &str| {
+            let mut archive = zip::ZipWriter::new(fs::File::create(&doc).unwrap());
+            let options = zip::write::SimpleFileOptions::default();
+            archive.start_file("word/document.xml", options).unwrap();
+            let body = format!("<w:document><w:body>{paragraphs}</w:body></w:document>");
+            archive.write_all(body.as_bytes()).unwrap();
+            archive.finish().unwrap();
+        };
+        write_doc("<w:p><w:r><w:t>Dear reader</w:t></w:r></w:p>");
+        let config = FilterConfig::default();
+        let mut target = WatchTarget::new(dir.path().to_str().unwrap(), &config).unwrap();
+        assert_eq!(target.file_count(), 2);
+
+        write_doc("<w:p><w:r><w:t>Dear</w:t></w:r></w:p><w:p><w:r><w:t>reader</w:t></w:r></w:p>");
+        let root = fs::canonicalize(dir.path()).unwrap();
+        assert!(target.update(&[root.join("doc.docx")], &config).unwrap());
+        assert_eq!(target.file_count(), 2);
+        assert_eq!(target.total().lines, 3);
+    }
+
+    #[test]
+    fn single_file_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.md");
+        fs::write(&file, "draft\n").unwrap();
+        let config = FilterConfig::default();
+        let mut target = WatchTarget::new(file.to_str().unwrap(), &config).unwrap();
+        assert!(!target.is_dir());
+
+        fs::write(&file, "draft\nmore words\n").unwrap();
+        let changed = [fs::canonicalize(&file).unwrap()];
+        assert!(target.update(&changed, &config).unwrap());
+        assert_eq!(target.total().words, 3);
+        assert_eq!(lines(&target), vec![(file.display().to_string(), 2)]);
+    }
+}
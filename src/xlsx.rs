@@ -0,0 +1,221 @@
+use std::io::{self, Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// A worksheet cell: text, or a number Excel can sum and sort.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::Text(text.to_string())
+    }
+}
+
+impl From<usize> for Cell {
+    fn from(n: usize) -> Self {
+        Cell::Number(n as f64)
+    }
+}
+
+/// A named worksheet, the first row usually being its header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sheet {
+    pub name: String,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+const CONTENT_TYPES: &str = "http://schemas.openxmlformats.org/package/2006/content-types";
+const RELATIONSHIPS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const DOCUMENT_RELATIONSHIPS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const SPREADSHEET: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+
+/// Text as XML character data; characters XML cannot hold at all are dropped.
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The letters of a zero-based column: `A` to `Z`, then `AA`, `AB`, ...
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+fn cell_xml(reference: &str, cell: &Cell) -> String {
+    match cell {
+        Cell::Number(n) => format!(r#"<c r="{reference}"><v>{n}</v></c>"#),
+        Cell::Text(text) => {
+            let space = if text.trim() == text {
+                ""
+            } else {
+                r#" xml:space="preserve""#
+            };
+            format!(
+                r#"<c r="{reference}" t="inlineStr"><is><t{space}>{}</t></is></c>"#,
+                escape_xml(text)
+            )
+        }
+    }
+}
+
+fn sheet_xml(sheet: &Sheet) -> String {
+    let mut xml = format!(r#"{XML_DECLARATION}<worksheet xmlns="{SPREADSHEET}"><sheetData>"#);
+    for (row_index, row) in sheet.rows.iter().enumerate() {
+        let number = row_index + 1;
+        xml.push_str(&format!(r#"<row r="{number}">"#));
+        for (column, cell) in row.iter().enumerate() {
+            xml.push_str(&cell_xml(&format!("{}{number}", column_name(column)), cell));
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// An `.xlsx` workbook holding `sheets` in order. Sheet names are cut to the 31
+/// characters Excel allows.
+pub fn write_workbook(sheets: &[Sheet]) -> io::Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    let mut part = |name: &str, content: &str| -> io::Result<()> {
+        zip.start_file(name, options).map_err(io::Error::other)?;
+        zip.write_all(content.as_bytes())
+    };
+
+    let overrides: String = (1..=sheets.len())
+        .map(|n| {
+            format!(
+                r#"<Override PartName="/xl/worksheets/sheet{n}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#
+            )
+        })
+        .collect();
+    part(
+        "[Content_Types].xml",
+        &format!(
+            r#"{XML_DECLARATION}<Types xmlns="{CONTENT_TYPES}"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>{overrides}</Types>"#
+        ),
+    )?;
+    part(
+        "_rels/.rels",
+        &format!(
+            r#"{XML_DECLARATION}<Relationships xmlns="{RELATIONSHIPS}"><Relationship Id="rId1" Type="{DOCUMENT_RELATIONSHIPS}/officeDocument" Target="xl/workbook.xml"/></Relationships>"#
+        ),
+    )?;
+
+    let entries: String = sheets
+        .iter()
+        .enumerate()
+        .map(|(index, sheet)| {
+            let name: String = sheet.name.chars().take(31).collect();
+            let n = index + 1;
+            format!(
+                r#"<sheet name="{}" sheetId="{n}" r:id="rId{n}"/>"#,
+                escape_xml(&name)
+            )
+        })
+        .collect();
+    part(
+        "xl/workbook.xml",
+        &format!(
+            r#"{XML_DECLARATION}<workbook xmlns="{SPREADSHEET}" xmlns:r="{DOCUMENT_RELATIONSHIPS}"><sheets>{entries}</sheets></workbook>"#
+        ),
+    )?;
+    let relationships: String = (1..=sheets.len())
+        .map(|n| {
+            format!(
+                r#"<Relationship Id="rId{n}" Type="{DOCUMENT_RELATIONSHIPS}/worksheet" Target="worksheets/sheet{n}.xml"/>"#
+            )
+        })
+        .collect();
+    part(
+        "xl/_rels/workbook.xml.rels",
+        &format!(
+            r#"{XML_DECLARATION}<Relationships xmlns="{RELATIONSHIPS}">{relationships}</Relationships>"#
+        ),
+    )?;
+    for (index, sheet) in sheets.iter().enumerate() {
+        part(
+            &format!("xl/worksheets/sheet{}.xml", index + 1),
+            &sheet_xml(sheet),
+        )?;
+    }
+
+    let cursor = zip.finish().map_err(io::Error::other)?;
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn names_columns_like_excel() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(27), "AB");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+
+    #[test]
+    fn writes_sheets_with_text_and_numbers() {
+        let sheets = [
+            Sheet {
+                name: "Summary".to_string(),
+                rows: vec![vec!["Path".into(), "Lines".into()]],
+            },
+            Sheet {
+                name: "Files".to_string(),
+                rows: vec![
+                    vec!["Path".into(), "Lines".into()],
+                    vec!["a <&> b.rs".into(), 1200.into()],
+                ],
+            },
+        ];
+        let bytes = write_workbook(&sheets).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+        assert!(read("[Content_Types].xml").contains("/xl/worksheets/sheet2.xml"));
+        assert!(read("xl/workbook.xml")
+            .contains(r#"<sheet name="Summary" sheetId="1" r:id="rId1"/><sheet name="Files""#));
+        let files = read("xl/worksheets/sheet2.xml");
+        assert!(files.contains(
+            r#"<row r="2"><c r="A2" t="inlineStr"><is><t>a &lt;&amp;&gt; b.rs</t></is></c><c r="B2"><v>1200</v></c></row>"#
+        ));
+    }
+}
@@ -11,10 +11,23 @@ struct CommandResult {
     stdout: String,
     stderr: String,
     success: bool,
+    code: Option<i32>,
+}
+
+/// An ewc command kept apart from the user's cache, config and `EWC_OPTS`, with its
+/// cache and config directories in `home`.
+fn ewc_command(home: &std::path::Path) -> Command {
+    let mut command = Command::new(std::fs::canonicalize("./target/debug/ewc").unwrap());
+    command
+        .env("XDG_CACHE_HOME", home)
+        .env("XDG_CONFIG_HOME", home)
+        .env_remove("EWC_OPTS");
+    command
 }
 
 fn run_ewc(args: &[&str]) -> CommandResult {
-    let output = Command::new("./target/debug/ewc")
+    let home = tempfile::tempdir().unwrap();
+    let output = ewc_command(home.path())
         .args(args)
         .output()
         .expect("failed to run ewc");
@@ -22,6 +35,7 @@ fn run_ewc(args: &[&str]) -> CommandResult {
         stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
         stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
         success: output.status.success(),
+        code: output.status.code(),
     }
 }
 
@@ -408,7 +422,8 @@ fn run_ewc_with_stdin(args: &[&str], stdin_content: &str) -> CommandResult {
     use std::io::Write;
     use std::process::Stdio;
 
-    let mut child = Command::new("./target/debug/ewc")
+    let home = tempfile::tempdir().unwrap();
+    let mut child = ewc_command(home.path())
         .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -429,6 +444,7 @@ fn run_ewc_with_stdin(args: &[&str], stdin_content: &str) -> CommandResult {
         stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
         stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
         success: output.status.success(),
+        code: output.status.code(),
     }
 }
 
@@ -523,3 +539,3009 @@ fn directory_and_nonexistent_file() {
     assert!(result.stderr.contains("nonexistent.txt"));
     assert!(result.stdout.contains("📁"));
 }
+
+// --over / --format sarif tests
+#[test]
+fn over_limit_reports_violation() {
+    let file = create_test_file("one\ntwo\nthree\n");
+    let result = run_ewc(&["--over", "lines=2", file.path().to_str().unwrap()]);
+
+    assert!(!result.success);
+    assert!(result.stdout.contains("Lines:"));
+    assert!(result.stderr.contains("3 lines exceeds limit of 2"));
+}
+
+#[test]
+fn over_limit_not_exceeded_succeeds() {
+    let file = create_test_file("one\ntwo\n");
+    let result = run_ewc(&["--over", "lines=2", file.path().to_str().unwrap()]);
+
+    assert!(result.success);
+    assert!(result.stderr.is_empty());
+}
+
+#[test]
+fn over_limit_reports_violation_with_json() {
+    let file = create_test_file("one\ntwo\nthree\n");
+    let result = run_ewc(&["--json", "--over", "lines=2", file.path().to_str().unwrap()]);
+
+    assert_eq!(result.code, Some(3));
+    assert!(result.stdout.contains(r#""lines":3"#));
+    assert!(result.stderr.contains("3 lines exceeds limit of 2"));
+
+    let result = run_ewc(&["--json", "--over", "lines=3", file.path().to_str().unwrap()]);
+    assert!(result.success);
+}
+
+#[test]
+fn over_limit_checks_files_inside_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("small.txt"), "a\n").unwrap();
+    std::fs::write(dir.path().join("big.txt"), "a\nb\nc\n").unwrap();
+
+    let result = run_ewc(&["--over", "lines=2", dir.path().to_str().unwrap()]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("big.txt"));
+    assert!(!result.stderr.contains("small.txt"));
+}
+
+#[test]
+fn sarif_format_lists_violations() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("small.txt"), "a\n").unwrap();
+    std::fs::write(dir.path().join("wide.txt"), "a very long line indeed\n").unwrap();
+
+    let result = run_ewc(&[
+        "--format",
+        "sarif",
+        "--over",
+        "max-line-length=10",
+        dir.path().to_str().unwrap(),
+    ]);
+
+    assert!(!result.success);
+    assert!(result.stdout.contains("\"version\":\"2.1.0\""));
+    assert!(result.stdout.contains("\"ruleId\":\"max-line-length\""));
+    assert!(result.stdout.contains("wide.txt"));
+    assert!(!result.stdout.contains("small.txt"));
+}
+
+#[test]
+fn sarif_format_without_violations_succeeds() {
+    let file = create_test_file("hello\n");
+    let result = run_ewc(&[
+        "--format",
+        "sarif",
+        "--over",
+        "lines=100",
+        file.path().to_str().unwrap(),
+    ]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("\"results\":[]"));
+}
+
+// --format pr-comment tests
+#[test]
+fn pr_comment_format_summarizes_directory() {
+    let dir = create_test_dir();
+    let result = run_ewc(&["--format", "pr-comment", dir.path().to_str().unwrap()]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("### ewc report"));
+    assert!(result.stdout.contains("| **Total** | 2 | 2 | 5 | 24 |"));
+    assert!(result.stdout.contains("Largest files"));
+}
+
+#[test]
+fn pr_comment_format_includes_budget_status() {
+    let dir = create_test_dir();
+    let result = run_ewc(&[
+        "--format",
+        "pr-comment",
+        "--over",
+        "words=2",
+        dir.path().to_str().unwrap(),
+    ]);
+
+    assert!(!result.success);
+    assert!(result.stdout.contains(":x: 1 violation"));
+    assert!(result.stdout.contains("file2.txt"));
+}
+
+// --format prometheus tests
+#[test]
+fn prometheus_format_labels_each_path() {
+    let dir = create_test_dir();
+    let dir_path = dir.path().to_str().unwrap();
+    let result = run_ewc(&["--format", "prometheus", dir_path]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("# TYPE ewc_lines_total gauge"));
+    assert!(result
+        .stdout
+        .contains(&format!("ewc_lines_total{{path=\"{dir_path}\"}} 2")));
+    assert!(result
+        .stdout
+        .contains(&format!("ewc_files_total{{path=\"{dir_path}\"}} 2")));
+}
+
+// --baseline tests
+fn save_baseline(args: &[&str]) -> tempfile::NamedTempFile {
+    let mut json_args = vec!["--json"];
+    json_args.extend_from_slice(args);
+    let result = run_ewc(&json_args);
+    assert!(result.success);
+    create_test_file(&result.stdout)
+}
+
+#[test]
+fn baseline_prints_deltas() {
+    let dir = create_test_dir();
+    let dir_path = dir.path().to_str().unwrap();
+    let baseline = save_baseline(&[dir_path]);
+
+    std::fs::write(dir.path().join("file3.txt"), "one\ntwo\n").unwrap();
+    let result = run_ewc(&["--baseline", baseline.path().to_str().unwrap(), dir_path]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("Change vs baseline"));
+    assert!(result.stdout.contains("Files:         +1"));
+    assert!(result.stdout.contains("Lines:         +2"));
+    assert!(result.stdout.contains(&format!("~ {dir_path}: +2 lines")));
+}
+
+#[test]
+fn baseline_fail_on_growth() {
+    let dir = create_test_dir();
+    let dir_path = dir.path().to_str().unwrap();
+    let baseline = save_baseline(&[dir_path]);
+
+    std::fs::write(dir.path().join("file3.txt"), "more\n").unwrap();
+    let result = run_ewc(&[
+        "--baseline",
+        baseline.path().to_str().unwrap(),
+        "--fail-on-growth",
+        dir_path,
+    ]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("grew"));
+}
+
+#[test]
+fn baseline_fail_on_growth_passes_when_shrinking() {
+    let dir = create_test_dir();
+    let dir_path = dir.path().to_str().unwrap();
+    let baseline = save_baseline(&[dir_path]);
+
+    std::fs::remove_file(dir.path().join("file2.txt")).unwrap();
+    let result = run_ewc(&[
+        "--baseline",
+        baseline.path().to_str().unwrap(),
+        "--fail-on-growth",
+        dir_path,
+    ]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("Files:         -1"));
+}
+
+#[test]
+fn baseline_invalid_report_errors() {
+    let file = create_test_file("hello\n");
+    let bogus = create_test_file("not json");
+    let result = run_ewc(&[
+        "--baseline",
+        bogus.path().to_str().unwrap(),
+        file.path().to_str().unwrap(),
+    ]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("invalid JSON report"));
+}
+
+// --git tests
+fn git(dir: &std::path::Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=ewc", "-c", "user.email=ewc@example.com"])
+        .args(args)
+        .output()
+        .expect("failed to run git")
+        .status;
+    assert!(status.success());
+}
+
+#[test]
+fn git_flag_counts_only_tracked_files() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    std::fs::write(dir.path().join("tracked.txt"), "hello\n").unwrap();
+    std::fs::write(dir.path().join("build.log"), "junk\n").unwrap();
+    git(dir.path(), &["add", "tracked.txt"]);
+
+    let result = run_ewc(&["--git", dir.path().to_str().unwrap()]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("(1 file)"));
+}
+
+#[test]
+fn git_flag_outside_repository_errors() {
+    let dir = create_test_dir();
+    let result = run_ewc(&["--git", dir.path().to_str().unwrap()]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("git ls-files"));
+}
+
+#[test]
+fn changed_flag_counts_only_modified_files() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    std::fs::write(dir.path().join("stable.txt"), "stable\n").unwrap();
+    std::fs::write(dir.path().join("edited.txt"), "one\n").unwrap();
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "-q", "-m", "initial"]);
+    std::fs::write(dir.path().join("edited.txt"), "one\ntwo\n").unwrap();
+
+    let result = run_ewc(&["--changed", dir.path().to_str().unwrap()]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("(1 file)"));
+}
+
+#[test]
+fn staged_flag_counts_only_staged_files() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    std::fs::write(dir.path().join("staged.txt"), "staged\n").unwrap();
+    std::fs::write(dir.path().join("unstaged.txt"), "unstaged\n").unwrap();
+    git(dir.path(), &["add", "staged.txt"]);
+
+    let result = run_ewc(&["--staged", "-v", dir.path().to_str().unwrap()]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("staged.txt"));
+    assert!(!result.stdout.contains("unstaged.txt"));
+}
+
+// --format markdown tests
+#[test]
+fn markdown_format_table() {
+    let file1 = create_test_file("hello world\n");
+    let file2 = create_test_file("foo\n");
+    let result = run_ewc(&[
+        "--format",
+        "markdown",
+        file1.path().to_str().unwrap(),
+        file2.path().to_str().unwrap(),
+    ]);
+
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .starts_with("| Path | Files | Lines | Words | Bytes |"));
+    assert!(result.stdout.contains("| **Total** | 2 | 2 | 3 | 16 |"));
+}
+
+// diff-git tests
+fn repo_with_two_commits() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    std::fs::write(dir.path().join("a.txt"), "one two\nthree\n").unwrap();
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "-q", "-m", "first"]);
+    std::fs::write(dir.path().join("a.txt"), "one two\nfour five\n").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "brand new file\n").unwrap();
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "-q", "-m", "second"]);
+    dir
+}
+
+fn run_ewc_in(dir: &std::path::Path, args: &[&str]) -> CommandResult {
+    let home = tempfile::tempdir().unwrap();
+    let output = ewc_command(home.path())
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("failed to run ewc");
+    CommandResult {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        success: output.status.success(),
+        code: output.status.code(),
+    }
+}
+
+#[test]
+fn diff_git_reports_added_and_removed() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["diff-git", "HEAD~1..HEAD"]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("a.txt  +1 -1 lines, +2 -1 words"));
+    assert!(result.stdout.contains("b.txt  +1 -0 lines, +3 -0 words"));
+    assert!(result
+        .stdout
+        .contains("Total (2 files)  +2 -1 lines, +5 -1 words"));
+}
+
+#[test]
+fn diff_git_json_output() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["diff-git", "HEAD~1..HEAD", "--json"]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("\"range\":\"HEAD~1..HEAD\""));
+    assert!(result.stdout.contains("\"lines_added\":2"));
+}
+
+#[test]
+fn diff_git_markdown_limited_to_path() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(
+        dir.path(),
+        &["diff-git", "HEAD~1..HEAD", "b.txt", "--format", "markdown"],
+    );
+
+    assert!(result.success);
+    assert!(result.stdout.contains("| `b.txt` | +1 | -0 | +3 | -0 |"));
+    assert!(!result.stdout.contains("a.txt"));
+}
+
+#[test]
+fn diff_git_invalid_range_errors() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["diff-git", "nope..HEAD"]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("nope..HEAD"));
+}
+
+// history / --format csv tests
+#[test]
+fn history_csv_output() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["history", "--format", "csv"]);
+
+    assert!(result.success);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(
+        lines[0],
+        "period,date,commit,files,lines,words,bytes,max_line_length"
+    );
+    // Both commits fall in the current month, so the later one is sampled
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains(",2,3,"));
+}
+
+#[test]
+fn history_text_output() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["-l", "history", "--interval", "year"]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("2 files, 3 lines"));
+    assert!(result.stdout.contains("Change since"));
+}
+
+#[test]
+fn history_no_commits_in_range_errors() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["history", "--since", "2099-01-01"]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("no commits since 2099-01-01"));
+}
+
+#[test]
+fn history_rejects_unsupported_format() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["history", "--format", "sarif"]);
+
+    assert!(!result.success);
+    assert!(result
+        .stderr
+        .contains("history does not support --format sarif"));
+}
+
+#[test]
+fn format_csv_lists_files() {
+    let dir = create_test_dir();
+    let result = run_ewc(&["--format", "csv", dir.path().to_str().unwrap()]);
+
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .starts_with("path,lines,words,bytes,max_line_length\n"));
+    assert!(result.stdout.contains("file1.txt,"));
+}
+
+// --by-author tests
+#[test]
+fn by_author_leaderboard() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["--by-author"]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("1. ewc  3 lines  100.0%"));
+    assert!(result.stdout.contains("Total (1 author)  3 lines"));
+}
+
+#[test]
+fn by_author_json_output() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["--by-author", "--json", "b.txt"]);
+
+    assert!(result.success);
+    assert_eq!(
+        result.stdout.trim(),
+        r#"{"authors":[{"author":"ewc","lines":1,"percent":100.0}],"total":{"author_count":1,"lines":1}}"#
+    );
+}
+
+#[test]
+fn by_author_outside_repository_errors() {
+    let dir = create_test_dir();
+    let result = run_ewc(&["--by-author", dir.path().to_str().unwrap()]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("--by-author"));
+}
+
+// generated file tests
+#[test]
+fn directory_skips_linguist_generated_files() {
+    let dir = create_test_dir();
+    std::fs::create_dir(dir.path().join("gen")).unwrap();
+    std::fs::write(dir.path().join("gen/schema.rs"), "pub struct Schema;\n").unwrap();
+    std::fs::write(
+        dir.path().join(".gitattributes"),
+        "gen/** linguist-generated\n",
+    )
+    .unwrap();
+
+    let result = run_ewc(&["--json", dir.path().to_str().unwrap()]);
+    assert!(result.success);
+    assert!(result.stdout.contains("\"file_count\":2"));
+
+    let result = run_ewc(&[
+        "--json",
+        "--include-generated",
+        dir.path().to_str().unwrap(),
+    ]);
+    assert!(result.success);
+    assert!(result.stdout.contains("\"file_count\":3"));
+}
+
+#[test]
+fn generated_file_counted_when_named_explicitly() {
+    let dir = tempfile::tempdir().unwrap();
+    let lock = dir.path().join("package-lock.json");
+    std::fs::write(&lock, "{}\n").unwrap();
+
+    let result = run_ewc(&["-l", lock.to_str().unwrap()]);
+    assert!(result.success);
+    assert!(result.stdout.contains("Lines:          1"));
+}
+
+// hook / install-hook tests
+#[test]
+fn hook_staged_reports_violations() {
+    let dir = repo_with_two_commits();
+    std::fs::write(dir.path().join("long.txt"), "1\n2\n3\n4\n").unwrap();
+    git(dir.path(), &["add", "long.txt"]);
+
+    let result = run_ewc_in(dir.path(), &["hook", "--staged", "--over", "lines=3"]);
+    assert!(!result.success);
+    assert!(result
+        .stderr
+        .contains("long.txt: 4 lines exceeds limit of 3"));
+
+    let result = run_ewc_in(dir.path(), &["hook", "--staged", "--over", "lines=4"]);
+    assert!(result.success);
+    assert!(result.stdout.is_empty());
+}
+
+#[test]
+fn hook_requires_limits() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["hook", "--staged"]);
+
+    assert!(!result.success);
+    assert!(result
+        .stderr
+        .contains("hook needs at least one --over limit"));
+}
+
+#[test]
+fn install_hook_writes_pre_commit_script() {
+    let dir = repo_with_two_commits();
+    let result = run_ewc_in(dir.path(), &["install-hook", "--over", "lines=500"]);
+
+    assert!(result.success);
+    let script = std::fs::read_to_string(dir.path().join(".git/hooks/pre-commit")).unwrap();
+    assert!(script.contains("exec ewc hook --staged --over lines=500"));
+
+    let result = run_ewc_in(dir.path(), &["install-hook", "--over", "lines=500"]);
+    assert!(!result.success);
+    assert!(result.stderr.contains("--force"));
+}
+
+// compare tests
+fn two_trees() -> (tempfile::TempDir, tempfile::TempDir) {
+    let a = create_test_dir();
+    let b = create_test_dir();
+    std::fs::write(b.path().join("file1.txt"), "hello world\nagain\n").unwrap();
+    std::fs::remove_file(b.path().join("file2.txt")).unwrap();
+    std::fs::write(b.path().join("file3.txt"), "new\n").unwrap();
+    (a, b)
+}
+
+#[test]
+fn compare_directories_text() {
+    let (a, b) = two_trees();
+    let result = run_ewc(&[
+        "-l",
+        "compare",
+        a.path().to_str().unwrap(),
+        b.path().to_str().unwrap(),
+    ]);
+
+    assert!(result.success);
+    assert!(result.stdout.contains("   - file2.txt: -1 lines"));
+    assert!(result.stdout.contains("   + file3.txt: +1 lines"));
+    assert!(result.stdout.contains("   ~ file1.txt: +1 lines"));
+    assert!(result.stdout.contains("Change: 0 files, +1 lines"));
+}
+
+#[test]
+fn compare_directories_json() {
+    let (a, b) = two_trees();
+    let result = run_ewc(&[
+        "compare",
+        a.path().to_str().unwrap(),
+        b.path().to_str().unwrap(),
+        "--json",
+    ]);
+
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains(r#""only_in_a":[{"file":"file2.txt","lines":1,"words":3,"bytes":12}]"#));
+    assert!(result
+        .stdout
+        .contains(r#""only_in_b":[{"file":"file3.txt""#));
+}
+
+#[test]
+fn compare_missing_directory_errors() {
+    let (a, _) = two_trees();
+    let result = run_ewc(&["compare", a.path().to_str().unwrap(), "/nonexistent/dir"]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("/nonexistent/dir: not a directory"));
+}
+
+// snapshot tests
+#[test]
+fn snapshot_save_then_diff() {
+    let dir = create_test_dir();
+    let result = run_ewc_in(dir.path(), &["snapshot", "save", "v1"]);
+    assert!(result.success);
+    assert!(result.stdout.contains("Saved snapshot v1 (2 files)"));
+    assert!(dir.path().join(".ewc/snapshots/v1.json").exists());
+
+    std::fs::write(dir.path().join("file1.txt"), "hello world\nmore\n").unwrap();
+    std::fs::write(dir.path().join("file3.txt"), "new\n").unwrap();
+
+    let result = run_ewc_in(dir.path(), &["-l", "snapshot", "diff", "v1"]);
+    assert!(result.success);
+    assert!(result.stdout.contains("Change vs snapshot v1"));
+    assert!(result.stdout.contains("   Files:         +1"));
+    assert!(result.stdout.contains("   Lines:         +2"));
+    assert!(result.stdout.contains("+ ./file3.txt: +1 lines"));
+    assert!(result.stdout.contains("~ ./file1.txt: +1 lines"));
+}
+
+#[test]
+fn snapshot_diff_json() {
+    let dir = create_test_dir();
+    run_ewc_in(dir.path(), &["snapshot", "save", "base"]);
+    std::fs::remove_file(dir.path().join("file2.txt")).unwrap();
+
+    let result = run_ewc_in(dir.path(), &["snapshot", "diff", "base", "--json"]);
+    assert!(result.success);
+    assert!(result.stdout.contains(
+        r#"{"file":"./file2.txt","status":"removed","lines":-1,"words":-3,"bytes":-12}"#
+    ));
+}
+
+#[test]
+fn snapshot_diff_unknown_name_errors() {
+    let dir = create_test_dir();
+    let result = run_ewc_in(dir.path(), &["snapshot", "diff", "missing"]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("no snapshot named 'missing'"));
+}
+
+// --db / report tests
+#[test]
+fn db_records_runs_for_report() {
+    let dir = create_test_dir();
+    let db = dir.path().join("metrics.sqlite");
+    let db_arg = db.to_str().unwrap();
+    let target = dir.path().to_str().unwrap();
+
+    assert!(run_ewc(&["--db", db_arg, target]).success);
+    std::fs::write(dir.path().join("file3.txt"), "one\ntwo\n").unwrap();
+    assert!(run_ewc(&["--db", db_arg, "--json", target]).success);
+
+    let result = run_ewc(&["report", "--db", db_arg, "--format", "csv"]);
+    assert!(result.success);
+    let rows: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[1].contains(",2,"));
+    assert!(rows[2].contains(",3,"));
+}
+
+#[test]
+fn report_without_db_errors() {
+    let result = run_ewc(&["report"]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("report needs --db PATH"));
+}
+
+// --watch tests
+#[test]
+fn watch_redraws_after_change() {
+    let dir = create_test_dir();
+    let home = tempfile::tempdir().unwrap();
+    let mut child = ewc_command(home.path())
+        .args(["--watch", "-C", dir.path().to_str().unwrap()])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run ewc");
+
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    std::fs::write(dir.path().join("file3.txt"), "one two\nthree\n").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1000));
+    child.kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("(2 files):  2 lines"));
+    assert!(stdout.contains("(3 files):  4 lines"));
+}
+
+#[test]
+fn watch_conflicts_with_json() {
+    let dir = create_test_dir();
+    let result = run_ewc(&["--watch", "--json", dir.path().to_str().unwrap()]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("cannot be used with"));
+}
+
+// --follow tests
+#[test]
+fn follow_prints_counts_as_file_grows() {
+    let file = create_test_file("started\n");
+    let home = tempfile::tempdir().unwrap();
+    let mut child = ewc_command(home.path())
+        .args(["--follow", file.path().to_str().unwrap(), "--json"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run ewc");
+
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let mut log = std::fs::OpenOptions::new()
+        .append(true)
+        .open(file.path())
+        .unwrap();
+    writeln!(log, "two words").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    child.kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains(r#""lines":1,"words":1,"bytes":8"#));
+    assert!(lines[1].contains(r#""lines":2,"words":3,"bytes":18"#));
+}
+
+#[test]
+fn follow_missing_file_errors() {
+    let result = run_ewc(&["--follow", "/nonexistent/app.log"]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("/nonexistent/app.log"));
+}
+
+// --interval tests
+#[test]
+fn interval_prints_running_counts_while_streaming_stdin() {
+    let home = tempfile::tempdir().unwrap();
+    let mut child = ewc_command(home.path())
+        .args(["-C", "--interval", "200ms"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to spawn ewc");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"first line\n").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(400));
+    stdin.write_all(b"second line\n").unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success());
+    assert!(stderr.contains("<stdin>: 2 lines"));
+    assert!(stdout.contains("<stdin>: 2 lines, 4 words, 23 bytes"));
+}
+
+#[test]
+fn interval_rejects_invalid_duration() {
+    let result = run_ewc(&["--interval", "soon"]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("invalid duration 'soon'"));
+}
+
+// serve tests
+fn http_get(port: u16, path: &str) -> Option<String> {
+    use std::io::Read;
+
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).ok()?;
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    Some(response)
+}
+
+#[test]
+fn serve_answers_count_queries() {
+    let dir = create_test_dir();
+    let port = 18385;
+    let home = tempfile::tempdir().unwrap();
+    let mut child = ewc_command(home.path())
+        .args(["serve", "--port", &port.to_string()])
+        .arg(dir.path())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .expect("failed to run ewc");
+
+    let mut response = None;
+    for _ in 0..50 {
+        response = http_get(port, "/counts?path=file1.txt&format=json");
+        if response.is_some() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let not_found = http_get(port, "/counts?path=missing.txt");
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    let response = response.expect("server did not start");
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response
+        .contains(r#"{"file":"file1.txt","max_line_length":11,"lines":1,"words":2,"bytes":12}"#));
+    assert!(not_found.unwrap().starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+fn serve_rejects_missing_directory() {
+    let result = run_ewc(&["serve", "/nonexistent/dir"]);
+
+    assert!(!result.success);
+    assert!(result.stderr.contains("/nonexistent/dir: not a directory"));
+}
+
+// --rpc tests
+#[test]
+fn rpc_answers_one_line_per_request() {
+    let dir = create_test_dir();
+    let file1 = dir.path().join("file1.txt");
+    let input = format!(
+        "{{\"id\":1,\"path\":\"{}\"}}\n{{\"id\":2,\"path\":\"{}\",\"options\":{{\"exclude\":[\"file2.txt\"]}}}}\n{{\"id\":3,\"path\":\"/nonexistent\"}}\n",
+        file1.display(),
+        dir.path().display()
+    );
+    let result = run_ewc_with_stdin(&["--rpc"], &input);
+
+    assert!(result.success);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with(r#"{"id":1,"result":{"file":"#));
+    assert!(lines[0].ends_with(r#""lines":1,"words":2,"bytes":12}}"#));
+    assert!(lines[1].contains(r#""file_count":1"#));
+    assert!(lines[2].starts_with(r#"{"id":3,"error":"/nonexistent: "#));
+}
+
+// daemon tests
+#[cfg(unix)]
+fn daemon_query(socket: &std::path::Path, request: &str) -> Option<String> {
+    use std::io::BufRead;
+
+    let mut stream = std::os::unix::net::UnixStream::connect(socket).ok()?;
+    writeln!(stream, "{request}").ok()?;
+    let mut line = String::new();
+    std::io::BufReader::new(&stream).read_line(&mut line).ok()?;
+    Some(line)
+}
+
+#[cfg(unix)]
+#[test]
+fn daemon_answers_from_watched_index() {
+    let dir = create_test_dir();
+    let socket = dir.path().join(".ewc/daemon.sock");
+    let home = tempfile::tempdir().unwrap();
+    let mut child = ewc_command(home.path())
+        .arg("daemon")
+        .arg(dir.path())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .expect("failed to run ewc");
+
+    let request = r#"{"id":1,"path":"file1.txt"}"#;
+    let mut before = None;
+    for _ in 0..50 {
+        before = daemon_query(&socket, request);
+        if before.is_some() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    std::fs::write(dir.path().join("file1.txt"), "one\ntwo\nthree\n").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(800));
+    let after = daemon_query(&socket, request);
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(before
+        .expect("daemon did not start")
+        .contains(r#""lines":1,"words":2,"bytes":12"#));
+    assert!(after.unwrap().contains(r#""lines":3,"words":3,"bytes":14"#));
+}
+
+// cache tests
+
+fn run_ewc_with_cache_home(cache_home: &std::path::Path, args: &[&str]) -> CommandResult {
+    let output = ewc_command(cache_home)
+        .args(args)
+        .output()
+        .expect("failed to run ewc");
+    CommandResult {
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        success: output.status.success(),
+        code: output.status.code(),
+    }
+}
+
+#[test]
+fn cache_is_created_and_cleared() {
+    let dir = create_test_dir();
+    let cache_home = tempfile::tempdir().unwrap();
+    let cache_file = cache_home.path().join("ewc/counts.sqlite");
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc_with_cache_home(cache_home.path(), &["--no-cache", path]);
+    assert!(result.success);
+    assert!(!cache_file.exists());
+
+    let first = run_ewc_with_cache_home(cache_home.path(), &["--json", path]);
+    let second = run_ewc_with_cache_home(cache_home.path(), &["--json", path]);
+    assert_eq!(first.stdout, second.stdout);
+    assert!(cache_file.exists());
+
+    let result = run_ewc_with_cache_home(cache_home.path(), &["cache", "clear"]);
+    assert!(result.success);
+    assert!(result.stdout.starts_with("Cleared cache at"));
+    assert!(!cache_file.exists());
+
+    let result = run_ewc_with_cache_home(cache_home.path(), &["cache", "clear"]);
+    assert!(result.stdout.starts_with("No cache at"));
+}
+
+// subcommand tests
+
+#[test]
+fn count_subcommand_matches_bare_invocation() {
+    let dir = create_test_dir();
+    let path = dir.path().to_str().unwrap();
+    let bare = run_ewc(&["--no-color", "-w", path]);
+    let count = run_ewc(&["count", "--no-color", "-w", path]);
+    assert!(count.success);
+    assert_eq!(count.stdout, bare.stdout);
+}
+
+#[test]
+fn check_subcommand_reports_only_violations() {
+    let dir = create_test_dir();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["check", "--over", "words=2", path]);
+    assert!(!result.success);
+    assert!(result.stdout.is_empty());
+    assert!(result
+        .stderr
+        .contains("file2.txt: 3 words exceeds limit of 2"));
+    assert!(!result.stderr.contains("file1.txt"));
+
+    let result = run_ewc(&["check", "--over", "words=3", path]);
+    assert!(result.success);
+
+    let result = run_ewc(&["check", path]);
+    assert!(!result.success);
+    assert!(result
+        .stderr
+        .contains("check needs at least one --over limit"));
+}
+
+// completions tests
+
+#[test]
+fn completions_include_subcommands_and_format_values() {
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let result = run_ewc(&["completions", shell]);
+        assert!(result.success, "{shell}");
+        assert!(result.stdout.contains("install-hook"), "{shell}");
+        // PowerShell completion does not list argument values
+        if shell != "powershell" {
+            assert!(result.stdout.contains("pr-comment"), "{shell}");
+        }
+    }
+}
+
+// filter compilation tests
+
+#[test]
+fn invalid_glob_pattern_fails_before_counting() {
+    let dir = create_test_dir();
+    let result = run_ewc(&["--exclude", "[", dir.path().to_str().unwrap()]);
+    assert!(!result.success);
+    assert!(result.stdout.is_empty());
+    assert!(result.stderr.contains("Invalid glob pattern '['"));
+}
+
+// logging tests
+
+#[test]
+fn debug_logs_why_files_were_skipped() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join(".hidden"), "hidden\n").unwrap();
+    std::fs::write(dir.path().join("binary.dat"), [0xff, 0xfe]).unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--debug", "--no-cache", "--exclude", "file1.txt", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("(1 file)"));
+    assert!(result.stderr.contains("reason=\"hidden\""));
+    assert!(result
+        .stderr
+        .contains("reason=\"matches an exclude pattern\""));
+    assert!(result.stderr.contains("reason=\"not UTF-8 text\""));
+    assert!(result.stderr.contains("counted files"));
+
+    let result = run_ewc(&["--no-cache", path]);
+    assert!(!result.stderr.contains("skipped"));
+}
+
+// list tests
+
+#[test]
+fn list_prints_filtered_files_without_counting() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join(".hidden"), "hidden\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--list", "--exclude", "file2.txt", path]);
+    assert!(result.success);
+    assert_eq!(
+        result.stdout.trim(),
+        dir.path().join("file1.txt").display().to_string()
+    );
+
+    let result = run_ewc(&["count", "--dry-run", "--json", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains(r#""file_count":2"#));
+    assert!(!result.stdout.contains("lines"));
+}
+
+// config file tests
+
+fn run_ewc_with_config(
+    config_home: &std::path::Path,
+    dir: &std::path::Path,
+    args: &[&str],
+) -> CommandResult {
+    let output = ewc_command(config_home)
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("failed to run ewc");
+    CommandResult {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        success: output.status.success(),
+        code: output.status.code(),
+    }
+}
+
+#[test]
+fn user_and_project_config_supply_defaults() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join("notes.md"), "notes\n").unwrap();
+    let config_home = tempfile::tempdir().unwrap();
+    std::fs::create_dir(config_home.path().join("ewc")).unwrap();
+    std::fs::write(
+        config_home.path().join("ewc/config.toml"),
+        "format = \"json\"\nexclude = [\"*.md\"]\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join(".ewc.toml"), "exclude = [\"file2.txt\"]\n").unwrap();
+
+    let result = run_ewc_with_config(config_home.path(), dir.path(), &["--no-cache", "."]);
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains(r#""file_count":1"#));
+
+    // Command-line flags win, and --no-config ignores both files
+    let result = run_ewc_with_config(config_home.path(), dir.path(), &["--format", "csv", "."]);
+    assert!(result.stdout.starts_with("path,"));
+    let result = run_ewc_with_config(config_home.path(), dir.path(), &["--no-config", "."]);
+    assert!(result.stdout.contains("(3 files)"));
+
+    std::fs::write(dir.path().join(".ewc.toml"), "threads = \"many\"\n").unwrap();
+    let result = run_ewc_with_config(config_home.path(), dir.path(), &["."]);
+    assert!(!result.success);
+    assert!(result
+        .stderr
+        .contains("'threads' must be a positive integer"));
+}
+
+#[test]
+fn config_json_format_does_not_apply_to_streamed_counts() {
+    let dir = create_test_dir();
+    let config_home = tempfile::tempdir().unwrap();
+    std::fs::create_dir(config_home.path().join("ewc")).unwrap();
+    std::fs::write(
+        config_home.path().join("ewc/config.toml"),
+        "format = \"json\"\n",
+    )
+    .unwrap();
+
+    let result = run_ewc_with_config(config_home.path(), dir.path(), &["--stream", "."]);
+    assert!(result.success, "{}", result.stderr);
+    assert!(!result.stdout.contains('{'));
+    assert!(result.stdout.contains("file1.txt"));
+}
+
+#[test]
+fn profile_selects_config_section() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join("notes.md"), "some notes here\n").unwrap();
+    std::fs::write(
+        dir.path().join(".ewc.toml"),
+        "[profile.docs]\ninclude = [\"*.md\"]\nmetrics = [\"words\"]\n",
+    )
+    .unwrap();
+    let config_home = tempfile::tempdir().unwrap();
+
+    let result = run_ewc_with_config(
+        config_home.path(),
+        dir.path(),
+        &["--profile", "docs", "--no-cache", "."],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("(1 file)"));
+    assert!(result.stdout.contains("Words:"));
+    assert!(!result.stdout.contains("Lines:"));
+
+    let result = run_ewc_with_config(config_home.path(), dir.path(), &["--profile", "code", "."]);
+    assert!(!result.success);
+    assert!(result
+        .stderr
+        .contains("unknown profile 'code' (available: docs)"));
+}
+
+// EWC_OPTS tests
+
+fn run_ewc_with_opts(opts: &str, args: &[&str]) -> CommandResult {
+    let home = tempfile::tempdir().unwrap();
+    let output = ewc_command(home.path())
+        .args(args)
+        .env("EWC_OPTS", opts)
+        .output()
+        .expect("failed to run ewc");
+    CommandResult {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        success: output.status.success(),
+        code: output.status.code(),
+    }
+}
+
+#[test]
+fn ewc_opts_are_parsed_before_command_line_arguments() {
+    let dir = create_test_dir();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc_with_opts("--format csv --exclude 'file1.*'", &["--no-cache", path]);
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.starts_with("path,"));
+    assert!(!result.stdout.contains("file1.txt"));
+
+    // The command line wins, and repeating a flag from EWC_OPTS is fine
+    let result = run_ewc_with_opts(
+        "--format csv --no-color",
+        &["--format", "markdown", "--no-color", path],
+    );
+    assert!(result.success, "{}", result.stderr);
+    assert!(result.stdout.contains("|"));
+
+    let result = run_ewc_with_opts("--exclude 'oops", &[path]);
+    assert!(!result.success);
+    assert!(result.stderr.contains("EWC_OPTS: unterminated ' quote"));
+}
+
+// total-only tests
+
+#[test]
+fn total_only_prints_bare_numbers() {
+    let dir = create_test_dir();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["-l", "-q", path]);
+    assert!(result.success);
+    assert_eq!(result.stdout, "2\n");
+
+    let result = run_ewc(&["count", "--total-only", path]);
+    assert_eq!(result.stdout, "2 5 24\n");
+
+    let result = run_ewc_with_stdin(&["-q", "-w"], "one two three\n");
+    assert_eq!(result.stdout, "3\n");
+
+    let result = run_ewc(&["-q", path, "missing.txt"]);
+    assert!(!result.success);
+    assert_eq!(result.stdout, "2 5 24\n");
+    assert!(result.stderr.contains("missing.txt"));
+}
+
+// per-file error tests
+
+#[cfg(unix)]
+#[test]
+fn unreadable_files_in_directories_are_reported() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = create_test_dir();
+    let secret = dir.path().join("secret.txt");
+    std::fs::write(&secret, "secret\n").unwrap();
+    std::fs::set_permissions(&secret, std::fs::Permissions::from_mode(0o000)).unwrap();
+    if std::fs::read(&secret).is_ok() {
+        // Running as root: permissions do not stop reads
+        return;
+    }
+    let path = dir.path().to_str().unwrap();
+    let cache_home = tempfile::tempdir().unwrap();
+
+    for _ in 0..2 {
+        // The second run must not have cached the file as unreadable
+        let result = run_ewc_with_cache_home(cache_home.path(), &["--json", path]);
+        assert!(result.success);
+        assert!(result.stdout.contains(r#""file_count":2"#));
+        assert!(result.stdout.contains(r#""skipped":[{"path":""#));
+        assert!(result.stderr.contains("secret.txt: skipped:"));
+    }
+
+    let result = run_ewc(&["-v", path]);
+    assert!(result.stdout.contains("Skipped (1):"));
+    std::fs::set_permissions(&secret, std::fs::Permissions::from_mode(0o644)).unwrap();
+    let result = run_ewc_with_cache_home(cache_home.path(), &["--json", path]);
+    assert!(result.stdout.contains(r#""file_count":3"#));
+}
+
+// strict tests
+
+#[test]
+fn strict_fails_when_files_in_directories_are_skipped() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join("image.bin"), [0xff, 0xfe, 0x00, 0x80]).unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&[path]);
+    assert!(result.success);
+    assert!(!result.stderr.contains("image.bin"));
+
+    let result = run_ewc(&["--strict", path]);
+    assert!(!result.success);
+    assert!(result.stdout.contains("(2 files)"));
+    assert!(result.stderr.contains("image.bin: skipped: not UTF-8 text"));
+
+    let result = run_ewc(&["--strict", "--json", path]);
+    assert!(!result.success);
+    assert!(result.stdout.contains(r#""reason":"not UTF-8 text""#));
+
+    std::fs::remove_file(dir.path().join("image.bin")).unwrap();
+    assert!(run_ewc(&["--strict", path]).success);
+}
+
+// exit status tests
+
+#[test]
+fn exit_status_distinguishes_failure_classes() {
+    let file = create_test_file("a\nb\nc\n");
+    let path = file.path().to_str().unwrap();
+
+    assert_eq!(run_ewc(&[path]).code, Some(0));
+    assert_eq!(run_ewc(&[path, "missing.txt"]).code, Some(1));
+    assert_eq!(run_ewc(&["--over", "lines=2", path]).code, Some(3));
+    assert_eq!(run_ewc(&["check", "--over", "lines=2", path]).code, Some(3));
+    assert_eq!(
+        run_ewc(&["--over", "lines=2", path, "missing.txt"]).code,
+        Some(1)
+    );
+    assert_eq!(run_ewc(&["--exclude", "[", path]).code, Some(2));
+    assert_eq!(run_ewc(&["--no-such-flag", path]).code, Some(2));
+    assert_eq!(run_ewc(&["check", path]).code, Some(2));
+}
+
+#[test]
+fn no_fail_exits_zero_except_for_usage_errors() {
+    let file = create_test_file("a\nb\nc\n");
+    let path = file.path().to_str().unwrap();
+
+    let result = run_ewc(&["--no-fail", "--over", "lines=2", path, "missing.txt"]);
+    assert_eq!(result.code, Some(0));
+    assert!(result.stdout.contains("Lines:"));
+    assert!(result.stderr.contains("missing.txt"));
+    assert!(result.stderr.contains("lines"));
+
+    assert_eq!(
+        run_ewc(&["--no-fail", "--exclude", "[", path]).code,
+        Some(2)
+    );
+}
+
+// structured error tests
+
+#[test]
+fn errors_json_writes_one_object_per_line() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join("image.bin"), [0xff, 0xfe, 0x00, 0x80]).unwrap();
+    std::fs::write(dir.path().join("long.txt"), "one\ntwo\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&[
+        "--errors",
+        "json",
+        "--strict",
+        "--over",
+        "lines=1",
+        path,
+        "missing.txt",
+    ]);
+    assert_eq!(result.code, Some(1));
+    assert!(!result.stderr.contains('\u{26A0}'));
+    let lines: Vec<&str> = result.stderr.lines().collect();
+    assert!(lines
+        .iter()
+        .all(|line| line.starts_with(r#"{"path":"#) && line.ends_with('}')));
+    assert!(lines
+        .iter()
+        .any(|line| line.contains(r#""kind":"invalid_data","message":"skipped: not UTF-8 text""#)));
+    assert!(lines.contains(
+        &r#"{"path":"missing.txt","kind":"not_found","message":"No such file or directory (os error 2)"}"#
+    ));
+    assert!(lines
+        .iter()
+        .any(|line| line.contains(r#""kind":"limit_exceeded""#)));
+
+    let result = run_ewc(&["--errors", "json", "check", path]);
+    assert_eq!(
+        result.stderr,
+        "{\"path\":null,\"kind\":\"usage\",\"message\":\"check needs at least one --over limit\"}\n"
+    );
+}
+
+// special file tests
+
+#[cfg(unix)]
+#[test]
+fn special_files_in_directories_are_skipped_with_a_warning() {
+    let dir = create_test_dir();
+    let pipe = dir.path().join("pipe");
+    assert!(Command::new("mkfifo")
+        .arg(&pipe)
+        .status()
+        .unwrap()
+        .success());
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&[path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("(2 files)"));
+    assert!(result
+        .stderr
+        .contains("pipe: skipped: not a regular file (named pipe)"));
+    assert!(!run_ewc(&["--strict", path]).success);
+
+    let writer = std::thread::spawn(move || std::fs::write(pipe, "from the pipe\n").unwrap());
+    let result = run_ewc(&["--special-files", path]);
+    writer.join().unwrap();
+    assert!(result.success);
+    assert!(result.stdout.contains("(3 files)"));
+}
+
+// pipe path tests
+
+#[cfg(unix)]
+#[test]
+fn dev_stdin_and_process_substitution_are_counted() {
+    let file = create_test_file("a\n");
+    let path = file.path().to_str().unwrap();
+
+    let result = run_ewc_with_stdin(&["--compact", "/dev/stdin", path], "one two\nthree\n");
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains("/dev/stdin: 2 lines, 3 words, 14 bytes"));
+    assert!(result.stdout.contains("(2 files)"));
+
+    // Process substitution needs bash
+    let home = tempfile::tempdir().unwrap();
+    let Ok(output) = Command::new("bash")
+        .env("XDG_CACHE_HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path())
+        .env_remove("EWC_OPTS")
+        .arg("-c")
+        .arg("./target/debug/ewc -q <(printf 'a b\\nc\\n') <(printf 'd\\n')")
+        .output()
+    else {
+        return;
+    };
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3 4 8\n");
+}
+
+// symlink tests
+
+#[cfg(unix)]
+#[test]
+fn symlinks_in_directories_follow_the_policy() {
+    let dir = create_test_dir();
+    let outside = create_test_dir();
+    std::os::unix::fs::symlink(outside.path(), dir.path().join("linked")).unwrap();
+    std::os::unix::fs::symlink(
+        outside.path().join("file1.txt"),
+        dir.path().join("link.txt"),
+    )
+    .unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--stats", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("(2 files)"));
+    assert!(result.stderr.contains("Files counted:    2"));
+    assert!(result.stderr.contains("Symlinks skipped: 2"));
+
+    let result = run_ewc(&["--symlinks", "report", path]);
+    assert!(result.success);
+    assert!(result
+        .stderr
+        .contains("link.txt: skipped: symbolic link not followed"));
+    assert!(!run_ewc(&["--symlinks", "report", "--strict", path]).success);
+
+    let result = run_ewc(&["--symlinks", "follow", "--stats", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("(5 files)"));
+    assert!(result.stderr.contains("Symlinks skipped: 0"));
+
+    let result = run_ewc(&["--symlinks", "report", "--stats", path]);
+    assert!(result.stderr.contains("Files skipped:    0"));
+    assert!(result.stderr.contains("Symlinks skipped: 2"));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_given_as_paths_follow_the_policy() {
+    let dir = create_test_dir();
+    let link = dir.path().join("link.txt");
+    std::os::unix::fs::symlink(dir.path().join("file1.txt"), &link).unwrap();
+    let link = link.to_str().unwrap();
+    let file = dir.path().join("file2.txt");
+    let file = file.to_str().unwrap();
+
+    let result = run_ewc(&["--compact", "--stats", link, file]);
+    assert!(result.success);
+    assert!(!result.stdout.contains("link.txt"));
+    assert!(result.stdout.contains("file2.txt"));
+    assert!(result.stderr.contains("Symlinks skipped: 1"));
+
+    let result = run_ewc(&["--symlinks", "report", link]);
+    assert!(result.success);
+    assert!(result.stdout.is_empty());
+    assert!(result
+        .stderr
+        .contains("link.txt: skipped: symbolic link not followed"));
+
+    let result = run_ewc(&["--symlinks", "follow", "--compact", link]);
+    assert!(result.success);
+    assert!(result.stdout.contains("link.txt"));
+}
+
+// duplicate path tests
+
+#[test]
+fn repeated_paths_are_counted_once() {
+    let dir = create_test_dir();
+    let file = dir.path().join("file1.txt");
+    let path = file.to_str().unwrap();
+    let respelled = dir.path().join(".").join("file1.txt");
+
+    let result = run_ewc(&["-q", path, respelled.to_str().unwrap()]);
+    assert!(result.success);
+    assert_eq!(result.stdout, "1 2 12\n");
+    assert!(result
+        .stderr
+        .contains("file1.txt: skipped: already given as an earlier path"));
+
+    let result = run_ewc(&["-q", "--allow-duplicates", path, path]);
+    assert!(result.success);
+    assert_eq!(result.stdout, "2 4 24\n");
+    assert!(result.stderr.is_empty());
+}
+
+// markdown tests
+
+#[test]
+fn markdown_stats_for_markdown_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("guide.md"),
+        "---\ntitle: Guide\n---\n# Guide\n\nRead [this](a.md).\n\n```sh\nrun me now\n```\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "# not markdown\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--markdown", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("   Prose:          3"));
+    assert!(result.stdout.contains("Headings:          1"));
+    assert!(result.stdout.contains("  Fences:          1"));
+
+    let result = run_ewc(&["--markdown", "--json", path]);
+    assert!(result
+        .stdout
+        .contains(r#""markdown":{"words":3,"headings":1,"links":1,"images":0,"code_blocks":1}"#));
+
+    let notes = dir.path().join("notes.txt");
+    let result = run_ewc(&["--markdown", "--json", notes.to_str().unwrap()]);
+    assert!(!result.stdout.contains("markdown"));
+    assert!(!run_ewc(&[path]).stdout.contains("Prose"));
+}
+
+// symbol tests
+
+#[test]
+fn symbols_are_counted_per_file_when_built_in() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("lib.rs"),
+        "struct A;\nfn a() {}\n#[test]\nfn a_works() {}\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "fn not_code() {}\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--symbols", "-v", path]);
+    if !cfg!(feature = "symbols") {
+        assert_eq!(result.code, Some(2));
+        assert!(result
+            .stderr
+            .contains("needs ewc built with the `symbols` feature"));
+        return;
+    }
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains("lib.rs  4 lines  (2 functions, 1 types, 1 tests)"));
+    assert!(result.stdout.contains("notes.txt  1 lines\n"));
+
+    let result = run_ewc(&["--symbols", "--json", path]);
+    assert!(result
+        .stdout
+        .contains(r#""symbols":{"functions":2,"types":1,"tests":1}"#));
+}
+
+// complexity tests
+
+#[test]
+fn complexity_ranks_files_in_verbose_output() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("flat.rs"),
+        "fn a() {}
+fn b() {}
+fn c() {}
+",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("branchy.rs"),
+        "fn a(x: u8) {
+    if x > 1 && x < 9 {}
+}
+",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "if and while\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--complexity", "-v", path]);
+    assert!(result.success);
+    let branchy = result
+        .stdout
+        .find("branchy.rs  3 lines  (2 branches)")
+        .unwrap();
+    let flat = result
+        .stdout
+        .find("flat.rs  3 lines  (0 branches)")
+        .unwrap();
+    let notes = result.stdout.find("notes.txt  1 lines\n").unwrap();
+    assert!(branchy < flat && flat < notes);
+    assert!(result.stdout.contains("Branches:          2"));
+
+    let result = run_ewc(&["--complexity", "--json", path]);
+    assert!(result.stdout.contains(r#""complexity":2"#));
+}
+
+// record tests
+
+#[test]
+fn records_are_counted_with_parse_errors_by_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let events = dir.path().join("events.jsonl");
+    std::fs::write(
+        &events,
+        "{\"id\": 1}\n{\"id\": 2}\n\n{\"id\": \n{\"id\": 4}\n",
+    )
+    .unwrap();
+    let rows = dir.path().join("rows.json");
+    std::fs::write(
+        &rows,
+        "[\n  {\"id\": 1},\n  {\"id\": 2},\n  {\"id\": 3}\n]\n",
+    )
+    .unwrap();
+
+    let result = run_ewc(&["--records", "jsonl", events.to_str().unwrap()]);
+    assert!(result.success);
+    assert!(result.stdout.contains(" Records:          3"));
+    assert!(result.stdout.contains(" Invalid:          1"));
+    assert!(result.stderr.contains("events.jsonl: line 4, column"));
+
+    let result = run_ewc(&["--records", "json", "--json", rows.to_str().unwrap()]);
+    assert!(result
+        .stdout
+        .contains(r#""records":{"count":3,"invalid":0}"#));
+    assert!(result.stderr.is_empty());
+
+    let result = run_ewc(&[
+        "--records",
+        "json",
+        "--errors",
+        "json",
+        events.to_str().unwrap(),
+    ]);
+    assert!(result.stderr.contains(r#""kind":"parse""#));
+}
+
+// structure tests
+
+#[test]
+fn structure_of_config_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("ci.yml"),
+        "on:\n  push:\n    branches: [main]\njobs:\n  test:\n    steps: [a, b, c]\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("app.ini"), "[server]\nport = 80\n").unwrap();
+    std::fs::write(dir.path().join("broken.toml"), "a = \n").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "key = value\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--structure", "-v", path]);
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains("ci.yml  6 lines  (6 keys, 4 max depth, 2 arrays, 3 longest array)"));
+    assert!(result
+        .stdout
+        .contains("app.ini  2 lines  (2 keys, 2 max depth"));
+    assert!(result.stdout.contains("notes.txt  1 lines\n"));
+    assert!(result.stderr.contains("broken.toml: line 1, column 5"));
+
+    let result = run_ewc(&["--structure", "--json", path]);
+    assert!(result
+        .stdout
+        .contains(r#""structure":{"keys":8,"max_depth":4,"arrays":2,"max_array_len":3}"#));
+}
+
+// log level tests
+
+#[test]
+fn log_levels_are_counted_in_log_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("app.log"),
+        "10:00 INFO up\n10:01 WARN slow\n10:02 ERROR down\n  at main\n10:03 INFO up\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "ERROR not a log\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--log-levels", "-v", path]);
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains("app.log  5 lines  (1 errors, 1 warnings, 2 info, 0 debug)"));
+    assert!(result.stdout.contains("notes.txt  1 lines\n"));
+
+    let result = run_ewc(&["--log-levels", "--log-pattern", "info=up$", "--json", path]);
+    assert!(result
+        .stdout
+        .contains(r#""log_levels":{"error":1,"warn":1,"info":2,"debug":0}"#));
+
+    let result = run_ewc(&["--log-levels", "--log-pattern", "error=[", path]);
+    assert_eq!(result.code, Some(2));
+}
+
+// dedupe lines tests
+
+#[test]
+fn dedupe_lines_reports_unique_and_repeated_lines() {
+    let dir = create_test_dir();
+    let noisy = dir.path().join("noisy.txt");
+    std::fs::write(&noisy, "retry\nok\nretry\n\nretry\nok\n").unwrap();
+
+    let result = run_ewc(&["--dedupe-lines", noisy.to_str().unwrap()]);
+    assert!(result.success);
+    assert!(result.stdout.contains("  Unique:          2"));
+    assert!(result.stdout.contains("Repeated:          3"));
+    assert!(result.stdout.contains("Top Line:          3  retry"));
+
+    let result = run_ewc(&["--dedupe-lines", "-v", dir.path().to_str().unwrap()]);
+    assert!(result
+        .stdout
+        .contains("file1.txt  1 lines  (1 unique lines, 0 repeated lines)"));
+
+    let result = run_ewc(&["--dedupe-lines", "--json", noisy.to_str().unwrap()]);
+    assert!(result.stdout.contains(
+        r#""dedupe_lines":{"unique":2,"duplicates":3,"most_repeated":{"line":"retry","count":3}}"#
+    ));
+}
+
+// entropy tests
+
+#[test]
+fn entropy_separates_text_from_encoded_data() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("plain.txt"), "aaaa bbbb\n").unwrap();
+    let encoded: String = (0u32..2000)
+        .map(|i| {
+            let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+            alphabet[(i * 7919 % 64) as usize] as char
+        })
+        .collect();
+    std::fs::write(dir.path().join("blob.txt"), &encoded).unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--entropy", "-v", path]);
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains("plain.txt  1 lines  (entropy 1.72 bits/byte)"));
+    assert!(result
+        .stdout
+        .contains("blob.txt  1 lines  (entropy 6.00 bits/byte)"));
+
+    let plain = dir.path().join("plain.txt");
+    let result = run_ewc(&["--entropy", "--json", plain.to_str().unwrap()]);
+    assert!(result.stdout.contains(r#""entropy":1.722"#));
+}
+
+// hash tests
+
+#[test]
+fn hash_column_in_verbose_csv_and_json_output() {
+    let dir = create_test_dir();
+    let path = dir.path().to_str().unwrap();
+    let hello = "a948904f2f0f479b8f8197694b30184b0d2ed1c1cd2a1ec0fb85d299a192a447";
+
+    let result = run_ewc(&["--hash", "sha256", "-v", path]);
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains(&format!("file1.txt  1 lines  (sha256:{hello})")));
+
+    let result = run_ewc(&["--hash", "sha256", "--format", "csv", path]);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(lines[0], "path,lines,words,bytes,max_line_length,sha256");
+    assert!(lines
+        .iter()
+        .any(|line| line.ends_with(&format!("file1.txt,1,2,12,11,{hello}"))));
+
+    let file = dir.path().join("file1.txt");
+    let result = run_ewc(&["--hash", "blake3", "--json", file.to_str().unwrap()]);
+    assert!(result.stdout.contains(
+        r#""blake3":"dc5a4edb8240b018124052c330270696f96771a63b45250a5c17d3000e823355""#
+    ));
+
+    let result = run_ewc(&["--hash", "md5", path]);
+    assert_eq!(result.code, Some(2));
+}
+
+// show-meta tests
+
+#[cfg(unix)]
+#[test]
+fn show_meta_columns_in_verbose_and_csv_output() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = create_test_dir();
+    let file = dir.path().join("file1.txt");
+    std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o600)).unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--show-meta", "-v", path]);
+    assert!(result.success);
+    let line = result
+        .stdout
+        .lines()
+        .find(|line| line.contains("file1.txt"))
+        .unwrap();
+    assert!(line.contains("bytes on disk, modified "), "{line}");
+    assert!(line.ends_with("Z, rw-------)"), "{line}");
+
+    let result = run_ewc(&["--show-meta", "--format", "csv", path]);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(
+        lines[0],
+        "path,lines,words,bytes,max_line_length,size_on_disk,modified,permissions"
+    );
+    assert!(lines
+        .iter()
+        .any(|line| line.contains("file1.txt,1,2,12,11,") && line.ends_with(",rw-------")));
+
+    let result = run_ewc(&["--show-meta", "--json", file.to_str().unwrap()]);
+    assert!(result.stdout.contains(r#""permissions":"rw-------"}"#));
+}
+
+// cloc format tests
+
+#[test]
+fn cloc_format_summarizes_languages() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("main.rs"),
+        "// entry point\n\nfn main() {\n    println!(\"/* not a comment */\");\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("lib.rs"),
+        "/*\n * docs\n */\npub fn f() {}\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("run.py"), "# run\nprint(1)\n").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "not code\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--format", "cloc", path]);
+    assert!(result.success);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert!(lines[0].starts_with("ewc v "));
+    assert_eq!(
+        lines[4],
+        "Rust                             2              1              4              4"
+    );
+    assert_eq!(
+        lines[5],
+        "Python                           1              0              1              1"
+    );
+    assert!(lines[7].starts_with("SUM:                             3"));
+
+    let result = run_ewc(&["--format", "cloc-csv", path]);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert!(lines[0].starts_with("files,language,blank,comment,code,"));
+    assert_eq!(
+        &lines[1..],
+        ["2,Rust,1,4,4", "1,Python,0,1,1", "3,SUM,1,5,5"]
+    );
+}
+
+// tokei-json format tests
+
+#[test]
+fn tokei_json_format_reports_languages_and_total() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("main.rs"), "// entry\n\nfn main() {}\n").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "not code\n").unwrap();
+    let file = dir.path().join("main.rs");
+
+    let result = run_ewc(&["--format", "tokei-json", dir.path().to_str().unwrap()]);
+    assert!(result.success);
+    let json = result.stdout.trim();
+    assert!(json.starts_with(r#"{"Rust":{"blanks":1,"code":1,"comments":1,"reports":[{"#));
+    assert!(json.contains(&format!(
+        r#"{{"stats":{{"blanks":1,"code":1,"comments":1,"blobs":{{}}}},"name":"{}"}}"#,
+        file.display()
+    )));
+    assert!(json.contains(
+        r#""Total":{"blanks":1,"code":1,"comments":1,"reports":[],"children":{"Rust":["#
+    ));
+    assert!(!json.contains("notes.txt"));
+}
+
+// cocomo tests
+
+#[test]
+fn cocomo_estimates_from_code_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let code: String = (0..10_000).map(|i| format!("let x{i} = {i};\n")).collect();
+    std::fs::write(dir.path().join("big.rs"), format!("// generated\n\n{code}")).unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--cocomo", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("    Code:     10,000"));
+    assert!(result
+        .stdout
+        .contains("Estimated Cost to Develop (organic) $303,139\n"));
+    assert!(result
+        .stdout
+        .contains("Estimated Schedule Effort (organic) 8.74 months\n"));
+    assert!(result
+        .stdout
+        .contains("Estimated People Required (organic) 3.08"));
+
+    let result = run_ewc(&["--cocomo", "--cocomo-wage", "112572", path]);
+    assert!(result.stdout.contains("(organic) $606,278"));
+
+    let result = run_ewc(&["--cocomo-type", "embedded", path]);
+    assert_eq!(result.code, Some(2));
+}
+
+// comment-ratio tests
+
+#[test]
+fn comment_ratio_per_file_and_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("documented.rs"),
+        "/// Adds one.\nfn inc(x: i32) -> i32 {\n    x + 1\n}\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("bare.py"), "x = 1\n\ny = 2\n").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "# not code\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--comment-ratio", "-v", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains(
+        "documented.rs  4 lines  (0 blank lines, 1 comment lines, 3 code lines, 33.3% comments/code)"
+    ));
+    assert!(result.stdout.contains(
+        "bare.py  3 lines  (1 blank lines, 0 comment lines, 2 code lines, 0.0% comments/code)"
+    ));
+    assert!(result.stdout.contains("notes.txt  1 lines\n"));
+    assert!(result
+        .stdout
+        .contains("   Ratio:       20.0  % comments/code"));
+}
+
+// detect-language tests
+
+#[test]
+fn detect_language_per_file_and_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("guide.md"),
+        "This guide explains how to install the application and which settings you can change.\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("guide.de.md"),
+        "Diese Anleitung erklärt, wie die Anwendung installiert wird und welche Einstellungen geändert werden können.\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("short.txt"), "ok\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--detect-language", "-v", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("guide.md  1 lines  (language en)"));
+    assert!(result
+        .stdout
+        .contains("guide.de.md  1 lines  (language de)"));
+    assert!(result.stdout.contains("short.txt  1 lines  (language und)"));
+    assert!(result
+        .stdout
+        .contains("Language: de (1 file), en (1 file), und (1 file)"));
+
+    let file = dir.path().join("guide.md");
+    let result = run_ewc(&["--detect-language", "--json", file.to_str().unwrap()]);
+    assert!(result.stdout.contains(r#""natural_languages":{"en":1}"#));
+}
+
+// vocabulary tests
+
+#[test]
+fn vocabulary_counts_distinct_words_across_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "The cat saw the dog.\n").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "A dog saw a bird!\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--vocabulary", "-v", path]);
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains("a.txt  1 lines  (4 distinct words, 3 hapax, TTR 0.800)"));
+    // "saw" and "dog" are in both files, so they are no longer hapax in the total
+    assert!(result.stdout.contains("Distinct:          6"));
+    assert!(result.stdout.contains("   Hapax:          2"));
+    assert!(result.stdout.contains("     TTR:      0.600"));
+}
+
+// dialogue tests
+
+#[test]
+fn dialogue_counts_quoted_words() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("chapter.txt");
+    std::fs::write(
+        &file,
+        "\"Where are you going?\" he asked.\n\nShe did not answer. “Away,” she said at last.\n",
+    )
+    .unwrap();
+
+    let result = run_ewc(&["--dialogue", "--compact", file.to_str().unwrap()]);
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains("2 quotes, 5 quoted words, 33.3% dialogue"));
+
+    let result = run_ewc(&["--dialogue", "--json", file.to_str().unwrap()]);
+    assert!(result
+        .stdout
+        .contains(r#""dialogue":{"quotes":2,"quoted_words":5,"words":15,"share":0.333}"#));
+}
+
+// strip-markup tests
+
+#[test]
+fn strip_markup_counts_visible_text() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("page.html"),
+        "<html>\n<head><style>body { margin: 0; }</style></head>\n<body>\n<h1>Release notes</h1>\n<p>Fixed <em>three</em> bugs.</p>\n</body>\n</html>\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "<b>not markup</b>\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--strip-markup", "--format", "csv", path]);
+    assert!(result.success);
+    assert!(
+        result.stdout.contains("page.html,2,5,32,"),
+        "{}",
+        result.stdout
+    );
+    // Only HTML and XML files are stripped
+    assert!(result.stdout.contains("notes.txt,1,2,18,"));
+
+    let result = run_ewc(&["--format", "csv", path]);
+    assert!(result.stdout.contains("page.html,7,14,"));
+
+    // The hash is of the file, whatever part of it is counted
+    let page = dir.path().join("page.html");
+    let hash = |flags: &[&str]| {
+        let result = run_ewc(
+            &[
+                flags,
+                &["--hash", "sha256", "--json", page.to_str().unwrap()],
+            ]
+            .concat(),
+        );
+        let start = result.stdout.find(r#""sha256":"#).unwrap();
+        result.stdout[start..start + 75].to_string()
+    };
+    assert_eq!(hash(&["--strip-markup"]), hash(&[]));
+}
+
+// pages and PDF tests
+
+#[test]
+fn pages_splits_text_at_form_feeds() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("listing.txt");
+    std::fs::write(&file, "page one\n\u{c}page two\n\u{c}page three\n").unwrap();
+
+    let result = run_ewc(&["--pages", "--compact", file.to_str().unwrap()]);
+    assert!(result.success);
+    assert!(result.stdout.contains("3 pages"));
+}
+
+#[cfg(feature = "pdf")]
+#[test]
+fn pdf_counted_by_text_and_pages() {
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Object, Stream};
+
+    let mut document = lopdf::Document::with_version("1.5");
+    let pages_id = document.new_object_id();
+    let font_id = document.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+    let resources_id = document.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+    let kids: Vec<Object> = ["Quarterly report draft", "Appendix"]
+        .iter()
+        .map(|text| {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                    Operation::new("Td", vec![72.into(), 720.into()]),
+                    Operation::new("Tj", vec![Object::string_literal(*text)]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id =
+                document.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            document
+                .add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                })
+                .into()
+        })
+        .collect();
+    document.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => 2,
+            "Kids" => kids,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        }),
+    );
+    let catalog_id = document.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    document.trailer.set("Root", catalog_id);
+    let dir = tempfile::tempdir().unwrap();
+    document.save(dir.path().join("report.pdf")).unwrap();
+
+    let result = run_ewc(&["--pages", "--json", dir.path().to_str().unwrap()]);
+    assert!(result.success);
+    assert!(result.stdout.contains(r#""words":4"#), "{}", result.stdout);
+    assert!(result.stdout.contains(r#""pages":2"#));
+}
+
+// office document tests
+
+#[test]
+fn docx_and_odt_counted_by_body_text() {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    for (name, member, xml) in [
+        (
+            "letter.docx",
+            "word/document.xml",
+            "<w:document><w:body><w:p><w:r><w:t>Dear reader,</w:t></w:r></w:p>\
+             <w:p><w:r><w:t>thanks for writing.</w:t></w:r></w:p></w:body></w:document>",
+        ),
+        (
+            "notes.odt",
+            "content.xml",
+            "<office:document-content><office:body><office:text>\
+             <text:p>Three short words</text:p></office:text></office:body></office:document-content>",
+        ),
+    ] {
+        let mut archive = zip::ZipWriter::new(std::fs::File::create(dir.path().join(name)).unwrap());
+        archive
+            .start_file(member, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        archive.write_all(xml.as_bytes()).unwrap();
+        archive.finish().unwrap();
+    }
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--format", "csv", path]);
+    assert!(result.success);
+    assert!(
+        result.stdout.contains("letter.docx,2,5,"),
+        "{}",
+        result.stdout
+    );
+    assert!(result.stdout.contains("notes.odt,1,3,"));
+}
+
+// code-blocks-only tests
+
+#[test]
+fn code_blocks_only_counts_fenced_code_in_markdown() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("guide.md"),
+        "# Guide\n\nRun this:\n\n```sh\ncargo build --release\n```\n\n```rust\nfn main() {}\n```\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "plain notes\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--code-blocks-only", "--format", "csv", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("guide.md,2,6,"), "{}", result.stdout);
+    // Other files are counted in full
+    assert!(result.stdout.contains("notes.txt,1,2,"));
+
+    let result = run_ewc(&["--code-blocks-only=rust", "--format", "csv", path]);
+    assert!(
+        result.stdout.contains("guide.md,1,3,13,"),
+        "{}",
+        result.stdout
+    );
+
+    // Hash, entropy and metadata describe the file, not the code counted in it
+    let guide = dir.path().join("guide.md");
+    let analyses = |flags: &[&str]| {
+        let analyzers = ["--hash", "sha256", "--entropy", "--show-meta", "--json"];
+        let result = run_ewc(&[flags, &analyzers, &[guide.to_str().unwrap()]].concat());
+        let start = result.stdout.find(r#""entropy":"#).unwrap();
+        result.stdout[start..].to_string()
+    };
+    assert_eq!(analyses(&["--code-blocks-only"]), analyses(&[]));
+}
+
+// EPUB tests
+
+#[test]
+fn epub_counted_by_chapter_text() {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let book = dir.path().join("story.epub");
+    let mut archive = zip::ZipWriter::new(std::fs::File::create(&book).unwrap());
+    for (name, content) in [
+        (
+            "META-INF/container.xml",
+            r#"<container><rootfiles><rootfile full-path="content.opf"/></rootfiles></container>"#,
+        ),
+        (
+            "content.opf",
+            r#"<package><manifest><item id="a" href="one.xhtml"/><item id="b" href="two.xhtml"/></manifest>
+            <spine><itemref idref="a"/><itemref idref="b"/></spine></package>"#,
+        ),
+        (
+            "one.xhtml",
+            "<html><body><p>Once upon a time.</p></body></html>",
+        ),
+        ("two.xhtml", "<html><body><p>The end.</p></body></html>"),
+    ] {
+        archive
+            .start_file(name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        archive.write_all(content.as_bytes()).unwrap();
+    }
+    archive.finish().unwrap();
+
+    let result = run_ewc(&["--chapters", "-v", book.to_str().unwrap()]);
+    assert!(result.success);
+    assert!(
+        result.stdout.contains("   Words:          6"),
+        "{}",
+        result.stdout
+    );
+    assert!(result.stdout.contains(" Chapter:          4  one.xhtml"));
+    assert!(result.stdout.contains(" Chapter:          2  two.xhtml"));
+}
+
+// subtitles tests
+
+#[test]
+fn subtitles_report_cues_and_pacing() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("episode.srt"),
+        "1\n00:00:01,000 --> 00:00:03,000\nWhere were you?\n\n2\n00:00:03,500 --> 00:00:05,500\n<i>Out.</i>\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("episode.vtt"),
+        "WEBVTT\n\n00:00.000 --> 00:02.000\nHello again\n",
+    )
+    .unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--subtitles", "-v", path]);
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .contains("episode.srt  7 lines  (2 cues, 4 subtitle words, 60.0 wpm)"));
+    assert!(result.stdout.contains("    Cues:          3"));
+    assert!(result.stdout.contains("     WPM:       60.0"));
+}
+
+// po tests
+
+#[test]
+fn po_reports_translation_progress() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("fr.po");
+    std::fs::write(
+        &file,
+        "msgid \"\"\nmsgstr \"Language: fr\\n\"\n\n\
+         msgid \"Save file\"\nmsgstr \"Enregistrer le fichier\"\n\n\
+         #, fuzzy\nmsgid \"Quit\"\nmsgstr \"Quitter\"\n\n\
+         msgid \"Open recent\"\nmsgstr \"\"\n",
+    )
+    .unwrap();
+
+    let result = run_ewc(&["--po", "--compact", file.to_str().unwrap()]);
+    assert!(result.success);
+    assert!(
+        result.stdout.contains(
+            "1 translated, 1 fuzzy, 1 untranslated, 5 source words, 4 target words, 33.3% complete"
+        ),
+        "{}",
+        result.stdout
+    );
+}
+
+// i18n tests
+
+#[test]
+fn i18n_counts_strings_and_missing_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let locales = dir.path().join("locales");
+    std::fs::create_dir(&locales).unwrap();
+    std::fs::write(
+        locales.join("en.json"),
+        r#"{"menu": {"open": "Open file", "close": "Close"}, "title": "Hello, {name}"}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        locales.join("de.yml"),
+        "de:\n  menu:\n    open: Datei öffnen\n  title: Hallo, {name}\n",
+    )
+    .unwrap();
+
+    let result = run_ewc(&["--i18n", "-v", locales.to_str().unwrap()]);
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result
+            .stdout
+            .contains("(2 strings, 3 words in strings, 1 missing keys)"),
+        "{}",
+        result.stdout
+    );
+    assert!(
+        result
+            .stdout
+            .contains("(3 strings, 4 words in strings, 0 missing keys)"),
+        "{}",
+        result.stdout
+    );
+    assert!(result.stdout.contains("  menu.close"), "{}", result.stdout);
+}
+
+// by-submodule tests
+
+#[test]
+fn by_submodule_counts_each_submodule_apart() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join(".gitmodules"),
+        "[submodule \"lib\"]\n\tpath = vendor/lib\n\turl = ../lib.git\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(dir.path().join("vendor/lib")).unwrap();
+    std::fs::write(dir.path().join("main.rs"), "one two\n").unwrap();
+    std::fs::write(dir.path().join("vendor/lib/lib.c"), "a\nb c\n").unwrap();
+    let root = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--by-submodule", "--compact", root]);
+    assert!(result.success, "{}", result.stderr);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", result.stdout);
+    assert!(lines[0].ends_with("(1 file):  1 lines, 2 words, 8 bytes"));
+    assert!(lines[1].contains("vendor/lib (1 file):  2 lines, 3 words, 6 bytes"));
+    assert!(lines[2].starts_with("Total (2 files)"));
+
+    let result = run_ewc(&["--by-submodule", "--format", "csv", root]);
+    assert_eq!(result.code, Some(2));
+}
+
+// by-package tests
+
+#[test]
+fn by_package_rolls_up_workspace_members() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    for (path, content) in [
+        (
+            "package.json",
+            r#"{"private": true, "workspaces": ["packages/*"]}"#,
+        ),
+        ("packages/ui/package.json", r#"{"name": "ui"}"#),
+        ("packages/ui/index.js", "export const a = 1;\n"),
+        ("services/api/go.mod", "module example.com/api\n"),
+        ("services/api/main.go", "package main\n"),
+        ("README.md", "Monorepo\n"),
+    ] {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    let result = run_ewc(&["--by-package", "--compact", root.to_str().unwrap()]);
+    assert!(result.success, "{}", result.stderr);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", result.stdout);
+    assert!(lines[0].contains("(2 files)"), "{}", result.stdout);
+    assert!(
+        lines[1].contains("packages/ui (2 files)"),
+        "{}",
+        result.stdout
+    );
+    assert!(
+        lines[2].contains("services/api (2 files)"),
+        "{}",
+        result.stdout
+    );
+    assert!(lines[3].starts_with("Total (6 files)"), "{}", result.stdout);
+}
+
+// print0 tests
+
+#[test]
+fn print0_separates_listed_files_with_nul() {
+    let dir = create_test_dir();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--list", "--print0", path]);
+    assert!(result.success);
+    let names: Vec<&str> = result.stdout.split_terminator('\0').collect();
+    assert_eq!(names.len(), 2, "{:?}", result.stdout);
+    assert!(!result.stdout.contains('\n'));
+    assert!(names[0].ends_with("file1.txt") && names[1].ends_with("file2.txt"));
+}
+
+// files-over tests
+
+#[test]
+fn files_over_lists_only_files_beyond_the_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("small.txt"), "one\n").unwrap();
+    std::fs::write(dir.path().join("big.txt"), "1\n2\n3\n4\n").unwrap();
+    std::fs::write(dir.path().join("bigger.txt"), "1\n2\n3\n4\n5\n6\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--files-over", "lines>3", path]);
+    assert!(result.success, "{}", result.stderr);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", result.stdout);
+    assert!(lines[0].ends_with("bigger.txt: 6 lines"));
+    assert!(lines[1].ends_with("big.txt: 4 lines"));
+
+    let result = run_ewc(&["--files-over", "lines>3", "--print0", path]);
+    let names: Vec<&str> = result.stdout.split_terminator('\0').collect();
+    assert_eq!(names.len(), 2, "{:?}", result.stdout);
+    assert!(names[0].ends_with("bigger.txt"));
+
+    let result = run_ewc(&["--files-over", "lines>100", path]);
+    assert!(result.success);
+    assert!(result.stdout.is_empty());
+}
+
+// ratios tests
+
+#[test]
+fn ratios_show_words_and_bytes_per_line() {
+    let dir = create_test_dir();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--ratios", "--no-color", "-v", path]);
+    assert!(result.success, "{}", result.stderr);
+    assert!(
+        result.stdout.contains("2.00 words/line, 12.00 bytes/line"),
+        "{}",
+        result.stdout
+    );
+    assert!(result.stdout.contains("Words/Ln:       2.50  words/line"));
+
+    let result = run_ewc(&["--ratios", "--format", "csv", path]);
+    assert!(result.success, "{}", result.stderr);
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert!(lines[0].ends_with(",words_per_line,bytes_per_line"));
+    assert!(lines[1].ends_with(",2.000,12.000"), "{}", result.stdout);
+}
+
+// delta tests
+
+#[test]
+fn delta_shows_change_since_last_run() {
+    let dir = create_test_dir();
+    let cache_home = tempfile::tempdir().unwrap();
+    let path = dir.path().to_str().unwrap();
+    let args = ["--delta", "--compact", "--no-color", path];
+
+    let first = run_ewc_with_cache_home(cache_home.path(), &args);
+    assert!(first.success, "{}", first.stderr);
+    assert!(
+        first.stdout.trim_end().ends_with("(first run)"),
+        "{}",
+        first.stdout
+    );
+
+    let unchanged = run_ewc_with_cache_home(cache_home.path(), &args);
+    assert!(
+        unchanged
+            .stdout
+            .trim_end()
+            .ends_with("(0 lines, 0 words, 0 bytes)"),
+        "{}",
+        unchanged.stdout
+    );
+
+    std::fs::write(dir.path().join("file3.txt"), "one\ntwo three\n").unwrap();
+    let grown = run_ewc_with_cache_home(cache_home.path(), &["--delta", "--no-color", path]);
+    assert!(
+        grown
+            .stdout
+            .contains("  Change: +2 lines, +3 words, +14 bytes"),
+        "{}",
+        grown.stdout
+    );
+}
+
+// merge tests
+
+#[test]
+fn merge_combines_reports_with_totals() {
+    let dir = tempfile::tempdir().unwrap();
+    let shard_a = dir.path().join("a.json");
+    let shard_b = dir.path().join("b.json");
+    std::fs::write(
+        &shard_a,
+        r#"{"directory":"api","file_count":2,"max_line_length":20,"lines":10,"words":30,"bytes":200}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &shard_b,
+        r#"{"files":[{"directory":"web","file_count":3,"max_line_length":80,"lines":40,"words":90,"bytes":900},{"file":"README.md","max_line_length":5,"lines":2,"words":3,"bytes":12}],"total":{"file_count":4,"max_line_length":80,"lines":42,"words":93,"bytes":912}}"#,
+    )
+    .unwrap();
+    let (a, b) = (shard_a.to_str().unwrap(), shard_b.to_str().unwrap());
+
+    let result = run_ewc(&["merge", a, b, "--json"]);
+    assert!(result.success, "{}", result.stderr);
+    assert!(result
+        .stdout
+        .contains(r#"{"directory":"api","file_count":2,"#));
+    assert!(result.stdout.contains(r#"{"file":"README.md","#));
+    assert!(
+        result.stdout.contains(
+            r#""total":{"file_count":6,"max_line_length":80,"lines":52,"words":123,"bytes":1112}"#
+        ),
+        "{}",
+        result.stdout
+    );
+
+    let result = run_ewc(&["merge", a, b, "--compact", "--no-color"]);
+    assert!(result.success);
+    assert!(result
+        .stdout
+        .trim_end()
+        .ends_with("Total (6 files): 52 lines, 123 words, 1,112 bytes"));
+
+    let result = run_ewc(&[
+        "merge",
+        a,
+        dir.path().join("missing.json").to_str().unwrap(),
+    ]);
+    assert_eq!(result.code, Some(1));
+}
+
+// convert tests
+
+#[test]
+fn convert_renders_saved_report() {
+    let dir = create_test_dir();
+    let path = dir.path().to_str().unwrap();
+    let saved = run_ewc(&[
+        "--json",
+        path,
+        dir.path().join("file1.txt").to_str().unwrap(),
+    ]);
+    let report = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(report.path(), &saved.stdout).unwrap();
+    let report = report.path().to_str().unwrap();
+
+    let markdown = run_ewc(&["convert", report, "--format", "markdown"]);
+    assert!(markdown.success, "{}", markdown.stderr);
+    assert!(markdown
+        .stdout
+        .starts_with("| Path | Files | Lines | Words | Bytes |"));
+    assert!(markdown.stdout.contains("| **Total** | 3 | 3 | 7 | 36 |"));
+
+    let html = run_ewc(&["convert", report, "--format", "html"]);
+    assert!(html.success);
+    assert!(html
+        .stdout
+        .contains("<tfoot><tr><th>Total</th><td>3</td><td>3</td>"));
+
+    let csv = run_ewc(&["convert", report, "--format", "csv"]);
+    assert_eq!(csv.stdout.lines().count(), 3, "{}", csv.stdout);
+
+    let json = run_ewc(&["convert", report, "--json"]);
+    assert_eq!(json.stdout, saved.stdout);
+
+    let sarif = run_ewc(&["convert", report, "--format", "sarif"]);
+    assert_eq!(sarif.code, Some(2));
+}
+
+// xlsx tests
+
+#[test]
+fn xlsx_format_writes_a_workbook() {
+    let dir = create_test_dir();
+    let home = tempfile::tempdir().unwrap();
+    let output = ewc_command(home.path())
+        .args(["--format", "xlsx", dir.path().to_str().unwrap()])
+        .output()
+        .expect("failed to run ewc");
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"PK\x03\x04"));
+    // Part names are stored uncompressed in the zip headers
+    for part in [
+        "xl/workbook.xml",
+        "xl/worksheets/sheet1.xml",
+        "xl/worksheets/sheet2.xml",
+    ] {
+        let part = part.as_bytes();
+        assert!(output
+            .stdout
+            .windows(part.len())
+            .any(|window| window == part));
+    }
+}
+
+// mermaid tests
+
+#[test]
+fn mermaid_format_maps_directories() {
+    let dir = create_test_dir();
+    let sub = dir.path().join("sub");
+    std::fs::create_dir(&sub).unwrap();
+    std::fs::write(sub.join("c.txt"), "one\ntwo\n").unwrap();
+    let result = run_ewc(&["--format", "mermaid", dir.path().to_str().unwrap()]);
+    assert_eq!(result.code, Some(0));
+    assert!(result.stdout.starts_with("flowchart TD\n"));
+    assert!(result.stdout.contains(r#"    n1["sub<br/>2 lines"]"#));
+    assert!(result.stdout.contains("    n0 --> n1"));
+
+    let dot = run_ewc(&["--format", "dot", dir.path().to_str().unwrap()]);
+    assert_eq!(dot.code, Some(0));
+    assert!(dot.stdout.contains(r#"    n1 [label="sub\n2 lines"];"#));
+}
+
+// heatmap tests
+
+#[test]
+fn heatmap_shows_directories_by_share() {
+    let dir = create_test_dir();
+    let sub = dir.path().join("sub");
+    std::fs::create_dir(&sub).unwrap();
+    std::fs::write(sub.join("c.txt"), "one\ntwo\n").unwrap();
+    let result = run_ewc(&["--heatmap", dir.path().to_str().unwrap()]);
+    assert_eq!(result.code, Some(0));
+    let lines: Vec<&str> = result.stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("100.0%"));
+    assert!(lines[1].starts_with("  sub"));
+    assert!(lines[1].ends_with(" 50.0%"));
+    // Colors are only used on a terminal
+    assert!(!result.stdout.contains('\x1b'));
+
+    let conflict = run_ewc(&["--heatmap", "--json", dir.path().to_str().unwrap()]);
+    assert_eq!(conflict.code, Some(2));
+}
+
+// max-stdin-bytes tests
+
+#[test]
+fn max_stdin_bytes_truncates_with_a_warning() {
+    let result = run_ewc_with_stdin(
+        &["--max-stdin-bytes", "8", "--compact", "--no-color"],
+        "one two\nthree four\n",
+    );
+    assert_eq!(result.code, Some(0));
+    assert_eq!(result.stdout.trim(), "<stdin>: 1 lines, 2 words, 8 bytes");
+    assert!(result.stderr.contains("truncated after 8 bytes"));
+
+    let whole = run_ewc_with_stdin(&["--max-stdin-bytes", "1K", "--compact"], "a b\n");
+    assert_eq!(whole.code, Some(0));
+    assert!(whole.stderr.is_empty());
+}
+
+// chunk-size tests
+
+#[test]
+fn chunk_size_does_not_change_counts() {
+    let content = "one two\nthree あいう\r\nfour\n";
+    let default = run_ewc_with_stdin(&["--compact"], content);
+    let tiny = run_ewc_with_stdin(&["--compact", "--chunk-size", "1"], content);
+    assert_eq!(tiny.code, Some(0));
+    assert_eq!(tiny.stdout, default.stdout);
+
+    let zero = run_ewc_with_stdin(&["--chunk-size", "0"], content);
+    assert_eq!(zero.code, Some(2));
+}
+
+// allocator tests
+
+#[test]
+fn stats_name_the_allocator() {
+    let dir = create_test_dir();
+    let result = run_ewc(&["--stats", dir.path().to_str().unwrap()]);
+    assert!(result.success);
+    let expected = if cfg!(feature = "mimalloc") {
+        "mimalloc"
+    } else if cfg!(all(feature = "jemalloc", not(target_env = "msvc"))) {
+        "jemalloc"
+    } else {
+        "system"
+    };
+    assert!(result
+        .stderr
+        .contains(&format!("Allocator:        {expected}")));
+}
+
+// default directory tests
+
+#[test]
+fn no_paths_on_a_terminal_count_the_current_directory() {
+    let dir = create_test_dir();
+    let ewc = std::env::current_dir().unwrap().join("target/debug/ewc");
+    // `script` runs ewc with a terminal for stdin; skip where it is unavailable
+    let Ok(output) = Command::new("script")
+        .args(["-qec", &format!("{} -l", ewc.display()), "/dev/null"])
+        .current_dir(dir.path())
+        .output()
+    else {
+        return;
+    };
+    let output = String::from_utf8_lossy(&output.stdout);
+    assert!(output.contains("counting the current directory"));
+    assert!(output.contains("(2 files)"));
+
+    let output = Command::new("script")
+        .args([
+            "-qec",
+            &format!("{} --errors json -l", ewc.display()),
+            "/dev/null",
+        ])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let output = String::from_utf8_lossy(&output.stdout);
+    assert!(output.contains(r#"{"path":null,"kind":"no_input","message":"No files given;"#));
+}
+
+#[test]
+fn no_paths_with_piped_input_count_stdin() {
+    let result = run_ewc_with_stdin(&["-l"], "one\n");
+    assert!(result.success);
+    assert!(!result.stderr.contains("current directory"));
+    assert!(result.stdout.contains("<stdin>"));
+}
+
+// stream tests
+
+#[test]
+fn stream_prints_each_file_before_the_total() {
+    let dir = create_test_dir();
+    let result = run_ewc(&["--stream", dir.path().to_str().unwrap()]);
+    assert!(result.success);
+    let total = result.stdout.find("(2 files)").unwrap();
+    for name in ["file1.txt", "file2.txt"] {
+        let line = result.stdout.find(name).unwrap();
+        assert!(line < total);
+    }
+    assert!(result
+        .stdout
+        .contains("file1.txt: 1 lines, 2 words, 12 bytes"));
+}
+
+#[test]
+fn stream_conflicts_with_verbose() {
+    let result = run_ewc(&["--stream", "--verbose", "."]);
+    assert_eq!(result.code, Some(2));
+}
+
+// order tests
+
+#[test]
+fn order_keeps_every_file_of_a_directory() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join("a.txt"), "first\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+    let sorted = run_ewc(&["--verbose", path]);
+    assert!(sorted.success);
+    let a = sorted.stdout.find("a.txt").unwrap();
+    assert!(a < sorted.stdout.find("file1.txt").unwrap());
+    for order in ["discovery", "completion"] {
+        let result = run_ewc(&["--verbose", "--order", order, path]);
+        assert!(result.success);
+        let mut lines: Vec<&str> = result.stdout.lines().collect();
+        let mut expected: Vec<&str> = sorted.stdout.lines().collect();
+        lines.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(lines, expected);
+    }
+}
+
+// skip summary tests
+
+#[test]
+fn directories_report_what_was_left_out() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join(".hidden"), "secret\n").unwrap();
+    std::fs::write(dir.path().join("notes.md"), "# notes\n").unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--exclude", "*.md", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("Left out: hidden 1, excluded 1"));
+
+    let result = run_ewc(&["--json", "--exclude", "*.md", path]);
+    assert!(result
+        .stdout
+        .contains(r#""skip_summary":{"hidden":1,"visible":0,"excluded":1,"generated":0"#));
+
+    let result = run_ewc(&["--all", "--include", "*.txt", path]);
+    assert!(result.stdout.contains("Left out: excluded 2"));
+}
+
+// only hidden tests
+
+#[test]
+fn only_hidden_counts_dotfiles() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join(".editorconfig"), "root = true\n").unwrap();
+    let result = run_ewc(&["--only-hidden", "-C", dir.path().to_str().unwrap()]);
+    assert!(result.success);
+    assert!(result.stdout.contains("(1 file):  1 lines, 3 words"));
+    assert!(result.stdout.contains("left out visible 2"));
+
+    let result = run_ewc(&["--only-hidden", "--all", dir.path().to_str().unwrap()]);
+    assert_eq!(result.code, Some(2));
+}
+
+// hidden include tests
+
+#[test]
+fn include_pattern_naming_a_hidden_directory_counts_it() {
+    let dir = create_test_dir();
+    std::fs::create_dir_all(dir.path().join(".github/workflows")).unwrap();
+    std::fs::write(dir.path().join(".github/workflows/ci.yml"), "on: push\n").unwrap();
+    let result = run_ewc(&[
+        "--list",
+        "--include",
+        ".github/**",
+        dir.path().to_str().unwrap(),
+    ]);
+    assert!(result.success);
+    assert!(result.stdout.contains("ci.yml"));
+    assert!(!result.stdout.contains("file1.txt"));
+}
+
+// fail-fast tests
+
+#[test]
+fn fail_fast_stops_at_the_first_unreadable_path() {
+    let dir = create_test_dir();
+    let valid = dir.path().join("file1.txt");
+    let missing = dir.path().join("missing.txt");
+    let paths = [missing.to_str().unwrap(), valid.to_str().unwrap()];
+
+    let result = run_ewc(&["-l", paths[0], paths[1]]);
+    assert!(!result.success);
+    assert!(result.stdout.contains("file1.txt"));
+
+    let result = run_ewc(&["--fail-fast", "-l", paths[0], paths[1]]);
+    assert!(!result.success);
+    assert!(result.stderr.contains("missing.txt"));
+    assert!(!result.stdout.contains("file1.txt"));
+
+    let result = run_ewc(&["--json", "--fail-fast", paths[0], paths[1]]);
+    assert_eq!(result.code, Some(1));
+    assert!(result.stderr.contains("missing.txt"));
+    assert!(result.stdout.is_empty());
+
+    let result = run_ewc(&["--json", "--errors", "json", paths[0], paths[1]]);
+    assert_eq!(result.code, Some(1));
+    assert!(result.stderr.contains(r#""kind":"not_found""#));
+    assert!(result.stdout.contains("file1.txt"));
+}
+
+#[cfg(unix)]
+#[test]
+fn fail_fast_stops_at_the_first_unreadable_file_in_a_directory() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = create_test_dir();
+    // A socket cannot be opened for reading, even by root
+    let socket = dir.path().join("socket");
+    let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--special-files", "--compact", path]);
+    assert!(result.success);
+    assert!(result.stdout.contains("(2 files)"));
+    assert!(result.stderr.contains("socket: skipped:"));
+
+    let result = run_ewc(&["--special-files", "--fail-fast", "--compact", path]);
+    assert_eq!(result.code, Some(1));
+    assert!(result.stderr.contains("socket: skipped:"));
+    assert!(!result.stdout.contains("files)"));
+
+    // Without --special-files the socket is left out rather than failed to read
+    assert!(run_ewc(&["--fail-fast", path]).success);
+
+    std::fs::remove_file(&socket).unwrap();
+    let secret = dir.path().join("secret.txt");
+    std::fs::write(&secret, "secret\n").unwrap();
+    std::fs::set_permissions(&secret, std::fs::Permissions::from_mode(0o000)).unwrap();
+    if std::fs::read(&secret).is_err() {
+        let result = run_ewc(&["--fail-fast", "--no-cache", path]);
+        assert_eq!(result.code, Some(1));
+        assert!(result.stderr.contains("secret.txt: skipped:"));
+    }
+}
+
+#[test]
+fn fail_fast_with_strict_stops_at_the_first_skipped_file() {
+    let dir = create_test_dir();
+    std::fs::write(dir.path().join("image.bin"), [0xff, 0xfe, 0x00, 0x80]).unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let result = run_ewc(&["--fail-fast", path]);
+    assert!(result.success);
+
+    let result = run_ewc(&["--fail-fast", "--strict", path]);
+    assert!(!result.success);
+    assert!(result.stderr.contains("image.bin: skipped: not UTF-8 text"));
+    assert!(!result.stdout.contains("files)"));
+}
+
+// timeout tests
+
+#[cfg(unix)]
+#[test]
+fn timeout_prints_what_was_counted_marked_incomplete() {
+    let dir = create_test_dir();
+    let pipe = dir.path().join("pipe");
+    assert!(Command::new("mkfifo")
+        .arg(&pipe)
+        .status()
+        .unwrap()
+        .success());
+    // Nothing ever writes to the pipe, so the run is stuck on it until given up on
+    let file = dir.path().join("file1.txt");
+    let result = run_ewc(&[
+        "--timeout",
+        "1s",
+        "--compact",
+        file.to_str().unwrap(),
+        pipe.to_str().unwrap(),
+    ]);
+    assert_eq!(result.code, Some(4));
+    assert!(result.stdout.contains("file1.txt"));
+    assert!(result
+        .stdout
+        .ends_with("Incomplete: --timeout ran out before everything was counted\n"));
+    assert!(result.stderr.contains("past --timeout; giving up"));
+
+    let result = run_ewc(&[
+        "--timeout",
+        "1s",
+        "--json",
+        file.to_str().unwrap(),
+        pipe.to_str().unwrap(),
+    ]);
+    assert_eq!(result.code, Some(4));
+    assert!(result.stdout.starts_with(r#"{"files":[{"file":"#));
+    assert!(result.stdout.contains("file1.txt"));
+    assert!(result.stdout.ends_with(",\"timed_out\":true}\n"));
+
+    let result = run_ewc(&["--timeout", "30s", "--compact", file.to_str().unwrap()]);
+    assert!(result.success);
+    assert!(!result.stdout.contains("Incomplete"));
+}
+
+#[test]
+fn timeout_leaves_out_paths_reached_after_the_deadline() {
+    let dir = create_test_dir();
+    let pipes = dir.path().join("pipes");
+    std::fs::create_dir(&pipes).unwrap();
+    assert!(Command::new("mkfifo")
+        .arg(pipes.join("pipe"))
+        .status()
+        .unwrap()
+        .success());
+    // Reading the unwritten pipe gives up after 5s, past the deadline yet before the
+    // watchdog would end the run
+    let file = dir.path().join("file1.txt");
+    let result = run_ewc(&[
+        "--timeout",
+        "3s",
+        "--special-files",
+        "--compact",
+        pipes.to_str().unwrap(),
+        file.to_str().unwrap(),
+    ]);
+    assert_eq!(result.code, Some(4));
+    assert!(result
+        .stderr
+        .contains("not counted before --timeout ran out"));
+    assert!(!result.stderr.contains("giving up"));
+    assert!(result
+        .stdout
+        .ends_with("Incomplete: --timeout ran out before everything was counted\n"));
+}
+
+#[test]
+fn timeout_is_refused_for_runs_that_go_on_until_stopped() {
+    let dir = create_test_dir();
+    let dir_path = dir.path().to_str().unwrap();
+    for args in [
+        &["serve", "--timeout", "1s", dir_path][..],
+        &["daemon", "--timeout", "1s", dir_path],
+        &["--rpc", "--timeout", "1s"],
+        &["--timeout", "1s", "--interval", "1s"],
+    ] {
+        let result = run_ewc_with_stdin(args, "");
+        assert_eq!(result.code, Some(2), "{args:?}");
+        assert!(result.stderr.contains("--timeout cannot be used with"));
+    }
+}